@@ -0,0 +1,264 @@
+//! Electron desktop clients
+//!
+//! The Slack, Discord and Teams desktop apps are Chromium windows showing the
+//! same web app as the browser version, so once one of them is started with
+//! `--remote-debugging-port` its renderer windows are tabs like any other:
+//! they load the web domains, and selectors, the context mapper, monitoring
+//! and replies all apply to them unchanged. Each app listens on a port of its
+//! own, which `CdpManager` connects to next to the browser.
+//!
+//! Attaching needs the app to have been started with the flag. Birdie can
+//! start it that way, but Electron apps allow a single instance: a second
+//! start hands its arguments to the running one and exits, so an app that is
+//! already open has to be closed first.
+//!
+//! Teams on Windows is not Electron but a WebView2 app, which ignores
+//! Chromium flags on its command line and reads them from the
+//! `WEBVIEW2_ADDITIONAL_BROWSER_ARGUMENTS` environment variable instead.
+
+use super::launcher::{free_port, wait_until_debuggable};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+/// Passes Chromium flags to WebView2 apps
+const WEBVIEW2_ARGUMENTS: &str = "WEBVIEW2_ADDITIONAL_BROWSER_ARGUMENTS";
+
+/// A desktop client that can be attached to
+#[derive(Debug, Serialize)]
+pub struct DesktopApp {
+    pub id: &'static str,
+    pub name: &'static str,
+    /// Domains its windows load, those of the web version
+    pub domains: &'static [&'static str],
+    /// Port to attach to when none is given, for apps started by hand
+    pub port: u16,
+    #[serde(skip)]
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    linux: &'static [&'static str],
+    /// Empty when the macOS client is not Chromium
+    #[serde(skip)]
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    macos: &'static str,
+    #[serde(skip)]
+    windows: &'static str,
+    /// Whether the Windows client is a WebView2 app
+    #[serde(skip)]
+    webview2: bool,
+}
+
+pub const DESKTOP_APPS: &[DesktopApp] = &[
+    DesktopApp {
+        id: "slack",
+        name: "Slack",
+        domains: &["app.slack.com"],
+        port: 9223,
+        linux: &["slack"],
+        macos: "Slack.app/Contents/MacOS/Slack",
+        windows: r"slack\slack.exe",
+        webview2: false,
+    },
+    DesktopApp {
+        id: "discord",
+        name: "Discord",
+        domains: &["discord.com"],
+        port: 9224,
+        linux: &["discord", "Discord"],
+        macos: "Discord.app/Contents/MacOS/Discord",
+        // Squirrel's launcher, which starts the installed version
+        windows: r"Discord\Update.exe",
+        webview2: false,
+    },
+    DesktopApp {
+        id: "teams",
+        name: "Microsoft Teams",
+        domains: &["teams.microsoft.com", "teams.live.com"],
+        port: 9225,
+        // The community client; Microsoft no longer ships one for Linux
+        linux: &["teams-for-linux"],
+        // The current client draws with WebKit, which has no DevTools port
+        macos: "",
+        // The execution alias of the Store package
+        windows: r"Microsoft\WindowsApps\ms-teams.exe",
+        webview2: true,
+    },
+];
+
+pub fn find(id: &str) -> Result<&'static DesktopApp, String> {
+    DESKTOP_APPS
+        .iter()
+        .find(|app| app.id == id)
+        .ok_or_else(|| format!("App de escritorio desconocida: {}", id))
+}
+
+impl DesktopApp {
+    /// Whether a target is one of the app's chat windows rather than a
+    /// splash screen, an overlay or a background page
+    pub fn is_renderer(&self, url: &str) -> bool {
+        let host = url::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+        self.domains
+            .iter()
+            .any(|domain| host == *domain || host.ends_with(&format!(".{}", domain)))
+    }
+
+    /// The installed executable
+    pub fn executable(&self) -> Option<PathBuf> {
+        #[cfg(target_os = "linux")]
+        let candidates: Vec<PathBuf> = std::env::var_os("PATH")
+            .map(|path| std::env::split_paths(&path).collect::<Vec<_>>())
+            .unwrap_or_default()
+            .iter()
+            .flat_map(|dir| self.linux.iter().map(move |program| dir.join(program)))
+            .collect();
+        #[cfg(target_os = "macos")]
+        let candidates: Vec<PathBuf> = {
+            let mut roots = vec![PathBuf::from("/Applications")];
+            if let Some(home) = std::env::var_os("HOME") {
+                roots.push(Path::new(&home).join("Applications"));
+            }
+            roots
+                .iter()
+                .filter(|_| !self.macos.is_empty())
+                .map(|root| root.join(self.macos))
+                .collect()
+        };
+        #[cfg(target_os = "windows")]
+        let candidates: Vec<PathBuf> = ["LOCALAPPDATA", "ProgramFiles"]
+            .iter()
+            .filter_map(std::env::var_os)
+            .map(|root| Path::new(&root).join(self.windows))
+            .collect();
+        #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+        let candidates: Vec<PathBuf> = Vec::new();
+
+        candidates.into_iter().find(|path| path.is_file())
+    }
+
+    /// Arguments that start the app with its debugging port open
+    fn arguments(&self, executable: &Path, port: u16) -> Vec<String> {
+        let flag = format!("--remote-debugging-port={}", port);
+        if self.is_webview2(executable) {
+            Vec::new()
+        } else if executable.to_string_lossy().ends_with("Update.exe") {
+            // Discord\Update.exe starts Discord.exe
+            let program = format!(
+                "{}.exe",
+                self.windows.split('\\').next().unwrap_or_default()
+            );
+            vec![
+                "--processStart".to_string(),
+                program,
+                "--process-start-args".to_string(),
+                flag,
+            ]
+        } else {
+            vec![flag]
+        }
+    }
+
+    /// Environment that starts the app with its debugging port open
+    fn environment(&self, executable: &Path, port: u16) -> Vec<(&'static str, String)> {
+        if self.is_webview2(executable) {
+            vec![(
+                WEBVIEW2_ARGUMENTS,
+                format!("--remote-debugging-port={}", port),
+            )]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn is_webview2(&self, executable: &Path) -> bool {
+        self.webview2 && executable.to_string_lossy().ends_with(".exe")
+    }
+
+    /// Start the app with a debugging port; returns the port
+    pub async fn launch(&self) -> Result<u16, String> {
+        let executable = self
+            .executable()
+            .ok_or_else(|| format!("{} no está instalado", self.name))?;
+        let port = free_port()?;
+        let mut child = tokio::process::Command::new(&executable)
+            .args(self.arguments(&executable, port))
+            .envs(self.environment(&executable, port))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("No se pudo abrir {}: {}", self.name, e))?;
+
+        wait_until_debuggable(&mut child, self.name, port)
+            .await
+            .map_err(|e| {
+                format!(
+                    "{}. Si {} ya estaba abierto, ciérralo del todo y vuelve a intentarlo",
+                    e, self.name
+                )
+            })?;
+        Ok(port)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renderer_windows() {
+        let slack = find("slack").unwrap();
+        assert!(slack.is_renderer("https://app.slack.com/client/T024BE7LD/C024BE91L"));
+        assert!(!slack.is_renderer("about:blank"));
+        assert!(!slack.is_renderer("file:///usr/lib/slack/resources/app.asar/index.html"));
+
+        let teams = find("teams").unwrap();
+        assert!(teams.is_renderer("https://teams.microsoft.com/v2/"));
+        assert!(!teams.is_renderer("https://notteams.microsoft.com.example.org/"));
+        assert!(find("skype").is_err());
+    }
+
+    #[test]
+    fn test_launch_arguments() {
+        let slack = find("slack").unwrap();
+        assert_eq!(
+            slack.arguments(Path::new("/usr/bin/slack"), 9300),
+            vec!["--remote-debugging-port=9300"]
+        );
+        // Squirrel's launcher passes the flag on to the app
+        let discord = find("discord").unwrap();
+        assert_eq!(
+            discord.arguments(
+                Path::new(r"C:\Users\ana\AppData\Local\Discord\Update.exe"),
+                9300
+            ),
+            vec![
+                "--processStart",
+                "Discord.exe",
+                "--process-start-args",
+                "--remote-debugging-port=9300"
+            ]
+        );
+        assert!(discord
+            .environment(Path::new(r"C:\Discord\Update.exe"), 9300)
+            .is_empty());
+
+        // WebView2 takes the flag from the environment
+        let teams = find("teams").unwrap();
+        let ms_teams = Path::new(r"C:\Users\ana\AppData\Local\Microsoft\WindowsApps\ms-teams.exe");
+        assert!(teams.arguments(ms_teams, 9300).is_empty());
+        assert_eq!(
+            teams.environment(ms_teams, 9300),
+            vec![(
+                "WEBVIEW2_ADDITIONAL_BROWSER_ARGUMENTS",
+                "--remote-debugging-port=9300".to_string()
+            )]
+        );
+        // The Linux client is Electron
+        assert_eq!(
+            teams.arguments(Path::new("/usr/bin/teams-for-linux"), 9300),
+            vec!["--remote-debugging-port=9300"]
+        );
+    }
+}
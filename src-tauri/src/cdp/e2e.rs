@@ -0,0 +1,302 @@
+//! End-to-end tests against headless Chromium and the chat fixtures
+//!
+//! They need a Chromium, so they are ignored by default; run them with
+//! `cargo test -- --ignored`, where a missing Chromium fails them instead of
+//! letting them pass unchecked. See `harness` for how the fixtures are served.
+
+use super::harness::{self, Harness, FIXTURES, TAB_TIMEOUT};
+use super::health::{inspect, SelectorCounts};
+use super::monitor::{extraction_script, Extracted, Seen};
+use super::observer::{inject, observer_script, Reported, BINDING, DETACH_SCRIPT, QUIET_MS};
+use super::{find_best_tab, get_selector_for_domain, ConnectionState, SelectorConfig, TabInfo};
+use crate::context_mapper::ContextMapper;
+use crate::reply::cdp::{
+    collapse, composer_text_script, discard, eval, locate, submit, type_reply,
+};
+use crate::vision::get_extraction_script;
+use chromiumoxide::cdp::js_protocol::runtime::{
+    AddBindingParams, EnableParams as RuntimeEnableParams, EventBindingCalled,
+};
+use chromiumoxide::Page;
+use futures::StreamExt;
+use serde_json::json;
+use std::time::Duration;
+
+async fn read(page: &Page, config: &SelectorConfig) -> Vec<Extracted> {
+    serde_json::from_value(harness::evaluate(page, &extraction_script(config)).await).unwrap()
+}
+
+#[tokio::test]
+#[ignore = "needs Chromium"]
+async fn test_connects_and_lists_fixture_tabs() {
+    let harness = Harness::start("tabs").await;
+    let mut opened = Vec::new();
+    for fixture in FIXTURES {
+        let (page, tab) = harness.open(fixture.host).await;
+        assert_eq!(tab.domain, fixture.host);
+        assert!(tab.has_selector, "{}", fixture.host);
+        opened.push(page);
+    }
+    assert!(matches!(
+        harness.manager.state(),
+        ConnectionState::Connected { .. }
+    ));
+
+    let tabs: Vec<TabInfo> = harness
+        .manager
+        .pages()
+        .await
+        .unwrap()
+        .iter()
+        .map(TabInfo::from)
+        .collect();
+    let best = find_best_tab(tabs, "whatsapp").unwrap();
+    assert_eq!(best.domain, "web.whatsapp.com");
+    assert_eq!(best.title, "WhatsApp");
+
+    // Nothing outside the fixtures is served, and nothing leaves the machine
+    let status = harness::evaluate(
+        &opened[0],
+        "fetch('https://example.com/').then(r => r.status, () => 0)",
+    )
+    .await;
+    assert_eq!(status, 404);
+
+    // A closed tab leaves the list as the browser reports it
+    let closed = opened.pop().unwrap();
+    let id = closed.target_id().as_ref().to_string();
+    closed.close().await.unwrap();
+    let started = tokio::time::Instant::now();
+    while harness
+        .manager
+        .pages()
+        .await
+        .unwrap()
+        .iter()
+        .any(|t| t.target_id.as_ref() == id)
+    {
+        assert!(started.elapsed() < TAB_TIMEOUT, "closed tab still listed");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs Chromium"]
+async fn test_extracts_fixture_messages() {
+    let harness = Harness::start("extraction").await;
+    let expected = [
+        ("meet.google.com", "Lucía Pérez", "¿Me oís bien?", 3),
+        (
+            "teams.microsoft.com",
+            "Ana Ruiz",
+            "¿Revisaste el canal de producto?",
+            2,
+        ),
+        ("discord.com", "Nora", "¿Entras al canal de voz?", 2),
+        (
+            "web.whatsapp.com",
+            "Mamá",
+            "Os mando una nota de voz luego",
+            2,
+        ),
+        ("web.telegram.org", "Iker", "¿Quedamos el sábado?", 3),
+    ];
+    let mapper = ContextMapper::new();
+    for (host, sender, message, count) in expected {
+        let (page, tab) = harness.open(host).await;
+        let config = get_selector_for_domain(&tab.domain).unwrap();
+        let extracted = read(&page, &config).await;
+        assert_eq!(extracted.len(), count, "{}", host);
+        assert_eq!(extracted[0].sender, sender, "{}", host);
+        assert_eq!(extracted[0].message, message, "{}", host);
+        // Grouped messages keep their group's sender
+        assert!(extracted.iter().all(|m| !m.sender.is_empty()), "{}", host);
+
+        let context = mapper
+            .get_enabled_contexts()
+            .into_iter()
+            .find(|c| c.url_patterns.iter().any(|p| host.contains(p.as_str())))
+            .unwrap();
+        let text = harness::evaluate(&page, &context.css_selector).await;
+        assert!(text.as_str().unwrap().contains(message), "{}", host);
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs Chromium"]
+async fn test_fixture_messages_are_reported_once() {
+    let harness = Harness::start("dedup").await;
+    let (page, tab) = harness.open("web.telegram.org").await;
+    let config = get_selector_for_domain(&tab.domain).unwrap();
+    let mut seen = Seen::default();
+
+    assert!(seen
+        .new_messages(&tab.id, read(&page, &config).await)
+        .is_empty());
+    harness::receive(&page, "Sara", "¿A qué hora?").await;
+    let fresh = seen.new_messages(&tab.id, read(&page, &config).await);
+    assert_eq!(fresh.len(), 1);
+    assert_eq!(fresh[0].sender, "Sara");
+    assert_eq!(fresh[0].message, "¿A qué hora?");
+    assert!(seen
+        .new_messages(&tab.id, read(&page, &config).await)
+        .is_empty());
+}
+
+#[tokio::test]
+#[ignore = "needs Chromium"]
+async fn test_observer_reports_added_fixture_messages() {
+    let harness = Harness::start("observer").await;
+    let (page, tab) = harness.open("discord.com").await;
+    let config = get_selector_for_domain(&tab.domain).unwrap();
+    page.execute(RuntimeEnableParams::default()).await.unwrap();
+    page.execute(AddBindingParams::new(BINDING)).await.unwrap();
+    let mut calls = page.event_listener::<EventBindingCalled>().await.unwrap();
+    inject(&page, &observer_script(&config)).await.unwrap();
+
+    // Messages added during the quiet period are history
+    harness::receive(&page, "pablo.dev", "cargando historial").await;
+    tokio::time::sleep(Duration::from_millis(QUIET_MS as u64 + 300)).await;
+    harness::receive(&page, "Nora", "¿Sigues ahí?").await;
+
+    let call = tokio::time::timeout(Duration::from_secs(5), calls.next())
+        .await
+        .expect("a reported message")
+        .unwrap();
+    let reported: Reported = serde_json::from_str(&call.payload).unwrap();
+    assert_eq!(reported.sender, "Nora");
+    assert_eq!(reported.message, "¿Sigues ahí?");
+
+    // Detaching leaves the page as it was
+    inject(&page, DETACH_SCRIPT).await.unwrap();
+    let restored = harness::evaluate(
+        &page,
+        "!window.__birdieObserver && history.pushState.toString().includes('[native code]')",
+    )
+    .await;
+    assert_eq!(restored, serde_json::json!(true));
+}
+
+#[tokio::test]
+#[ignore = "needs Chromium"]
+async fn test_inspects_fixture_tabs() {
+    let harness = Harness::start("health").await;
+    let mapper = ContextMapper::new();
+    let context = mapper.find_context("Discord").unwrap();
+    let (page, tab) = harness.open("discord.com").await;
+    let config = get_selector_for_domain(&tab.domain).unwrap();
+
+    let health = inspect(&page, &tab, Some(&config), Some(&context)).await;
+    assert_eq!(health.error, None);
+    assert_eq!(
+        health.selectors,
+        Some(SelectorCounts {
+            container: 2,
+            sender: Some(2),
+            text: 2,
+        })
+    );
+    assert_eq!(health.context_found, Some(true));
+    assert!(health.is_chat);
+    assert!(!health.broken);
+
+    // A redesign renames the attributes the selectors look for
+    harness::evaluate(
+        &page,
+        "document.querySelectorAll('[data-testid]').forEach(el => el.removeAttribute('data-testid'))",
+    )
+    .await;
+    let health = inspect(&page, &tab, Some(&config), Some(&context)).await;
+    assert_eq!(health.selectors.as_ref().map(|s| s.text), Some(0));
+    assert_eq!(health.context_found, Some(false));
+    assert!(health.is_chat);
+    assert!(health.broken);
+}
+
+#[tokio::test]
+#[ignore = "needs Chromium"]
+async fn test_extraction_scripts_read_fixtures() {
+    let harness = Harness::start("vision").await;
+    let expected = [
+        ("meet.google.com", "Participante: Marcos Gil"),
+        ("teams.microsoft.com", "Subo el acta en un momento"),
+        ("discord.com", "Canal: Nora"),
+        ("web.whatsapp.com", "¿Habéis visto mi estado?"),
+        ("web.telegram.org", "Yo me apunto"),
+    ];
+    for fixture in FIXTURES {
+        let (page, tab) = harness.open(fixture.host).await;
+        let content = harness::evaluate(&page, &get_extraction_script(&tab.url)).await;
+        let wanted = expected
+            .iter()
+            .find(|(host, _)| *host == fixture.host)
+            .map(|(_, text)| *text)
+            .unwrap();
+        assert!(
+            content.as_str().unwrap().contains(wanted),
+            "{}",
+            fixture.host
+        );
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs Chromium"]
+async fn test_replies_reach_fixture_composers() {
+    let harness = Harness::start("reply").await;
+    let cdp = &harness.manager;
+    let mapper = ContextMapper::new();
+
+    // A send button, an Enter key, and a composer with no chat list
+    let cases = [
+        (
+            "teams.microsoft.com",
+            "Microsoft Teams",
+            Some("Ana"),
+            json!("Ana Ruiz"),
+        ),
+        ("discord.com", "Discord", Some("nora"), json!("Nora")),
+        ("meet.google.com", "Google Meet", None, json!(null)),
+    ];
+    for (host, app_name, recipient, conversation) in cases {
+        let (page, _) = harness.open(host).await;
+        let tab = locate(cdp, app_name, &mapper).await.unwrap();
+        type_reply(cdp, &tab, recipient, "Te lo paso ahora")
+            .await
+            .unwrap();
+        let typed: Option<String> = eval(&page, composer_text_script(&tab.selectors.composer))
+            .await
+            .unwrap();
+        assert_eq!(
+            typed.as_deref().map(collapse).as_deref(),
+            Some("Te lo paso ahora")
+        );
+
+        submit(cdp, &tab, "Te lo paso ahora").await.unwrap();
+        let sent = harness::evaluate(&page, "window.fixture.sent").await;
+        assert_eq!(
+            sent,
+            json!([{ "conversation": conversation, "message": "Te lo paso ahora" }]),
+            "{}",
+            app_name
+        );
+        page.close().await.unwrap();
+    }
+
+    // A discarded reply leaves the composer empty
+    let (page, _) = harness.open("web.whatsapp.com").await;
+    let tab = locate(cdp, "WhatsApp", &mapper).await.unwrap();
+    type_reply(cdp, &tab, Some("Carmen"), "Ya voy")
+        .await
+        .unwrap();
+    // Text other than the confirmed reply is never sent
+    let edited = submit(cdp, &tab, "Ya voy, tardo diez minutos").await;
+    assert!(edited.is_err());
+    discard(cdp, &tab).await.unwrap();
+    let typed: Option<String> = eval(&page, composer_text_script(&tab.selectors.composer))
+        .await
+        .unwrap();
+    assert_eq!(typed.as_deref().map(str::trim), Some(""));
+    let sent = harness::evaluate(&page, "window.fixture.sent").await;
+    assert_eq!(sent, json!([]));
+}
@@ -0,0 +1,453 @@
+//! Messages read from a chat app's WebSocket traffic
+//!
+//! Chat apps receive each message over a WebSocket before they render it.
+//! With the `Network` domain enabled the browser reports every frame as
+//! `Network.webSocketFrameReceived`, and a decoder for the app turns the
+//! frames that carry messages into `CDPMessage`s. Unlike selectors, the
+//! events hold the sender's id, the channel and the time the server gave
+//! the message, and they survive UI redesigns.
+//!
+//! Discord compresses its gateway as a single zlib stream. Its frames can
+//! only be inflated from the start of the stream, so a gateway connection
+//! opened before capture began is skipped until the app reconnects; reloading
+//! the tab is enough.
+
+use super::health::SelectorHealth;
+use super::monitor::{self, Attached, MonitorStats};
+use super::{CDPMessage, TabInfo};
+use base64::Engine;
+use chromiumoxide::cdp::browser_protocol::network::{
+    DisableParams, EnableParams, EventWebSocketClosed, EventWebSocketCreated,
+    EventWebSocketFrameReceived, WebSocketFrame,
+};
+use chromiumoxide::Page;
+use flate2::{Decompress, FlushDecompress};
+use futures::StreamExt;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+
+/// Ends every complete message of a zlib stream (a sync flush)
+const ZLIB_SUFFIX: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// An app whose WebSocket messages can be read
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decoder {
+    Slack,
+    Discord,
+}
+
+impl Decoder {
+    pub fn for_domain(domain: &str) -> Option<Self> {
+        if domain == "slack.com" || domain.ends_with(".slack.com") {
+            Some(Self::Slack)
+        } else if domain == "discord.com" || domain.ends_with(".discord.com") {
+            Some(Self::Discord)
+        } else {
+            None
+        }
+    }
+
+    fn source_name(self) -> &'static str {
+        match self {
+            Self::Slack => "slack",
+            Self::Discord => "discord",
+        }
+    }
+
+    /// The message a frame carries; other events are ignored
+    fn decode(self, text: &str) -> Option<Decoded> {
+        match self {
+            Self::Slack => decode_slack(text),
+            Self::Discord => decode_discord(text),
+        }
+    }
+}
+
+/// A message as the app's server sent it
+#[derive(Debug, Clone, PartialEq)]
+struct Decoded {
+    sender_id: String,
+    sender: String,
+    channel: String,
+    message: String,
+    timestamp: String,
+}
+
+#[derive(Deserialize)]
+struct SlackEvent {
+    r#type: String,
+    subtype: Option<String>,
+    channel: Option<String>,
+    user: Option<String>,
+    bot_id: Option<String>,
+    username: Option<String>,
+    user_profile: Option<SlackProfile>,
+    text: Option<String>,
+    ts: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SlackProfile {
+    display_name: Option<String>,
+    real_name: Option<String>,
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|v| !v.trim().is_empty())
+}
+
+fn decode_slack(text: &str) -> Option<Decoded> {
+    let event: SlackEvent = serde_json::from_str(text).ok()?;
+    if event.r#type != "message" {
+        return None;
+    }
+    // Edits, deletions, joins and the like carry a subtype
+    let (sender_id, name) = match event.subtype.as_deref() {
+        None | Some("thread_broadcast") => (event.user?, None),
+        Some("bot_message") => (event.bot_id?, event.username),
+        Some(_) => return None,
+    };
+    let name = name.or_else(|| {
+        let profile = event.user_profile?;
+        non_empty(profile.display_name).or(non_empty(profile.real_name))
+    });
+    // "1712345678.000200": seconds and microseconds, also the message's id
+    let ts = event.ts?;
+    let (secs, micros) = ts.split_once('.').unwrap_or((&ts, "0"));
+    let timestamp = chrono::DateTime::from_timestamp(
+        secs.parse().ok()?,
+        micros.parse::<u32>().ok()?.saturating_mul(1000),
+    )?;
+    Some(Decoded {
+        sender: name.unwrap_or_else(|| sender_id.clone()),
+        sender_id,
+        channel: event.channel?,
+        message: non_empty(event.text)?,
+        timestamp: timestamp.to_rfc3339(),
+    })
+}
+
+#[derive(Deserialize)]
+struct GatewayEvent {
+    op: u8,
+    t: Option<String>,
+    d: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct DiscordMessage {
+    channel_id: String,
+    content: String,
+    timestamp: String,
+    author: DiscordUser,
+    member: Option<DiscordMember>,
+}
+
+#[derive(Deserialize)]
+struct DiscordUser {
+    id: String,
+    username: String,
+    global_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DiscordMember {
+    nick: Option<String>,
+}
+
+fn decode_discord(text: &str) -> Option<Decoded> {
+    let event: GatewayEvent = serde_json::from_str(text).ok()?;
+    // Opcode 0 is a dispatched event
+    if event.op != 0 || event.t.as_deref() != Some("MESSAGE_CREATE") {
+        return None;
+    }
+    let message: DiscordMessage = serde_json::from_value(event.d?).ok()?;
+    // Messages with only attachments or embeds have no text to read
+    let content = non_empty(Some(message.content))?;
+    let sender = non_empty(message.member.and_then(|m| m.nick))
+        .or(non_empty(message.author.global_name))
+        .unwrap_or(message.author.username);
+    Some(Decoded {
+        sender_id: message.author.id,
+        sender,
+        channel: message.channel_id,
+        message: content,
+        timestamp: message.timestamp,
+    })
+}
+
+/// Inflates a zlib stream whose messages end in a sync flush
+struct Inflater {
+    inflate: Decompress,
+    pending: Vec<u8>,
+}
+
+impl Inflater {
+    fn new() -> Self {
+        Self {
+            inflate: Decompress::new(true),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Add a frame; returns the message once its last frame arrived
+    fn push(&mut self, bytes: &[u8]) -> Result<Option<String>, String> {
+        self.pending.extend_from_slice(bytes);
+        if !self.pending.ends_with(&ZLIB_SUFFIX) {
+            return Ok(None);
+        }
+        let input = std::mem::take(&mut self.pending);
+        let mut output = Vec::with_capacity(input.len() * 4);
+        let mut offset = 0;
+        loop {
+            let before = self.inflate.total_in();
+            self.inflate
+                .decompress_vec(&input[offset..], &mut output, FlushDecompress::Sync)
+                .map_err(|e| e.to_string())?;
+            offset += (self.inflate.total_in() - before) as usize;
+            // Room left in the output means everything was inflated
+            if offset >= input.len() && output.len() < output.capacity() {
+                break;
+            }
+            output.reserve(input.len() * 4);
+        }
+        String::from_utf8(output)
+            .map(Some)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// How a socket's binary frames are read
+enum Socket {
+    /// Text frames only
+    Plain,
+    Zlib(Inflater),
+    /// A compression there is no support for, or a stream joined midway
+    Unreadable,
+}
+
+impl Socket {
+    fn for_url(url: &str) -> Self {
+        let compress = url::Url::parse(url).ok().and_then(|url| {
+            url.query_pairs()
+                .find(|(key, _)| key == "compress")
+                .map(|(_, value)| value.into_owned())
+        });
+        match compress.as_deref() {
+            None => Self::Plain,
+            Some("zlib-stream") => Self::Zlib(Inflater::new()),
+            Some(_) => Self::Unreadable,
+        }
+    }
+
+    /// The text of a frame, once a whole message has arrived
+    fn read(&mut self, frame: &WebSocketFrame) -> Result<Option<String>, String> {
+        // Opcode 1 is a text frame, its payload is the text itself
+        if frame.opcode == 1.0 {
+            return Ok(Some(frame.payload_data.clone()));
+        }
+        let Self::Zlib(inflater) = self else {
+            return Ok(None);
+        };
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(&frame.payload_data)
+            .map_err(|e| e.to_string())?;
+        inflater.push(&bytes)
+    }
+}
+
+pub async fn attach(
+    app: &AppHandle,
+    page: Page,
+    tab: TabInfo,
+    decoder: Decoder,
+    stats: Arc<MonitorStats>,
+) -> Result<Attached, String> {
+    let protocol = |e: chromiumoxide::error::CdpError| e.to_string();
+    let mut created = page
+        .event_listener::<EventWebSocketCreated>()
+        .await
+        .map_err(protocol)?;
+    let mut closed = page
+        .event_listener::<EventWebSocketClosed>()
+        .await
+        .map_err(protocol)?;
+    let mut frames = page
+        .event_listener::<EventWebSocketFrameReceived>()
+        .await
+        .map_err(protocol)?;
+    page.execute(EnableParams::default())
+        .await
+        .map_err(protocol)?;
+
+    let source = decoder.source_name();
+    let app_name = monitor::app_name(&tab.domain, source);
+    let app = app.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        let mut sockets: HashMap<String, Socket> = HashMap::new();
+        loop {
+            tokio::select! {
+                Some(socket) = created.next() => {
+                    sockets.insert(socket.request_id.inner().clone(), Socket::for_url(&socket.url));
+                }
+                Some(socket) = closed.next() => {
+                    sockets.remove(socket.request_id.inner());
+                }
+                Some(received) = frames.next() => {
+                    let socket = sockets
+                        .entry(received.request_id.inner().clone())
+                        // Opened before capture started
+                        .or_insert(Socket::Unreadable);
+                    let text = match socket.read(&received.response) {
+                        Ok(Some(text)) => text,
+                        Ok(None) => continue,
+                        Err(e) => {
+                            eprintln!("⚠ CDP frames, '{}': {}", tab.title, e);
+                            *socket = Socket::Unreadable;
+                            continue;
+                        }
+                    };
+                    let Some(decoded) = decoder.decode(&text) else {
+                        continue;
+                    };
+                    stats.messages_detected.fetch_add(1, Ordering::Relaxed);
+                    app.state::<SelectorHealth>().extracted(source);
+                    let message = CDPMessage {
+                        tab_id: tab.id.clone(),
+                        tab_title: tab.title.clone(),
+                        domain: tab.domain.clone(),
+                        sender: decoded.sender,
+                        message: decoded.message,
+                        timestamp: decoded.timestamp,
+                        source: source.to_string(),
+                        sender_id: Some(decoded.sender_id),
+                        channel: Some(decoded.channel),
+                    };
+                    monitor::deliver(&app, &message, &app_name);
+                }
+                else => break,
+            }
+        }
+    });
+
+    let teardown = async move {
+        if let Err(e) = page.execute(DisableParams::default()).await {
+            eprintln!("⚠ CDP frames: {}", e);
+        }
+    };
+    Ok(Attached {
+        tasks: vec![task],
+        teardown: Some(Box::pin(teardown)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    #[test]
+    fn test_decoders_by_domain() {
+        assert_eq!(Decoder::for_domain("app.slack.com"), Some(Decoder::Slack));
+        assert_eq!(Decoder::for_domain("discord.com"), Some(Decoder::Discord));
+        assert_eq!(Decoder::for_domain("notslack.com"), None);
+        assert_eq!(Decoder::for_domain("web.whatsapp.com"), None);
+    }
+
+    #[test]
+    fn test_slack_messages() {
+        let decoded = decode_slack(
+            r#"{"type":"message","channel":"C024BE91L","user":"U2147483697",
+                "user_profile":{"display_name":"","real_name":"Ana Pérez"},
+                "text":"¿Subes el informe?","ts":"1712345678.000200"}"#,
+        )
+        .unwrap();
+        assert_eq!(decoded.sender_id, "U2147483697");
+        assert_eq!(decoded.sender, "Ana Pérez");
+        assert_eq!(decoded.channel, "C024BE91L");
+        assert_eq!(decoded.message, "¿Subes el informe?");
+        assert_eq!(decoded.timestamp, "2024-04-05T19:34:38.000200+00:00");
+
+        // Without a profile the id is all there is
+        let decoded = decode_slack(
+            r#"{"type":"message","channel":"C1","user":"U1","text":"hola","ts":"1712345678.000000"}"#,
+        )
+        .unwrap();
+        assert_eq!(decoded.sender, "U1");
+
+        assert!(decode_slack(
+            r#"{"type":"message","subtype":"message_changed","channel":"C1","ts":"1712345678.000000"}"#
+        )
+        .is_none());
+        assert!(decode_slack(r#"{"type":"user_typing","channel":"C1","user":"U1"}"#).is_none());
+        assert!(decode_slack("not json").is_none());
+    }
+
+    #[test]
+    fn test_discord_messages() {
+        let decoded = decode_discord(
+            r#"{"op":0,"s":42,"t":"MESSAGE_CREATE","d":{"id":"1","channel_id":"81384788765712384",
+                "content":"gg","timestamp":"2024-04-05T19:34:38.123000+00:00",
+                "author":{"id":"80351110224678912","username":"luis","global_name":"Luis"},
+                "member":{"nick":null}}}"#,
+        )
+        .unwrap();
+        assert_eq!(decoded.sender_id, "80351110224678912");
+        assert_eq!(decoded.sender, "Luis");
+        assert_eq!(decoded.channel, "81384788765712384");
+        assert_eq!(decoded.timestamp, "2024-04-05T19:34:38.123000+00:00");
+
+        assert!(decode_discord(r#"{"op":11}"#).is_none());
+        assert!(decode_discord(
+            r#"{"op":0,"t":"TYPING_START","d":{"channel_id":"1","user_id":"2"}}"#
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_zlib_stream() {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        let mut frames = Vec::new();
+        for text in [r#"{"op":10}"#, r#"{"op":0,"t":"READY"}"#] {
+            encoder.write_all(text.as_bytes()).unwrap();
+            // A sync flush, as the gateway sends at the end of each message
+            encoder.flush().unwrap();
+            frames.push(std::mem::take(encoder.get_mut()));
+        }
+        assert!(frames[0].ends_with(&ZLIB_SUFFIX));
+
+        let mut inflater = Inflater::new();
+        assert_eq!(
+            inflater.push(&frames[0]).unwrap().as_deref(),
+            Some(r#"{"op":10}"#)
+        );
+        // A message split across frames arrives with its last frame
+        let (head, tail) = frames[1].split_at(3);
+        assert_eq!(inflater.push(head).unwrap(), None);
+        assert_eq!(
+            inflater.push(tail).unwrap().as_deref(),
+            Some(r#"{"op":0,"t":"READY"}"#)
+        );
+    }
+
+    #[test]
+    fn test_socket_compression() {
+        assert!(matches!(
+            Socket::for_url("wss://gateway.discord.gg/?encoding=json&v=9&compress=zlib-stream"),
+            Socket::Zlib(_)
+        ));
+        assert!(matches!(
+            Socket::for_url("wss://gateway.discord.gg/?encoding=json&v=9&compress=zstd-stream"),
+            Socket::Unreadable
+        ));
+        assert!(matches!(
+            Socket::for_url("wss://wss-primary.slack.com/?token=x"),
+            Socket::Plain
+        ));
+    }
+}
@@ -0,0 +1,360 @@
+//! End-to-end test harness: headless Chromium and local chat fixtures
+//!
+//! Each fixture page copies the markup a chat app renders, down to the
+//! attributes its selectors and reply selectors look for. A local HTTP server
+//! serves them, and Chromium loads them under the apps' real URLs: every
+//! request a page makes is paused over DevTools and answered from the server,
+//! and any other lookup fails to resolve, so no test reaches the network and
+//! domains, tab matching, extraction and replies behave as on the live sites.
+//!
+//! Chromium is looked up like the launcher looks for a browser, or taken from
+//! `BIRDIE_TEST_CHROMIUM`. The tests that need it are in `e2e`.
+
+use super::launcher::{self, free_port, wait_until_debuggable};
+use super::manager::CdpConfig;
+use super::{script, CdpManager, TabInfo};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chromiumoxide::cdp::browser_protocol::fetch::{
+    EnableParams as FetchEnableParams, EventRequestPaused, FulfillRequestParams, HeaderEntry,
+};
+use chromiumoxide::Page;
+use futures::StreamExt;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// A chat app's fixture page
+pub(crate) struct Fixture {
+    pub host: &'static str,
+    /// Where the page is opened
+    pub url: &'static str,
+    html: &'static str,
+}
+
+pub(crate) const FIXTURES: &[Fixture] = &[
+    Fixture {
+        host: "meet.google.com",
+        url: "https://meet.google.com/abc-defg-hij",
+        html: include_str!("../../tests/fixtures/meet.html"),
+    },
+    Fixture {
+        host: "teams.microsoft.com",
+        url: "https://teams.microsoft.com/v2/",
+        html: include_str!("../../tests/fixtures/teams.html"),
+    },
+    Fixture {
+        host: "discord.com",
+        url: "https://discord.com/channels/@me/1",
+        html: include_str!("../../tests/fixtures/discord.html"),
+    },
+    Fixture {
+        host: "web.whatsapp.com",
+        url: "https://web.whatsapp.com/",
+        html: include_str!("../../tests/fixtures/whatsapp.html"),
+    },
+    Fixture {
+        host: "web.telegram.org",
+        url: "https://web.telegram.org/k/",
+        html: include_str!("../../tests/fixtures/telegram.html"),
+    },
+];
+
+/// Script the fixture pages share
+const CHAT_JS: &str = include_str!("../../tests/fixtures/chat.js");
+
+/// A tab that has not shown up this long after loading never will
+pub(crate) const TAB_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What the server answers for `/<host>/<path>`: the shared script, or the
+/// page of the app at `<host>` whatever the path
+fn route(path: &str) -> Option<(&'static str, &'static str)> {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let (host, rest) = path
+        .trim_start_matches('/')
+        .split_once('/')
+        .unwrap_or((path.trim_start_matches('/'), ""));
+    match rest {
+        "chat.js" => Some(("text/javascript", CHAT_JS)),
+        "favicon.ico" => None,
+        _ => FIXTURES
+            .iter()
+            .find(|fixture| fixture.host == host)
+            .map(|fixture| ("text/html; charset=utf-8", fixture.html)),
+    }
+}
+
+async fn respond(mut stream: TcpStream) -> std::io::Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+    let (status, content_type, body) = match route(path) {
+        Some((content_type, body)) => ("200 OK", content_type, body),
+        None => ("404 Not Found", "text/plain", "Not found"),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await
+}
+
+/// The local server the fixtures come from
+struct FixtureServer {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl FixtureServer {
+    async fn start() -> Result<Self, String> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .map_err(|e| e.to_string())?;
+        let addr = listener.local_addr().map_err(|e| e.to_string())?;
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(respond(stream));
+            }
+        });
+        Ok(Self { addr, task })
+    }
+
+    /// The server's copy of what a page asked for
+    async fn fetch(
+        &self,
+        client: &reqwest::Client,
+        url: &str,
+    ) -> Result<(u16, String, Vec<u8>), String> {
+        let url = url::Url::parse(url).map_err(|e| e.to_string())?;
+        let local = format!(
+            "http://{}/{}{}",
+            self.addr,
+            url.host_str().unwrap_or_default(),
+            url.path()
+        );
+        let response = client.get(local).send().await.map_err(|e| e.to_string())?;
+        let status = response.status().as_u16();
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("text/plain")
+            .to_string();
+        let body = response.bytes().await.map_err(|e| e.to_string())?;
+        Ok((status, content_type, body.to_vec()))
+    }
+
+    /// Answer the page's requests from the server from now on
+    async fn intercept(self: &Arc<Self>, page: &Page) -> Result<(), String> {
+        let mut paused = page
+            .event_listener::<EventRequestPaused>()
+            .await
+            .map_err(|e| e.to_string())?;
+        page.execute(FetchEnableParams::default())
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let server = self.clone();
+        let page = page.clone();
+        tokio::spawn(async move {
+            let client = reqwest::Client::builder()
+                .no_proxy()
+                .build()
+                .unwrap_or_default();
+            while let Some(request) = paused.next().await {
+                let (status, content_type, body) =
+                    match server.fetch(&client, &request.request.url).await {
+                        Ok(response) => response,
+                        Err(e) => (502, "text/plain".to_string(), e.into_bytes()),
+                    };
+                let fulfill = FulfillRequestParams::builder()
+                    .request_id(request.request_id.clone())
+                    .response_code(status)
+                    .response_header(HeaderEntry::new("Content-Type", content_type))
+                    .response_header(HeaderEntry::new("Access-Control-Allow-Origin", "*"))
+                    .body(BASE64.encode(body))
+                    .build();
+                if let Ok(fulfill) = fulfill {
+                    let _ = page.execute(fulfill).await;
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+impl Drop for FixtureServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// The Chromium to test against
+fn chromium() -> Option<PathBuf> {
+    std::env::var_os("BIRDIE_TEST_CHROMIUM")
+        .map(PathBuf::from)
+        .or_else(|| {
+            launcher::discover()
+                .into_iter()
+                .next()
+                .map(|browser| browser.path)
+        })
+}
+
+/// A headless Chromium with its own profile, and a manager connected to it
+pub(crate) struct Harness {
+    pub manager: CdpManager,
+    server: Arc<FixtureServer>,
+    chromium: tokio::process::Child,
+    dir: PathBuf,
+}
+
+impl Harness {
+    /// Start Chromium for the test `name`
+    pub async fn start(name: &str) -> Self {
+        let chromium = chromium().unwrap_or_else(|| {
+            panic!(
+                "No Chromium found for {}; install one or set BIRDIE_TEST_CHROMIUM",
+                name
+            )
+        });
+        Self::launch(name, &chromium)
+            .await
+            .unwrap_or_else(|e| panic!("Could not start {}: {}", chromium.display(), e))
+    }
+
+    async fn launch(name: &str, chromium: &Path) -> Result<Self, String> {
+        let dir =
+            std::env::temp_dir().join(format!("birdie-harness-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+        let server = Arc::new(FixtureServer::start().await?);
+        let port = free_port()?;
+        let mut child = tokio::process::Command::new(chromium)
+            .args([
+                "--headless=new".to_string(),
+                format!("--remote-debugging-port={}", port),
+                format!("--user-data-dir={}", dir.join("profile").display()),
+                "--no-first-run".to_string(),
+                "--no-default-browser-check".to_string(),
+                "--disable-background-networking".to_string(),
+                "--disable-component-update".to_string(),
+                "--disable-sync".to_string(),
+                "--disable-extensions".to_string(),
+                // Whatever the fixture server does not answer goes nowhere
+                "--host-resolver-rules=MAP * ~NOTFOUND".to_string(),
+                // CI containers run as root, where the sandbox cannot start
+                "--no-sandbox".to_string(),
+                "about:blank".to_string(),
+            ])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+        wait_until_debuggable(&mut child, "Chromium", port).await?;
+
+        let manager = CdpManager::new(&dir);
+        manager.set_config(CdpConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+        })?;
+        Ok(Self {
+            manager,
+            server,
+            chromium: child,
+            dir,
+        })
+    }
+
+    /// Open the fixture of the app at `host` in a new tab, once it is listed
+    pub async fn open(&self, host: &str) -> (Page, TabInfo) {
+        let fixture = FIXTURES
+            .iter()
+            .find(|fixture| fixture.host == host)
+            .unwrap_or_else(|| panic!("No fixture for {}", host));
+        let browser = self.manager.browser().await.expect("connected");
+        let page = browser.new_page("about:blank").await.expect("new tab");
+        self.server.intercept(&page).await.expect("interception");
+        page.goto(fixture.url).await.expect("fixture loads");
+
+        let started = tokio::time::Instant::now();
+        loop {
+            let pages = self.manager.pages().await.expect("tabs");
+            let tab = pages.iter().find(|t| {
+                t.target_id.as_ref() == page.target_id().as_ref() && t.url == fixture.url
+            });
+            if let Some(tab) = tab {
+                return (page, TabInfo::from(tab));
+            }
+            assert!(started.elapsed() < TAB_TIMEOUT, "{} was not listed", host);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        self.manager.disconnect();
+        let _ = self.chromium.start_kill();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Run a script in the page
+pub(crate) async fn evaluate(page: &Page, script: &str) -> Value {
+    script::evaluate(page, script, script::DEFAULT_TIMEOUT)
+        .await
+        .unwrap_or_else(|e| panic!("{}: {}", script, e))
+}
+
+/// Have a message arrive the way the app renders one
+pub(crate) async fn receive(page: &Page, sender: &str, message: &str) {
+    evaluate(
+        page,
+        &format!(
+            "window.fixture.receive({}, {})",
+            serde_json::to_string(sender).unwrap(),
+            serde_json::to_string(message).unwrap()
+        ),
+    )
+    .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixture_routes() {
+        let (content_type, html) = route("/discord.com/channels/@me/1?x=1").unwrap();
+        assert_eq!(content_type, "text/html; charset=utf-8");
+        assert!(html.contains("data-slate-editor"));
+        assert_eq!(route("/web.telegram.org/chat.js").unwrap().1, CHAT_JS);
+        assert!(route("/web.telegram.org/favicon.ico").is_none());
+        assert!(route("/example.com/").is_none());
+        for fixture in FIXTURES {
+            assert!(fixture.url.contains(fixture.host));
+            assert!(fixture.html.contains(r#"<script src="/chat.js">"#));
+        }
+    }
+}
@@ -0,0 +1,314 @@
+//! Selector health
+//!
+//! Chat apps change their markup without notice, and selectors that no
+//! longer match fail silently: the monitor finds no new messages and context
+//! reads return "No messages". A health check runs every app definition, the
+//! monitor's selectors and the context mapper's script, against the open tabs
+//! of that app and counts what they match. A tab that shows a chat, with the
+//! app's composer in place, but has no messages for them is broken; when that
+//! happens to a tab that was fine, or to an app the monitor has read messages
+//! from before, a `cdp-selector-warning` event is emitted.
+
+use super::monitor;
+use super::{get_selector_for_domain, script, CdpManager, SelectorConfig, TabInfo};
+use crate::context_mapper::{ContextMapper, ContextTask};
+use chromiumoxide::Page;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+/// What the context mapper's scripts return when they find nothing
+const NO_MESSAGES: &str = "No messages";
+
+/// Matches of the monitor's selectors
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelectorCounts {
+    pub container: usize,
+    /// `None` for apps without a sender selector
+    pub sender: Option<usize>,
+    pub text: usize,
+}
+
+/// The health of one tab
+#[derive(Debug, Clone, Serialize)]
+pub struct TabHealth {
+    pub tab_id: String,
+    pub title: String,
+    pub domain: String,
+    pub app_name: String,
+    /// `None` for apps the monitor does not read
+    pub selectors: Option<SelectorCounts>,
+    /// Whether the context mapper's script found messages; `None` for apps
+    /// it has no task for
+    pub context_found: Option<bool>,
+    /// The tab shows a chat: the app's composer is there, and its chat list
+    /// if it has one
+    pub is_chat: bool,
+    /// A chat in which the app's selectors find no messages
+    pub broken: bool,
+    /// When the monitor last read messages from this app
+    pub last_extraction: Option<String>,
+    /// Why the tab could not be checked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// What the health script counts
+#[derive(Debug, Deserialize)]
+struct Counts {
+    container: Option<usize>,
+    sender: Option<usize>,
+    text: Option<usize>,
+    composer: Option<usize>,
+    conversations: Option<usize>,
+}
+
+/// Count the matches of each selector; a missing selector counts `null`
+fn health_script(config: Option<&SelectorConfig>, context: Option<&ContextTask>) -> String {
+    let reply = context.and_then(|c| c.reply.as_ref());
+    let quote = |selector: Option<&str>| serde_json::to_string(&selector).unwrap_or_default();
+    format!(
+        r#"
+        (function() {{
+            const count = selector => selector ? document.querySelectorAll(selector).length : null;
+            return {{
+                container: count({container}),
+                sender: count({sender}),
+                text: count({text}),
+                composer: count({composer}),
+                conversations: count({conversations}),
+            }};
+        }})()
+        "#,
+        container = quote(config.map(|c| c.container_selector)),
+        sender = quote(config.and_then(|c| c.sender_selector)),
+        text = quote(config.map(|c| c.message_selector)),
+        composer = quote(reply.map(|r| r.composer.as_str())),
+        conversations = quote(reply.and_then(|r| r.conversation.as_deref())),
+    )
+}
+
+impl Counts {
+    fn selectors(&self) -> Option<SelectorCounts> {
+        Some(SelectorCounts {
+            container: self.container?,
+            sender: self.sender,
+            text: self.text?,
+        })
+    }
+
+    fn is_chat(&self) -> bool {
+        self.composer.unwrap_or(0) > 0 && self.conversations.is_none_or(|n| n > 0)
+    }
+}
+
+/// Whether a chat's selectors find nothing; a missing sender alone is not
+/// breakage, as one-to-one chats often show none
+fn is_broken(selectors: Option<&SelectorCounts>, context_found: Option<bool>) -> bool {
+    selectors.is_some_and(|s| s.container == 0 || s.text == 0) || context_found == Some(false)
+}
+
+/// Run the app's definitions in one tab
+pub(super) async fn inspect(
+    page: &Page,
+    tab: &TabInfo,
+    config: Option<&SelectorConfig>,
+    context: Option<&ContextTask>,
+) -> TabHealth {
+    let mut health = TabHealth {
+        tab_id: tab.id.clone(),
+        title: tab.title.clone(),
+        domain: tab.domain.clone(),
+        app_name: match (context, config) {
+            (Some(context), _) => context.app_name.clone(),
+            (None, Some(config)) => monitor::app_name(&tab.domain, config.source_name),
+            (None, None) => tab.domain.clone(),
+        },
+        selectors: None,
+        context_found: None,
+        is_chat: false,
+        broken: false,
+        last_extraction: None,
+        error: None,
+    };
+
+    let counts = script::evaluate(page, &health_script(config, context), CHECK_TIMEOUT)
+        .await
+        .and_then(|value| {
+            serde_json::from_value::<Counts>(value)
+                .map_err(|e| format!("Resultado inesperado: {}", e))
+        });
+    let counts = match counts {
+        Ok(counts) => counts,
+        Err(e) => {
+            health.error = Some(e);
+            return health;
+        }
+    };
+    health.selectors = counts.selectors();
+    health.is_chat = counts.is_chat();
+
+    if let Some(context) = context {
+        match script::evaluate(page, &context.css_selector, CHECK_TIMEOUT).await {
+            Ok(found) => {
+                let found = found.as_str().map(str::trim).unwrap_or_default();
+                health.context_found = Some(!found.is_empty() && found != NO_MESSAGES);
+            }
+            Err(e) => health.error = Some(e),
+        }
+    }
+    health.broken = health.is_chat && is_broken(health.selectors.as_ref(), health.context_found);
+    health
+}
+
+#[derive(Default)]
+struct State {
+    /// When messages were last read, by app source
+    extracted: HashMap<String, DateTime<Utc>>,
+    /// Tabs whose last check was fine
+    healthy: HashSet<String>,
+    /// Broken tabs already warned about
+    warned: HashSet<String>,
+}
+
+/// Extraction times and check results, held in Tauri managed state
+#[derive(Default)]
+pub struct SelectorHealth {
+    state: Mutex<State>,
+}
+
+impl SelectorHealth {
+    /// Note that messages were just read from the app `source`
+    pub fn extracted(&self, source: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .extracted
+            .insert(source.to_string(), Utc::now());
+    }
+
+    pub fn last_extraction(&self, source: &str) -> Option<DateTime<Utc>> {
+        self.state.lock().unwrap().extracted.get(source).copied()
+    }
+
+    /// Record a check of a tab; true when it has just broken, having been
+    /// fine before or belonging to an app that was read before
+    fn record(&self, tab_id: &str, broken: bool, read_before: bool) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if !broken {
+            state.healthy.insert(tab_id.to_string());
+            state.warned.remove(tab_id);
+            return false;
+        }
+        let dropped = state.healthy.remove(tab_id) || read_before;
+        dropped && state.warned.insert(tab_id.to_string())
+    }
+
+    /// Forget tabs that were closed
+    fn retain(&self, open: &HashSet<String>) {
+        let mut state = self.state.lock().unwrap();
+        state.healthy.retain(|id| open.contains(id));
+        state.warned.retain(|id| open.contains(id));
+    }
+}
+
+/// Check every open tab of a known app, warning about those that broke
+pub async fn check(app: &AppHandle) -> Result<Vec<TabHealth>, String> {
+    let manager = app.state::<CdpManager>();
+    let health = app.state::<SelectorHealth>();
+    let tabs: Vec<TabInfo> = manager.pages().await?.iter().map(TabInfo::from).collect();
+    let contexts = ContextMapper::new().get_enabled_contexts();
+
+    let mut report = Vec::new();
+    for tab in &tabs {
+        let config = get_selector_for_domain(&tab.domain);
+        let context = contexts.iter().find(|c| {
+            c.url_patterns
+                .iter()
+                .any(|p| tab.domain.contains(p.as_str()))
+        });
+        if config.is_none() && context.is_none() {
+            continue;
+        }
+
+        let mut checked = match manager.page(&tab.id).await {
+            Ok(page) => inspect(&page, tab, config.as_ref(), context).await,
+            Err(e) => {
+                eprintln!("⚠ Selector health, '{}': {}", tab.title, e);
+                continue;
+            }
+        };
+        let last = config.and_then(|c| health.last_extraction(c.source_name));
+        checked.last_extraction = last.map(|at| at.to_rfc3339());
+        if health.record(&tab.id, checked.broken, last.is_some()) {
+            eprintln!(
+                "⚠ The {} selectors find no messages in '{}'",
+                checked.app_name, checked.title
+            );
+            if let Err(e) = app.emit("cdp-selector-warning", &checked) {
+                eprintln!("✗ Error emitting selector warning: {}", e);
+            }
+        }
+        report.push(checked);
+    }
+    health.retain(&tabs.iter().map(|tab| tab.id.clone()).collect());
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_script() {
+        let config = get_selector_for_domain("web.whatsapp.com").unwrap();
+        let mapper = ContextMapper::new();
+        let context = mapper.find_context("WhatsApp").unwrap();
+        let script = health_script(Some(&config), Some(&context));
+        assert!(script.contains(r#"container: count("[data-testid='msg-container']")"#));
+        assert!(script.contains(r##"conversations: count("#pane-side [role=\"listitem\"]")"##));
+        // Apps the monitor does not read have no selector counts
+        let script = health_script(None, Some(&context));
+        assert!(script.contains("container: count(null)"));
+    }
+
+    #[test]
+    fn test_broken_chats() {
+        let counts = |container, sender, text| SelectorCounts {
+            container,
+            sender,
+            text,
+        };
+        assert!(!is_broken(Some(&counts(3, Some(3), 3)), Some(true)));
+        // One-to-one chats may show no sender
+        assert!(!is_broken(Some(&counts(3, Some(0), 3)), None));
+        assert!(is_broken(Some(&counts(3, Some(3), 0)), Some(true)));
+        assert!(is_broken(Some(&counts(0, None, 0)), None));
+        assert!(is_broken(None, Some(false)));
+        assert!(!is_broken(None, None));
+    }
+
+    #[test]
+    fn test_warns_once_when_a_tab_breaks() {
+        let health = SelectorHealth::default();
+        // Never fine and never read: an empty chat, not a broken one
+        assert!(!health.record("tab", true, false));
+        assert!(!health.record("tab", false, false));
+        assert!(health.record("tab", true, false));
+        assert!(!health.record("tab", true, false));
+
+        // An app that was read before is broken from the first check
+        assert!(health.record("other", true, true));
+        assert!(!health.record("other", true, true));
+        health.retain(&HashSet::new());
+        assert!(health.record("other", true, true));
+
+        assert!(health.last_extraction("discord").is_none());
+        health.extracted("discord");
+        assert!(health.last_extraction("discord").is_some());
+    }
+}
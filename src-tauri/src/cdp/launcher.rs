@@ -0,0 +1,507 @@
+//! Launching a browser for birdie to drive
+//!
+//! CDP needs a Chromium-family browser started with `--remote-debugging-port`.
+//! Instead of asking the user to run a script, birdie finds the installed
+//! browsers and starts one on a free port with its own profile directory,
+//! so the user's everyday browser is left alone. Chrome 136 and later ignore
+//! the flag on the default profile anyway.
+//!
+//! The launched process is supervised: when it exits, the connection to it is
+//! dropped and `cdp-browser-exited` is emitted. A browser that is already
+//! listening on the configured endpoint is used as is; `/json/version` tells
+//! a debuggable browser apart from some other program holding the port.
+
+use super::manager::{CdpConfig, CdpManager};
+use serde::{Deserialize, Serialize};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::oneshot;
+
+/// Time a launched browser has to open its debugging port
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(15);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BrowserKind {
+    Chrome,
+    Chromium,
+    Edge,
+    Brave,
+}
+
+impl BrowserKind {
+    /// In order of preference
+    const ALL: [BrowserKind; 4] = [Self::Chrome, Self::Chromium, Self::Edge, Self::Brave];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Chrome => "Google Chrome",
+            Self::Chromium => "Chromium",
+            Self::Edge => "Microsoft Edge",
+            Self::Brave => "Brave",
+        }
+    }
+
+    /// Names the browser's profile directory
+    fn id(self) -> &'static str {
+        match self {
+            Self::Chrome => "chrome",
+            Self::Chromium => "chromium",
+            Self::Edge => "edge",
+            Self::Brave => "brave",
+        }
+    }
+
+    /// Executable names looked up on `PATH`
+    #[cfg(target_os = "linux")]
+    fn programs(self) -> &'static [&'static str] {
+        match self {
+            Self::Chrome => &["google-chrome", "google-chrome-stable"],
+            Self::Chromium => &["chromium", "chromium-browser"],
+            Self::Edge => &["microsoft-edge", "microsoft-edge-stable"],
+            Self::Brave => &["brave-browser", "brave"],
+        }
+    }
+
+    /// Install locations of the executable
+    #[cfg(target_os = "macos")]
+    fn paths(self) -> Vec<PathBuf> {
+        let app = match self {
+            Self::Chrome => "Google Chrome",
+            Self::Chromium => "Chromium",
+            Self::Edge => "Microsoft Edge",
+            Self::Brave => "Brave Browser",
+        };
+        let bundle = format!("{0}.app/Contents/MacOS/{0}", app);
+        let mut paths = vec![Path::new("/Applications").join(&bundle)];
+        if let Some(home) = std::env::var_os("HOME") {
+            paths.push(Path::new(&home).join("Applications").join(&bundle));
+        }
+        paths
+    }
+
+    /// Install locations of the executable
+    #[cfg(target_os = "windows")]
+    fn paths(self) -> Vec<PathBuf> {
+        let relative = match self {
+            Self::Chrome => r"Google\Chrome\Application\chrome.exe",
+            Self::Chromium => r"Chromium\Application\chrome.exe",
+            Self::Edge => r"Microsoft\Edge\Application\msedge.exe",
+            Self::Brave => r"BraveSoftware\Brave-Browser\Application\brave.exe",
+        };
+        ["ProgramFiles", "ProgramFiles(x86)", "LOCALAPPDATA"]
+            .iter()
+            .filter_map(std::env::var_os)
+            .map(|root| Path::new(&root).join(relative))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InstalledBrowser {
+    pub kind: BrowserKind,
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// The first of `programs` found in `dirs`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn search(dirs: &[PathBuf], programs: &[&str]) -> Option<PathBuf> {
+    programs
+        .iter()
+        .flat_map(|program| dirs.iter().map(move |dir| dir.join(program)))
+        .find(|path| path.is_file())
+}
+
+/// Chromium-family browsers installed on this machine, preferred first
+pub fn discover() -> Vec<InstalledBrowser> {
+    #[cfg(target_os = "linux")]
+    let find = {
+        let dirs: Vec<PathBuf> = std::env::var_os("PATH")
+            .map(|path| std::env::split_paths(&path).collect())
+            .unwrap_or_default();
+        move |kind: BrowserKind| search(&dirs, kind.programs())
+    };
+    #[cfg(any(target_os = "macos", target_os = "windows"))]
+    let find = |kind: BrowserKind| kind.paths().into_iter().find(|path| path.is_file());
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    let find = |_: BrowserKind| None::<PathBuf>;
+
+    BrowserKind::ALL
+        .into_iter()
+        .filter_map(|kind| {
+            Some(InstalledBrowser {
+                kind,
+                name: kind.name().to_string(),
+                path: find(kind)?,
+            })
+        })
+        .collect()
+}
+
+/// A port nothing listens on right now
+pub(super) fn free_port() -> Result<u16, String> {
+    TcpListener::bind(("127.0.0.1", 0))
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .map_err(|e| format!("No se encontró un puerto libre: {}", e))
+}
+
+/// What answers on a DevTools endpoint
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum EndpointStatus {
+    /// A browser with remote debugging enabled
+    Debuggable {
+        browser: String,
+    },
+    /// Something that is not a DevTools endpoint
+    Busy,
+    Closed,
+}
+
+#[derive(Deserialize)]
+struct Version {
+    #[serde(rename = "Browser")]
+    browser: String,
+}
+
+pub async fn probe(config: &CdpConfig) -> EndpointStatus {
+    let version = async {
+        reqwest::Client::new()
+            .get(format!("{}/json/version", config.endpoint()))
+            .timeout(PROBE_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Version>()
+            .await
+    };
+    if let Ok(version) = version.await {
+        return EndpointStatus::Debuggable {
+            browser: version.browser,
+        };
+    }
+    let connect = tokio::net::TcpStream::connect((config.host.as_str(), config.port));
+    if let Ok(Ok(_)) = tokio::time::timeout(PROBE_TIMEOUT, connect).await {
+        EndpointStatus::Busy
+    } else {
+        EndpointStatus::Closed
+    }
+}
+
+/// Wait for a started program to open its debugging port, unless it exits
+/// first; a program still not listening in time is killed
+pub(super) async fn wait_until_debuggable(
+    child: &mut tokio::process::Child,
+    name: &str,
+    port: u16,
+) -> Result<(), String> {
+    let config = CdpConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+    };
+    let started = tokio::time::Instant::now();
+    loop {
+        if let Ok(Some(status)) = child.try_wait() {
+            return Err(format!("{} se cerró al arrancar ({})", name, status));
+        }
+        if let EndpointStatus::Debuggable { .. } = probe(&config).await {
+            return Ok(());
+        }
+        if started.elapsed() > STARTUP_TIMEOUT {
+            let _ = child.kill().await;
+            return Err(format!(
+                "{} no abrió el puerto de depuración {} a tiempo",
+                name, port
+            ));
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
+
+/// The browser birdie started
+#[derive(Debug, Clone, Serialize)]
+pub struct ManagedBrowser {
+    pub kind: BrowserKind,
+    pub name: String,
+    pub port: u16,
+    pub pid: Option<u32>,
+}
+
+struct Supervised {
+    browser: ManagedBrowser,
+    kill: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+/// Why CDP features cannot reach a browser, and what to do about it
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostics {
+    pub endpoint: String,
+    pub status: EndpointStatus,
+    pub browsers: Vec<InstalledBrowser>,
+    pub managed: Option<ManagedBrowser>,
+    /// Steps to follow, in the order to try them
+    pub hints: Vec<String>,
+}
+
+fn hints(
+    config: &CdpConfig,
+    status: &EndpointStatus,
+    browsers: &[InstalledBrowser],
+) -> Vec<String> {
+    let mut hints = Vec::new();
+    match status {
+        EndpointStatus::Debuggable { .. } => return hints,
+        EndpointStatus::Busy => hints.push(format!(
+            "El puerto {} está ocupado por un programa que no es un navegador con \
+            depuración remota. Usa otro puerto.",
+            config.port
+        )),
+        EndpointStatus::Closed => hints.push(format!(
+            "Ningún navegador escucha en {}.",
+            config.endpoint()
+        )),
+    }
+    match browsers.first() {
+        Some(browser) => {
+            hints.push(format!(
+                "Birdie puede abrir {} con un perfil propio y depuración activada.",
+                browser.name
+            ));
+            hints.push(
+                "Para usar tu navegador de siempre, ciérralo del todo y ábrelo con \
+                --remote-debugging-port y un --user-data-dir distinto del habitual: \
+                desde Chrome 136 el puerto se ignora con el perfil por defecto."
+                    .to_string(),
+            );
+        }
+        None => hints.push(
+            "No se encontró Chrome, Chromium, Edge ni Brave. Instala uno de ellos.".to_string(),
+        ),
+    }
+    hints
+}
+
+/// The launched browser, held in Tauri managed state
+pub struct BrowserLauncher {
+    /// Holds one profile per browser, since their profiles are incompatible
+    profile_dir: PathBuf,
+    running: Arc<Mutex<Option<Supervised>>>,
+}
+
+impl BrowserLauncher {
+    pub fn new(config_dir: &Path) -> Self {
+        Self {
+            profile_dir: config_dir.join("browser-profile"),
+            running: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn managed(&self) -> Option<ManagedBrowser> {
+        self.running
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|s| s.browser.clone())
+    }
+
+    pub async fn diagnose(&self, manager: &CdpManager) -> Diagnostics {
+        let config = manager.config();
+        let status = probe(&config).await;
+        let browsers = discover();
+        Diagnostics {
+            endpoint: config.endpoint(),
+            hints: hints(&config, &status, &browsers),
+            status,
+            browsers,
+            managed: self.managed(),
+        }
+    }
+
+    /// Make a debuggable browser available and point the connection at it
+    ///
+    /// A browser already listening on the configured endpoint, or one birdie
+    /// launched before, is reused; otherwise `kind`, or the first browser
+    /// found, is started.
+    pub async fn ensure(&self, app: &AppHandle, kind: Option<BrowserKind>) -> Result<(), String> {
+        let manager = app.state::<CdpManager>();
+        if let Some(browser) = self.managed() {
+            if kind.is_none_or(|kind| kind == browser.kind) {
+                point_at(&manager, browser.port);
+                return Ok(());
+            }
+            self.close().await;
+        }
+        if kind.is_none() {
+            if let EndpointStatus::Debuggable { .. } = probe(&manager.config()).await {
+                return Ok(());
+            }
+        }
+
+        let browsers = discover();
+        let browser = match kind {
+            Some(kind) => browsers
+                .into_iter()
+                .find(|b| b.kind == kind)
+                .ok_or_else(|| format!("{} no está instalado", kind.name()))?,
+            None => browsers
+                .into_iter()
+                .next()
+                .ok_or_else(|| "No se encontró Chrome, Chromium, Edge ni Brave".to_string())?,
+        };
+        let port = free_port()?;
+        self.launch(app, &browser, port).await?;
+        point_at(&manager, port);
+        Ok(())
+    }
+
+    async fn launch(
+        &self,
+        app: &AppHandle,
+        browser: &InstalledBrowser,
+        port: u16,
+    ) -> Result<(), String> {
+        let profile_dir = self.profile_dir.join(browser.kind.id());
+        std::fs::create_dir_all(&profile_dir)
+            .map_err(|e| format!("No se pudo crear el perfil del navegador: {}", e))?;
+        let mut child = tokio::process::Command::new(&browser.path)
+            .arg(format!("--remote-debugging-port={}", port))
+            .arg(format!("--user-data-dir={}", profile_dir.display()))
+            .args(["--no-first-run", "--no-default-browser-check"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            // Not left running if birdie exits without closing it
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("No se pudo abrir {}: {}", browser.name, e))?;
+
+        wait_until_debuggable(&mut child, &browser.name, port).await?;
+
+        let managed = ManagedBrowser {
+            kind: browser.kind,
+            name: browser.name.clone(),
+            port,
+            pid: child.id(),
+        };
+        let (kill, killed) = oneshot::channel();
+        let running = self.running.clone();
+        let app = app.clone();
+        let supervised = managed.clone();
+        let task = tauri::async_runtime::spawn(async move {
+            let status = tokio::select! {
+                status = child.wait() => status,
+                _ = killed => {
+                    let _ = child.kill().await;
+                    return;
+                }
+            };
+            // Exited on its own: the user closed it or it crashed
+            running.lock().unwrap().take();
+            let manager = app.state::<CdpManager>();
+            if manager.config().port == supervised.port {
+                manager.disconnect();
+            }
+            let code = status.ok().and_then(|s| s.code());
+            eprintln!("⚠ {} exited ({:?})", supervised.name, code);
+            if let Err(e) = app.emit("cdp-browser-exited", &supervised) {
+                eprintln!("✗ Error emitting browser exit: {}", e);
+            }
+        });
+
+        *self.running.lock().unwrap() = Some(Supervised {
+            browser: managed,
+            kill,
+            task,
+        });
+        Ok(())
+    }
+
+    /// Close the launched browser, if any
+    pub async fn close(&self) -> Option<ManagedBrowser> {
+        let supervised = self.running.lock().unwrap().take()?;
+        let _ = supervised.kill.send(());
+        let _ = supervised.task.await;
+        Some(supervised.browser)
+    }
+}
+
+/// Connect to the launched browser for this session only; its port is
+/// random, so it is not saved over the user's endpoint
+fn point_at(manager: &CdpManager, port: u16) {
+    manager.use_endpoint(CdpConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search() {
+        let dir = std::env::temp_dir().join(format!("birdie-browsers-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("chromium-browser"), "").unwrap();
+        let dirs = vec![PathBuf::from("/nonexistent"), dir.clone()];
+
+        assert_eq!(
+            search(&dirs, &["chromium", "chromium-browser"]),
+            Some(dir.join("chromium-browser"))
+        );
+        assert_eq!(search(&dirs, &["brave"]), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_free_port() {
+        let port = free_port().unwrap();
+        assert_ne!(port, 0);
+        assert!(TcpListener::bind(("127.0.0.1", port)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_probe_tells_busy_from_closed() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let config = CdpConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+        };
+        // Accepts connections but never answers HTTP
+        assert_eq!(probe(&config).await, EndpointStatus::Busy);
+        drop(listener);
+        assert_eq!(probe(&config).await, EndpointStatus::Closed);
+    }
+
+    #[test]
+    fn test_hints() {
+        let config = CdpConfig::default();
+        let chromium = InstalledBrowser {
+            kind: BrowserKind::Chromium,
+            name: "Chromium".to_string(),
+            path: PathBuf::from("/usr/bin/chromium"),
+        };
+        let debuggable = EndpointStatus::Debuggable {
+            browser: "Chrome/126.0".to_string(),
+        };
+        assert!(hints(&config, &debuggable, &[]).is_empty());
+
+        let closed = hints(&config, &EndpointStatus::Closed, &[chromium]);
+        assert!(closed[0].contains("http://localhost:9222"));
+        assert!(closed[1].contains("Chromium"));
+
+        let busy = hints(&config, &EndpointStatus::Busy, &[]);
+        assert!(busy[0].contains("9222"));
+        assert!(busy[1].contains("Instala"));
+    }
+}
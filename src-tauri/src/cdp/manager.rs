@@ -0,0 +1,737 @@
+//! The shared browser connection
+//!
+//! One connection to the browser's DevTools endpoint is opened on first use
+//! and kept in Tauri managed state, so commands, context extraction and
+//! replies all go through it. chromiumoxide only processes messages while its
+//! `Handler` is polled: the handler runs on its own task for as long as the
+//! connection lives, and the state goes back to disconnected when it ends.
+//!
+//! The targets of each connection are tracked through the `Target` domain's
+//! events, so listing tabs needs no round trip, and every change is emitted
+//! as `cdp-tab`. A connection that drops is opened again with a growing
+//! delay until it comes back or is disconnected; its state is emitted as
+//! `cdp-connection-state` on every change.
+//!
+//! Desktop apps attached over their own debugging port get a connection
+//! each; their windows are listed with the browser's tabs, and a tab is
+//! reached through the connection it was listed from.
+
+use super::desktop::DesktopApp;
+use super::TabInfo;
+use crate::settings;
+use chromiumoxide::browser::Browser;
+use chromiumoxide::cdp::browser_protocol::target::{
+    EventTargetCreated, EventTargetDestroyed, EventTargetInfoChanged, GetTargetsParams, TargetId,
+    TargetInfo,
+};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::listeners::EventStream;
+use chromiumoxide::Page;
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{oneshot, watch};
+
+/// Time allowed to reach the endpoint and open the websocket
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const RECONNECT_MIN_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Where the browser's DevTools endpoint listens
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CdpConfig {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Default for CdpConfig {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 9222,
+        }
+    }
+}
+
+impl CdpConfig {
+    pub fn endpoint(&self) -> String {
+        format!("http://{}:{}", self.host, self.port)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ConnectionState {
+    Disconnected,
+    Connecting,
+    Connected {
+        /// Product and version, such as "Chrome/126.0.6478.126"
+        browser: String,
+        endpoint: String,
+    },
+    /// The connection dropped and is being opened again
+    Reconnecting {
+        attempt: u32,
+        retry_in_ms: u64,
+        error: String,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+/// Carried by the `cdp-connection-state` event
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionEvent {
+    /// "browser", or the id of a desktop app
+    pub source: &'static str,
+    #[serde(flatten)]
+    pub state: ConnectionState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TabChange {
+    Created,
+    Changed,
+    Destroyed,
+}
+
+/// Carried by the `cdp-tab` event
+#[derive(Debug, Clone, Serialize)]
+pub struct TabEvent {
+    pub source: &'static str,
+    pub change: TabChange,
+    pub tab_id: String,
+    /// `None` for closed tabs
+    pub tab: Option<TabInfo>,
+}
+
+/// Delay before reconnection attempt `attempt`, counting from 1
+fn backoff(attempt: u32) -> Duration {
+    let delay = RECONNECT_MIN_DELAY.saturating_mul(1 << attempt.saturating_sub(1).min(16));
+    delay.min(RECONNECT_MAX_DELAY)
+}
+
+/// Where state and tab changes are reported, shared by every connection
+#[derive(Clone)]
+struct Events {
+    app: Arc<OnceLock<AppHandle>>,
+    /// Bumped whenever the open tabs change or a connection opens
+    revision: Arc<watch::Sender<u64>>,
+    /// Connections opened so far
+    opened: Arc<AtomicU64>,
+}
+
+impl Events {
+    fn new() -> Self {
+        Self {
+            app: Arc::new(OnceLock::new()),
+            revision: Arc::new(watch::channel(0).0),
+            opened: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    fn emit<T: Serialize + Clone>(&self, event: &str, payload: T) {
+        let Some(app) = self.app.get() else {
+            return;
+        };
+        if let Err(e) = app.emit(event, payload) {
+            eprintln!("✗ Error emitting {}: {}", event, e);
+        }
+    }
+
+    fn bump(&self) {
+        self.revision.send_modify(|revision| *revision += 1);
+    }
+}
+
+struct Connection {
+    browser: Option<Arc<Browser>>,
+    state: ConnectionState,
+    /// Tells a finished handler task whether its connection is still current
+    generation: u64,
+    /// Every target of the open connection, newest first
+    targets: Vec<TargetInfo>,
+}
+
+/// A connection to one DevTools endpoint
+struct Link {
+    /// "browser", or the id of a desktop app
+    source: &'static str,
+    /// Named in errors: "Chrome", or the desktop app
+    product: &'static str,
+    config: Mutex<CdpConfig>,
+    connection: Arc<Mutex<Connection>>,
+    /// Serializes connection attempts
+    connecting: tokio::sync::Mutex<()>,
+    events: Events,
+}
+
+impl Link {
+    fn new(source: &'static str, product: &'static str, config: CdpConfig, events: Events) -> Self {
+        Self {
+            source,
+            product,
+            config: Mutex::new(config),
+            connection: Arc::new(Mutex::new(Connection {
+                browser: None,
+                state: ConnectionState::Disconnected,
+                generation: 0,
+                targets: Vec::new(),
+            })),
+            connecting: tokio::sync::Mutex::new(()),
+            events,
+        }
+    }
+
+    fn config(&self) -> CdpConfig {
+        self.config.lock().unwrap().clone()
+    }
+
+    fn state(&self) -> ConnectionState {
+        self.connection.lock().unwrap().state.clone()
+    }
+
+    fn set_state(&self, state: ConnectionState) {
+        self.connection.lock().unwrap().state = state.clone();
+        self.emit_state(state);
+    }
+
+    fn emit_state(&self, state: ConnectionState) {
+        self.events.emit(
+            "cdp-connection-state",
+            ConnectionEvent {
+                source: self.source,
+                state,
+            },
+        );
+    }
+
+    fn current(&self) -> Option<Arc<Browser>> {
+        self.connection.lock().unwrap().browser.clone()
+    }
+
+    fn is_current(&self, generation: u64) -> bool {
+        self.connection.lock().unwrap().generation == generation
+    }
+
+    async fn browser(self: &Arc<Self>) -> Result<Arc<Browser>, String> {
+        if let Some(browser) = self.current() {
+            return Ok(browser);
+        }
+        let _connecting = self.connecting.lock().await;
+        // Another caller may have connected while this one waited
+        if let Some(browser) = self.current() {
+            return Ok(browser);
+        }
+
+        if !matches!(self.state(), ConnectionState::Reconnecting { .. }) {
+            self.set_state(ConnectionState::Connecting);
+        }
+        match self.open().await {
+            Ok(browser) => Ok(browser),
+            Err(e) => {
+                self.set_state(ConnectionState::Failed { error: e.clone() });
+                Err(e)
+            }
+        }
+    }
+
+    async fn open(self: &Arc<Self>) -> Result<Arc<Browser>, String> {
+        let endpoint = self.config().endpoint();
+        let unreachable = |e: String| {
+            format!(
+                "No se pudo conectar a {} en {}: {}. \
+                Asegúrate de que {} está abierto con --remote-debugging-port",
+                self.product, endpoint, e, self.product
+            )
+        };
+        let (browser, mut handler) =
+            tokio::time::timeout(CONNECT_TIMEOUT, Browser::connect(endpoint.clone()))
+                .await
+                .map_err(|_| unreachable("timeout".to_string()))?
+                .map_err(|e| unreachable(e.to_string()))?;
+        let browser = Arc::new(browser);
+
+        // The handler must run for the connection to answer, but the
+        // connection only counts as open, and only changes the generation,
+        // once it has; a failed attempt leaves a reconnect loop running
+        let (opened_as, generation) = oneshot::channel();
+        let link = self.clone();
+        let handling = tauri::async_runtime::spawn(async move {
+            let mut error = "closed".to_string();
+            while let Some(event) = handler.next().await {
+                match event {
+                    // The websocket is gone
+                    Err(CdpError::Ws(e)) => {
+                        error = e.to_string();
+                        break;
+                    }
+                    // Events this chromiumoxide version cannot parse are not fatal
+                    Err(e) => eprintln!("⚠ DevTools: {}", e),
+                    Ok(()) => {}
+                }
+            }
+            // Dropped before it opened
+            let Ok(generation) = generation.await else {
+                return;
+            };
+            {
+                let mut connection = link.connection.lock().unwrap();
+                if connection.generation != generation {
+                    // Disconnected on purpose, or replaced
+                    return;
+                }
+                connection.browser = None;
+                connection.targets.clear();
+            }
+            link.events.bump();
+            link.reconnect(generation, error).await;
+        });
+
+        let opened = async {
+            let version = browser.version().await?;
+            // Listen before the snapshot, so no change falls between them
+            let created = browser.event_listener::<EventTargetCreated>().await?;
+            let changed = browser.event_listener::<EventTargetInfoChanged>().await?;
+            let destroyed = browser.event_listener::<EventTargetDestroyed>().await?;
+            let targets = browser.execute(GetTargetsParams::default()).await?;
+            Ok::<_, CdpError>((
+                version.product,
+                targets.result.target_infos,
+                (created, changed, destroyed),
+            ))
+        };
+        let (product, targets, listeners) = match opened.await {
+            Ok(opened) => opened,
+            Err(e) => {
+                // Not a connection to keep, nor to reconnect
+                handling.abort();
+                return Err(unreachable(e.to_string()));
+            }
+        };
+
+        let state = ConnectionState::Connected {
+            browser: product,
+            endpoint,
+        };
+        let generation = {
+            let mut connection = self.connection.lock().unwrap();
+            connection.generation += 1;
+            connection.browser = Some(browser.clone());
+            connection.state = state.clone();
+            connection.targets = targets;
+            connection.generation
+        };
+        let _ = opened_as.send(generation);
+        tauri::async_runtime::spawn(self.clone().track(generation, listeners));
+        self.emit_state(state);
+        self.events.opened.fetch_add(1, Ordering::Relaxed);
+        self.events.bump();
+        Ok(browser)
+    }
+
+    /// Keep the target list current for as long as the connection lives
+    async fn track(
+        self: Arc<Self>,
+        generation: u64,
+        (mut created, mut changed, mut destroyed): (
+            EventStream<EventTargetCreated>,
+            EventStream<EventTargetInfoChanged>,
+            EventStream<EventTargetDestroyed>,
+        ),
+    ) {
+        loop {
+            let (change, tab_id, info) = tokio::select! {
+                Some(event) = created.next() => {
+                    let info = event.target_info.clone();
+                    (TabChange::Created, info.target_id.as_ref().to_string(), Some(info))
+                }
+                Some(event) = changed.next() => {
+                    let info = event.target_info.clone();
+                    (TabChange::Changed, info.target_id.as_ref().to_string(), Some(info))
+                }
+                Some(event) = destroyed.next() => {
+                    (TabChange::Destroyed, event.target_id.as_ref().to_string(), None)
+                }
+                else => break,
+            };
+            let page = {
+                let mut connection = self.connection.lock().unwrap();
+                if connection.generation != generation {
+                    break;
+                }
+                let targets = &mut connection.targets;
+                let index = targets.iter().position(|t| t.target_id.as_ref() == tab_id);
+                let was_page = index.is_some_and(|i| targets[i].r#type == "page");
+                match (&info, index) {
+                    (Some(info), Some(index)) => targets[index] = info.clone(),
+                    (Some(info), None) => targets.insert(0, info.clone()),
+                    (None, Some(index)) => {
+                        targets.remove(index);
+                    }
+                    (None, None) => {}
+                }
+                was_page || info.as_ref().is_some_and(|t| t.r#type == "page")
+            };
+            if !page {
+                continue;
+            }
+            self.events.bump();
+            self.events.emit(
+                "cdp-tab",
+                TabEvent {
+                    source: self.source,
+                    change,
+                    tab_id,
+                    tab: info.as_ref().map(TabInfo::from),
+                },
+            );
+        }
+    }
+
+    /// Open a dropped connection again, waiting longer after each failure,
+    /// until it opens or it is disconnected or reconfigured
+    ///
+    /// Boxed, as it opens connections whose handler tasks may call it again
+    fn reconnect(self: Arc<Self>, generation: u64, mut error: String) -> BoxFuture<'static, ()> {
+        Box::pin(async move {
+            eprintln!("✗ DevTools connection to {} lost: {}", self.product, error);
+            let mut attempt = 0;
+            loop {
+                attempt += 1;
+                let delay = backoff(attempt);
+                if !self.is_current(generation) {
+                    return;
+                }
+                self.set_state(ConnectionState::Reconnecting {
+                    attempt,
+                    retry_in_ms: delay.as_millis() as u64,
+                    error: error.clone(),
+                });
+                tokio::time::sleep(delay).await;
+                if !self.is_current(generation) {
+                    return;
+                }
+                match self.browser().await {
+                    Ok(_) => return,
+                    Err(e) => error = e,
+                }
+            }
+        })
+    }
+
+    fn disconnect(&self) {
+        {
+            let mut connection = self.connection.lock().unwrap();
+            connection.generation += 1;
+            connection.browser = None;
+            connection.targets.clear();
+        }
+        self.set_state(ConnectionState::Disconnected);
+        self.events.bump();
+    }
+
+    /// Whether the open connection has the target `target_id`
+    fn has_target(&self, target_id: &str) -> bool {
+        self.connection
+            .lock()
+            .unwrap()
+            .targets
+            .iter()
+            .any(|t| t.target_id.as_ref() == target_id)
+    }
+
+    /// Open tabs, without extensions, workers or devtools windows
+    async fn pages(self: &Arc<Self>) -> Result<Vec<TargetInfo>, String> {
+        self.browser().await?;
+        Ok(self
+            .connection
+            .lock()
+            .unwrap()
+            .targets
+            .iter()
+            .filter(|t| t.r#type == "page")
+            .cloned()
+            .collect())
+    }
+}
+
+/// A desktop app birdie is attached to
+struct Desktop {
+    app: &'static DesktopApp,
+    link: Arc<Link>,
+}
+
+impl Desktop {
+    async fn pages(&self) -> Result<Vec<TargetInfo>, String> {
+        let mut pages = self.link.pages().await?;
+        pages.retain(|t| self.app.is_renderer(&t.url));
+        Ok(pages)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DesktopStatus {
+    pub app: &'static DesktopApp,
+    pub state: ConnectionState,
+}
+
+/// The browser connection, and those of attached desktop apps, held in Tauri
+/// managed state
+pub struct CdpManager {
+    config_path: PathBuf,
+    link: Arc<Link>,
+    desktop: Mutex<Vec<Desktop>>,
+    events: Events,
+}
+
+impl CdpManager {
+    pub fn new(config_dir: &Path) -> Self {
+        let config_path = config_dir.join("cdp.json");
+        let events = Events::new();
+        Self {
+            link: Arc::new(Link::new(
+                "browser",
+                "Chrome",
+                settings::load(&config_path),
+                events.clone(),
+            )),
+            config_path,
+            desktop: Mutex::new(Vec::new()),
+            events,
+        }
+    }
+
+    /// Emit connection and tab events to the frontend through `app`
+    pub fn emit_to(&self, app: &AppHandle) {
+        let _ = self.events.app.set(app.clone());
+    }
+
+    /// Changes whenever a tab opens, closes or navigates, or a connection
+    /// opens or drops
+    pub fn changes(&self) -> watch::Receiver<u64> {
+        self.events.revision.subscribe()
+    }
+
+    /// Connections opened so far; pages from before a change are gone
+    pub fn opened(&self) -> u64 {
+        self.events.opened.load(Ordering::Relaxed)
+    }
+
+    pub fn config(&self) -> CdpConfig {
+        self.link.config()
+    }
+
+    /// The endpoint saved by the user, which a launched browser's does not
+    /// replace
+    pub fn saved_config(&self) -> CdpConfig {
+        settings::load(&self.config_path)
+    }
+
+    /// Change and save the endpoint; an open connection to the old one is
+    /// closed
+    pub fn set_config(&self, config: CdpConfig) -> Result<(), String> {
+        if config.host.trim().is_empty() || config.port == 0 {
+            return Err("Invalid DevTools endpoint".to_string());
+        }
+        settings::save(&self.config_path, &config)?;
+        self.use_endpoint(config);
+        Ok(())
+    }
+
+    /// Connect to `config` until the app exits or the endpoint is set again,
+    /// without saving it
+    pub fn use_endpoint(&self, config: CdpConfig) {
+        if config == self.config() {
+            return;
+        }
+        *self.link.config.lock().unwrap() = config;
+        self.disconnect();
+    }
+
+    /// State of the browser connection
+    pub fn state(&self) -> ConnectionState {
+        self.link.state()
+    }
+
+    /// The open browser connection, connecting first if needed
+    pub async fn browser(&self) -> Result<Arc<Browser>, String> {
+        self.link.browser().await
+    }
+
+    /// Drop the browser connection; the next call connects again
+    pub fn disconnect(&self) {
+        self.link.disconnect();
+    }
+
+    /// Tabs of the browser only
+    pub async fn browser_pages(&self) -> Result<Vec<TargetInfo>, String> {
+        self.link.pages().await
+    }
+
+    fn desktops(&self) -> Vec<(&'static DesktopApp, Arc<Link>)> {
+        self.desktop
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|d| (d.app, d.link.clone()))
+            .collect()
+    }
+
+    /// Tabs of the browser and windows of attached desktop apps
+    ///
+    /// With desktop apps attached, a browser that cannot be reached only
+    /// leaves its tabs out.
+    pub async fn pages(&self) -> Result<Vec<TargetInfo>, String> {
+        let desktops = self.desktops();
+        let mut pages = match self.link.pages().await {
+            Ok(pages) => pages,
+            Err(e) if desktops.is_empty() => return Err(e),
+            Err(_) => Vec::new(),
+        };
+        for (app, link) in desktops {
+            let desktop = Desktop { app, link };
+            match desktop.pages().await {
+                Ok(windows) => pages.extend(windows),
+                Err(e) => eprintln!("⚠ {}: {}", app.name, e),
+            }
+        }
+        Ok(pages)
+    }
+
+    /// A tab of the browser or a window of an attached desktop app
+    pub async fn page(&self, target_id: &str) -> Result<Page, String> {
+        // Each connection tracks its targets, so the owner is known even if
+        // the tabs were never listed
+        let desktop = self
+            .desktops()
+            .into_iter()
+            .map(|(_, link)| link)
+            .find(|link| link.has_target(target_id));
+        let browser = match desktop {
+            Some(link) => link.browser().await?,
+            None => self.browser().await?,
+        };
+        browser
+            .get_page(TargetId::new(target_id))
+            .await
+            .map_err(|e| format!("No se pudo acceder a la pestaña {}: {}", target_id, e))
+    }
+
+    /// Connect to a desktop app listening on `port`; returns its windows
+    pub async fn attach_desktop(
+        &self,
+        app: &'static DesktopApp,
+        port: u16,
+    ) -> Result<Vec<TargetInfo>, String> {
+        let desktop = Desktop {
+            app,
+            link: Arc::new(Link::new(
+                app.id,
+                app.name,
+                CdpConfig {
+                    host: "127.0.0.1".to_string(),
+                    port,
+                },
+                self.events.clone(),
+            )),
+        };
+        let windows = desktop.pages().await?;
+        self.detach_desktop(app.id);
+        self.desktop.lock().unwrap().push(desktop);
+        Ok(windows)
+    }
+
+    /// Drop the connection to a desktop app; the app keeps running
+    pub fn detach_desktop(&self, id: &str) -> bool {
+        let mut desktop = self.desktop.lock().unwrap();
+        let Some(index) = desktop.iter().position(|d| d.app.id == id) else {
+            return false;
+        };
+        desktop.remove(index).link.disconnect();
+        true
+    }
+
+    /// Windows of one attached desktop app
+    pub async fn desktop_pages(&self, id: &str) -> Result<Vec<TargetInfo>, String> {
+        let (app, link) = self
+            .desktops()
+            .into_iter()
+            .find(|(app, _)| app.id == id)
+            .ok_or_else(|| format!("{} no está conectado", id))?;
+        Desktop { app, link }.pages().await
+    }
+
+    pub fn desktop_status(&self) -> Vec<DesktopStatus> {
+        self.desktops()
+            .into_iter()
+            .map(|(app, link)| DesktopStatus {
+                app,
+                state: link.state(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config() {
+        let dir = std::env::temp_dir().join(format!("birdie-cdp-{}", std::process::id()));
+        let manager = CdpManager::new(&dir);
+        assert_eq!(manager.config().endpoint(), "http://localhost:9222");
+        assert_eq!(manager.state(), ConnectionState::Disconnected);
+        let changes = manager.changes();
+
+        assert!(manager
+            .set_config(CdpConfig {
+                host: "".to_string(),
+                port: 9222
+            })
+            .is_err());
+        manager
+            .set_config(CdpConfig {
+                host: "127.0.0.1".to_string(),
+                port: 9333,
+            })
+            .unwrap();
+        // The old connection is dropped, which monitors are told about
+        assert!(changes.has_changed().unwrap());
+        assert_eq!(
+            CdpManager::new(&dir).config().endpoint(),
+            "http://127.0.0.1:9333"
+        );
+
+        // A launched browser's port is used for this session only
+        manager.use_endpoint(CdpConfig {
+            host: "127.0.0.1".to_string(),
+            port: 41234,
+        });
+        assert_eq!(manager.config().endpoint(), "http://127.0.0.1:41234");
+        assert_eq!(manager.saved_config().endpoint(), "http://127.0.0.1:9333");
+        assert_eq!(
+            CdpManager::new(&dir).config().endpoint(),
+            "http://127.0.0.1:9333"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1), Duration::from_millis(500));
+        assert_eq!(backoff(2), Duration::from_secs(1));
+        assert_eq!(backoff(4), Duration::from_secs(4));
+        assert_eq!(backoff(7), Duration::from_secs(30));
+        assert_eq!(backoff(u32::MAX), Duration::from_secs(30));
+    }
+}
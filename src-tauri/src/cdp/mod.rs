@@ -0,0 +1,597 @@
+mod desktop;
+mod frames;
+#[cfg(test)]
+pub(crate) mod harness;
+#[cfg(test)]
+mod e2e;
+mod health;
+mod launcher;
+mod manager;
+mod monitor;
+mod observer;
+mod script;
+
+pub use health::SelectorHealth;
+pub use launcher::BrowserLauncher;
+pub use manager::{CdpManager, ConnectionState};
+pub use monitor::{CdpMonitor, MonitorMode};
+
+use chromiumoxide::cdp::browser_protocol::target::TargetInfo;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tauri::{Manager, State};
+
+/// Browser connection result
+#[derive(Debug, Serialize)]
+pub struct ConnectionResult {
+    pub success: bool,
+    pub message: String,
+    pub tabs_count: usize,
+    /// What is wrong with the setup, when the connection failed
+    pub diagnostics: Option<launcher::Diagnostics>,
+}
+
+/// Information about a browser tab
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TabInfo {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub domain: String,
+    pub has_selector: bool,
+}
+
+impl From<&TargetInfo> for TabInfo {
+    fn from(target: &TargetInfo) -> Self {
+        let domain = extract_domain(&target.url);
+        Self {
+            id: target.target_id.as_ref().to_string(),
+            title: target.title.clone(),
+            url: target.url.clone(),
+            has_selector: has_selector_for_domain(&domain),
+            domain,
+        }
+    }
+}
+
+/// Message detected from a tab
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CDPMessage {
+    pub tab_id: String,
+    pub tab_title: String,
+    pub domain: String,
+    pub sender: String,
+    pub message: String,
+    pub timestamp: String,
+    pub source: String,
+    /// The sender's account id, known when read from the app's traffic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender_id: Option<String>,
+    /// The channel or conversation id, known when read from the app's traffic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+}
+
+/// Result of script execution
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScriptResult {
+    pub success: bool,
+    /// The script's return value, awaited if it is a promise
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
+/// Monitoring status
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MonitoringStatus {
+    pub is_monitoring: bool,
+    pub tabs_monitored: usize,
+    pub interval_ms: u64,
+    pub mode: MonitorMode,
+    /// New messages found since monitoring started
+    pub messages_detected: usize,
+}
+
+impl MonitoringStatus {
+    fn new(is_monitoring: bool, run: Option<monitor::Run>) -> Self {
+        let Some(run) = run else {
+            return Self {
+                is_monitoring: false,
+                tabs_monitored: 0,
+                interval_ms: 0,
+                mode: MonitorMode::default(),
+                messages_detected: 0,
+            };
+        };
+        Self {
+            is_monitoring,
+            tabs_monitored: run.stats.tabs_monitored.load(Ordering::Relaxed),
+            interval_ms: run.interval_ms,
+            mode: run.mode,
+            messages_detected: run.stats.messages_detected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Configuration for domain-specific selectors
+#[derive(Debug, Clone)]
+struct SelectorConfig {
+    domain: &'static str,
+    /// The element holding one message, its sender and text
+    container_selector: &'static str,
+    message_selector: &'static str,
+    sender_selector: Option<&'static str>,
+    source_name: &'static str,
+}
+
+/// Get selector configurations for different platforms
+fn get_selector_configs() -> Vec<SelectorConfig> {
+    vec![
+        SelectorConfig {
+            domain: "meet.google.com",
+            container_selector: "[data-is-own-message]",
+            message_selector: "[data-is-own-message='false'] span[data-message-text]",
+            sender_selector: Some("[data-sender-nickname]"),
+            source_name: "google-meet",
+        },
+        SelectorConfig {
+            domain: "teams.microsoft.com",
+            container_selector: "[data-tid='chat-pane-item']",
+            message_selector: "[data-testid='message-content']",
+            sender_selector: Some("[data-testid='message-sender']"),
+            source_name: "teams",
+        },
+        SelectorConfig {
+            domain: "discord.com",
+            container_selector: "li[id^='chat-messages-']",
+            message_selector: "[data-testid='message-content']",
+            sender_selector: Some("[data-testid='username']"),
+            source_name: "discord",
+        },
+        SelectorConfig {
+            domain: "app.slack.com",
+            container_selector: "[data-qa='virtual_list_item']",
+            message_selector: "[data-qa='message-text']",
+            sender_selector: Some("[data-qa='message_sender_name']"),
+            source_name: "slack",
+        },
+        SelectorConfig {
+            domain: "web.whatsapp.com",
+            container_selector: "[data-testid='msg-container']",
+            message_selector: "[data-testid='msg-container'] [class*='message']",
+            sender_selector: Some("[data-testid='msg-sender']"),
+            source_name: "whatsapp",
+        },
+        SelectorConfig {
+            domain: "web.telegram.org",
+            container_selector: ".bubble",
+            message_selector: ".message-content",
+            sender_selector: Some(".message-sender"),
+            source_name: "telegram",
+        },
+    ]
+}
+
+/// Extract domain from URL
+fn extract_domain(url: &str) -> String {
+    if let Ok(parsed_url) = url.parse::<url::Url>() {
+        if let Some(host) = parsed_url.host_str() {
+            return host.to_string();
+        }
+    }
+    String::new()
+}
+
+/// Check if domain has a configured selector
+fn has_selector_for_domain(domain: &str) -> bool {
+    get_selector_configs().iter().any(|c| c.domain == domain)
+}
+
+/// Get selector config for a domain
+fn get_selector_for_domain(domain: &str) -> Option<SelectorConfig> {
+    get_selector_configs()
+        .into_iter()
+        .find(|c| c.domain == domain)
+}
+
+/// How well a tab matches a search, from the title or the URL; `None` if it
+/// does not match at all
+fn rank_tab(tab: &TabInfo, query: &str) -> Option<u32> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return None;
+    }
+    let title = tab.title.to_lowercase();
+    let domain = tab.domain.to_lowercase();
+
+    if title == query {
+        Some(100)
+    } else if title.starts_with(&query) {
+        Some(80)
+    } else if title
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| word == query)
+    {
+        Some(70)
+    } else if title.contains(&query) {
+        Some(60)
+    } else if domain == query || domain.ends_with(&format!(".{}", query)) {
+        Some(50)
+    } else if domain.contains(&query) {
+        Some(40)
+    } else if tab.url.to_lowercase().contains(&query) {
+        Some(20)
+    } else {
+        None
+    }
+}
+
+/// The best match for `query`; tabs with a selector win ties
+fn find_best_tab(tabs: Vec<TabInfo>, query: &str) -> Option<TabInfo> {
+    tabs.into_iter()
+        .filter_map(|tab| Some((rank_tab(&tab, query)?, tab)))
+        .max_by_key(|(rank, tab)| (*rank, tab.has_selector))
+        .map(|(_, tab)| tab)
+}
+
+/// Hash a string for deduplication
+fn hash_string(s: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    s.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Connect to Chrome DevTools Protocol
+///
+/// The host and port are remembered; the connection is shared by every CDP
+/// feature until it drops or `cdp_disconnect` is called.
+#[tauri::command]
+pub async fn cdp_connect(
+    manager: State<'_, CdpManager>,
+    launcher: State<'_, BrowserLauncher>,
+    port: Option<u16>,
+    host: Option<String>,
+) -> Result<ConnectionResult, String> {
+    // Not the launched browser's endpoint, which is for this session only
+    let mut config = manager.saved_config();
+    if let Some(port) = port {
+        config.port = port;
+    }
+    if let Some(host) = host {
+        config.host = host;
+    }
+    manager.set_config(config)?;
+    Ok(connection_result(&manager, &launcher).await)
+}
+
+async fn connection_result(manager: &CdpManager, launcher: &BrowserLauncher) -> ConnectionResult {
+    match manager.browser_pages().await {
+        Ok(pages) => ConnectionResult {
+            success: true,
+            message: format!("Conectado a {}", manager.config().endpoint()),
+            tabs_count: pages.len(),
+            diagnostics: None,
+        },
+        Err(e) => ConnectionResult {
+            success: false,
+            message: e,
+            tabs_count: 0,
+            diagnostics: Some(launcher.diagnose(manager).await),
+        },
+    }
+}
+
+/// Chromium-family browsers found on this machine
+#[tauri::command]
+pub fn cdp_list_browsers() -> Vec<launcher::InstalledBrowser> {
+    launcher::discover()
+}
+
+/// Open a browser with remote debugging and connect to it
+///
+/// A browser already debuggable on the configured endpoint is used instead,
+/// unless `kind` asks for a specific one. Launched browsers get a free port
+/// and their own profile.
+#[tauri::command]
+pub async fn cdp_launch_browser(
+    app_handle: tauri::AppHandle,
+    manager: State<'_, CdpManager>,
+    launcher: State<'_, BrowserLauncher>,
+    kind: Option<launcher::BrowserKind>,
+) -> Result<ConnectionResult, String> {
+    launcher.ensure(&app_handle, kind).await?;
+    Ok(connection_result(&manager, &launcher).await)
+}
+
+/// Close the browser birdie launched
+#[tauri::command]
+pub async fn cdp_close_browser(
+    manager: State<'_, CdpManager>,
+    launcher: State<'_, BrowserLauncher>,
+) -> Result<Option<launcher::ManagedBrowser>, String> {
+    let closed = launcher.close().await;
+    if closed.is_some() {
+        manager.disconnect();
+    }
+    Ok(closed)
+}
+
+/// Check the endpoint and installed browsers
+#[tauri::command]
+pub async fn cdp_diagnose(
+    manager: State<'_, CdpManager>,
+    launcher: State<'_, BrowserLauncher>,
+) -> Result<launcher::Diagnostics, String> {
+    Ok(launcher.diagnose(&manager).await)
+}
+
+/// Close the shared connection
+#[tauri::command]
+pub fn cdp_disconnect(manager: State<'_, CdpManager>) -> ConnectionState {
+    manager.disconnect();
+    manager.state()
+}
+
+#[tauri::command]
+pub fn cdp_get_connection_state(manager: State<'_, CdpManager>) -> ConnectionState {
+    manager.state()
+}
+
+/// A desktop app, whether it is installed and its connection if attached
+#[derive(Debug, Serialize)]
+pub struct DesktopAppInfo {
+    #[serde(flatten)]
+    pub app: &'static desktop::DesktopApp,
+    pub installed: bool,
+    pub connection: Option<ConnectionState>,
+}
+
+/// Desktop apps that can be attached to
+#[tauri::command]
+pub fn cdp_list_desktop_apps(manager: State<'_, CdpManager>) -> Vec<DesktopAppInfo> {
+    let attached = manager.desktop_status();
+    desktop::DESKTOP_APPS
+        .iter()
+        .map(|app| DesktopAppInfo {
+            app,
+            installed: app.executable().is_some(),
+            connection: attached
+                .iter()
+                .find(|status| status.app.id == app.id)
+                .map(|status| status.state.clone()),
+        })
+        .collect()
+}
+
+/// Attach to a desktop app already running with `--remote-debugging-port`
+///
+/// Its windows are listed with the browser tabs from then on, so context,
+/// monitoring and replies reach them too.
+#[tauri::command]
+pub async fn cdp_attach_desktop_app(
+    manager: State<'_, CdpManager>,
+    id: String,
+    port: Option<u16>,
+) -> Result<Vec<TabInfo>, String> {
+    let app = desktop::find(&id)?;
+    let windows = manager
+        .attach_desktop(app, port.unwrap_or(app.port))
+        .await?;
+    Ok(windows.iter().map(TabInfo::from).collect())
+}
+
+/// Start a desktop app with a debugging port and attach to it
+///
+/// The app must not be running already; Electron would hand the flag to the
+/// open instance, which ignores it.
+#[tauri::command]
+pub async fn cdp_launch_desktop_app(
+    manager: State<'_, CdpManager>,
+    id: String,
+) -> Result<Vec<TabInfo>, String> {
+    let app = desktop::find(&id)?;
+    let port = app.launch().await?;
+    let windows = manager.attach_desktop(app, port).await?;
+    Ok(windows.iter().map(TabInfo::from).collect())
+}
+
+/// Stop using a desktop app; the app keeps running
+#[tauri::command]
+pub fn cdp_detach_desktop_app(manager: State<'_, CdpManager>, id: String) -> bool {
+    manager.detach_desktop(&id)
+}
+
+/// The renderer windows of an attached desktop app
+#[tauri::command]
+pub async fn cdp_get_desktop_tabs(
+    manager: State<'_, CdpManager>,
+    id: String,
+) -> Result<Vec<TabInfo>, String> {
+    Ok(manager
+        .desktop_pages(&id)
+        .await?
+        .iter()
+        .map(TabInfo::from)
+        .collect())
+}
+
+/// Get list of all tabs, with the windows of attached desktop apps
+#[tauri::command]
+pub async fn cdp_get_tabs(manager: State<'_, CdpManager>) -> Result<Vec<TabInfo>, String> {
+    Ok(manager.pages().await?.iter().map(TabInfo::from).collect())
+}
+
+/// Find the tab that best matches a title or URL fragment
+#[tauri::command]
+pub async fn cdp_find_tab(
+    manager: State<'_, CdpManager>,
+    title_contains: String,
+) -> Result<Option<TabInfo>, String> {
+    let tabs = manager.pages().await?.iter().map(TabInfo::from).collect();
+    Ok(find_best_tab(tabs, &title_contains))
+}
+
+/// Execute JavaScript in a tab
+///
+/// Promises are awaited and the result is returned as JSON. A script that
+/// throws or runs past `timeout_ms` reports the error in the result.
+#[tauri::command]
+pub async fn cdp_execute_script(
+    manager: State<'_, CdpManager>,
+    tab_id: String,
+    script: String,
+    timeout_ms: Option<u64>,
+) -> Result<ScriptResult, String> {
+    let page = manager.page(&tab_id).await?;
+    let timeout = timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(script::DEFAULT_TIMEOUT);
+
+    Ok(match script::evaluate(&page, &script, timeout).await {
+        Ok(result) => ScriptResult {
+            success: true,
+            result: Some(result),
+            error: None,
+        },
+        Err(e) => ScriptResult {
+            success: false,
+            result: None,
+            error: Some(e),
+        },
+    })
+}
+
+/// Start monitoring tabs for messages
+///
+/// New messages are emitted as `cdp-message` events and forwarded as
+/// notifications. Starting again restarts the monitor with the new interval.
+/// In observe mode tabs report messages as they appear instead of being read
+/// every interval; in frames mode Slack and Discord tabs are read from their
+/// WebSocket traffic.
+#[tauri::command]
+pub async fn cdp_start_monitoring(
+    app_handle: tauri::AppHandle,
+    monitor: State<'_, CdpMonitor>,
+    interval_ms: u64,
+    mode: Option<MonitorMode>,
+) -> Result<MonitoringStatus, String> {
+    // Fail early when neither the browser nor a desktop app can be reached
+    app_handle.state::<CdpManager>().pages().await?;
+    monitor
+        .start(&app_handle, interval_ms, mode.unwrap_or_default())
+        .await;
+    Ok(MonitoringStatus::new(true, monitor.running()))
+}
+
+/// Stop monitoring; the counts are those of the run that was stopped
+#[tauri::command]
+pub async fn cdp_stop_monitoring(
+    monitor: State<'_, CdpMonitor>,
+) -> Result<MonitoringStatus, String> {
+    Ok(MonitoringStatus::new(false, monitor.stop().await))
+}
+
+#[tauri::command]
+pub fn cdp_get_monitoring_status(monitor: State<'_, CdpMonitor>) -> MonitoringStatus {
+    MonitoringStatus::new(true, monitor.running())
+}
+
+/// Count what each app's selectors match in its open tabs
+///
+/// Chats in which they find no messages are reported as broken, and a tab
+/// that breaks is also announced with a `cdp-selector-warning` event. The
+/// monitor runs the same check while it is running.
+#[tauri::command]
+pub async fn cdp_check_selectors(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<health::TabHealth>, String> {
+    health::check(&app_handle).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_domain() {
+        assert_eq!(
+            extract_domain("https://meet.google.com/abc-def-ghi"),
+            "meet.google.com"
+        );
+        assert_eq!(
+            extract_domain("https://teams.microsoft.com/v2/"),
+            "teams.microsoft.com"
+        );
+    }
+
+    #[test]
+    fn test_has_selector_for_domain() {
+        assert!(has_selector_for_domain("meet.google.com"));
+        assert!(has_selector_for_domain("teams.microsoft.com"));
+        assert!(has_selector_for_domain("app.slack.com"));
+        assert!(!has_selector_for_domain("example.com"));
+    }
+
+    #[test]
+    fn test_get_selector_for_domain() {
+        let config = get_selector_for_domain("meet.google.com");
+        assert!(config.is_some());
+        assert_eq!(config.unwrap().source_name, "google-meet");
+    }
+
+    fn tab(title: &str, url: &str) -> TabInfo {
+        let domain = extract_domain(url);
+        TabInfo {
+            id: title.to_string(),
+            title: title.to_string(),
+            url: url.to_string(),
+            has_selector: has_selector_for_domain(&domain),
+            domain,
+        }
+    }
+
+    #[test]
+    fn test_find_best_tab() {
+        let tabs = || {
+            vec![
+                tab(
+                    "Notas de la reunión con Teams",
+                    "https://docs.example.com/teams",
+                ),
+                tab("Chat | Microsoft Teams", "https://teams.microsoft.com/v2/"),
+                tab("Meet - abc-def-ghi", "https://meet.google.com/abc-def-ghi"),
+                tab("Inbox", "https://mail.example.com/"),
+            ]
+        };
+
+        // A whole word beats a substring, and a chat tab wins ties
+        assert_eq!(
+            find_best_tab(tabs(), "teams").unwrap().url,
+            "https://teams.microsoft.com/v2/"
+        );
+        assert_eq!(
+            find_best_tab(tabs(), "MEET").unwrap().id,
+            "Meet - abc-def-ghi"
+        );
+        assert_eq!(
+            find_best_tab(tabs(), "google").unwrap().id,
+            "Meet - abc-def-ghi"
+        );
+        assert_eq!(find_best_tab(tabs(), "inbox").unwrap().id, "Inbox");
+        assert!(find_best_tab(tabs(), "slack").is_none());
+        assert!(find_best_tab(tabs(), " ").is_none());
+    }
+
+    #[test]
+    fn test_hash_string() {
+        let hash1 = hash_string("test message");
+        let hash2 = hash_string("test message");
+        let hash3 = hash_string("different message");
+
+        assert_eq!(hash1, hash2);
+        assert_ne!(hash1, hash3);
+    }
+}
@@ -0,0 +1,546 @@
+//! Background monitoring of chat tabs
+//!
+//! Every `interval_ms` the monitor reads the messages of each open tab whose
+//! domain has a selector configuration. Messages already seen in a tab are
+//! remembered by hash, and the first read of a tab only records what is
+//! there, so the history on screen is not announced as new. New messages are
+//! emitted as `cdp-message` events and enter the notification flow like
+//! desktop notifications do.
+//!
+//! In the observe and frames modes the tabs are not read on a timer: tabs are
+//! attached as they open, or as their connection comes back, and each tab
+//! reports its own messages, from the page (see `observer`) or from
+//! the app's WebSocket traffic (see `frames`).
+
+use super::frames::{self, Decoder};
+use super::health::{self, SelectorHealth};
+use super::observer;
+use super::{
+    get_selector_for_domain, hash_string, script, CDPMessage, CdpManager, SelectorConfig, TabInfo,
+};
+use crate::context_mapper::ContextMapper;
+use crate::notifications::{self, NotificationEvent};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::watch;
+
+pub const MIN_INTERVAL_MS: u64 = 500;
+/// Only the latest messages of each tab are read
+const MESSAGES_PER_TAB: usize = 50;
+/// Messages remembered per tab; older ones have long left what is read
+const SEEN_PER_TAB: usize = MESSAGES_PER_TAB * 4;
+/// A tab that takes longer is skipped until the next poll
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(3);
+/// How often the selectors are checked while monitoring
+const HEALTH_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Default)]
+pub struct MonitorStats {
+    pub tabs_monitored: AtomicUsize,
+    pub messages_detected: AtomicUsize,
+}
+
+/// How new messages are found
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MonitorMode {
+    /// Read every tab each interval
+    #[default]
+    Poll,
+    /// Have each tab report messages as they are added to the page
+    Observe,
+    /// Decode the messages in the app's WebSocket frames; only tabs of apps
+    /// with a decoder are monitored
+    Frames,
+}
+
+/// Settings and stats of a monitor run
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub interval_ms: u64,
+    pub mode: MonitorMode,
+    pub stats: Arc<MonitorStats>,
+}
+
+struct Running {
+    stop: watch::Sender<bool>,
+    task: JoinHandle<()>,
+    run: Run,
+}
+
+/// The monitor task, held in Tauri managed state
+#[derive(Default)]
+pub struct CdpMonitor {
+    running: Mutex<Option<Running>>,
+}
+
+/// A message read from the page
+#[derive(Debug, Clone, Deserialize)]
+pub(super) struct Extracted {
+    pub sender: String,
+    pub message: String,
+}
+
+/// Read the latest messages with their senders
+///
+/// The sender is looked up in the closest ancestor that has one; chats that
+/// group consecutive messages show it only once, so a message without one
+/// takes the previous message's sender.
+pub(super) fn extraction_script(config: &SelectorConfig) -> String {
+    format!(
+        r#"
+        (function() {{
+            const senderSelector = {sender};
+            const text = el => (el.innerText || el.textContent || '').trim();
+            const messages = Array.from(document.querySelectorAll({message})).slice(-{limit});
+            let previous = '';
+            return messages.map(el => {{
+                let sender = '';
+                if (senderSelector) {{
+                    for (let node = el.parentElement; node && node !== document.body; node = node.parentElement) {{
+                        const found = node.matches(senderSelector) ? node : node.querySelector(senderSelector);
+                        if (found) {{
+                            sender = text(found);
+                            break;
+                        }}
+                    }}
+                }}
+                sender = sender || previous;
+                previous = sender;
+                return {{ sender, message: text(el) }};
+            }}).filter(m => m.message);
+        }})()
+        "#,
+        message = serde_json::to_string(config.message_selector).unwrap_or_default(),
+        sender = serde_json::to_string(&config.sender_selector).unwrap_or_default(),
+        limit = MESSAGES_PER_TAB,
+    )
+}
+
+fn message_key(sender: &str, message: &str) -> u64 {
+    hash_string(&format!("{}\u{1f}{}", sender, message))
+}
+
+/// Hashes of the latest messages seen in one tab
+#[derive(Default)]
+struct TabSeen {
+    keys: HashSet<u64>,
+    order: VecDeque<u64>,
+}
+
+impl TabSeen {
+    /// Whether `key` is new; the oldest key is forgotten past `SEEN_PER_TAB`
+    fn insert(&mut self, key: u64) -> bool {
+        if !self.keys.insert(key) {
+            return false;
+        }
+        self.order.push_back(key);
+        if self.order.len() > SEEN_PER_TAB {
+            if let Some(oldest) = self.order.pop_front() {
+                self.keys.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// Hashes of the messages seen in each tab
+#[derive(Default)]
+pub(super) struct Seen {
+    tabs: HashMap<String, TabSeen>,
+}
+
+impl Seen {
+    /// The messages not seen before; everything is new to a tab seen for the
+    /// first time, but nothing is reported for it
+    pub(super) fn new_messages(
+        &mut self,
+        tab_id: &str,
+        extracted: Vec<Extracted>,
+    ) -> Vec<Extracted> {
+        let first_read = !self.tabs.contains_key(tab_id);
+        let seen = self.tabs.entry(tab_id.to_string()).or_default();
+        let fresh: Vec<Extracted> = extracted
+            .into_iter()
+            .filter(|m| seen.insert(message_key(&m.sender, &m.message)))
+            .collect();
+        if first_read {
+            Vec::new()
+        } else {
+            fresh
+        }
+    }
+
+    /// Whether a message reported by a tab's observer is new
+    pub(super) fn observed(&mut self, tab_id: &str, sender: &str, message: &str) -> bool {
+        self.tabs
+            .entry(tab_id.to_string())
+            .or_default()
+            .insert(message_key(sender, message))
+    }
+
+    /// Forget tabs that were closed
+    pub(super) fn retain(&mut self, open: &HashSet<String>) {
+        self.tabs.retain(|id, _| open.contains(id));
+    }
+}
+
+/// The app a domain belongs to, as named in notifications
+pub(super) fn app_name(domain: &str, source_name: &str) -> String {
+    ContextMapper::new()
+        .get_enabled_contexts()
+        .into_iter()
+        .find(|c| c.url_patterns.iter().any(|p| domain.contains(p.as_str())))
+        .map(|c| c.app_name)
+        .unwrap_or_else(|| source_name.to_string())
+}
+
+/// Emit a detected message and pass it on as a notification
+pub(super) fn deliver(app: &AppHandle, message: &CDPMessage, app_name: &str) {
+    if let Err(e) = app.emit("cdp-message", message) {
+        eprintln!("✗ Error emitting CDP message: {}", e);
+    }
+    let event = NotificationEvent {
+        app_name: app_name.to_string(),
+        sender: message.sender.clone(),
+        message: message.message.clone(),
+        timestamp: message.timestamp.clone(),
+        app_icon: None,
+        inline_reply: None,
+    };
+    if let Err(e) = notifications::dispatch(app, &event) {
+        eprintln!("✗ Error emitting notification: {}", e);
+    }
+}
+
+/// Read every monitored tab once
+async fn poll(app: &AppHandle, seen: &mut Seen, stats: &MonitorStats) {
+    let manager = app.state::<CdpManager>();
+    let tabs: Vec<TabInfo> = match manager.pages().await {
+        Ok(pages) => pages.iter().map(TabInfo::from).collect(),
+        Err(e) => {
+            eprintln!("⚠ CDP monitor: {}", e);
+            stats.tabs_monitored.store(0, Ordering::Relaxed);
+            return;
+        }
+    };
+
+    let monitored: Vec<(TabInfo, SelectorConfig)> = tabs
+        .into_iter()
+        .filter_map(|tab| {
+            let config = get_selector_for_domain(&tab.domain)?;
+            Some((tab, config))
+        })
+        .collect();
+    stats
+        .tabs_monitored
+        .store(monitored.len(), Ordering::Relaxed);
+    seen.retain(&monitored.iter().map(|(tab, _)| tab.id.clone()).collect());
+
+    for (tab, config) in monitored {
+        let extracted = match manager.page(&tab.id).await {
+            Ok(page) => script::evaluate(&page, &extraction_script(&config), SCRIPT_TIMEOUT).await,
+            Err(e) => Err(e),
+        };
+        let extracted: Vec<Extracted> = match extracted.and_then(|value| {
+            serde_json::from_value(value).map_err(|e| format!("Resultado inesperado: {}", e))
+        }) {
+            Ok(extracted) => extracted,
+            Err(e) => {
+                eprintln!("⚠ CDP monitor, '{}': {}", tab.title, e);
+                continue;
+            }
+        };
+
+        if !extracted.is_empty() {
+            app.state::<SelectorHealth>().extracted(config.source_name);
+        }
+        let app_name = app_name(&tab.domain, config.source_name);
+        for found in seen.new_messages(&tab.id, extracted) {
+            stats.messages_detected.fetch_add(1, Ordering::Relaxed);
+            let message = CDPMessage {
+                tab_id: tab.id.clone(),
+                tab_title: tab.title.clone(),
+                domain: tab.domain.clone(),
+                sender: found.sender,
+                message: found.message,
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                source: config.source_name.to_string(),
+                sender_id: None,
+                channel: None,
+            };
+            deliver(app, &message, &app_name);
+        }
+    }
+}
+
+/// The tasks listening to one tab, aborted when dropped
+pub(super) struct Attached {
+    pub tasks: Vec<JoinHandle<()>>,
+    /// Removes what was installed in the page; only run by `detach`, as a
+    /// dropped tab is closed or its connection gone
+    pub teardown: Option<BoxFuture<'static, ()>>,
+}
+
+impl Attached {
+    /// Stop listening and undo what was installed in the page
+    pub async fn detach(mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+        if let Some(teardown) = self.teardown.take() {
+            teardown.await;
+        }
+    }
+}
+
+impl Drop for Attached {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Tabs that report their own messages; dropping it detaches them all
+#[derive(Default)]
+struct Attachments {
+    tabs: HashMap<String, Attached>,
+    seen: Arc<Mutex<Seen>>,
+    /// `CdpManager::opened` when the tabs were attached
+    opened: u64,
+}
+
+enum Listener {
+    Observer(SelectorConfig),
+    Frames(Decoder),
+}
+
+impl Attachments {
+    /// Attach to monitored tabs that opened and detach from closed ones
+    async fn sync(&mut self, app: &AppHandle, mode: MonitorMode, stats: &Arc<MonitorStats>) {
+        let manager = app.state::<CdpManager>();
+        // A reconnection leaves the listeners with pages that are gone
+        if manager.opened() != self.opened {
+            self.tabs.clear();
+            self.opened = manager.opened();
+        }
+        let tabs: Vec<TabInfo> = match manager.pages().await {
+            Ok(pages) => pages.iter().map(TabInfo::from).collect(),
+            Err(e) => {
+                eprintln!("⚠ CDP monitor: {}", e);
+                // The connection is gone and the listeners with it
+                self.tabs.clear();
+                stats.tabs_monitored.store(0, Ordering::Relaxed);
+                return;
+            }
+        };
+
+        let monitored: Vec<(TabInfo, Listener)> = tabs
+            .into_iter()
+            .filter_map(|tab| {
+                let listener = match mode {
+                    MonitorMode::Frames => Listener::Frames(Decoder::for_domain(&tab.domain)?),
+                    _ => Listener::Observer(get_selector_for_domain(&tab.domain)?),
+                };
+                Some((tab, listener))
+            })
+            .collect();
+        let open: HashSet<String> = monitored.iter().map(|(tab, _)| tab.id.clone()).collect();
+        self.tabs.retain(|id, _| open.contains(id));
+        self.seen.lock().unwrap().retain(&open);
+
+        for (tab, listener) in monitored {
+            if self.tabs.contains_key(&tab.id) {
+                continue;
+            }
+            let id = tab.id.clone();
+            let attached = match manager.page(&id).await {
+                Ok(page) => match listener {
+                    Listener::Observer(config) => {
+                        let seen = self.seen.clone();
+                        observer::attach(app, page, tab, config, seen, stats.clone()).await
+                    }
+                    Listener::Frames(decoder) => {
+                        frames::attach(app, page, tab, decoder, stats.clone()).await
+                    }
+                },
+                Err(e) => Err(e),
+            };
+            match attached {
+                Ok(attached) => {
+                    self.tabs.insert(id, attached);
+                }
+                // Tried again on the next sync
+                Err(e) => eprintln!("⚠ CDP monitor, tab {}: {}", id, e),
+            }
+        }
+        stats
+            .tabs_monitored
+            .store(self.tabs.len(), Ordering::Relaxed);
+    }
+}
+
+impl CdpMonitor {
+    /// Start monitoring, replacing a monitor already running
+    ///
+    /// The first pass over the tabs is made before returning, so the run's
+    /// stats already count the monitored tabs.
+    pub async fn start(&self, app: &AppHandle, interval_ms: u64, mode: MonitorMode) {
+        self.stop().await;
+
+        let interval = Duration::from_millis(interval_ms.max(MIN_INTERVAL_MS));
+        let stats = Arc::new(MonitorStats::default());
+        let (stop, mut stopped) = watch::channel(false);
+        let app = app.clone();
+        let task_stats = stats.clone();
+
+        let mut seen = Seen::default();
+        // Dropped when the loop ends, which detaches every tab
+        let mut attachments = Attachments::default();
+        let mut changes = app.state::<CdpManager>().changes();
+        changes.mark_unchanged();
+        match mode {
+            MonitorMode::Poll => poll(&app, &mut seen, &stats).await,
+            _ => attachments.sync(&app, mode, &stats).await,
+        }
+
+        let task = tauri::async_runtime::spawn(async move {
+            let mut checked = tokio::time::Instant::now();
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(interval) => {}
+                    // Tabs that open or come back are attached right away
+                    _ = changes.changed(), if mode != MonitorMode::Poll => {}
+                    _ = stopped.changed() => {
+                        // Restarting attaches again, so this run's observers
+                        // must be gone first
+                        for (_, attached) in attachments.tabs.drain() {
+                            attached.detach().await;
+                        }
+                        break;
+                    }
+                }
+                changes.mark_unchanged();
+                match mode {
+                    MonitorMode::Poll => poll(&app, &mut seen, &task_stats).await,
+                    _ => attachments.sync(&app, mode, &task_stats).await,
+                }
+                if checked.elapsed() >= HEALTH_INTERVAL {
+                    checked = tokio::time::Instant::now();
+                    if let Err(e) = health::check(&app).await {
+                        eprintln!("⚠ Selector health: {}", e);
+                    }
+                }
+            }
+        });
+
+        *self.running.lock().unwrap() = Some(Running {
+            stop,
+            task,
+            run: Run {
+                interval_ms: interval.as_millis() as u64,
+                mode,
+                stats,
+            },
+        });
+    }
+
+    /// Stop the monitor and wait for its task; returns the run that stopped
+    pub async fn stop(&self) -> Option<Run> {
+        let running = self.running.lock().unwrap().take()?;
+        let _ = running.stop.send(true);
+        // A poll in progress finishes first
+        let _ = running.task.await;
+        Some(running.run)
+    }
+
+    /// The running monitor
+    pub fn running(&self) -> Option<Run> {
+        self.running.lock().unwrap().as_ref().map(|r| r.run.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extracted(sender: &str, message: &str) -> Extracted {
+        Extracted {
+            sender: sender.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_history_is_not_reported() {
+        let mut seen = Seen::default();
+        let history = vec![extracted("Ana", "hola"), extracted("Luis", "¿vamos?")];
+        assert!(seen.new_messages("tab", history.clone()).is_empty());
+
+        let mut update = history.clone();
+        update.push(extracted("Ana", "voy"));
+        let fresh = seen.new_messages("tab", update);
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(fresh[0].message, "voy");
+
+        // The same text from someone else is a different message
+        let fresh = seen.new_messages("tab", vec![extracted("Luis", "voy")]);
+        assert_eq!(fresh.len(), 1);
+
+        seen.retain(&HashSet::new());
+        assert!(seen.new_messages("tab", history).is_empty());
+    }
+
+    #[test]
+    fn test_seen_messages_are_capped() {
+        let mut seen = Seen::default();
+        for i in 0..SEEN_PER_TAB + 10 {
+            assert!(seen.observed("tab", "Ana", &i.to_string()));
+        }
+        let tab = &seen.tabs["tab"];
+        assert_eq!(tab.keys.len(), SEEN_PER_TAB);
+        assert_eq!(tab.order.len(), SEEN_PER_TAB);
+        // The oldest are forgotten, the latest still count as seen
+        assert!(!seen.observed("tab", "Ana", &(SEEN_PER_TAB + 9).to_string()));
+        assert!(seen.observed("tab", "Ana", "0"));
+    }
+
+    #[test]
+    fn test_observed_messages() {
+        let mut seen = Seen::default();
+        // Observers only report what is added, so the first one counts
+        assert!(seen.observed("tab", "Ana", "hola"));
+        assert!(!seen.observed("tab", "Ana", "hola"));
+        assert!(seen.observed("other", "Ana", "hola"));
+        assert!(seen.observed("tab", "Luis", "hola"));
+    }
+
+    #[test]
+    fn test_extraction_script_quotes_selectors() {
+        let config = get_selector_for_domain("web.whatsapp.com").unwrap();
+        let script = extraction_script(&config);
+        assert!(script.contains(r#""[data-testid='msg-container'] [class*='message']""#));
+        assert!(script.contains(r#"const senderSelector = "[data-testid='msg-sender']";"#));
+        assert!(script.contains(".slice(-50)"));
+    }
+
+    #[test]
+    fn test_app_name_from_domain() {
+        let config = get_selector_for_domain("teams.microsoft.com").unwrap();
+        assert_eq!(
+            app_name("teams.microsoft.com", config.source_name),
+            "Microsoft Teams"
+        );
+        let config = get_selector_for_domain("web.telegram.org").unwrap();
+        assert!(!app_name("web.telegram.org", config.source_name).is_empty());
+        assert_eq!(app_name("app.slack.com", "slack"), "Slack");
+        assert_eq!(app_name("example.org", "example"), "example");
+    }
+}
@@ -0,0 +1,272 @@
+//! Push-based monitoring with a MutationObserver
+//!
+//! Instead of reading each tab on a timer, a MutationObserver injected into
+//! the page reports every message node the app adds, through a binding
+//! registered with `Runtime.addBinding`; each call arrives here as a
+//! `Runtime.bindingCalled` event. Messages are caught as they are rendered,
+//! even if they scroll out of view before the next poll would have run.
+//!
+//! The binding survives navigations but the observer does not: it is
+//! injected again on `Page.frameNavigated` for the main frame. Single-page
+//! apps that switch chats through the History API are handled in the page,
+//! where the chat rendered after a route change is treated as history.
+//!
+//! Detaching disconnects the observer, restores the History API methods and
+//! removes the binding, leaving the page as it was.
+
+use super::health::SelectorHealth;
+use super::monitor::{self, Attached, MonitorStats, Seen};
+use super::{script, CDPMessage, SelectorConfig, TabInfo};
+use chromiumoxide::cdp::browser_protocol::page::{
+    EnableParams as PageEnableParams, EventFrameNavigated, EventNavigatedWithinDocument,
+};
+use chromiumoxide::cdp::js_protocol::runtime::{
+    AddBindingParams, EnableParams as RuntimeEnableParams, EventBindingCalled, RemoveBindingParams,
+};
+use chromiumoxide::Page;
+use futures::StreamExt;
+use serde::Deserialize;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+pub(super) const BINDING: &str = "__birdieMessage";
+/// After a page load or route change, nodes added for this long are the
+/// chat's history rendering, not new messages
+pub(super) const QUIET_MS: u32 = 1500;
+const INJECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Install the observer, or rescan if it is already there
+pub(super) fn observer_script(config: &SelectorConfig) -> String {
+    format!(
+        r#"
+        (function() {{
+            if (window.__birdieObserver) {{
+                window.__birdieObserver.quiet();
+                return true;
+            }}
+            const selector = {message};
+            const senderSelector = {sender};
+            const text = el => (el.innerText || el.textContent || '').trim();
+            const senderOf = el => {{
+                if (!senderSelector) return '';
+                for (let node = el.parentElement; node && node !== document.body; node = node.parentElement) {{
+                    const found = node.matches(senderSelector) ? node : node.querySelector(senderSelector);
+                    if (found) return text(found);
+                }}
+                return '';
+            }};
+
+            const seen = new WeakSet();
+            let quietUntil = 0;
+            let previous = '';
+            const report = el => {{
+                if (seen.has(el)) return;
+                seen.add(el);
+                if (Date.now() < quietUntil) return;
+                const message = text(el);
+                if (!message) return;
+                const sender = senderOf(el) || previous;
+                previous = sender;
+                window.{binding}(JSON.stringify({{ sender, message }}));
+            }};
+            const quiet = () => {{
+                quietUntil = Date.now() + {quiet};
+                document.querySelectorAll(selector).forEach(el => seen.add(el));
+            }};
+
+            const observer = new MutationObserver(mutations => {{
+                for (const mutation of mutations) {{
+                    for (const node of mutation.addedNodes) {{
+                        if (node.nodeType !== Node.ELEMENT_NODE) continue;
+                        if (node.matches(selector)) report(node);
+                        node.querySelectorAll(selector).forEach(report);
+                    }}
+                }}
+            }});
+            const start = () => {{
+                quiet();
+                observer.observe(document.body, {{ childList: true, subtree: true }});
+            }};
+
+            // Switching chats in a single-page app renders its history
+            const originals = {{}};
+            for (const method of ['pushState', 'replaceState']) {{
+                const original = originals[method] = history[method];
+                history[method] = function() {{
+                    const result = original.apply(this, arguments);
+                    quiet();
+                    return result;
+                }};
+            }}
+            window.addEventListener('popstate', quiet);
+            window.addEventListener('hashchange', quiet);
+
+            const detach = () => {{
+                observer.disconnect();
+                Object.assign(history, originals);
+                window.removeEventListener('popstate', quiet);
+                window.removeEventListener('hashchange', quiet);
+                delete window.__birdieObserver;
+            }};
+            window.__birdieObserver = {{ observer, quiet, detach }};
+            if (document.body) {{
+                start();
+            }} else {{
+                document.addEventListener('DOMContentLoaded', start, {{ once: true }});
+            }}
+            return true;
+        }})()
+        "#,
+        message = serde_json::to_string(config.message_selector).unwrap_or_default(),
+        sender = serde_json::to_string(&config.sender_selector).unwrap_or_default(),
+        binding = BINDING,
+        quiet = QUIET_MS,
+    )
+}
+
+/// A message reported by the observer
+#[derive(Debug, Deserialize)]
+pub(super) struct Reported {
+    pub sender: String,
+    pub message: String,
+}
+
+/// Undo `observer_script`
+pub(super) const DETACH_SCRIPT: &str = "window.__birdieObserver?.detach()";
+
+pub(super) async fn inject(page: &Page, script: &str) -> Result<(), String> {
+    script::evaluate(page, script, INJECT_TIMEOUT)
+        .await
+        .map(|_| ())
+}
+
+pub async fn attach(
+    app: &AppHandle,
+    page: Page,
+    tab: TabInfo,
+    config: SelectorConfig,
+    seen: Arc<Mutex<Seen>>,
+    stats: Arc<MonitorStats>,
+) -> Result<Attached, String> {
+    let protocol = |e: chromiumoxide::error::CdpError| e.to_string();
+    page.execute(PageEnableParams::default())
+        .await
+        .map_err(protocol)?;
+    page.execute(RuntimeEnableParams::default())
+        .await
+        .map_err(protocol)?;
+    page.execute(AddBindingParams::new(BINDING))
+        .await
+        .map_err(protocol)?;
+    let mut calls = page
+        .event_listener::<EventBindingCalled>()
+        .await
+        .map_err(protocol)?;
+    let mut navigations = page
+        .event_listener::<EventFrameNavigated>()
+        .await
+        .map_err(protocol)?;
+    let mut route_changes = page
+        .event_listener::<EventNavigatedWithinDocument>()
+        .await
+        .map_err(protocol)?;
+
+    let script = observer_script(&config);
+    inject(&page, &script).await?;
+
+    let app_name = monitor::app_name(&tab.domain, config.source_name);
+    let app = app.clone();
+    let tab_id = tab.id.clone();
+    let receive = tauri::async_runtime::spawn(async move {
+        while let Some(call) = calls.next().await {
+            if call.name != BINDING {
+                continue;
+            }
+            let Ok(reported) = serde_json::from_str::<Reported>(&call.payload) else {
+                continue;
+            };
+            // Apps that virtualize their lists render the same message again
+            if !seen
+                .lock()
+                .unwrap()
+                .observed(&tab_id, &reported.sender, &reported.message)
+            {
+                continue;
+            }
+            stats.messages_detected.fetch_add(1, Ordering::Relaxed);
+            app.state::<SelectorHealth>().extracted(config.source_name);
+            let message = CDPMessage {
+                tab_id: tab_id.clone(),
+                tab_title: tab.title.clone(),
+                domain: tab.domain.clone(),
+                sender: reported.sender,
+                message: reported.message,
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                source: config.source_name.to_string(),
+                sender_id: None,
+                channel: None,
+            };
+            monitor::deliver(&app, &message, &app_name);
+        }
+    });
+
+    let cleanup = page.clone();
+    let teardown = async move {
+        if let Err(e) = inject(&cleanup, DETACH_SCRIPT).await {
+            eprintln!("⚠ CDP observer: {}", e);
+        }
+        if let Err(e) = cleanup.execute(RemoveBindingParams::new(BINDING)).await {
+            eprintln!("⚠ CDP observer: {}", e);
+        }
+    };
+
+    let reinject = tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::select! {
+                Some(navigated) = navigations.next() => {
+                    // Only a new document in the main frame loses the observer
+                    if navigated.frame.parent_id.is_some() {
+                        continue;
+                    }
+                }
+                Some(_) = route_changes.next() => {}
+                else => break,
+            }
+            if let Err(e) = inject(&page, &script).await {
+                eprintln!("⚠ CDP observer: {}", e);
+            }
+        }
+    });
+
+    Ok(Attached {
+        tasks: vec![receive, reinject],
+        teardown: Some(Box::pin(teardown)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cdp::get_selector_for_domain;
+
+    #[test]
+    fn test_observer_script() {
+        let config = get_selector_for_domain("discord.com").unwrap();
+        let script = observer_script(&config);
+        assert!(script.contains(r#"const selector = "[data-testid='message-content']";"#));
+        assert!(script.contains("window.__birdieMessage(JSON.stringify"));
+        assert!(script.contains("quietUntil = Date.now() + 1500"));
+        // Injecting twice keeps a single observer
+        assert!(script.contains("if (window.__birdieObserver)"));
+    }
+
+    #[test]
+    fn test_reported_payload() {
+        let reported: Reported =
+            serde_json::from_str(r#"{"sender":"Ana","message":"hola"}"#).unwrap();
+        assert_eq!(reported.sender, "Ana");
+        assert_eq!(reported.message, "hola");
+    }
+}
@@ -0,0 +1,142 @@
+//! Script evaluation in a tab
+//!
+//! Scripts run with `awaitPromise` and `returnByValue`, so a script may
+//! return a promise and its result arrives as JSON rather than as a handle to
+//! a remote object. Exceptions thrown by the script are reported with their
+//! message and position instead of a protocol error.
+//!
+//! A script that overruns its time is stopped in the page, not just given up
+//! on: the evaluation carries the timeout for Chromium to enforce, and when
+//! no answer arrives in time `Runtime.terminateExecution` is sent as well.
+
+use chromiumoxide::cdp::js_protocol::runtime::{
+    EvaluateParams, ExceptionDetails, RemoteObject, RemoteObjectType, TerminateExecutionParams,
+    TimeDelta,
+};
+use chromiumoxide::Page;
+use serde_json::Value;
+use std::time::Duration;
+
+/// Longest a script may run when the caller sets no limit
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a hung page gets to acknowledge the termination
+const TERMINATE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Evaluate `script` and return its result as JSON
+pub async fn evaluate(page: &Page, script: &str, timeout: Duration) -> Result<Value, String> {
+    let params = EvaluateParams::builder()
+        .expression(script)
+        .await_promise(true)
+        .return_by_value(true)
+        .user_gesture(false)
+        .timeout(TimeDelta::new(timeout.as_millis() as f64))
+        .build()
+        .map_err(|e| format!("Script inválido: {}", e))?;
+
+    let evaluated = match tokio::time::timeout(timeout, page.execute(params)).await {
+        Ok(evaluated) => evaluated.map_err(|e| format!("Error al ejecutar script: {}", e))?,
+        Err(_) => {
+            let terminate = page.execute(TerminateExecutionParams::default());
+            let terminated = tokio::time::timeout(TERMINATE_TIMEOUT, terminate).await;
+            if !matches!(terminated, Ok(Ok(_))) {
+                eprintln!("⚠ Could not stop a script that timed out");
+            }
+            return Err(format!("El script tardó más de {} ms", timeout.as_millis()));
+        }
+    };
+
+    if let Some(details) = &evaluated.result.exception_details {
+        return Err(exception_message(details));
+    }
+    Ok(to_json(&evaluated.result.result))
+}
+
+/// A remote value as JSON; values JSON cannot hold are described as strings
+pub fn to_json(object: &RemoteObject) -> Value {
+    if let Some(value) = &object.value {
+        return value.clone();
+    }
+    if let Some(value) = &object.unserializable_value {
+        // NaN, Infinity, -0 and bigints such as 12n
+        return Value::String(value.as_ref().to_string());
+    }
+    match object.r#type {
+        RemoteObjectType::Undefined => Value::Null,
+        _ => object
+            .description
+            .clone()
+            .map(Value::String)
+            .unwrap_or(Value::Null),
+    }
+}
+
+/// "Uncaught TypeError: x is not a function (line 3, column 12)"
+pub fn exception_message(details: &ExceptionDetails) -> String {
+    let message = details
+        .exception
+        .as_ref()
+        .and_then(|e| e.description.as_deref())
+        // The description carries the stack; its first line is the message
+        .and_then(|d| d.lines().next())
+        .map(|d| format!("{}: {}", details.text.trim_end_matches(':'), d))
+        .unwrap_or_else(|| details.text.clone());
+    format!(
+        "{} (line {}, column {})",
+        message,
+        details.line_number + 1,
+        details.column_number + 1
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn remote(object: Value) -> RemoteObject {
+        serde_json::from_value(object).unwrap()
+    }
+
+    #[test]
+    fn test_remote_objects_become_json() {
+        assert_eq!(
+            to_json(&remote(
+                json!({"type": "object", "value": {"sender": "Ana", "unread": 2}})
+            )),
+            json!({"sender": "Ana", "unread": 2})
+        );
+        assert_eq!(to_json(&remote(json!({"type": "undefined"}))), Value::Null);
+        assert_eq!(
+            to_json(&remote(
+                json!({"type": "number", "unserializableValue": "NaN", "description": "NaN"})
+            )),
+            json!("NaN")
+        );
+        assert_eq!(
+            to_json(&remote(
+                json!({"type": "symbol", "description": "Symbol(x)", "objectId": "1"})
+            )),
+            json!("Symbol(x)")
+        );
+    }
+
+    #[test]
+    fn test_exception_message() {
+        let details: ExceptionDetails = serde_json::from_value(json!({
+            "exceptionId": 1,
+            "text": "Uncaught",
+            "lineNumber": 2,
+            "columnNumber": 11,
+            "exception": {
+                "type": "object",
+                "subtype": "error",
+                "description": "TypeError: x is not a function\n    at <anonymous>:3:12"
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            exception_message(&details),
+            "Uncaught: TypeError: x is not a function (line 3, column 12)"
+        );
+    }
+}
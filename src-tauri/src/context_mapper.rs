@@ -1,3 +1,4 @@
+use crate::tts::VoiceProfile;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -10,6 +11,30 @@ pub struct ContextTask {
     pub css_selector: String,           // Selector CSS básico para extraer contenido
     pub priority: TaskPriority,         // Prioridad de procesamiento
     pub enabled: bool,                  // Si está habilitado
+    #[serde(default)]
+    pub vocabulary: Vec<String>,        // Términos de la app para el reconocimiento de voz
+    #[serde(default)]
+    pub voice_profile: Option<VoiceProfile>, // Voz y estilo con que se leen sus mensajes
+    #[serde(default)]
+    pub reply: Option<ReplySelectors>,  // Selectores para responder desde la pestaña
+}
+
+/// Selectores CSS para escribir y enviar una respuesta en la web de la app
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReplySelectors {
+    pub conversation: Option<String>,   // Entradas de la lista de chats; se abre la que contiene el nombre
+    pub composer: String,               // Caja donde se escribe el mensaje
+    pub send_button: Option<String>,    // Botón de enviar; sin él se pulsa Enter
+}
+
+impl ReplySelectors {
+    fn new(conversation: Option<&str>, composer: &str, send_button: Option<&str>) -> Self {
+        Self {
+            conversation: conversation.map(str::to_string),
+            composer: composer.to_string(),
+            send_button: send_button.map(str::to_string),
+        }
+    }
 }
 
 /// Nivel de prioridad para procesamiento
@@ -65,6 +90,18 @@ impl ContextMapper {
                 .to_string(),
                 priority: TaskPriority::High,
                 enabled: true,
+                vocabulary: vec![
+                    "Meet".to_string(),
+                    "Google Meet".to_string(),
+                    "presentar pantalla".to_string(),
+                    "levantar la mano".to_string(),
+                ],
+                voice_profile: None,
+                reply: Some(ReplySelectors::new(
+                    None,
+                    r#"textarea[aria-label*="mensaje" i], textarea[aria-label*="message" i]"#,
+                    None,
+                )),
             },
         );
 
@@ -89,6 +126,18 @@ impl ContextMapper {
                 .to_string(),
                 priority: TaskPriority::High,
                 enabled: true,
+                vocabulary: vec![
+                    "Teams".to_string(),
+                    "Microsoft Teams".to_string(),
+                    "canal".to_string(),
+                    "equipo".to_string(),
+                ],
+                voice_profile: None,
+                reply: Some(ReplySelectors::new(
+                    Some(r#"[data-tid="chat-list-item"]"#),
+                    r#"div[role="textbox"][contenteditable="true"]"#,
+                    Some(r#"button[data-tid="newMessageCommands-send"]"#),
+                )),
             },
         );
 
@@ -113,6 +162,18 @@ impl ContextMapper {
                 .to_string(),
                 priority: TaskPriority::High,
                 enabled: true,
+                vocabulary: vec![
+                    "Discord".to_string(),
+                    "servidor".to_string(),
+                    "canal de voz".to_string(),
+                    "mención".to_string(),
+                ],
+                voice_profile: None,
+                reply: Some(ReplySelectors::new(
+                    Some(r#"a[data-list-item-id^="private-channels"], a[data-list-item-id^="channels"]"#),
+                    r#"div[role="textbox"][data-slate-editor="true"]"#,
+                    None,
+                )),
             },
         );
 
@@ -137,6 +198,19 @@ impl ContextMapper {
                 .to_string(),
                 priority: TaskPriority::High,
                 enabled: true,
+                vocabulary: vec![
+                    "Slack".to_string(),
+                    "canal".to_string(),
+                    "hilo".to_string(),
+                    "huddle".to_string(),
+                    "mención".to_string(),
+                ],
+                voice_profile: None,
+                reply: Some(ReplySelectors::new(
+                    Some(".p-channel_sidebar__channel"),
+                    r#"[data-qa="message_input"] [contenteditable="true"]"#,
+                    Some(r#"button[data-qa="texty_send_button"]"#),
+                )),
             },
         );
 
@@ -161,6 +235,17 @@ impl ContextMapper {
                 .to_string(),
                 priority: TaskPriority::Normal,
                 enabled: true,
+                vocabulary: vec![
+                    "WhatsApp".to_string(),
+                    "nota de voz".to_string(),
+                    "estado".to_string(),
+                ],
+                voice_profile: None,
+                reply: Some(ReplySelectors::new(
+                    Some(r#"#pane-side [role="listitem"]"#),
+                    r#"footer div[contenteditable="true"][role="textbox"]"#,
+                    Some(r#"footer button[aria-label="Send"], footer button[aria-label="Enviar"]"#),
+                )),
             },
         );
 
@@ -185,6 +270,13 @@ impl ContextMapper {
                 .to_string(),
                 priority: TaskPriority::Normal,
                 enabled: true,
+                vocabulary: vec!["Telegram".to_string(), "grupo".to_string(), "canal".to_string()],
+                voice_profile: None,
+                reply: Some(ReplySelectors::new(
+                    Some(".chatlist-chat"),
+                    r#".input-message-input[contenteditable="true"]"#,
+                    Some("button.btn-send"),
+                )),
             },
         );
 
@@ -209,6 +301,17 @@ impl ContextMapper {
                 .to_string(),
                 priority: TaskPriority::Normal,
                 enabled: true,
+                vocabulary: vec![
+                    "Google Chat".to_string(),
+                    "espacio".to_string(),
+                    "hilo".to_string(),
+                ],
+                voice_profile: None,
+                reply: Some(ReplySelectors::new(
+                    Some(r#"[role="treeitem"]"#),
+                    r#"div[role="textbox"][contenteditable="true"]"#,
+                    Some(r#"button[aria-label*="Send" i], button[aria-label*="Enviar" i]"#),
+                )),
             },
         );
 
@@ -264,6 +367,9 @@ impl ContextMapper {
             css_selector,
             priority,
             enabled: true,
+            vocabulary: Vec::new(),
+            voice_profile: None,
+            reply: None,
         };
         self.tasks.insert(app_name, task);
     }
@@ -0,0 +1,219 @@
+//! Encrypted file fallback for systems without a Secret Service
+//!
+//! Secrets are sealed with ChaCha20-Poly1305 under a random key kept in a
+//! separate owner-only file. This keeps keys out of plain-text settings,
+//! logs and casual backups, but it does not protect against other programs
+//! running as the same user the way an OS keyring does.
+
+use super::Provider;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const KEY_FILE: &str = "credentials.key";
+const SECRETS_FILE: &str = "credentials.enc";
+
+/// Length of a ChaCha20-Poly1305 nonce
+const NONCE_LEN: usize = 12;
+
+pub struct EncryptedFile {
+    dir: PathBuf,
+}
+
+impl EncryptedFile {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Write a file readable only by the current user
+    fn write_private(path: &Path, contents: &[u8]) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+
+        // "credentials.key.tmp": each file gets its own, so writes to the key
+        // and to the secrets never share one
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        // Created fresh so the owner-only mode applies from the start
+        let _ = fs::remove_file(&tmp);
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options
+            .open(&tmp)
+            .and_then(|mut file| file.write_all(contents))
+            .map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    fn cipher(&self, create: bool) -> Result<Option<ChaCha20Poly1305>, String> {
+        let path = self.dir.join(KEY_FILE);
+        match fs::read(&path) {
+            Ok(bytes) if bytes.len() == 32 => {
+                Ok(Some(ChaCha20Poly1305::new(Key::from_slice(&bytes))))
+            }
+            Ok(_) => Err(format!("Credential key file {} is corrupt", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                if !create {
+                    return Ok(None);
+                }
+                let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+                Self::write_private(&path, key.as_slice())?;
+                Ok(Some(ChaCha20Poly1305::new(&key)))
+            }
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Stored entries: provider id -> base64(nonce || ciphertext)
+    fn entries(&self) -> Result<HashMap<String, String>, String> {
+        let path = self.dir.join(SECRETS_FILE);
+        match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    fn save_entries(&self, entries: &HashMap<String, String>) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(entries).map_err(|e| e.to_string())?;
+        Self::write_private(&self.dir.join(SECRETS_FILE), &json)
+    }
+
+    pub fn get(&self, provider: Provider) -> Result<Option<String>, String> {
+        let entries = self.entries()?;
+        let Some(sealed) = entries.get(provider.id()) else {
+            return Ok(None);
+        };
+        let Some(cipher) = self.cipher(false)? else {
+            return Err("Credential key file is missing".to_string());
+        };
+
+        let sealed = BASE64
+            .decode(sealed)
+            .map_err(|_| "Stored secret is corrupt".to_string())?;
+        if sealed.len() < NONCE_LEN {
+            return Err("Stored secret is corrupt".to_string());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "Failed to decrypt stored secret".to_string())?;
+
+        String::from_utf8(plaintext)
+            .map(Some)
+            .map_err(|_| "Stored secret is not valid UTF-8".to_string())
+    }
+
+    pub fn set(&self, provider: Provider, secret: &str) -> Result<(), String> {
+        let cipher = self
+            .cipher(true)?
+            .ok_or_else(|| "Failed to create credential key".to_string())?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, secret.as_bytes())
+            .map_err(|_| "Failed to encrypt secret".to_string())?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+
+        let mut entries = self.entries()?;
+        entries.insert(provider.id().to_string(), BASE64.encode(sealed));
+        self.save_entries(&entries)
+    }
+
+    pub fn clear(&self, provider: Provider) -> Result<(), String> {
+        let mut entries = self.entries()?;
+        if entries.remove(provider.id()).is_some() {
+            self.save_entries(&entries)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("birdie-credentials-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_roundtrip_and_clear() {
+        let dir = temp_dir("roundtrip");
+        let store = EncryptedFile::new(dir.clone());
+
+        assert_eq!(store.get(Provider::Google).unwrap(), None);
+        store.set(Provider::Google, "AIza-secret").unwrap();
+        assert_eq!(store.get(Provider::Google).unwrap().as_deref(), Some("AIza-secret"));
+
+        // Never written in clear text
+        let on_disk = fs::read_to_string(dir.join(SECRETS_FILE)).unwrap();
+        assert!(!on_disk.contains("AIza-secret"));
+
+        store.clear(Provider::Google).unwrap();
+        assert_eq!(store.get(Provider::Google).unwrap(), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_key_and_secrets_are_written_separately() {
+        let dir = temp_dir("separate");
+        let store = EncryptedFile::new(dir.clone());
+        store.set(Provider::Google, "AIza-secret").unwrap();
+        assert_eq!(fs::read(dir.join(KEY_FILE)).unwrap().len(), 32);
+        assert!(!dir.join("credentials.tmp").exists());
+        assert!(!dir.join(format!("{}.tmp", KEY_FILE)).exists());
+        assert!(!dir.join(format!("{}.tmp", SECRETS_FILE)).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_tampered_secret_is_rejected() {
+        let dir = temp_dir("tamper");
+        let store = EncryptedFile::new(dir.clone());
+        store.set(Provider::Google, "AIza-secret").unwrap();
+
+        let mut entries = store.entries().unwrap();
+        let mut sealed = BASE64.decode(&entries["google"]).unwrap();
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        entries.insert("google".to_string(), BASE64.encode(sealed));
+        store.save_entries(&entries).unwrap();
+
+        assert!(store.get(Provider::Google).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_files_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("perms");
+        let store = EncryptedFile::new(dir.clone());
+        store.set(Provider::Google, "AIza-secret").unwrap();
+
+        for file in [KEY_FILE, SECRETS_FILE] {
+            let mode = fs::metadata(dir.join(file)).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600, "{}", file);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,237 @@
+//! Storage for speech provider API keys
+//!
+//! Keys are set from the settings UI once and never travel back to the
+//! frontend: the backend reads them when it needs to call a provider. On
+//! Linux they live in the Secret Service (GNOME Keyring, KWallet...); when
+//! none is available, or on other platforms, an encrypted file in the app
+//! data directory is used instead.
+
+mod file;
+#[cfg(target_os = "linux")]
+mod secret_service;
+
+use crate::stt::{self, SttClient, SttError};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::State;
+
+/// Service whose API key is stored
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Provider {
+    /// Google Cloud Speech-to-Text
+    Google,
+}
+
+impl Provider {
+    pub fn id(&self) -> &'static str {
+        match self {
+            Provider::Google => "google",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Provider::Google => "Google Cloud Speech-to-Text",
+        }
+    }
+}
+
+/// Where a key is stored
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    SecretService,
+    EncryptedFile,
+}
+
+/// Whether a provider has a key, without revealing it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CredentialStatus {
+    pub provider: Provider,
+    pub configured: bool,
+    pub backend: Option<StorageBackend>,
+}
+
+/// Result of checking a stored key against the provider
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeyTestResult {
+    pub valid: bool,
+    pub message: String,
+}
+
+/// API key store held in Tauri managed state
+pub struct CredentialStore {
+    file: file::EncryptedFile,
+    /// Serializes writes so the secrets file is never updated concurrently
+    lock: tokio::sync::Mutex<()>,
+    /// The Secret Service connection, or `None` when there is none; opened
+    /// on first use
+    #[cfg(target_os = "linux")]
+    secret_service: tokio::sync::OnceCell<Option<secret_service::SecretService>>,
+}
+
+impl CredentialStore {
+    /// Create a store whose fallback file lives in `data_dir`
+    pub fn new(data_dir: PathBuf) -> Self {
+        Self {
+            file: file::EncryptedFile::new(data_dir),
+            lock: tokio::sync::Mutex::new(()),
+            #[cfg(target_os = "linux")]
+            secret_service: tokio::sync::OnceCell::new(),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn secret_service(&self) -> Option<&secret_service::SecretService> {
+        self.secret_service
+            .get_or_init(|| async {
+                match secret_service::SecretService::connect().await {
+                    Ok(service) => Some(service),
+                    Err(e) => {
+                        eprintln!("ℹ {}; using encrypted file", e);
+                        None
+                    }
+                }
+            })
+            .await
+            .as_ref()
+    }
+
+    /// Read a key and the backend it came from
+    pub async fn lookup(&self, provider: Provider) -> Result<Option<(String, StorageBackend)>, String> {
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(service) = self.secret_service().await {
+                match service.get(provider).await {
+                    Ok(Some(key)) => return Ok(Some((key, StorageBackend::SecretService))),
+                    Ok(None) => {}
+                    Err(e) => eprintln!("⚠ Secret Service lookup failed: {}", e),
+                }
+            }
+        }
+
+        Ok(self
+            .file
+            .get(provider)?
+            .map(|key| (key, StorageBackend::EncryptedFile)))
+    }
+
+    pub async fn get(&self, provider: Provider) -> Result<Option<String>, String> {
+        Ok(self.lookup(provider).await?.map(|(key, _)| key))
+    }
+
+    /// Store a key, preferring the OS keyring
+    pub async fn set(&self, provider: Provider, key: &str) -> Result<StorageBackend, String> {
+        let _guard = self.lock.lock().await;
+
+        #[cfg(target_os = "linux")]
+        {
+            let stored = match self.secret_service().await {
+                Some(service) => service.set(provider, key).await,
+                None => Err("Secret Service is not available".to_string()),
+            };
+            match stored {
+                Ok(()) => {
+                    // Drop any older copy so lookups cannot return a stale key
+                    self.file.clear(provider)?;
+                    return Ok(StorageBackend::SecretService);
+                }
+                Err(e) => eprintln!("⚠ Could not use Secret Service ({}); using encrypted file", e),
+            }
+        }
+
+        self.file.set(provider, key)?;
+        Ok(StorageBackend::EncryptedFile)
+    }
+
+    /// Remove a key from every backend
+    pub async fn clear(&self, provider: Provider) -> Result<(), String> {
+        let _guard = self.lock.lock().await;
+
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(service) = self.secret_service().await {
+                service.clear(provider).await?;
+            }
+        }
+
+        self.file.clear(provider)
+    }
+
+    async fn status(&self, provider: Provider) -> Result<CredentialStatus, String> {
+        let found = self.lookup(provider).await?;
+        Ok(CredentialStatus {
+            provider,
+            configured: found.is_some(),
+            backend: found.map(|(_, backend)| backend),
+        })
+    }
+}
+
+/// Save an API key
+#[tauri::command]
+pub async fn set_api_key(
+    store: State<'_, CredentialStore>,
+    provider: Provider,
+    api_key: String,
+) -> Result<CredentialStatus, String> {
+    let api_key = api_key.trim();
+    if api_key.is_empty() {
+        return Err("API key is empty".to_string());
+    }
+
+    let backend = store.set(provider, api_key).await?;
+    Ok(CredentialStatus {
+        provider,
+        configured: true,
+        backend: Some(backend),
+    })
+}
+
+/// Report whether a provider has a key configured
+#[tauri::command]
+pub async fn get_credential_status(
+    store: State<'_, CredentialStore>,
+    provider: Provider,
+) -> Result<CredentialStatus, String> {
+    store.status(provider).await
+}
+
+/// Check the stored key with a minimal request to the provider
+#[tauri::command]
+pub async fn test_api_key(
+    store: State<'_, CredentialStore>,
+    client: State<'_, SttClient>,
+    provider: Provider,
+) -> Result<KeyTestResult, String> {
+    let Some(key) = store.get(provider).await? else {
+        return Ok(KeyTestResult {
+            valid: false,
+            message: SttError::MissingApiKey.to_string(),
+        });
+    };
+
+    Ok(match provider {
+        Provider::Google => match stt::verify_api_key(&client, &key).await {
+            Ok(()) => KeyTestResult {
+                valid: true,
+                message: "API key is valid".to_string(),
+            },
+            Err(e) => KeyTestResult {
+                valid: false,
+                message: e.to_string(),
+            },
+        },
+    })
+}
+
+/// Delete a stored API key
+#[tauri::command]
+pub async fn clear_api_key(
+    store: State<'_, CredentialStore>,
+    provider: Provider,
+) -> Result<CredentialStatus, String> {
+    store.clear(provider).await?;
+    store.status(provider).await
+}
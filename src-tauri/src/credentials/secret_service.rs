@@ -0,0 +1,260 @@
+//! Freedesktop Secret Service backend (GNOME Keyring, KWallet, KeePassXC)
+//!
+//! Talks to `org.freedesktop.secrets` directly over the session bus with a
+//! "plain" session, the same transport libsecret uses when no encryption is
+//! negotiated. Items are stored in the default collection and looked up by
+//! the `application`/`provider` attributes.
+
+use super::Provider;
+use futures::StreamExt;
+use std::collections::HashMap;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+use zbus::Connection;
+
+const APPLICATION: &str = "birdie";
+
+/// `(session, parameters, value, content_type)`
+type Secret = (OwnedObjectPath, Vec<u8>, Vec<u8>, String);
+
+#[zbus::proxy(
+    interface = "org.freedesktop.Secret.Service",
+    default_service = "org.freedesktop.secrets",
+    default_path = "/org/freedesktop/secrets"
+)]
+trait Service {
+    fn open_session(&self, algorithm: &str, input: &Value<'_>)
+        -> zbus::Result<(OwnedValue, OwnedObjectPath)>;
+
+    fn search_items(
+        &self,
+        attributes: HashMap<&str, &str>,
+    ) -> zbus::Result<(Vec<OwnedObjectPath>, Vec<OwnedObjectPath>)>;
+
+    fn unlock(
+        &self,
+        objects: &[ObjectPath<'_>],
+    ) -> zbus::Result<(Vec<OwnedObjectPath>, OwnedObjectPath)>;
+
+    fn read_alias(&self, name: &str) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.Secret.Collection",
+    default_service = "org.freedesktop.secrets"
+)]
+trait Collection {
+    fn create_item(
+        &self,
+        properties: HashMap<&str, Value<'_>>,
+        secret: &Secret,
+        replace: bool,
+    ) -> zbus::Result<(OwnedObjectPath, OwnedObjectPath)>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.Secret.Item",
+    default_service = "org.freedesktop.secrets"
+)]
+trait Item {
+    fn get_secret(&self, session: &ObjectPath<'_>) -> zbus::Result<Secret>;
+
+    fn delete(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.Secret.Prompt",
+    default_service = "org.freedesktop.secrets"
+)]
+trait Prompt {
+    fn prompt(&self, window_id: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn completed(&self, dismissed: bool, result: Value<'_>) -> zbus::Result<()>;
+}
+
+/// Connection to the user's Secret Service
+pub struct SecretService {
+    connection: Connection,
+    service: ServiceProxy<'static>,
+    session: OwnedObjectPath,
+}
+
+impl SecretService {
+    /// Connect and open a session; fails when no Secret Service is running
+    pub async fn connect() -> Result<Self, String> {
+        let connection = Connection::session()
+            .await
+            .map_err(|e| format!("Failed to connect to D-Bus: {}", e))?;
+        let service = ServiceProxy::new(&connection)
+            .await
+            .map_err(|e| format!("Secret Service unavailable: {}", e))?;
+        let (_, session) = service
+            .open_session("plain", &Value::from(""))
+            .await
+            .map_err(|e| format!("Secret Service unavailable: {}", e))?;
+
+        Ok(Self {
+            connection,
+            service,
+            session,
+        })
+    }
+
+    fn attributes(provider: Provider) -> HashMap<&'static str, &'static str> {
+        HashMap::from([("application", APPLICATION), ("provider", provider.id())])
+    }
+
+    /// Wait for the user to answer an unlock prompt, if one is needed
+    async fn run_prompt(&self, prompt: OwnedObjectPath) -> Result<(), String> {
+        if prompt.as_str() == "/" {
+            return Ok(());
+        }
+
+        let proxy = PromptProxy::builder(&self.connection)
+            .path(prompt)
+            .map_err(|e| e.to_string())?
+            .build()
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut completed = proxy
+            .receive_completed()
+            .await
+            .map_err(|e| e.to_string())?;
+        proxy.prompt("").await.map_err(|e| e.to_string())?;
+
+        let signal = completed
+            .next()
+            .await
+            .ok_or_else(|| "Secret Service prompt was closed".to_string())?;
+        let args = signal.args().map_err(|e| e.to_string())?;
+        if args.dismissed {
+            return Err("Keyring unlock was dismissed".to_string());
+        }
+        Ok(())
+    }
+
+    /// Find stored items, unlocking them if necessary
+    async fn find_items(&self, provider: Provider) -> Result<Vec<OwnedObjectPath>, String> {
+        let (mut unlocked, locked) = self
+            .service
+            .search_items(Self::attributes(provider))
+            .await
+            .map_err(|e| format!("Failed to search keyring: {}", e))?;
+
+        if !locked.is_empty() {
+            let objects: Vec<ObjectPath<'_>> = locked.iter().map(|p| p.as_ref()).collect();
+            let (newly_unlocked, prompt) = self
+                .service
+                .unlock(&objects)
+                .await
+                .map_err(|e| format!("Failed to unlock keyring: {}", e))?;
+            unlocked.extend(newly_unlocked);
+            if prompt.as_str() != "/" {
+                self.run_prompt(prompt).await?;
+                unlocked.extend(locked);
+            }
+        }
+
+        Ok(unlocked)
+    }
+
+    async fn item(&self, path: OwnedObjectPath) -> Result<ItemProxy<'static>, String> {
+        ItemProxy::builder(&self.connection)
+            .path(path)
+            .map_err(|e| e.to_string())?
+            .build()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn get(&self, provider: Provider) -> Result<Option<String>, String> {
+        let Some(path) = self.find_items(provider).await?.into_iter().next() else {
+            return Ok(None);
+        };
+
+        let (_, _, value, _) = self
+            .item(path)
+            .await?
+            .get_secret(&self.session.as_ref())
+            .await
+            .map_err(|e| format!("Failed to read secret: {}", e))?;
+        String::from_utf8(value)
+            .map(Some)
+            .map_err(|_| "Stored secret is not valid UTF-8".to_string())
+    }
+
+    pub async fn set(&self, provider: Provider, secret: &str) -> Result<(), String> {
+        let mut collection_path = self
+            .service
+            .read_alias("default")
+            .await
+            .map_err(|e| format!("Failed to open default keyring: {}", e))?;
+        if collection_path.as_str() == "/" {
+            collection_path = ObjectPath::try_from("/org/freedesktop/secrets/aliases/default")
+                .map_err(|e| e.to_string())?
+                .into();
+        }
+
+        let collection = CollectionProxy::builder(&self.connection)
+            .path(collection_path.clone())
+            .map_err(|e| e.to_string())?
+            .build()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let properties = || {
+            HashMap::from([
+                (
+                    "org.freedesktop.Secret.Item.Label",
+                    Value::from(format!("Birdie {} API key", provider.label())),
+                ),
+                (
+                    "org.freedesktop.Secret.Item.Attributes",
+                    Value::from(Self::attributes(provider)),
+                ),
+            ])
+        };
+
+        let secret: Secret = (
+            self.session.clone(),
+            Vec::new(),
+            secret.as_bytes().to_vec(),
+            "text/plain".to_string(),
+        );
+
+        let (item, prompt) = match collection.create_item(properties(), &secret, true).await {
+            Ok(created) => created,
+            Err(_) => {
+                // The default collection is probably locked
+                let (_, unlock_prompt) = self
+                    .service
+                    .unlock(&[collection_path.as_ref()])
+                    .await
+                    .map_err(|e| format!("Failed to unlock keyring: {}", e))?;
+                self.run_prompt(unlock_prompt).await?;
+                collection
+                    .create_item(properties(), &secret, true)
+                    .await
+                    .map_err(|e| format!("Failed to store secret: {}", e))?
+            }
+        };
+
+        if item.as_str() == "/" {
+            self.run_prompt(prompt).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn clear(&self, provider: Provider) -> Result<(), String> {
+        for path in self.find_items(provider).await? {
+            let prompt = self
+                .item(path)
+                .await?
+                .delete()
+                .await
+                .map_err(|e| format!("Failed to delete secret: {}", e))?;
+            self.run_prompt(prompt).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+//! Native microphone capture
+//!
+//! Audio is read as raw 16 kHz mono PCM from `parec` (PulseAudio/PipeWire)
+//! or, failing that, `arecord` (ALSA). Using the system tools keeps the app
+//! free of native audio dependencies and follows the user's default source.
+
+use crate::stt::PcmAudio;
+use std::io::{ErrorKind, Read};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+pub const SAMPLE_RATE: u32 = 16_000;
+
+/// 20 ms of audio
+pub const FRAME_SAMPLES: usize = SAMPLE_RATE as usize / 50;
+
+/// Push-to-talk recordings are cut off after this long
+const MAX_RECORDING_SECS: usize = 300;
+
+fn capture_commands() -> Vec<Command> {
+    let rate = SAMPLE_RATE.to_string();
+
+    let mut parec = Command::new("parec");
+    parec.args([
+        "--raw",
+        "--format=s16le",
+        "--channels=1",
+        "--latency-msec=40",
+    ]);
+    parec.arg(format!("--rate={}", rate));
+
+    let mut arecord = Command::new("arecord");
+    arecord.args(["-q", "-t", "raw", "-f", "S16_LE", "-c", "1", "-r", &rate]);
+
+    vec![parec, arecord]
+}
+
+/// A running capture process
+pub struct CaptureStream {
+    child: Child,
+    stdout: ChildStdout,
+    bytes: Vec<u8>,
+}
+
+impl CaptureStream {
+    pub fn open() -> Result<Self, String> {
+        if !cfg!(target_os = "linux") {
+            return Err("Native audio capture is only supported on Linux".to_string());
+        }
+
+        for mut command in capture_commands() {
+            let spawned = command
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn();
+            match spawned {
+                Ok(mut child) => {
+                    let Some(stdout) = child.stdout.take() else {
+                        let _ = child.kill();
+                        continue;
+                    };
+                    return Ok(Self {
+                        child,
+                        stdout,
+                        bytes: vec![0; FRAME_SAMPLES * 2],
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(format!("Failed to start audio capture: {}", e)),
+            }
+        }
+
+        Err("No audio capture tool found (install pulseaudio-utils or alsa-utils)".to_string())
+    }
+
+    /// Read the next 20 ms frame
+    pub fn read_frame(&mut self, frame: &mut [i16; FRAME_SAMPLES]) -> Result<(), String> {
+        self.stdout
+            .read_exact(&mut self.bytes)
+            .map_err(|e| format!("Audio capture stopped: {}", e))?;
+        for (sample, bytes) in frame.iter_mut().zip(self.bytes.chunks_exact(2)) {
+            *sample = i16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for CaptureStream {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// A push-to-talk recording that runs until stopped
+pub struct Recording {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<Vec<i16>>,
+}
+
+impl Recording {
+    pub fn start() -> Result<Self, String> {
+        let mut stream = CaptureStream::open()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+
+        let thread = std::thread::spawn(move || {
+            let mut samples = Vec::new();
+            let mut frame = [0i16; FRAME_SAMPLES];
+            while !stop_flag.load(Ordering::Relaxed)
+                && samples.len() < MAX_RECORDING_SECS * SAMPLE_RATE as usize
+            {
+                if stream.read_frame(&mut frame).is_err() {
+                    break;
+                }
+                samples.extend_from_slice(&frame);
+            }
+            samples
+        });
+
+        Ok(Self { stop, thread })
+    }
+
+    pub fn stop(self) -> PcmAudio {
+        self.stop.store(true, Ordering::Relaxed);
+        let samples = self.thread.join().unwrap_or_default();
+        PcmAudio {
+            sample_rate: SAMPLE_RATE,
+            samples,
+        }
+    }
+}
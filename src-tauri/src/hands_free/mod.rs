@@ -0,0 +1,481 @@
+//! Hands-free voice commands
+//!
+//! Push-to-talk is a global shortcut registered from the backend, so it keeps
+//! working when the webview is closed or reloaded. The optional wake phrase
+//! ("oye birdie") is detected offline on native audio capture. Either one
+//! records a command, transcribes it and runs it through the voice command
+//! interpreter.
+
+mod capture;
+mod wake;
+
+use crate::stt::{self, PcmAudio};
+use crate::voice_commands::{self, VoiceCommandParser};
+use crate::{context_mapper::ContextMapper, settings};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use capture::{CaptureStream, Recording, FRAME_SAMPLES};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+use wake::{FeatureExtractor, Features, SpeechSegmenter, WakeDetector};
+
+/// Silence that ends a wake-phrase candidate
+const WAKE_END_SILENCE_MS: usize = 300;
+/// Bursts longer than this are not the wake phrase
+const WAKE_MAX_MS: usize = 2500;
+/// Bursts shorter than this are clicks or coughs
+const WAKE_MIN_SECS: f32 = 0.3;
+
+/// Silence that ends a spoken command
+const COMMAND_END_SILENCE_MS: usize = 900;
+const COMMAND_MAX_MS: usize = 15_000;
+/// How long to wait for a command after the wake phrase
+const COMMAND_START_TIMEOUT_MS: usize = 5000;
+
+/// Recordings shorter than this are treated as accidental presses
+const MIN_COMMAND_SECS: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PushToTalkMode {
+    /// Record while the shortcut is held down
+    Hold,
+    /// Press once to start, again to stop
+    Toggle,
+}
+
+/// Persisted hands-free settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HandsFreeConfig {
+    pub push_to_talk_enabled: bool,
+    pub push_to_talk_shortcut: String,
+    pub push_to_talk_mode: PushToTalkMode,
+    pub wake_phrase_enabled: bool,
+    /// Shown in the UI; detection uses the recorded samples
+    pub wake_phrase: String,
+    /// From 0 (strict) to 1 (permissive)
+    pub wake_sensitivity: f32,
+    pub language_code: String,
+}
+
+impl Default for HandsFreeConfig {
+    fn default() -> Self {
+        Self {
+            push_to_talk_enabled: true,
+            push_to_talk_shortcut: "ctrl+shift+space".to_string(),
+            push_to_talk_mode: PushToTalkMode::Hold,
+            wake_phrase_enabled: false,
+            wake_phrase: "oye birdie".to_string(),
+            wake_sensitivity: 0.5,
+            language_code: "es-ES".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandsFreeState {
+    Idle,
+    /// Waiting for the wake phrase
+    Listening,
+    Recording,
+    Transcribing,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HandsFreeStatus {
+    pub config: HandsFreeConfig,
+    /// Number of recorded wake-phrase samples
+    pub wake_samples: usize,
+}
+
+/// Background thread listening for the wake phrase
+struct WakeListener {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl WakeListener {
+    fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.thread.join();
+    }
+}
+
+/// Managed state for push-to-talk and the wake phrase
+pub struct HandsFree {
+    config_path: PathBuf,
+    templates_path: PathBuf,
+    config: Mutex<HandsFreeConfig>,
+    templates: Mutex<Vec<Features>>,
+    registered: Mutex<Option<Shortcut>>,
+    recording: Mutex<Option<Recording>>,
+    listener: Mutex<Option<WakeListener>>,
+    /// Set while a command is recorded or transcribed; pauses the wake listener
+    busy: Arc<AtomicBool>,
+}
+
+impl HandsFree {
+    pub fn new(config_dir: &Path) -> Self {
+        let config_path = config_dir.join("hands_free.json");
+        let templates_path = config_dir.join("wake_phrase.json");
+        Self {
+            config: Mutex::new(settings::load(&config_path)),
+            templates: Mutex::new(settings::load(&templates_path)),
+            config_path,
+            templates_path,
+            registered: Mutex::new(None),
+            recording: Mutex::new(None),
+            listener: Mutex::new(None),
+            busy: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub(crate) fn config(&self) -> HandsFreeConfig {
+        self.config.lock().unwrap().clone()
+    }
+
+    fn idle_state(&self) -> HandsFreeState {
+        if self.listener.lock().unwrap().is_some() {
+            HandsFreeState::Listening
+        } else {
+            HandsFreeState::Idle
+        }
+    }
+
+    /// Register the shortcut and start or stop the wake listener
+    pub fn apply(&self, app: &AppHandle) -> Result<(), String> {
+        let config = self.config();
+        self.register_shortcut(app, &config)?;
+
+        if let Some(listener) = self.listener.lock().unwrap().take() {
+            listener.stop();
+        }
+        let templates = self.templates.lock().unwrap().clone();
+        if config.wake_phrase_enabled && !templates.is_empty() {
+            let detector = WakeDetector::new(templates, config.wake_sensitivity);
+            *self.listener.lock().unwrap() = Some(start_wake_listener(
+                app.clone(),
+                detector,
+                self.busy.clone(),
+            ));
+        }
+
+        emit_state(app, self.idle_state());
+        Ok(())
+    }
+
+    fn register_shortcut(&self, app: &AppHandle, config: &HandsFreeConfig) -> Result<(), String> {
+        let global_shortcut = app.global_shortcut();
+        if let Some(previous) = self.registered.lock().unwrap().take() {
+            global_shortcut
+                .unregister(previous)
+                .map_err(|e| format!("Failed to unregister shortcut: {}", e))?;
+        }
+        if !config.push_to_talk_enabled {
+            return Ok(());
+        }
+
+        let shortcut = parse_shortcut(&config.push_to_talk_shortcut)?;
+        global_shortcut
+            .on_shortcut(shortcut, |app, _shortcut, event| {
+                on_push_to_talk(app, event.state);
+            })
+            .map_err(|e| {
+                format!(
+                    "Failed to register shortcut {}: {}",
+                    config.push_to_talk_shortcut, e
+                )
+            })?;
+        *self.registered.lock().unwrap() = Some(shortcut);
+        eprintln!("✓ Push-to-talk bound to {}", config.push_to_talk_shortcut);
+        Ok(())
+    }
+
+    fn start_recording(&self, app: &AppHandle) {
+        let mut recording = self.recording.lock().unwrap();
+        if recording.is_some() || self.busy.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        match Recording::start() {
+            Ok(started) => {
+                *recording = Some(started);
+                emit_state(app, HandsFreeState::Recording);
+            }
+            Err(e) => {
+                self.busy.store(false, Ordering::SeqCst);
+                emit_error(app, &e);
+            }
+        }
+    }
+
+    fn finish_recording(&self, app: &AppHandle) {
+        let Some(recording) = self.recording.lock().unwrap().take() else {
+            return;
+        };
+        let audio = recording.stop();
+        tauri::async_runtime::spawn(run_command(app.clone(), audio));
+    }
+}
+
+pub(crate) fn parse_shortcut(shortcut: &str) -> Result<Shortcut, String> {
+    shortcut
+        .parse()
+        .map_err(|e| format!("Invalid shortcut {:?}: {}", shortcut, e))
+}
+
+fn emit_state(app: &AppHandle, state: HandsFreeState) {
+    if let Err(e) = app.emit("hands-free-state", state) {
+        eprintln!("✗ Error emitting hands-free state: {}", e);
+    }
+}
+
+fn emit_error(app: &AppHandle, message: &str) {
+    eprintln!("✗ Hands-free: {}", message);
+    let _ = app.emit("hands-free-error", message.to_string());
+}
+
+fn on_push_to_talk(app: &AppHandle, state: ShortcutState) {
+    let hands_free = app.state::<HandsFree>();
+    let recording = hands_free.recording.lock().unwrap().is_some();
+    match (hands_free.config().push_to_talk_mode, state) {
+        (PushToTalkMode::Hold, ShortcutState::Pressed) => hands_free.start_recording(app),
+        (PushToTalkMode::Hold, ShortcutState::Released) => hands_free.finish_recording(app),
+        (PushToTalkMode::Toggle, ShortcutState::Pressed) if recording => {
+            hands_free.finish_recording(app)
+        }
+        (PushToTalkMode::Toggle, ShortcutState::Pressed) => hands_free.start_recording(app),
+        (PushToTalkMode::Toggle, ShortcutState::Released) => {}
+    }
+}
+
+/// Transcribe a spoken command and hand it to the interpreter
+async fn run_command(app: AppHandle, audio: PcmAudio) {
+    if audio.duration_secs() >= MIN_COMMAND_SECS {
+        emit_state(&app, HandsFreeState::Transcribing);
+        let audio_base64 = BASE64.encode(stt::encode_wav(&audio));
+        let language_code = app.state::<HandsFree>().config().language_code;
+
+        match crate::transcribe_with_context(&app, audio_base64, language_code.clone(), None).await
+        {
+            Ok(result) if result.success => {
+                let _ = app.emit("hands-free-transcript", &result);
+                let parser = VoiceCommandParser::new(&ContextMapper::new());
+                // Commands are parsed from the raw transcript; only the
+                // dictated reply text is post-processed
+                let mut interpretation = parser.interpret(&result.text, result.confidence);
+                let replacements = crate::dictation_replacements(&app, &language_code);
+                voice_commands::postprocess_dictation(
+                    &mut interpretation,
+                    &language_code,
+                    &replacements,
+                );
+                voice_commands::execute(&app, &interpretation, &language_code);
+                let _ = app.emit("voice-command-result", &interpretation);
+            }
+            Ok(result) => emit_error(&app, &result.error.unwrap_or_default()),
+            Err(e) => emit_error(&app, &e),
+        }
+    }
+
+    let hands_free = app.state::<HandsFree>();
+    hands_free.busy.store(false, Ordering::SeqCst);
+    emit_state(&app, hands_free.idle_state());
+}
+
+fn start_wake_listener(
+    app: AppHandle,
+    detector: WakeDetector,
+    busy: Arc<AtomicBool>,
+) -> WakeListener {
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_flag = stop.clone();
+
+    let thread = std::thread::spawn(move || {
+        let mut stream = match CaptureStream::open() {
+            Ok(stream) => stream,
+            Err(e) => return emit_error(&app, &e),
+        };
+        eprintln!("✓ Listening for the wake phrase");
+
+        let mut segmenter = SpeechSegmenter::new(WAKE_END_SILENCE_MS, WAKE_MAX_MS);
+        let mut frame = [0i16; FRAME_SAMPLES];
+        while !stop_flag.load(Ordering::Relaxed) {
+            if let Err(e) = stream.read_frame(&mut frame) {
+                return emit_error(&app, &e);
+            }
+            if busy.load(Ordering::SeqCst) {
+                segmenter.reset();
+                continue;
+            }
+            let Some(utterance) = segmenter.push(&frame) else {
+                continue;
+            };
+            let secs = utterance.len() as f32 / capture::SAMPLE_RATE as f32;
+            if secs < WAKE_MIN_SECS || detector.detect(&utterance).is_none() {
+                continue;
+            }
+            if busy.swap(true, Ordering::SeqCst) {
+                continue;
+            }
+
+            let _ = app.emit("wake-phrase-detected", ());
+            emit_state(&app, HandsFreeState::Recording);
+            segmenter.set_limits(COMMAND_END_SILENCE_MS, COMMAND_MAX_MS);
+            match record_until_silence(&mut stream, &mut segmenter, &stop_flag) {
+                Some(samples) => {
+                    let audio = PcmAudio {
+                        sample_rate: capture::SAMPLE_RATE,
+                        samples,
+                    };
+                    tauri::async_runtime::spawn(run_command(app.clone(), audio));
+                }
+                None => {
+                    busy.store(false, Ordering::SeqCst);
+                    emit_state(&app, HandsFreeState::Listening);
+                }
+            }
+            segmenter.set_limits(WAKE_END_SILENCE_MS, WAKE_MAX_MS);
+        }
+    });
+
+    WakeListener { stop, thread }
+}
+
+/// Record one utterance, giving up if it does not start in time
+fn record_until_silence(
+    stream: &mut CaptureStream,
+    segmenter: &mut SpeechSegmenter,
+    stop: &AtomicBool,
+) -> Option<Vec<i16>> {
+    let mut frame = [0i16; FRAME_SAMPLES];
+    let mut waited_ms = 0;
+    while !stop.load(Ordering::Relaxed) {
+        stream.read_frame(&mut frame).ok()?;
+        if let Some(utterance) = segmenter.push(&frame) {
+            return Some(utterance);
+        }
+        if !segmenter.in_speech() {
+            waited_ms += 20;
+            if waited_ms >= COMMAND_START_TIMEOUT_MS {
+                return None;
+            }
+        }
+    }
+    None
+}
+
+#[tauri::command]
+pub fn get_hands_free_status(hands_free: tauri::State<'_, HandsFree>) -> HandsFreeStatus {
+    HandsFreeStatus {
+        config: hands_free.config(),
+        wake_samples: hands_free.templates.lock().unwrap().len(),
+    }
+}
+
+#[tauri::command]
+pub fn set_hands_free_config(
+    app_handle: AppHandle,
+    hands_free: tauri::State<'_, HandsFree>,
+    config: HandsFreeConfig,
+) -> Result<(), String> {
+    if config.push_to_talk_enabled {
+        parse_shortcut(&config.push_to_talk_shortcut)?;
+    }
+    if config.wake_phrase_enabled && hands_free.templates.lock().unwrap().is_empty() {
+        return Err("Record the wake phrase before enabling it".to_string());
+    }
+
+    settings::save(&hands_free.config_path, &config)?;
+    *hands_free.config.lock().unwrap() = config;
+    hands_free.apply(&app_handle)
+}
+
+/// Record one sample of the wake phrase; returns the number of samples
+#[tauri::command]
+pub async fn record_wake_phrase_sample(app_handle: AppHandle) -> Result<usize, String> {
+    let hands_free = app_handle.state::<HandsFree>();
+    if hands_free.busy.swap(true, Ordering::SeqCst) {
+        return Err("A command is being recorded".to_string());
+    }
+    emit_state(&app_handle, HandsFreeState::Recording);
+
+    let recorded = tauri::async_runtime::spawn_blocking(|| {
+        let mut stream = CaptureStream::open()?;
+        let mut segmenter = SpeechSegmenter::new(WAKE_END_SILENCE_MS, WAKE_MAX_MS);
+        // Let the segmenter learn the background level first
+        let mut frame = [0i16; FRAME_SAMPLES];
+        for _ in 0..15 {
+            stream.read_frame(&mut frame)?;
+            segmenter.push(&frame);
+        }
+        record_until_silence(&mut stream, &mut segmenter, &AtomicBool::new(false))
+            .ok_or_else(|| "No speech heard".to_string())
+    })
+    .await
+    .map_err(|e| format!("Recording failed: {}", e))
+    .and_then(|r| r);
+
+    hands_free.busy.store(false, Ordering::SeqCst);
+    emit_state(&app_handle, hands_free.idle_state());
+
+    let samples = recorded?;
+    if (samples.len() as f32) < WAKE_MIN_SECS * capture::SAMPLE_RATE as f32 {
+        return Err("Sample too short".to_string());
+    }
+
+    let count = {
+        let mut templates = hands_free.templates.lock().unwrap();
+        templates.push(FeatureExtractor::new().extract(&samples));
+        settings::save(&hands_free.templates_path, &*templates)?;
+        templates.len()
+    };
+    hands_free.apply(&app_handle)?;
+    Ok(count)
+}
+
+#[tauri::command]
+pub fn clear_wake_phrase_samples(
+    app_handle: AppHandle,
+    hands_free: tauri::State<'_, HandsFree>,
+) -> Result<(), String> {
+    hands_free.templates.lock().unwrap().clear();
+    settings::save(&hands_free.templates_path, &Vec::<Features>::new())?;
+    hands_free.apply(&app_handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_defaults_fill_missing_fields() {
+        let config: HandsFreeConfig =
+            serde_json::from_str(r#"{"push_to_talk_mode": "toggle", "wake_phrase_enabled": true}"#)
+                .unwrap();
+        assert_eq!(config.push_to_talk_mode, PushToTalkMode::Toggle);
+        assert!(config.wake_phrase_enabled);
+        assert_eq!(config.push_to_talk_shortcut, "ctrl+shift+space");
+        assert_eq!(config.language_code, "es-ES");
+    }
+
+    #[test]
+    fn test_settings_persist_across_instances() {
+        let dir = std::env::temp_dir().join(format!("birdie-hands-free-{}", std::process::id()));
+        let hands_free = HandsFree::new(&dir);
+        let mut config = hands_free.config();
+        config.push_to_talk_shortcut = "alt+f9".to_string();
+        settings::save(&hands_free.config_path, &config).unwrap();
+
+        let reloaded = HandsFree::new(&dir);
+        assert_eq!(reloaded.config().push_to_talk_shortcut, "alt+f9");
+        assert_eq!(reloaded.templates.lock().unwrap().len(), 0);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
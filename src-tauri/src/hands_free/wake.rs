@@ -0,0 +1,431 @@
+//! Offline wake-phrase detection
+//!
+//! Speech bursts are cut out of the microphone stream by an energy-based
+//! segmenter, turned into mel-cepstral features and compared with the
+//! user's recorded samples of the wake phrase using dynamic time warping.
+//! Nothing leaves the machine until the phrase has been heard.
+
+use super::capture::{FRAME_SAMPLES, SAMPLE_RATE};
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+const ANALYSIS_FRAME: usize = 400; // 25 ms
+const ANALYSIS_HOP: usize = 160; // 10 ms
+const DFT_LEN: usize = 512;
+const MEL_BANDS: usize = 24;
+pub const CEPSTRA: usize = 12;
+
+/// Mel-cepstral features of an utterance, one row per 10 ms
+pub type Features = Vec<[f32; CEPSTRA]>;
+
+/// Distance used when there is only one sample to calibrate against
+const SINGLE_TEMPLATE_THRESHOLD: f32 = 9.0;
+
+/// Levels below this are never treated as speech
+const MIN_SPEECH_LEVEL: f32 = 300.0;
+
+/// Speech must be this many times louder than the background
+const SPEECH_TO_NOISE: f32 = 3.0;
+
+/// Frames kept from before the speech onset
+const PRE_ROLL_FRAMES: usize = 10;
+
+fn hz_to_mel(hz: f32) -> f32 {
+    2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+fn mel_to_hz(mel: f32) -> f32 {
+    700.0 * (10f32.powf(mel / 2595.0) - 1.0)
+}
+
+/// Computes mel-frequency cepstral coefficients
+pub struct FeatureExtractor {
+    window: Vec<f32>,
+    cos: Vec<f32>,
+    sin: Vec<f32>,
+    /// Triangular mel filters as `(bin, weight)` pairs
+    filters: Vec<Vec<(usize, f32)>>,
+}
+
+impl FeatureExtractor {
+    pub fn new() -> Self {
+        let window = (0..ANALYSIS_FRAME)
+            .map(|n| 0.54 - 0.46 * (2.0 * PI * n as f32 / (ANALYSIS_FRAME - 1) as f32).cos())
+            .collect();
+        let cos = (0..DFT_LEN)
+            .map(|i| (2.0 * PI * i as f32 / DFT_LEN as f32).cos())
+            .collect();
+        let sin = (0..DFT_LEN)
+            .map(|i| (2.0 * PI * i as f32 / DFT_LEN as f32).sin())
+            .collect();
+
+        let bins = DFT_LEN / 2 + 1;
+        let (low, high) = (hz_to_mel(100.0), hz_to_mel(7000.0));
+        let edges: Vec<f32> = (0..MEL_BANDS + 2)
+            .map(|i| {
+                let hz = mel_to_hz(low + (high - low) * i as f32 / (MEL_BANDS + 1) as f32);
+                hz * DFT_LEN as f32 / SAMPLE_RATE as f32
+            })
+            .collect();
+        let filters = (0..MEL_BANDS)
+            .map(|band| {
+                let (left, center, right) = (edges[band], edges[band + 1], edges[band + 2]);
+                (0..bins)
+                    .filter_map(|bin| {
+                        let b = bin as f32;
+                        let weight = if b > left && b <= center {
+                            (b - left) / (center - left)
+                        } else if b > center && b < right {
+                            (right - b) / (right - center)
+                        } else {
+                            return None;
+                        };
+                        Some((bin, weight))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            window,
+            cos,
+            sin,
+            filters,
+        }
+    }
+
+    pub fn extract(&self, samples: &[i16]) -> Features {
+        let mut features = Features::new();
+        let mut frame = [0f32; ANALYSIS_FRAME];
+        let mut power = vec![0f32; DFT_LEN / 2 + 1];
+
+        let mut start = 0;
+        while start + ANALYSIS_FRAME <= samples.len() {
+            // Pre-emphasis and windowing
+            let mut previous = if start > 0 {
+                samples[start - 1] as f32
+            } else {
+                0.0
+            };
+            for (i, value) in frame.iter_mut().enumerate() {
+                let sample = samples[start + i] as f32;
+                *value = (sample - 0.97 * previous) * self.window[i];
+                previous = sample;
+            }
+
+            for (k, p) in power.iter_mut().enumerate() {
+                let (mut re, mut im) = (0f32, 0f32);
+                for (n, value) in frame.iter().enumerate() {
+                    let index = (k * n) % DFT_LEN;
+                    re += value * self.cos[index];
+                    im -= value * self.sin[index];
+                }
+                *p = re * re + im * im;
+            }
+
+            let log_mel: Vec<f32> = self
+                .filters
+                .iter()
+                .map(|filter| {
+                    let energy: f32 = filter.iter().map(|&(bin, w)| power[bin] * w).sum();
+                    energy.max(1e-6).ln()
+                })
+                .collect();
+
+            let mut row = [0f32; CEPSTRA];
+            for (i, coefficient) in row.iter_mut().enumerate() {
+                *coefficient = log_mel
+                    .iter()
+                    .enumerate()
+                    .map(|(j, e)| {
+                        e * (PI * (i + 1) as f32 * (j as f32 + 0.5) / MEL_BANDS as f32).cos()
+                    })
+                    .sum();
+            }
+            features.push(row);
+            start += ANALYSIS_HOP;
+        }
+
+        // Cepstral mean normalization removes the microphone's colouring
+        if !features.is_empty() {
+            let mut mean = [0f32; CEPSTRA];
+            for row in &features {
+                for (m, c) in mean.iter_mut().zip(row) {
+                    *m += c / features.len() as f32;
+                }
+            }
+            for row in &mut features {
+                for (c, m) in row.iter_mut().zip(&mean) {
+                    *c -= m;
+                }
+            }
+        }
+
+        features
+    }
+}
+
+/// Length-normalized dynamic time warping distance
+pub fn dtw_distance(a: &Features, b: &Features) -> f32 {
+    if a.is_empty() || b.is_empty() {
+        return f32::INFINITY;
+    }
+
+    let cost = |x: &[f32; CEPSTRA], y: &[f32; CEPSTRA]| {
+        x.iter()
+            .zip(y)
+            .map(|(p, q)| (p - q) * (p - q))
+            .sum::<f32>()
+            .sqrt()
+    };
+
+    let mut previous = vec![f32::INFINITY; b.len() + 1];
+    let mut current = vec![f32::INFINITY; b.len() + 1];
+    previous[0] = 0.0;
+
+    for x in a {
+        current[0] = f32::INFINITY;
+        for (j, y) in b.iter().enumerate() {
+            let best = previous[j].min(previous[j + 1]).min(current[j]);
+            current[j + 1] = cost(x, y) + best;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()] / (a.len() + b.len()) as f32
+}
+
+/// Matches utterances against recorded samples of the wake phrase
+pub struct WakeDetector {
+    extractor: FeatureExtractor,
+    templates: Vec<Features>,
+    threshold: f32,
+}
+
+impl WakeDetector {
+    /// `sensitivity` goes from 0 (strict) to 1 (permissive)
+    pub fn new(templates: Vec<Features>, sensitivity: f32) -> Self {
+        // Calibrate against how much the user's own samples differ
+        let mut pairs = Vec::new();
+        for (i, a) in templates.iter().enumerate() {
+            for b in &templates[i + 1..] {
+                pairs.push(dtw_distance(a, b));
+            }
+        }
+        let spread = if pairs.is_empty() {
+            SINGLE_TEMPLATE_THRESHOLD / 1.5
+        } else {
+            pairs.iter().sum::<f32>() / pairs.len() as f32
+        };
+
+        Self {
+            extractor: FeatureExtractor::new(),
+            templates,
+            threshold: spread * (1.0 + sensitivity.clamp(0.0, 1.0)),
+        }
+    }
+
+    /// Best distance to a template, when it is close enough to count
+    pub fn detect(&self, samples: &[i16]) -> Option<f32> {
+        let features = self.extractor.extract(samples);
+        self.templates
+            .iter()
+            .map(|template| dtw_distance(&features, template))
+            .fold(None, |best: Option<f32>, d| {
+                Some(best.map_or(d, |b| b.min(d)))
+            })
+            .filter(|&d| d <= self.threshold)
+    }
+}
+
+fn frame_level(frame: &[i16]) -> f32 {
+    let sum: f64 = frame.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    (sum / frame.len().max(1) as f64).sqrt() as f32
+}
+
+/// Cuts speech bursts out of a stream of 20 ms frames
+pub struct SpeechSegmenter {
+    end_silence_frames: usize,
+    max_frames: usize,
+    noise_floor: f32,
+    pre_roll: VecDeque<Vec<i16>>,
+    speech: Vec<i16>,
+    in_speech: bool,
+    speech_frames: usize,
+    silence_frames: usize,
+}
+
+impl SpeechSegmenter {
+    pub fn new(end_silence_ms: usize, max_ms: usize) -> Self {
+        Self {
+            end_silence_frames: end_silence_ms / 20,
+            max_frames: max_ms / 20,
+            noise_floor: 0.0,
+            pre_roll: VecDeque::new(),
+            speech: Vec::new(),
+            in_speech: false,
+            speech_frames: 0,
+            silence_frames: 0,
+        }
+    }
+
+    /// Change the limits, keeping the learned background level
+    pub fn set_limits(&mut self, end_silence_ms: usize, max_ms: usize) {
+        self.end_silence_frames = end_silence_ms / 20;
+        self.max_frames = max_ms / 20;
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        self.pre_roll.clear();
+        self.speech.clear();
+        self.in_speech = false;
+        self.speech_frames = 0;
+        self.silence_frames = 0;
+    }
+
+    pub fn in_speech(&self) -> bool {
+        self.in_speech
+    }
+
+    /// Feed a frame; returns the utterance once it has ended
+    pub fn push(&mut self, frame: &[i16]) -> Option<Vec<i16>> {
+        let level = frame_level(frame);
+        let threshold = (self.noise_floor * SPEECH_TO_NOISE).max(MIN_SPEECH_LEVEL);
+
+        if !self.in_speech {
+            if level > threshold {
+                self.in_speech = true;
+                self.speech = self.pre_roll.drain(..).flatten().collect();
+                self.speech.extend_from_slice(frame);
+                self.speech_frames = 1;
+                self.silence_frames = 0;
+            } else {
+                self.noise_floor = if self.noise_floor == 0.0 {
+                    level
+                } else {
+                    self.noise_floor * 0.95 + level * 0.05
+                };
+                self.pre_roll.push_back(frame.to_vec());
+                if self.pre_roll.len() > PRE_ROLL_FRAMES {
+                    self.pre_roll.pop_front();
+                }
+            }
+            return None;
+        }
+
+        self.speech.extend_from_slice(frame);
+        self.speech_frames += 1;
+        if level > threshold {
+            self.silence_frames = 0;
+        } else {
+            self.silence_frames += 1;
+        }
+
+        let ended = self.silence_frames >= self.end_silence_frames;
+        if !ended && self.speech_frames < self.max_frames {
+            return None;
+        }
+
+        // Drop the trailing silence, keeping a little tail
+        let tail = self.silence_frames.saturating_sub(5) * FRAME_SAMPLES;
+        let mut speech = std::mem::take(&mut self.speech);
+        speech.truncate(speech.len().saturating_sub(tail));
+        self.reset();
+        Some(speech)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A vowel-like sound: harmonics of `pitch` shaped by two formants
+    fn voice(formants: (f32, f32), pitch: f32, secs: f32) -> Vec<i16> {
+        let n = (secs * SAMPLE_RATE as f32) as usize;
+        (0..n)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                let mut value = 0.0;
+                let mut h = pitch;
+                while h < 4000.0 {
+                    let gain = (-((h - formants.0) / 200.0).powi(2)).exp()
+                        + (-((h - formants.1) / 300.0).powi(2)).exp();
+                    value += gain * (2.0 * PI * h * t).sin();
+                    h += pitch;
+                }
+                (value * 3000.0) as i16
+            })
+            .collect()
+    }
+
+    fn phrase(pitch: f32) -> Vec<i16> {
+        // "o-ye" then "bir-die": four vowel-ish segments
+        [
+            (500.0, 900.0),
+            (300.0, 2300.0),
+            (400.0, 1600.0),
+            (300.0, 2300.0),
+        ]
+        .iter()
+        .flat_map(|&f| voice(f, pitch, 0.15))
+        .collect()
+    }
+
+    #[test]
+    fn test_dtw_of_identical_features_is_zero() {
+        let features = FeatureExtractor::new().extract(&phrase(120.0));
+        assert!(!features.is_empty());
+        assert!(dtw_distance(&features, &features) < 1e-4);
+    }
+
+    #[test]
+    fn test_detector_prefers_the_enrolled_phrase() {
+        let extractor = FeatureExtractor::new();
+        let templates = vec![
+            extractor.extract(&phrase(110.0)),
+            extractor.extract(&phrase(130.0)),
+        ];
+        let detector = WakeDetector::new(templates, 0.5);
+
+        assert!(detector.detect(&phrase(120.0)).is_some());
+
+        let other: Vec<i16> = [
+            (700.0, 1200.0),
+            (700.0, 1200.0),
+            (500.0, 900.0),
+            (700.0, 1200.0),
+        ]
+        .iter()
+        .flat_map(|&f| voice(f, 120.0, 0.15))
+        .collect();
+        assert!(detector.detect(&other).is_none());
+    }
+
+    #[test]
+    fn test_segmenter_returns_burst_after_silence() {
+        let mut segmenter = SpeechSegmenter::new(300, 2500);
+        let silence = [10i16; FRAME_SAMPLES];
+        for _ in 0..20 {
+            assert!(segmenter.push(&silence).is_none());
+        }
+
+        let speech = voice((500.0, 900.0), 120.0, 0.6);
+        for frame in speech.chunks_exact(FRAME_SAMPLES) {
+            assert!(segmenter.push(frame).is_none());
+        }
+        assert!(segmenter.in_speech());
+
+        let mut utterance = None;
+        for _ in 0..20 {
+            if let Some(u) = segmenter.push(&silence) {
+                utterance = Some(u);
+                break;
+            }
+        }
+        let utterance = utterance.expect("utterance should end after silence");
+        let secs = utterance.len() as f32 / SAMPLE_RATE as f32;
+        assert!(secs > 0.6 && secs < 1.1, "unexpected length {}", secs);
+        assert!(!segmenter.in_speech());
+    }
+}
@@ -1,8 +1,16 @@
+mod credentials;
+mod hands_free;
 mod notifications;
 mod stt;
 mod cdp;
 mod vision;
 mod context_mapper;
+mod reply;
+mod settings;
+mod tts;
+mod voice_commands;
+
+use tauri::Manager;
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
@@ -10,13 +18,111 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+/// Transcribe with the stored API key and phrase hints for the active app
+///
+/// The transcript is returned as recognized; dictation is post-processed by
+/// the caller so that voice commands are matched against the raw words.
+pub(crate) async fn transcribe_with_context(
+    app_handle: &tauri::AppHandle,
+    audio_base64: String,
+    language_code: String,
+    app_name: Option<&str>,
+) -> Result<stt::TranscriptionResult, String> {
+    let client = app_handle.state::<stt::SttClient>();
+    let credentials = app_handle.state::<credentials::CredentialStore>();
+    let adaptation = app_handle.state::<stt::adaptation::SpeechAdaptation>();
+
+    // The key is read here so it never has to pass through the webview
+    let api_key = credentials
+        .get(credentials::Provider::Google)
+        .await?
+        .unwrap_or_default();
+    let hints = adaptation.hints(
+        app_name,
+        &context_mapper::ContextMapper::new(),
+        chrono::Utc::now().timestamp(),
+    );
+
+    stt::transcribe_audio(&client, audio_base64, api_key, language_code, &hints).await
+}
+
+/// The user's replacements for `language_code`, for post-processing dictation
+pub(crate) fn dictation_replacements(
+    app_handle: &tauri::AppHandle,
+    language_code: &str,
+) -> Vec<stt::postprocess::Replacement> {
+    app_handle
+        .state::<stt::postprocess::Replacements>()
+        .get(language_code)
+}
+
 #[tauri::command]
 async fn transcribe_audio(
+    app_handle: tauri::AppHandle,
     audio_base64: String,
-    api_key: String,
     language_code: String,
+    app_name: Option<String>,
 ) -> Result<stt::TranscriptionResult, String> {
-    stt::transcribe_audio(audio_base64, api_key, language_code).await
+    let mut result = transcribe_with_context(
+        &app_handle,
+        audio_base64,
+        language_code.clone(),
+        app_name.as_deref(),
+    )
+    .await?;
+    if result.success {
+        let replacements = dictation_replacements(&app_handle, &language_code);
+        stt::postprocess::apply(&mut result, &language_code, &replacements);
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+fn get_glossary(adaptation: tauri::State<'_, stt::adaptation::SpeechAdaptation>) -> Vec<String> {
+    adaptation.glossary()
+}
+
+#[tauri::command]
+fn set_glossary(
+    adaptation: tauri::State<'_, stt::adaptation::SpeechAdaptation>,
+    terms: Vec<String>,
+) -> Result<Vec<String>, String> {
+    adaptation.set_glossary(terms)?;
+    Ok(adaptation.glossary())
+}
+
+#[tauri::command]
+fn get_replacements(
+    replacements: tauri::State<'_, stt::postprocess::Replacements>,
+    language_code: String,
+) -> Vec<stt::postprocess::Replacement> {
+    replacements.get(&language_code)
+}
+
+#[tauri::command]
+fn set_replacements(
+    replacements: tauri::State<'_, stt::postprocess::Replacements>,
+    language_code: String,
+    entries: Vec<stt::postprocess::Replacement>,
+) -> Result<Vec<stt::postprocess::Replacement>, String> {
+    replacements.set(&language_code, entries)?;
+    Ok(replacements.get(&language_code))
+}
+
+#[tauri::command]
+fn get_phrase_hints(
+    adaptation: tauri::State<'_, stt::adaptation::SpeechAdaptation>,
+    app_name: Option<String>,
+) -> stt::adaptation::PhraseHintsPreview {
+    let hints = adaptation.hints(
+        app_name.as_deref(),
+        &context_mapper::ContextMapper::new(),
+        chrono::Utc::now().timestamp(),
+    );
+    stt::adaptation::PhraseHintsPreview {
+        whisper_prompt: hints.whisper_prompt(),
+        contexts: hints.contexts,
+    }
 }
 
 #[tauri::command]
@@ -52,9 +158,34 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_tts::init())
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .manage(stt::SttClient::new())
+        .manage(reply::PendingReplies::new())
+        .manage(cdp::CdpMonitor::default())
+        .manage(cdp::SelectorHealth::default())
         .setup(|app| {
             let handle = app.handle().clone();
 
+            let data_dir = app.path().app_data_dir()?;
+            app.manage(credentials::CredentialStore::new(data_dir.clone()));
+            let config_dir = app.path().app_config_dir()?;
+            app.manage(stt::adaptation::SpeechAdaptation::new(config_dir.clone()));
+            app.manage(stt::postprocess::Replacements::new(config_dir.clone()));
+            app.manage(hands_free::HandsFree::new(&config_dir));
+            app.manage(tts::Tts::new(&config_dir, &data_dir));
+            app.manage(tts::SpeechQueue::new(&config_dir));
+            tts::SpeechQueue::start(app.handle());
+            if let Err(e) = app.state::<hands_free::HandsFree>().apply(app.handle()) {
+                eprintln!("✗ Failed to set up hands-free commands: {}", e);
+            }
+            app.manage(cdp::CdpManager::new(&config_dir));
+            app.state::<cdp::CdpManager>().emit_to(app.handle());
+            app.manage(cdp::BrowserLauncher::new(&config_dir));
+            app.manage(reply::QuickReplies::new(&config_dir));
+            app.manage(notifications::NotificationRules::new(&config_dir));
+            if let Err(e) = app.state::<reply::QuickReplies>().apply(app.handle()) {
+                eprintln!("✗ Failed to set up quick reply shortcuts: {}", e);
+            }
+
             // Spawn notification listener in background
             tauri::async_runtime::spawn(async move {
                 notifications::start_notification_listener(handle).await;
@@ -66,17 +197,77 @@ pub fn run() {
             greet,
             transcribe_audio,
             copy_to_clipboard,
+            get_glossary,
+            set_glossary,
+            get_replacements,
+            set_replacements,
+            get_phrase_hints,
+            credentials::set_api_key,
+            credentials::get_credential_status,
+            credentials::test_api_key,
+            credentials::clear_api_key,
+            voice_commands::run_voice_command,
+            notifications::rules::get_notification_rules,
+            notifications::rules::set_notification_rules,
+            reply::send_reply,
+            reply::confirm_reply,
+            reply::cancel_reply,
+            reply::get_quick_replies,
+            reply::set_quick_replies,
+            reply::set_calendar_state,
+            reply::send_quick_reply,
+            tts::tts_get_engines,
+            tts::tts_list_voices,
+            tts::tts_get_settings,
+            tts::tts_set_default_voice,
+            tts::tts_speak,
+            tts::tts_synthesize,
+            tts::tts_get_voice_profile,
+            tts::tts_set_voice_profile,
+            tts::tts_speak_for_app,
+            tts::tts_enqueue,
+            tts::tts_clear_queue,
+            tts::tts_list_earcons,
+            tts::tts_get_earcons,
+            tts::tts_set_earcons,
+            tts::tts_preview_earcon,
+            tts::tts_export_audio,
+            tts::tts_stop,
+            hands_free::get_hands_free_status,
+            hands_free::set_hands_free_config,
+            hands_free::record_wake_phrase_sample,
+            hands_free::clear_wake_phrase_samples,
             cdp::cdp_connect,
+            cdp::cdp_disconnect,
+            cdp::cdp_get_connection_state,
+            cdp::cdp_list_browsers,
+            cdp::cdp_launch_browser,
+            cdp::cdp_close_browser,
+            cdp::cdp_diagnose,
+            cdp::cdp_list_desktop_apps,
+            cdp::cdp_attach_desktop_app,
+            cdp::cdp_launch_desktop_app,
+            cdp::cdp_detach_desktop_app,
+            cdp::cdp_get_desktop_tabs,
             cdp::cdp_get_tabs,
             cdp::cdp_find_tab,
             cdp::cdp_execute_script,
             cdp::cdp_start_monitoring,
             cdp::cdp_stop_monitoring,
+            cdp::cdp_get_monitoring_status,
+            cdp::cdp_check_selectors,
             vision::get_active_tab_context,
             validate_app_context,
             get_search_targets,
             should_process_app
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                // Don't leave the browser birdie launched running
+                let launcher = app.state::<cdp::BrowserLauncher>();
+                tauri::async_runtime::block_on(launcher.close());
+            }
+        });
 }
@@ -1,6 +1,159 @@
+//! Linux notifications, observed on the session bus
+//!
+//! Birdie does not serve `org.freedesktop.Notifications`: the desktop's own
+//! daemon keeps owning the name and showing notifications. Instead a
+//! connection becomes a bus monitor (`org.freedesktop.DBus.Monitoring`) and
+//! reads each `Notify` call as it passes to the daemon, along with the id the
+//! daemon returns for it.
+//!
+//! A monitor can only listen, so replies go through a second, ordinary
+//! connection. Notifications with an `inline-reply` action (Thunderbird,
+//! Element, Signal...) are answered through the daemon, which passes the
+//! text on in a `NotificationReplied` signal, the same way KDE Plasma
+//! delivers replies typed into its popups. Daemons without `InvokeAction`
+//! get the app told directly, with `NotificationReplied` and
+//! `ActionInvoked`.
+
+use super::{dispatch, InlineReply, NotificationEvent};
 use chrono::Utc;
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
 use tauri::AppHandle;
-use zbus::Connection;
+use zbus::fdo::MonitoringProxy;
+use zbus::message::Type as MessageType;
+use zbus::zvariant::OwnedValue;
+use zbus::{Connection, MatchRule, Message, MessageStream};
+
+const INTERFACE: &str = "org.freedesktop.Notifications";
+const OBJECT_PATH: &str = "/org/freedesktop/Notifications";
+const NOTIFY: &str = "Notify";
+const NOTIFICATION_CLOSED: &str = "NotificationClosed";
+const NOTIFICATION_REPLIED: &str = "NotificationReplied";
+const ACTION_INVOKED: &str = "ActionInvoked";
+/// Daemon method that answers a notification's action, with the reply text
+const INVOKE_ACTION: &str = "InvokeAction";
+
+/// Action key of notifications that take a reply text
+const INLINE_REPLY_ACTION: &str = "inline-reply";
+const PLACEHOLDER_HINT: &str = "x-kde-reply-placeholder-text";
+
+/// Arguments of a `Notify` call: app name, replaced id, icon, summary, body,
+/// actions, hints and timeout
+type NotifyArgs = (
+    String,
+    u32,
+    String,
+    String,
+    String,
+    Vec<String>,
+    HashMap<String, OwnedValue>,
+    i32,
+);
+
+/// A method call, by its caller's unique name and serial
+type CallKey = (String, u32);
+
+struct Replies {
+    /// Ordinary connection replies are sent through
+    connection: Connection,
+    /// Open notifications that accept an inline reply, with the unique name
+    /// of the app that sent them
+    replyable: Mutex<HashMap<u32, String>>,
+}
+
+static REPLIES: OnceLock<Replies> = OnceLock::new();
+
+/// Actions come as a flat list of key, label pairs
+fn accepts_inline_reply(actions: &[String]) -> bool {
+    actions
+        .iter()
+        .step_by(2)
+        .any(|key| key == INLINE_REPLY_ACTION)
+}
+
+fn string_hint(hints: &HashMap<String, OwnedValue>, key: &str) -> Option<String> {
+    hints
+        .get(key)
+        .and_then(|value| <&str>::try_from(value).ok())
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// The sender's app name, or its desktop entry when it sends none
+fn display_name(app_name: &str, hints: &HashMap<String, OwnedValue>) -> String {
+    if !app_name.is_empty() {
+        return app_name.to_string();
+    }
+    string_hint(hints, "desktop-entry").unwrap_or_else(|| "Unknown".to_string())
+}
+
+fn is_member(message: &Message, member: &str) -> bool {
+    let header = message.header();
+    header.interface().map(|i| i.as_str()) == Some(INTERFACE)
+        && header.member().map(|m| m.as_str()) == Some(member)
+}
+
+/// The notification a `Notify` call carries; other messages the monitor
+/// sees, such as its own `NameLost`, give `None`. A notification that takes
+/// a reply has id 0 until the daemon returns one, unless it replaces another
+fn parse_notify(message: &Message) -> Option<NotificationEvent> {
+    if message.message_type() != MessageType::MethodCall || !is_member(message, NOTIFY) {
+        return None;
+    }
+
+    let (app_name, replaces_id, app_icon, summary, body, actions, hints, _): NotifyArgs =
+        match message.body().deserialize() {
+            Ok(args) => args,
+            Err(e) => {
+                eprintln!("⚠ Malformed Notify call: {}", e);
+                return None;
+            }
+        };
+    let inline_reply = accepts_inline_reply(&actions).then(|| InlineReply {
+        id: replaces_id,
+        placeholder: string_hint(&hints, PLACEHOLDER_HINT),
+    });
+    Some(NotificationEvent {
+        app_name: display_name(&app_name, &hints),
+        sender: summary,
+        message: body,
+        timestamp: Utc::now().to_rfc3339(),
+        app_icon: Some(app_icon).filter(|icon| !icon.is_empty()),
+        inline_reply,
+    })
+}
+
+fn call_key(call: &Message) -> Option<CallKey> {
+    let sender = call.header().sender()?.to_string();
+    Some((sender, call.primary_header().serial_num().get()))
+}
+
+/// The call a method return or error answers
+fn answered_call(answer: &Message) -> Option<CallKey> {
+    let header = answer.header();
+    let caller = header.destination()?.to_string();
+    Some((caller, header.reply_serial()?.get()))
+}
+
+/// The id the daemon gave a notification; `None` if it failed
+fn returned_id(answer: &Message) -> Option<u32> {
+    if answer.message_type() != MessageType::MethodReturn {
+        return None;
+    }
+    answer.body().deserialize::<(u32,)>().ok().map(|(id,)| id)
+}
+
+fn closed_id(message: &Message) -> Option<u32> {
+    if message.message_type() != MessageType::Signal || !is_member(message, NOTIFICATION_CLOSED) {
+        return None;
+    }
+    message
+        .body()
+        .deserialize::<(u32, u32)>()
+        .ok()
+        .map(|(id, _)| id)
+}
 
 pub async fn listen_linux_notifications(app_handle: AppHandle) {
     eprintln!("📬 Starting Linux notification listener (D-Bus)...");
@@ -15,41 +168,287 @@ pub async fn listen_linux_notifications(app_handle: AppHandle) {
     }
 }
 
-async fn setup_dbus_listener(_app_handle: AppHandle) -> Result<(), String> {
-    // Connect to D-Bus session bus
-    let _connection = Connection::session()
+async fn setup_dbus_listener(app_handle: AppHandle) -> Result<(), String> {
+    let replies = Connection::session()
         .await
         .map_err(|e| format!("Failed to connect to D-Bus: {}", e))?;
+    REPLIES
+        .set(Replies {
+            connection: replies,
+            replyable: Mutex::new(HashMap::new()),
+        })
+        .map_err(|_| "The D-Bus listener is already running".to_string())?;
 
-    // TODO: Implementation Steps:
-    // 1. Register ourselves as a Notify service on the session bus
-    // 2. Implement org.freedesktop.Notifications interface with:
-    //    - GetCapabilities() method
-    //    - Notify(app_name, replaces_id, app_icon, summary, body, actions, hints, timeout) -> id
-    //    - CloseNotification(id) method
-    //    - NotificationClosed signal
-    //    - ActionInvoked signal
-    // 3. In Notify method callback:
-    //    - Extract notification fields
-    //    - Create NotificationEvent with parsed data
-    //    - Emit notification-received event to frontend
-    // 4. Subscribe to incoming notifications from all applications
-    //
-    // Challenges:
-    // - zbus macro-based interface definitions
-    // - Async D-Bus method handling
-    // - Proper D-Bus object path management
-    // - Error handling for D-Bus communication failures
-    // - Threading/async event loop integration
-    //
-    // For now, this is a placeholder structure
-
-    eprintln!("  ℹ D-Bus listener structure ready");
-    eprintln!("  ℹ Full implementation requires:");
-    eprintln!("    - D-Bus service registration");
-    eprintln!("    - org.freedesktop.Notifications interface implementation");
-    eprintln!("    - Notification method callback handlers");
-    eprintln!("    - Proper async/await event loop integration");
+    // A monitor connection can do nothing else, so it gets its own
+    let connection = Connection::session()
+        .await
+        .map_err(|e| format!("Failed to connect to D-Bus: {}", e))?;
+    let rules = monitor_rules().map_err(|e| e.to_string())?;
+    MonitoringProxy::new(&connection)
+        .await
+        .map_err(|e| format!("Failed to connect to D-Bus: {}", e))?
+        .become_monitor(&rules, 0)
+        .await
+        .map_err(|e| format!("Failed to monitor {} calls: {}", INTERFACE, e))?;
 
+    let mut messages = MessageStream::from(connection);
+    tauri::async_runtime::spawn(async move {
+        // Notifications that take a reply, until the daemon returns their id
+        let mut awaiting: HashMap<CallKey, (String, NotificationEvent)> = HashMap::new();
+        while let Some(message) = messages.next().await {
+            let Ok(message) = message else {
+                continue;
+            };
+            match message.message_type() {
+                MessageType::MethodCall => {
+                    let (Some(event), Some(key)) = (parse_notify(&message), call_key(&message))
+                    else {
+                        continue;
+                    };
+                    if event.inline_reply.as_ref().is_some_and(|r| r.id == 0) {
+                        awaiting.insert(key.clone(), (key.0, event));
+                    } else {
+                        notified(&app_handle, key.0, event);
+                    }
+                }
+                MessageType::MethodReturn | MessageType::Error => {
+                    let Some((app, mut event)) =
+                        answered_call(&message).and_then(|key| awaiting.remove(&key))
+                    else {
+                        continue;
+                    };
+                    match returned_id(&message) {
+                        Some(id) => event.inline_reply.iter_mut().for_each(|r| r.id = id),
+                        None => event.inline_reply = None,
+                    }
+                    notified(&app_handle, app, event);
+                }
+                MessageType::Signal => {
+                    if let (Some(id), Some(replies)) = (closed_id(&message), REPLIES.get()) {
+                        replies.replyable.lock().unwrap().remove(&id);
+                    }
+                }
+            }
+        }
+        eprintln!("⚠ D-Bus notification monitor stopped");
+    });
     Ok(())
 }
+
+/// `Notify` calls, and the daemon's answers and `NotificationClosed` signals
+fn monitor_rules() -> zbus::Result<Vec<MatchRule<'static>>> {
+    let from_daemon = |msg_type| MatchRule::builder().msg_type(msg_type).sender(INTERFACE);
+    Ok(vec![
+        MatchRule::builder()
+            .msg_type(MessageType::MethodCall)
+            .interface(INTERFACE)?
+            .member(NOTIFY)?
+            .build(),
+        from_daemon(MessageType::MethodReturn)?.build(),
+        from_daemon(MessageType::Error)?.build(),
+        from_daemon(MessageType::Signal)?
+            .interface(INTERFACE)?
+            .member(NOTIFICATION_CLOSED)?
+            .build(),
+    ])
+}
+
+/// Remember the notification from `app` if it takes a reply, then pass it on
+fn notified(app_handle: &AppHandle, app: String, event: NotificationEvent) {
+    if let (Some(replies), Some(inline)) = (REPLIES.get(), &event.inline_reply) {
+        replies.replyable.lock().unwrap().insert(inline.id, app);
+    }
+    if let Err(e) = dispatch(app_handle, &event) {
+        eprintln!("✗ Error emitting notification: {}", e);
+    }
+}
+
+/// Whether notification `id` is still open and accepts an inline reply
+pub fn can_reply(id: u32) -> bool {
+    REPLIES
+        .get()
+        .is_some_and(|replies| replies.replyable.lock().unwrap().contains_key(&id))
+}
+
+/// Whether the daemon lacks a method, rather than failing to run it
+fn is_unsupported(error: &zbus::Error) -> bool {
+    matches!(error, zbus::Error::MethodError(name, _, _) if matches!(
+        name.as_str(),
+        "org.freedesktop.DBus.Error.UnknownMethod"
+            | "org.freedesktop.DBus.Error.UnknownInterface"
+            | "org.freedesktop.DBus.Error.InvalidArgs"
+    ))
+}
+
+/// Answer notification `id` with `text`
+pub async fn reply(id: u32, text: &str) -> Result<(), String> {
+    let replies = REPLIES
+        .get()
+        .ok_or("Birdie no está recibiendo las notificaciones del escritorio")?;
+    let app = replies
+        .replyable
+        .lock()
+        .unwrap()
+        .remove(&id)
+        .ok_or_else(|| format!("La notificación {} se cerró o no admite respuesta", id))?;
+
+    let sent = deliver(&replies.connection, id, &app, text).await;
+    if sent.is_err() {
+        // Still open, so it can be answered again
+        replies.replyable.lock().unwrap().insert(id, app);
+    }
+    sent.map_err(|e| format!("No se pudo enviar la respuesta: {}", e))
+}
+
+async fn deliver(connection: &Connection, id: u32, app: &str, text: &str) -> zbus::Result<()> {
+    let invoked = connection
+        .call_method(
+            Some(INTERFACE),
+            OBJECT_PATH,
+            Some(INTERFACE),
+            INVOKE_ACTION,
+            &(id, INLINE_REPLY_ACTION, text),
+        )
+        .await;
+    match invoked {
+        Ok(_) => return Ok(()),
+        Err(e) if !is_unsupported(&e) => return Err(e),
+        Err(_) => {}
+    }
+
+    // The daemon cannot take the text, so the app is told as it would be
+    connection
+        .emit_signal(
+            Some(app),
+            OBJECT_PATH,
+            INTERFACE,
+            NOTIFICATION_REPLIED,
+            &(id, text),
+        )
+        .await?;
+    connection
+        .emit_signal(
+            Some(app),
+            OBJECT_PATH,
+            INTERFACE,
+            ACTION_INVOKED,
+            &(id, INLINE_REPLY_ACTION),
+        )
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zbus::zvariant::Value;
+
+    fn hints(entries: &[(&str, &str)]) -> HashMap<String, OwnedValue> {
+        entries
+            .iter()
+            .map(|(key, value)| {
+                (
+                    key.to_string(),
+                    OwnedValue::try_from(Value::from(*value)).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn strings(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn notify_call(actions: &[&str]) -> Message {
+        let args: NotifyArgs = (
+            String::new(),
+            0,
+            "thunderbird".to_string(),
+            "Ana Ruiz".to_string(),
+            "¿Comemos juntos?".to_string(),
+            strings(actions),
+            hints(&[
+                ("desktop-entry", "org.mozilla.Thunderbird"),
+                (PLACEHOLDER_HINT, "Responder a Ana"),
+            ]),
+            -1,
+        );
+        Message::method(OBJECT_PATH, NOTIFY)
+            .and_then(|m| m.interface(INTERFACE))
+            .and_then(|m| m.sender(":1.7"))
+            .and_then(|m| m.build(&args))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_inline_reply_is_an_action_key() {
+        assert!(accepts_inline_reply(&strings(&[
+            "default",
+            "Open",
+            "inline-reply",
+            "Reply"
+        ])));
+        // A label that happens to say inline-reply is not the action
+        assert!(!accepts_inline_reply(&strings(&["reply", "inline-reply"])));
+        assert!(!accepts_inline_reply(&[]));
+    }
+
+    #[test]
+    fn test_hints() {
+        let hints = hints(&[
+            ("desktop-entry", "org.mozilla.Thunderbird"),
+            ("category", ""),
+        ]);
+        assert_eq!(
+            string_hint(&hints, "desktop-entry").as_deref(),
+            Some("org.mozilla.Thunderbird")
+        );
+        assert_eq!(string_hint(&hints, "category"), None);
+        assert_eq!(display_name("Element", &hints), "Element");
+        assert_eq!(display_name("", &hints), "org.mozilla.Thunderbird");
+    }
+
+    #[test]
+    fn test_notify_calls_are_parsed() {
+        let event = parse_notify(&notify_call(&["default", "Open"])).unwrap();
+        assert_eq!(event.app_name, "org.mozilla.Thunderbird");
+        assert_eq!(event.sender, "Ana Ruiz");
+        assert_eq!(event.message, "¿Comemos juntos?");
+        assert_eq!(event.app_icon.as_deref(), Some("thunderbird"));
+        assert_eq!(event.inline_reply, None);
+
+        // The monitor also sees bus signals, which are not notifications
+        let signal = Message::signal("/org/freedesktop/DBus", "org.freedesktop.DBus", "NameLost")
+            .and_then(|m| m.build(&(":1.42",)))
+            .unwrap();
+        assert!(parse_notify(&signal).is_none());
+    }
+
+    #[test]
+    fn test_inline_reply_ids_come_from_the_daemon() {
+        let call = notify_call(&["default", "Open", "inline-reply", "Reply"]);
+        let event = parse_notify(&call).unwrap();
+        let inline = event.inline_reply.unwrap();
+        assert_eq!(inline.id, 0);
+        assert_eq!(inline.placeholder.as_deref(), Some("Responder a Ana"));
+
+        let answer = Message::method_reply(&call)
+            .and_then(|m| m.build(&(42u32,)))
+            .unwrap();
+        let key = call_key(&call).unwrap();
+        assert_eq!(key.0, ":1.7");
+        assert_eq!(answered_call(&answer), Some(key));
+        assert_eq!(returned_id(&answer), Some(42));
+
+        let closed = Message::signal(OBJECT_PATH, INTERFACE, NOTIFICATION_CLOSED)
+            .and_then(|m| m.build(&(42u32, 2u32)))
+            .unwrap();
+        assert_eq!(closed_id(&closed), Some(42));
+        assert_eq!(closed_id(&call), None);
+    }
+
+    #[test]
+    fn test_reply_needs_the_listener() {
+        assert!(!can_reply(42));
+        assert!(monitor_rules().is_ok());
+    }
+}
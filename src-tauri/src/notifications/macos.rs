@@ -59,5 +59,6 @@ fn parse_macos_notification(notification_data: &str) -> Result<NotificationEvent
         message: notification_data.to_string(),
         timestamp: Utc::now().to_rfc3339(),
         app_icon: None,
+        inline_reply: None,
     })
 }
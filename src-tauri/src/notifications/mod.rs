@@ -1,5 +1,6 @@
+use crate::stt::adaptation::SpeechAdaptation;
 use serde::{Deserialize, Serialize};
-use tauri::AppHandle;
+use tauri::{AppHandle, Emitter, Manager};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
@@ -10,6 +11,19 @@ pub struct NotificationEvent {
     pub timestamp: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub app_icon: Option<String>,
+    /// Set when the notification can be answered in place
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inline_reply: Option<InlineReply>,
+}
+
+/// A notification that takes a reply text through its `inline-reply` action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InlineReply {
+    /// The notification's id on the notification daemon
+    pub id: u32,
+    /// Hint the app gives for the reply field
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placeholder: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +34,10 @@ pub struct NotificationError {
     pub suggestion: String,
 }
 
+pub mod rules;
+
+pub use rules::NotificationRules;
+
 // Platform-specific implementations
 #[cfg(target_os = "windows")]
 pub mod windows;
@@ -30,6 +48,31 @@ pub mod macos;
 #[cfg(target_os = "linux")]
 pub mod linux;
 
+/// Forward a captured notification to the frontend
+///
+/// Notifications that do-not-disturb, a muted app or an ignore rule hold back
+/// go no further. The sender is also remembered so that it can be suggested
+/// to the speech recognizer when the user dictates a reply, and the
+/// notification becomes the one quick replies answer.
+pub fn dispatch(app_handle: &AppHandle, event: &NotificationEvent) -> Result<(), String> {
+    let now = chrono::Utc::now().timestamp();
+    if let Some(rules) = app_handle.try_state::<NotificationRules>() {
+        if !rules.allows(&event.app_name, &event.sender, now) {
+            return Ok(());
+        }
+    }
+    if let Some(adaptation) = app_handle.try_state::<SpeechAdaptation>() {
+        adaptation.record_contact(&event.sender, now);
+    }
+    if let Some(quick_replies) = app_handle.try_state::<crate::reply::QuickReplies>() {
+        quick_replies.record(event);
+    }
+
+    app_handle
+        .emit("notification-received", event)
+        .map_err(|e| e.to_string())
+}
+
 /// Start the notification listener for the current platform
 pub async fn start_notification_listener(app_handle: AppHandle) {
     #[cfg(target_os = "windows")]
@@ -52,3 +95,31 @@ pub async fn start_notification_listener(app_handle: AppHandle) {
         eprintln!("Notification listener not supported on this platform");
     }
 }
+
+/// Whether notification `id` is still open and accepts an inline reply
+pub fn can_reply_inline(id: u32) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        linux::can_reply(id)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = id;
+        false
+    }
+}
+
+/// Answer notification `id` in place with `text`
+pub async fn reply_inline(id: u32, text: &str) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::reply(id, text).await
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (id, text);
+        Err("Las respuestas desde la notificación solo están disponibles en Linux".to_string())
+    }
+}
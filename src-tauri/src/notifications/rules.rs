@@ -0,0 +1,240 @@
+//! Do-not-disturb and notification rules
+//!
+//! Set by voice ("silencia Slack una hora", "ignora a Pablo") or from the UI,
+//! and applied in `dispatch`, before a notification reaches the frontend or
+//! the speech queue. Ignored senders and apps are always dropped; prioritized
+//! ones get through do-not-disturb and muted apps.
+
+use crate::context_mapper::ContextMapper;
+use crate::settings;
+use crate::stt::postprocess::normalize;
+use crate::voice_commands::{DndCommand, RuleCommand, RuleTarget};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A silence that ends on its own, or when turned off
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Silence {
+    /// Unix time it ends at; unset until turned off
+    pub until: Option<i64>,
+}
+
+impl Silence {
+    fn for_minutes(minutes: Option<u32>, now: i64) -> Self {
+        Self {
+            until: minutes.map(|m| now + i64::from(m) * 60),
+        }
+    }
+
+    fn is_active(&self, now: i64) -> bool {
+        self.until.is_none_or(|until| now < until)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationRuleSettings {
+    /// Unset when do-not-disturb is off
+    pub do_not_disturb: Option<Silence>,
+    /// By app name
+    pub muted_apps: HashMap<String, Silence>,
+    pub ignored: Vec<RuleTarget>,
+    pub prioritized: Vec<RuleTarget>,
+}
+
+/// Notification rules, held in Tauri managed state
+pub struct NotificationRules {
+    path: PathBuf,
+    settings: Mutex<NotificationRuleSettings>,
+}
+
+/// The app as the context mapper names it, for comparing
+fn app_key(app_name: &str) -> String {
+    let name = ContextMapper::new()
+        .find_context(app_name)
+        .filter(|_| !app_name.trim().is_empty())
+        .map(|c| c.app_name)
+        .unwrap_or_else(|| app_name.to_string());
+    normalize(name.trim())
+}
+
+/// "Pablo" matches "Pablo Ruiz", but not "Pablito"
+fn is_sender(name: &str, sender: &str) -> bool {
+    let sender = normalize(sender);
+    let words: Vec<&str> = sender.split_whitespace().collect();
+    let name = normalize(name);
+    let mut wanted = name.split_whitespace().peekable();
+    wanted.peek().is_some() && wanted.all(|word| words.contains(&word))
+}
+
+fn matches(target: &RuleTarget, app_name: &str, sender: &str) -> bool {
+    match target {
+        RuleTarget::Sender(name) => is_sender(name, sender),
+        RuleTarget::App(name) => app_key(name) == app_key(app_name),
+    }
+}
+
+impl NotificationRules {
+    pub fn new(config_dir: &Path) -> Self {
+        let path = config_dir.join("notification_rules.json");
+        Self {
+            settings: Mutex::new(settings::load(&path)),
+            path,
+        }
+    }
+
+    pub fn settings(&self) -> NotificationRuleSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn set_settings(&self, settings: NotificationRuleSettings) -> Result<(), String> {
+        settings::save(&self.path, &settings)?;
+        *self.settings.lock().unwrap() = settings;
+        Ok(())
+    }
+
+    fn update(&self, change: impl FnOnce(&mut NotificationRuleSettings)) -> Result<(), String> {
+        let mut settings = self.settings();
+        change(&mut settings);
+        self.set_settings(settings)
+    }
+
+    /// Carry out a do-not-disturb command given at `now`
+    pub fn do_not_disturb(&self, command: &DndCommand, now: i64) -> Result<(), String> {
+        self.update(|settings| match command {
+            DndCommand::Enable { minutes } => {
+                settings.do_not_disturb = Some(Silence::for_minutes(*minutes, now));
+            }
+            DndCommand::Disable => settings.do_not_disturb = None,
+            DndCommand::MuteApp { app, minutes } => {
+                settings
+                    .muted_apps
+                    .insert(app.clone(), Silence::for_minutes(*minutes, now));
+            }
+            DndCommand::UnmuteApp { app } => {
+                let key = app_key(app);
+                settings.muted_apps.retain(|muted, _| app_key(muted) != key);
+            }
+        })
+    }
+
+    /// Add a rule; a target is either ignored or prioritized, not both
+    pub fn add(&self, command: &RuleCommand) -> Result<(), String> {
+        self.update(|settings| {
+            let (target, add, remove) = match command {
+                RuleCommand::Ignore { target } => {
+                    (target, &mut settings.ignored, &mut settings.prioritized)
+                }
+                RuleCommand::Prioritize { target } => {
+                    (target, &mut settings.prioritized, &mut settings.ignored)
+                }
+            };
+            remove.retain(|t| t != target);
+            if !add.contains(target) {
+                add.push(target.clone());
+            }
+        })
+    }
+
+    /// Whether a notification from `sender` in `app_name` is let through at
+    /// `now`
+    pub fn allows(&self, app_name: &str, sender: &str, now: i64) -> bool {
+        let settings = self.settings.lock().unwrap();
+        let any = |targets: &[RuleTarget]| targets.iter().any(|t| matches(t, app_name, sender));
+        if any(&settings.ignored) {
+            return false;
+        }
+        if any(&settings.prioritized) {
+            return true;
+        }
+        if settings.do_not_disturb.is_some_and(|s| s.is_active(now)) {
+            return false;
+        }
+        let key = app_key(app_name);
+        !settings
+            .muted_apps
+            .iter()
+            .any(|(app, silence)| app_key(app) == key && silence.is_active(now))
+    }
+}
+
+#[tauri::command]
+pub fn get_notification_rules(
+    rules: tauri::State<'_, NotificationRules>,
+) -> NotificationRuleSettings {
+    rules.settings()
+}
+
+#[tauri::command]
+pub fn set_notification_rules(
+    rules: tauri::State<'_, NotificationRules>,
+    settings: NotificationRuleSettings,
+) -> Result<(), String> {
+    rules.set_settings(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(name: &str) -> (NotificationRules, PathBuf) {
+        let dir =
+            std::env::temp_dir().join(format!("birdie-rules-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        (NotificationRules::new(&dir), dir)
+    }
+
+    #[test]
+    fn test_do_not_disturb_and_muted_apps() {
+        let (rules, dir) = rules("dnd");
+        assert!(rules.allows("Slack", "Ana", 0));
+
+        let mute = DndCommand::MuteApp {
+            app: "Slack".to_string(),
+            minutes: Some(60),
+        };
+        rules.do_not_disturb(&mute, 0).unwrap();
+        assert!(!rules.allows("slack", "Ana", 60));
+        assert!(rules.allows("Discord", "Ana", 60));
+        // An hour later the app is heard again
+        assert!(rules.allows("Slack", "Ana", 3600));
+
+        rules
+            .do_not_disturb(&DndCommand::Enable { minutes: None }, 0)
+            .unwrap();
+        assert!(!rules.allows("Discord", "Ana", 1_000_000));
+        rules.do_not_disturb(&DndCommand::Disable, 0).unwrap();
+        assert!(rules.allows("Discord", "Ana", 0));
+
+        // Saved for the next start
+        assert_eq!(NotificationRules::new(&dir).settings(), rules.settings());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_ignored_and_prioritized_senders() {
+        let (rules, dir) = rules("senders");
+        let pablo = RuleTarget::Sender("Pablo".to_string());
+        rules
+            .add(&RuleCommand::Ignore {
+                target: pablo.clone(),
+            })
+            .unwrap();
+        assert!(!rules.allows("Slack", "Pablo Ruiz", 0));
+        assert!(rules.allows("Slack", "Pablito", 0));
+
+        // Prioritizing replaces ignoring, and gets through do-not-disturb
+        rules
+            .add(&RuleCommand::Prioritize { target: pablo })
+            .unwrap();
+        rules
+            .do_not_disturb(&DndCommand::Enable { minutes: Some(30) }, 0)
+            .unwrap();
+        assert!(rules.allows("Slack", "Pablo Ruiz", 60));
+        assert!(!rules.allows("Slack", "Ana", 60));
+        assert!(rules.settings().ignored.is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
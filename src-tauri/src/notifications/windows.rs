@@ -1,8 +1,8 @@
-use crate::notifications::NotificationEvent;
+use crate::notifications::{self, NotificationEvent};
 use chrono::Utc;
 use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
-use tauri::{AppHandle, Emitter};
+use tauri::AppHandle;
 use windows::UI::Notifications::Management::UserNotificationListener;
 use windows::UI::Notifications::NotificationKinds;
 
@@ -232,13 +232,14 @@ fn process_notifications_polling(
             message,
             timestamp: Utc::now().to_rfc3339(),
             app_icon: None,
+            inline_reply: None,
         };
 
         // ════════════════════════════════════════════════════════════
         // PASO 4E: EMITIR EVENTO AL FRONTEND
         // ════════════════════════════════════════════════════════════
         if let Ok(app_guard) = app_handle.lock() {
-            match app_guard.emit("notification-received", &event) {
+            match notifications::dispatch(&app_guard, &event) {
                 Ok(_) => {
                     eprintln!("📬 Notificación recibida de: {}", event.app_name);
                     eprintln!("   Remitente: {}", event.sender);
@@ -326,5 +327,6 @@ fn create_notification_event(app_name: &str, message: &str, sender: &str) -> Not
         message: message.to_string(),
         timestamp: Utc::now().to_rfc3339(),
         app_icon: None,
+        inline_reply: None,
     }
 }
@@ -0,0 +1,345 @@
+//! Replies typed into the app's web client over the Chrome DevTools Protocol
+//!
+//! The tab is found with the app's URL patterns from [`ContextMapper`], the
+//! conversation is opened by clicking the chat-list entry that contains the
+//! recipient's name, and the text is typed with `Input.insertText`. That
+//! fires the same input events as the keyboard, so composers built on
+//! contenteditable (Slack, Discord, WhatsApp) notice the text, which setting
+//! `.value` or `textContent` would not do.
+
+use crate::cdp::CdpManager;
+use crate::context_mapper::{ContextMapper, ReplySelectors};
+use chromiumoxide::cdp::browser_protocol::input::{
+    DispatchKeyEventParams, DispatchKeyEventType, InsertTextParams,
+};
+use chromiumoxide::Page;
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+
+/// How long to wait for the composer after opening a conversation
+const COMPOSER_TIMEOUT: Duration = Duration::from_secs(3);
+const POLL_INTERVAL: Duration = Duration::from_millis(150);
+
+/// The tab a reply goes to
+#[derive(Debug, Clone)]
+pub struct Tab {
+    pub target_id: String,
+    pub title: String,
+    pub selectors: ReplySelectors,
+}
+
+impl Tab {
+    /// How the reply is submitted, for previews
+    pub fn submit_with(&self) -> String {
+        match &self.selectors.send_button {
+            Some(selector) => format!("click {}", selector),
+            None => "Enter".to_string(),
+        }
+    }
+}
+
+fn matches_app(url: &str, patterns: &[String]) -> bool {
+    let url = url.to_lowercase();
+    patterns
+        .iter()
+        .any(|pattern| url.contains(&pattern.to_lowercase()))
+}
+
+/// A JavaScript string literal
+fn js_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+/// Click the chat-list entry whose text contains `recipient`
+fn open_conversation_script(selector: &str, recipient: &str) -> String {
+    format!(
+        r#"
+        (function() {{
+            const wanted = {recipient}.toLowerCase();
+            const entries = Array.from(document.querySelectorAll({selector}));
+            const entry = entries.find(e => (e.innerText || e.textContent || '').toLowerCase().includes(wanted));
+            if (!entry) return false;
+            entry.scrollIntoView({{ block: 'center' }});
+            entry.click();
+            return true;
+        }})()
+        "#,
+        selector = js_string(selector),
+        recipient = js_string(recipient),
+    )
+}
+
+/// Focus the composer with the caret at the end of any draft
+fn focus_composer_script(selector: &str) -> String {
+    format!(
+        r#"
+        (function() {{
+            const el = document.querySelector({selector});
+            if (!el) return false;
+            el.focus();
+            if (el.isContentEditable) {{
+                const range = document.createRange();
+                range.selectNodeContents(el);
+                range.collapse(false);
+                const selection = window.getSelection();
+                selection.removeAllRanges();
+                selection.addRange(range);
+            }} else if (typeof el.value === 'string') {{
+                el.setSelectionRange(el.value.length, el.value.length);
+            }}
+            return document.activeElement === el || el.contains(document.activeElement);
+        }})()
+        "#,
+        selector = js_string(selector),
+    )
+}
+
+pub(crate) fn composer_text_script(selector: &str) -> String {
+    format!(
+        r#"
+        (function() {{
+            const el = document.querySelector({selector});
+            if (!el) return null;
+            return typeof el.value === 'string' ? el.value : (el.innerText || '');
+        }})()
+        "#,
+        selector = js_string(selector),
+    )
+}
+
+/// Select the composer's contents and delete them as an edit, so the app's
+/// editor state follows
+fn clear_composer_script(selector: &str) -> String {
+    format!(
+        r#"
+        (function() {{
+            const el = document.querySelector({selector});
+            if (!el) return false;
+            el.focus();
+            document.execCommand('selectAll');
+            document.execCommand('delete');
+            return true;
+        }})()
+        "#,
+        selector = js_string(selector),
+    )
+}
+
+fn click_script(selector: &str) -> String {
+    format!(
+        r#"
+        (function() {{
+            const el = document.querySelector({selector});
+            if (!el || el.disabled) return false;
+            el.click();
+            return true;
+        }})()
+        "#,
+        selector = js_string(selector),
+    )
+}
+
+/// Collapse whitespace so editors that add line breaks still compare equal
+pub(crate) fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub(crate) async fn eval<T: DeserializeOwned>(page: &Page, script: String) -> Result<T, String> {
+    page.evaluate(script)
+        .await
+        .map_err(|e| format!("Error al ejecutar script: {}", e))?
+        .into_value()
+        .map_err(|e| format!("Resultado inesperado del script: {}", e))
+}
+
+/// Find the app's tab and its reply selectors
+pub async fn locate(
+    cdp: &CdpManager,
+    app_name: &str,
+    mapper: &ContextMapper,
+) -> Result<Tab, String> {
+    let context = mapper
+        .find_context(app_name)
+        .ok_or_else(|| format!("App desconocida: {}", app_name))?;
+    let selectors = context.reply.clone().ok_or_else(|| {
+        format!(
+            "{} no tiene selectores para responder desde el navegador",
+            context.app_name
+        )
+    })?;
+
+    let targets = cdp.pages().await?;
+    let target = targets
+        .iter()
+        .find(|t| matches_app(&t.url, &context.url_patterns))
+        .ok_or_else(|| format!("No hay ninguna pestaña abierta de {}", context.app_name))?;
+
+    Ok(Tab {
+        target_id: target.target_id.as_ref().to_string(),
+        title: target.title.clone(),
+        selectors,
+    })
+}
+
+/// Bring the tab forward, open the conversation and type the text without
+/// sending it
+pub async fn type_reply(
+    cdp: &CdpManager,
+    tab: &Tab,
+    recipient: Option<&str>,
+    text: &str,
+) -> Result<(), String> {
+    let page = cdp.page(&tab.target_id).await?;
+    page.bring_to_front()
+        .await
+        .map_err(|e| format!("No se pudo mostrar la pestaña: {}", e))?;
+
+    if let (Some(recipient), Some(selector)) = (recipient, &tab.selectors.conversation) {
+        let opened: bool = eval(&page, open_conversation_script(selector, recipient)).await?;
+        if !opened {
+            return Err(format!(
+                "No se encontró la conversación con {} en '{}'",
+                recipient, tab.title
+            ));
+        }
+    }
+
+    // Opening a conversation re-renders the composer
+    let started = std::time::Instant::now();
+    loop {
+        let focused: bool = eval(&page, focus_composer_script(&tab.selectors.composer)).await?;
+        if focused {
+            break;
+        }
+        if started.elapsed() > COMPOSER_TIMEOUT {
+            return Err(format!(
+                "No se encontró la caja de texto en '{}' ({})",
+                tab.title, tab.selectors.composer
+            ));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+
+    page.execute(InsertTextParams::new(text))
+        .await
+        .map_err(|e| format!("No se pudo escribir el mensaje: {}", e))?;
+
+    let typed: Option<String> = eval(&page, composer_text_script(&tab.selectors.composer)).await?;
+    if !collapse(&typed.unwrap_or_default()).contains(&collapse(text)) {
+        return Err("El texto no llegó a la caja de mensaje".to_string());
+    }
+    Ok(())
+}
+
+/// Send what was typed: click the send button, or press Enter
+///
+/// Nothing is sent if the composer no longer holds exactly `text`, as when
+/// the user edited it or switched conversations since it was typed.
+pub async fn submit(cdp: &CdpManager, tab: &Tab, text: &str) -> Result<(), String> {
+    let page = cdp.page(&tab.target_id).await?;
+
+    let typed: Option<String> = eval(&page, composer_text_script(&tab.selectors.composer)).await?;
+    if collapse(&typed.unwrap_or_default()) != collapse(text) {
+        return Err(format!(
+            "La caja de texto de '{}' ya no contiene la respuesta; no se ha enviado",
+            tab.title
+        ));
+    }
+
+    if let Some(selector) = &tab.selectors.send_button {
+        let clicked: bool = eval(&page, click_script(selector)).await?;
+        if clicked {
+            return Ok(());
+        }
+    }
+
+    let focused: bool = eval(&page, focus_composer_script(&tab.selectors.composer)).await?;
+    if !focused {
+        return Err(format!(
+            "No se encontró la caja de texto en '{}'",
+            tab.title
+        ));
+    }
+    for kind in [DispatchKeyEventType::KeyDown, DispatchKeyEventType::KeyUp] {
+        let mut key = DispatchKeyEventParams::builder()
+            .r#type(kind.clone())
+            .key("Enter")
+            .code("Enter")
+            .windows_virtual_key_code(13)
+            .native_virtual_key_code(13);
+        if kind == DispatchKeyEventType::KeyDown {
+            key = key.text("\r");
+        }
+        let key = key.build().map_err(|e| e.to_string())?;
+        page.execute(key)
+            .await
+            .map_err(|e| format!("No se pudo enviar el mensaje: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Remove a typed reply that was not confirmed
+pub async fn discard(cdp: &CdpManager, tab: &Tab) -> Result<(), String> {
+    let page = cdp.page(&tab.target_id).await?;
+    let cleared: bool = eval(&page, clear_composer_script(&tab.selectors.composer)).await?;
+    if !cleared {
+        return Err(format!(
+            "No se encontró la caja de texto en '{}'",
+            tab.title
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tabs_are_matched_by_url_pattern() {
+        let mapper = ContextMapper::new();
+        let slack = mapper.find_context("Slack").unwrap();
+        assert!(matches_app(
+            "https://app.slack.com/client/T0/C1",
+            &slack.url_patterns
+        ));
+        assert!(!matches_app(
+            "https://example.com/slack",
+            &["app.slack.com".to_string()]
+        ));
+    }
+
+    #[test]
+    fn test_scripts_quote_their_arguments() {
+        let script = open_conversation_script("[data-qa='x']", "Ana \"la jefa\" O'Neil");
+        assert!(script.contains(r#""Ana \"la jefa\" O'Neil""#));
+        assert!(script.contains(r#""[data-qa='x']""#));
+        assert!(
+            focus_composer_script("div[role=\"textbox\"]").contains(r#""div[role=\"textbox\"]""#)
+        );
+    }
+
+    #[test]
+    fn test_every_default_app_can_reply() {
+        let mapper = ContextMapper::new();
+        for context in mapper.get_enabled_contexts() {
+            let selectors = context.reply.expect("reply selectors");
+            assert!(!selectors.composer.is_empty(), "{}", context.app_name);
+        }
+    }
+
+    #[test]
+    fn test_submit_description() {
+        let tab = Tab {
+            target_id: "1".to_string(),
+            title: "Slack".to_string(),
+            selectors: ReplySelectors {
+                conversation: None,
+                composer: "textarea".to_string(),
+                send_button: None,
+            },
+        };
+        assert_eq!(tab.submit_with(), "Enter");
+        assert_eq!(collapse(" on  my\nway "), "on my way");
+    }
+}
@@ -0,0 +1,486 @@
+//! Replies delivered to the app a message came from
+//!
+//! A reply is prepared first: the conversation is opened and the text typed,
+//! but nothing is sent until the user confirms it, by voice or in the UI.
+//! Unconfirmed replies expire. A dry run only reports what would be sent.
+//!
+//! Notifications that offer an inline reply are answered in place through
+//! the desktop's notification daemon, which needs no browser; everything else is typed
+//! into the app's web client. When the app's tab cannot be found the reply is
+//! copied to the clipboard instead, to be pasted by hand.
+
+pub(crate) mod cdp;
+mod quick;
+
+pub use quick::{
+    get_quick_replies, send_latest as send_quick_latest, send_quick_reply, set_calendar_state,
+    set_quick_replies, QuickReplies,
+};
+
+use crate::cdp::CdpManager;
+use crate::context_mapper::{ContextMapper, TaskPriority};
+use crate::notifications;
+use crate::stt;
+use crate::tts::{Announcement, SpeechQueue};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Unconfirmed replies are dropped after this long
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplyChannel {
+    /// Typed into the app's web client
+    Cdp,
+    /// Sent through the notification's inline-reply action
+    Notification,
+    /// Copied to the clipboard
+    Clipboard,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReplyRequest {
+    pub app_name: String,
+    /// Conversation to open; the one already open if unset
+    #[serde(default)]
+    pub recipient: Option<String>,
+    pub text: String,
+    /// Answer this notification in place when it still accepts a reply
+    #[serde(default)]
+    pub notification_id: Option<u32>,
+    /// Use this channel instead of the best one available
+    #[serde(default)]
+    pub channel: Option<ReplyChannel>,
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Ask for confirmation aloud, in this language
+    #[serde(default)]
+    pub speak_confirmation: Option<String>,
+}
+
+/// What will be sent, and where
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplyPreview {
+    /// Id to confirm or cancel with; unset for dry runs
+    pub id: Option<u64>,
+    pub channel: ReplyChannel,
+    pub app_name: String,
+    pub recipient: Option<String>,
+    pub text: String,
+    /// Where the text goes, such as the tab title
+    pub destination: String,
+    /// How it is submitted once confirmed
+    pub submit_with: String,
+    pub dry_run: bool,
+}
+
+/// A reply ready to be submitted through its channel
+enum Prepared {
+    Cdp(cdp::Tab),
+    Notification(u32),
+    Clipboard,
+}
+
+impl Prepared {
+    fn channel(&self) -> ReplyChannel {
+        match self {
+            Prepared::Cdp(_) => ReplyChannel::Cdp,
+            Prepared::Notification(_) => ReplyChannel::Notification,
+            Prepared::Clipboard => ReplyChannel::Clipboard,
+        }
+    }
+
+    fn destination(&self) -> String {
+        match self {
+            Prepared::Cdp(tab) => tab.title.clone(),
+            Prepared::Notification(id) => format!("notification #{}", id),
+            Prepared::Clipboard => "clipboard".to_string(),
+        }
+    }
+
+    fn submit_with(&self) -> String {
+        match self {
+            Prepared::Cdp(tab) => tab.submit_with(),
+            Prepared::Notification(_) => "inline-reply".to_string(),
+            Prepared::Clipboard => "paste".to_string(),
+        }
+    }
+}
+
+struct Pending {
+    preview: ReplyPreview,
+    prepared: Prepared,
+    created: Instant,
+}
+
+/// Replies waiting for confirmation, held in Tauri managed state
+pub struct PendingReplies {
+    pending: Mutex<HashMap<u64, Pending>>,
+    next_id: AtomicU64,
+}
+
+impl PendingReplies {
+    pub fn new() -> Self {
+        Self {
+            pending: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    fn insert(&self, mut preview: ReplyPreview, prepared: Prepared) -> ReplyPreview {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        preview.id = Some(id);
+        self.pending.lock().unwrap().insert(
+            id,
+            Pending {
+                preview: preview.clone(),
+                prepared,
+                created: Instant::now(),
+            },
+        );
+        preview
+    }
+
+    /// Remove the replies that were not confirmed in time
+    fn expire(&self) -> Vec<Pending> {
+        let mut pending = self.pending.lock().unwrap();
+        let expired: Vec<u64> = pending
+            .iter()
+            .filter(|(_, p)| p.created.elapsed() >= CONFIRMATION_TIMEOUT)
+            .map(|(id, _)| *id)
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|id| pending.remove(&id))
+            .collect()
+    }
+
+    /// Take a pending reply; the most recent one if `id` is unset
+    fn take(&self, id: Option<u64>) -> Result<Pending, String> {
+        let mut pending = self.pending.lock().unwrap();
+        let id = match id {
+            Some(id) => id,
+            None => *pending
+                .keys()
+                .max()
+                .ok_or("No hay ninguna respuesta pendiente de confirmar")?,
+        };
+        pending
+            .remove(&id)
+            .ok_or_else(|| "La respuesta ya no está pendiente o ha caducado".to_string())
+    }
+
+    /// Put back a reply that could not be sent, so it can be retried or
+    /// cancelled
+    fn restore(&self, pending: Pending) {
+        if let Some(id) = pending.preview.id {
+            self.pending.lock().unwrap().insert(id, pending);
+        }
+    }
+}
+
+/// Clear the composers of replies that were not confirmed in time
+async fn discard_expired(app_handle: &AppHandle) {
+    let expired = app_handle.state::<PendingReplies>().expire();
+    let manager = app_handle.state::<CdpManager>();
+    for pending in expired {
+        if let Prepared::Cdp(tab) = &pending.prepared {
+            if let Err(e) = cdp::discard(&manager, tab).await {
+                eprintln!("⚠ Failed to clear expired reply: {}", e);
+            }
+        }
+        let _ = app_handle.emit("reply-cancelled", &pending.preview);
+    }
+}
+
+impl Default for PendingReplies {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Question read aloud before sending
+fn confirmation_question(preview: &ReplyPreview, language: &str) -> String {
+    let spanish = language.to_lowercase().starts_with("es");
+    match (&preview.recipient, spanish) {
+        (Some(recipient), true) => format!(
+            "¿Envío a {} en {}: {}?",
+            recipient, preview.app_name, preview.text
+        ),
+        (None, true) => format!("¿Envío en {}: {}?", preview.app_name, preview.text),
+        (Some(recipient), false) => format!(
+            "Send to {} on {}: {}?",
+            recipient, preview.app_name, preview.text
+        ),
+        (None, false) => format!("Send on {}: {}?", preview.app_name, preview.text),
+    }
+}
+
+/// The requested channel, or the best one available: the notification
+/// itself, the app's tab, then the clipboard
+async fn choose_channel(
+    manager: &CdpManager,
+    request: &ReplyRequest,
+    app_name: &str,
+    mapper: &ContextMapper,
+) -> Result<Prepared, String> {
+    let inline = request
+        .notification_id
+        .filter(|id| notifications::can_reply_inline(*id));
+    match (request.channel, inline) {
+        (Some(ReplyChannel::Clipboard), _) => Ok(Prepared::Clipboard),
+        (Some(ReplyChannel::Notification), Some(id)) | (None, Some(id)) => {
+            Ok(Prepared::Notification(id))
+        }
+        (Some(ReplyChannel::Notification), None) => {
+            Err("La notificación no admite respuesta directa".to_string())
+        }
+        (Some(ReplyChannel::Cdp), _) => {
+            Ok(Prepared::Cdp(cdp::locate(manager, app_name, mapper).await?))
+        }
+        (None, None) => match cdp::locate(manager, app_name, mapper).await {
+            Ok(tab) => Ok(Prepared::Cdp(tab)),
+            Err(e) => {
+                eprintln!("⚠ {}; the reply will be copied to the clipboard", e);
+                Ok(Prepared::Clipboard)
+            }
+        },
+    }
+}
+
+/// Prepare a reply and wait for confirmation; dry runs touch nothing
+pub async fn prepare(
+    app_handle: &AppHandle,
+    request: ReplyRequest,
+) -> Result<ReplyPreview, String> {
+    let text = request.text.trim().to_string();
+    if text.is_empty() {
+        return Err("La respuesta está vacía".to_string());
+    }
+    let mapper = ContextMapper::new();
+    let app_name = mapper
+        .find_context(&request.app_name)
+        .map(|c| c.app_name)
+        .unwrap_or_else(|| request.app_name.clone());
+
+    let manager = app_handle.state::<CdpManager>();
+    let prepared = choose_channel(&manager, &request, &app_name, &mapper).await?;
+    let preview = ReplyPreview {
+        id: None,
+        channel: prepared.channel(),
+        app_name,
+        recipient: request.recipient,
+        text,
+        destination: prepared.destination(),
+        submit_with: prepared.submit_with(),
+        dry_run: request.dry_run,
+    };
+    if request.dry_run {
+        return Ok(preview);
+    }
+
+    // Other channels hold the reply here until confirmed; nothing is typed
+    if let Prepared::Cdp(tab) = &prepared {
+        cdp::type_reply(&manager, tab, preview.recipient.as_deref(), &preview.text).await?;
+    }
+    let preview = app_handle
+        .state::<PendingReplies>()
+        .insert(preview, prepared);
+
+    let _ = app_handle.emit("reply-pending", &preview);
+    let expiring = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(CONFIRMATION_TIMEOUT).await;
+        discard_expired(&expiring).await;
+    });
+    if let Some(language) = request.speak_confirmation {
+        app_handle.state::<SpeechQueue>().push(
+            Announcement {
+                app_name: preview.app_name.clone(),
+                text: confirmation_question(&preview, &language),
+                language,
+                priority: Some(TaskPriority::High),
+            },
+            &ContextMapper::new(),
+        );
+    }
+    Ok(preview)
+}
+
+/// Send a pending reply, or discard it when `send` is false
+pub async fn resolve(
+    app_handle: &AppHandle,
+    id: Option<u64>,
+    send: bool,
+) -> Result<ReplyPreview, String> {
+    discard_expired(app_handle).await;
+    let replies = app_handle.state::<PendingReplies>();
+    let pending = replies.take(id)?;
+    let manager = app_handle.state::<CdpManager>();
+    let resolved = match &pending.prepared {
+        Prepared::Cdp(tab) if send => cdp::submit(&manager, tab, &pending.preview.text).await,
+        Prepared::Cdp(tab) => cdp::discard(&manager, tab).await,
+        Prepared::Notification(id) if send => {
+            notifications::reply_inline(*id, &pending.preview.text).await
+        }
+        Prepared::Clipboard if send => stt::copy_to_clipboard(pending.preview.text.clone()),
+        // The notification stays open and can still be answered
+        Prepared::Notification(_) | Prepared::Clipboard => Ok(()),
+    };
+    if let Err(e) = resolved {
+        replies.restore(pending);
+        return Err(e);
+    }
+    let event = if send {
+        "reply-sent"
+    } else {
+        "reply-cancelled"
+    };
+    let _ = app_handle.emit(event, &pending.preview);
+    Ok(pending.preview)
+}
+
+/// Confirm or cancel the latest pending reply from a voice command
+pub fn resolve_latest(app_handle: &AppHandle, send: bool) {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = resolve(&app_handle, None, send).await {
+            eprintln!("✗ Failed to resolve reply: {}", e);
+            let _ = app_handle.emit("reply-error", e);
+        }
+    });
+}
+
+/// Reply to the latest notification with dictated text, from a voice
+/// command; it waits for confirmation, asked for aloud in `language`
+pub fn send_dictated_latest(
+    app_handle: &AppHandle,
+    recipient: Option<String>,
+    text: String,
+    language: String,
+) {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let request = app_handle
+            .state::<QuickReplies>()
+            .dictated(recipient, text, &language);
+        let result = match request {
+            Ok(request) => prepare(&app_handle, request).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            eprintln!("✗ Failed to prepare reply: {}", e);
+            let _ = app_handle.emit("reply-error", e);
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn send_reply(
+    app_handle: AppHandle,
+    request: ReplyRequest,
+) -> Result<ReplyPreview, String> {
+    prepare(&app_handle, request).await
+}
+
+#[tauri::command]
+pub async fn confirm_reply(app_handle: AppHandle, id: Option<u64>) -> Result<ReplyPreview, String> {
+    resolve(&app_handle, id, true).await
+}
+
+#[tauri::command]
+pub async fn cancel_reply(app_handle: AppHandle, id: Option<u64>) -> Result<ReplyPreview, String> {
+    resolve(&app_handle, id, false).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context_mapper::ReplySelectors;
+
+    fn preview(recipient: Option<&str>) -> ReplyPreview {
+        ReplyPreview {
+            id: None,
+            channel: ReplyChannel::Cdp,
+            app_name: "Slack".to_string(),
+            recipient: recipient.map(str::to_string),
+            text: "voy en cinco minutos".to_string(),
+            destination: "general - Slack".to_string(),
+            submit_with: "Enter".to_string(),
+            dry_run: false,
+        }
+    }
+
+    fn tab() -> Prepared {
+        Prepared::Cdp(cdp::Tab {
+            target_id: "1".to_string(),
+            title: "general - Slack".to_string(),
+            selectors: ReplySelectors {
+                conversation: None,
+                composer: "textarea".to_string(),
+                send_button: None,
+            },
+        })
+    }
+
+    #[test]
+    fn test_pending_replies_are_taken_once() {
+        let pending = PendingReplies::new();
+        let first = pending.insert(preview(None), tab());
+        let second = pending.insert(preview(Some("Ana")), tab());
+
+        // Without an id the latest one is resolved
+        let taken = pending.take(None).unwrap();
+        assert_eq!(taken.preview.id, second.id);
+        assert!(pending.take(second.id).is_err());
+        assert_eq!(pending.take(first.id).unwrap().preview.id, first.id);
+        assert!(pending.take(None).is_err());
+    }
+
+    #[test]
+    fn test_expired_replies_are_returned_for_discarding() {
+        let pending = PendingReplies::new();
+        let fresh = pending.insert(preview(None), tab());
+        let stale = pending.insert(preview(Some("Ana")), tab());
+        if let Some(p) = pending.pending.lock().unwrap().get_mut(&stale.id.unwrap()) {
+            p.created -= CONFIRMATION_TIMEOUT;
+        }
+
+        let expired = pending.expire();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].preview.id, stale.id);
+        assert!(pending.take(stale.id).is_err());
+
+        // A reply that failed to send can be resolved again
+        let taken = pending.take(None).unwrap();
+        assert_eq!(taken.preview.id, fresh.id);
+        pending.restore(taken);
+        assert_eq!(pending.take(fresh.id).unwrap().preview.id, fresh.id);
+    }
+
+    #[test]
+    fn test_inline_replies_need_no_tab() {
+        let prepared = Prepared::Notification(7);
+        assert_eq!(prepared.channel(), ReplyChannel::Notification);
+        assert_eq!(prepared.destination(), "notification #7");
+        assert_eq!(prepared.submit_with(), "inline-reply");
+        assert_eq!(tab().channel(), ReplyChannel::Cdp);
+    }
+
+    #[test]
+    fn test_confirmation_question() {
+        assert_eq!(
+            confirmation_question(&preview(Some("Ana")), "es-ES"),
+            "¿Envío a Ana en Slack: voy en cinco minutos?"
+        );
+        assert_eq!(
+            confirmation_question(&preview(None), "en-US"),
+            "Send on Slack: voy en cinco minutos?"
+        );
+    }
+}
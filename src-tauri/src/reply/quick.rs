@@ -0,0 +1,568 @@
+//! Quick replies: canned texts with variables
+//!
+//! A quick reply answers the latest notification, or one chosen in the UI,
+//! and goes out through the usual reply channels, confirmation included. It
+//! is picked by name ("respuesta rápida reunión"), by its own global
+//! shortcut, or as the default for the notification's app.
+//!
+//! Templates use `{variable}` placeholders filled from the notification
+//! (`{sender}`, `{first_name}`, `{app}`), from the command (`{minutes}`), the
+//! clock (`{time}`) and the calendar state (`{event}`, `{free_at}`). Birdie
+//! has no calendar of its own: the calendar state is pushed to it with
+//! `set_calendar_state`.
+
+use super::{ReplyChannel, ReplyPreview, ReplyRequest};
+use crate::context_mapper::ContextMapper;
+use crate::hands_free::parse_shortcut;
+use crate::notifications::NotificationEvent;
+use crate::settings;
+use crate::stt::postprocess::normalize;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+/// Variables a template can use
+pub const VARIABLES: &[&str] = &[
+    "sender",
+    "first_name",
+    "app",
+    "minutes",
+    "time",
+    "event",
+    "free_at",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuickReply {
+    /// Short name to pick it by voice, such as "reunión"
+    pub id: String,
+    pub text: String,
+    /// Global shortcut that sends it to the latest notification
+    #[serde(default)]
+    pub shortcut: Option<String>,
+    /// Apps it is the default quick reply for
+    #[serde(default)]
+    pub default_for: Vec<String>,
+}
+
+impl QuickReply {
+    fn new(id: &str, text: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            text: text.to_string(),
+            shortcut: None,
+            default_for: Vec::new(),
+        }
+    }
+}
+
+/// Persisted quick replies
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuickReplySettings {
+    pub replies: Vec<QuickReply>,
+    /// Used for apps without a default of their own
+    pub default: Option<String>,
+    /// Global shortcut that sends the default quick reply of the latest
+    /// notification's app
+    pub shortcut: Option<String>,
+}
+
+impl Default for QuickReplySettings {
+    fn default() -> Self {
+        Self {
+            replies: vec![
+                QuickReply::new("reunión", "En una reunión, te respondo luego"),
+                QuickReply::new("voy", "Voy en {minutes} minutos"),
+                QuickReply::new("gracias", "¡Gracias, {first_name}!"),
+                QuickReply::new(
+                    "ocupado",
+                    "Estoy en {event} hasta las {free_at}, te escribo después",
+                ),
+                QuickReply::new("meeting", "In a meeting, I'll get back to you later"),
+            ],
+            default: Some("reunión".to_string()),
+            shortcut: None,
+        }
+    }
+}
+
+impl QuickReplySettings {
+    fn validate(&self) -> Result<(), String> {
+        let mut ids = Vec::new();
+        for reply in &self.replies {
+            let id = normalize(reply.id.trim());
+            if id.is_empty() {
+                return Err("Quick replies need a name".to_string());
+            }
+            if ids.contains(&id) {
+                return Err(format!("Duplicate quick reply {:?}", reply.id));
+            }
+            ids.push(id);
+            if reply.text.trim().is_empty() {
+                return Err(format!("Quick reply {:?} is empty", reply.id));
+            }
+            parse(&reply.text)?;
+        }
+        for shortcut in self.shortcuts() {
+            parse_shortcut(shortcut)?;
+        }
+        if let Some(default) = &self.default {
+            self.by_name(default)
+                .ok_or_else(|| format!("Unknown default quick reply {:?}", default))?;
+        }
+        Ok(())
+    }
+
+    fn shortcuts(&self) -> impl Iterator<Item = &String> {
+        self.shortcut
+            .iter()
+            .chain(self.replies.iter().filter_map(|r| r.shortcut.as_ref()))
+    }
+
+    /// By name, or by the start of its text
+    fn by_name(&self, name: &str) -> Option<&QuickReply> {
+        let name = normalize(name.trim());
+        self.replies
+            .iter()
+            .find(|r| normalize(&r.id) == name)
+            .or_else(|| {
+                self.replies
+                    .iter()
+                    .find(|r| !name.is_empty() && normalize(&r.text).starts_with(&name))
+            })
+    }
+
+    /// The quick reply named `name`, or the default for `app_name`
+    fn find(&self, name: Option<&str>, app_name: &str) -> Result<&QuickReply, String> {
+        if let Some(name) = name {
+            return self
+                .by_name(name)
+                .ok_or_else(|| format!("No hay ninguna respuesta rápida \"{}\"", name));
+        }
+        self.replies
+            .iter()
+            .find(|r| {
+                r.default_for
+                    .iter()
+                    .any(|a| a.eq_ignore_ascii_case(app_name))
+            })
+            .or_else(|| self.by_name(self.default.as_deref()?))
+            .ok_or_else(|| format!("No hay respuesta rápida por defecto para {}", app_name))
+    }
+}
+
+/// What the calendar says the user is doing
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CalendarState {
+    /// The current event, if busy
+    #[serde(default)]
+    pub event: Option<String>,
+    /// When the user is free again, as it should be read ("11:30")
+    #[serde(default)]
+    pub free_at: Option<String>,
+}
+
+/// Split a template into text and `{name}` placeholders, which must all be
+/// known variables
+fn parse(template: &str) -> Result<Vec<Part<'_>>, String> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed {{ in {:?}", template))?;
+        let name = rest[start + 1..start + end].trim();
+        if !VARIABLES.contains(&name) {
+            return Err(format!(
+                "Unknown variable {{{}}}; use one of {}",
+                name,
+                VARIABLES.join(", ")
+            ));
+        }
+        parts.push(Part::Text(&rest[..start]));
+        parts.push(Part::Variable(name));
+        rest = &rest[start + end + 1..];
+    }
+    parts.push(Part::Text(rest));
+    Ok(parts)
+}
+
+#[derive(Debug, PartialEq)]
+enum Part<'a> {
+    Text(&'a str),
+    Variable(&'a str),
+}
+
+/// "Ana Pérez" -> "Ana"
+fn first_name(sender: &str) -> Option<String> {
+    sender
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+        .find(|word| !word.is_empty())
+        .map(str::to_string)
+}
+
+fn variables(
+    notification: Option<&NotificationEvent>,
+    minutes: Option<u32>,
+    calendar: &CalendarState,
+    now: DateTime<Local>,
+) -> HashMap<&'static str, String> {
+    let mut values = HashMap::new();
+    values.insert("time", now.format("%H:%M").to_string());
+    if let Some(notification) = notification {
+        values.insert("sender", notification.sender.clone());
+        values.insert("app", notification.app_name.clone());
+        if let Some(name) = first_name(&notification.sender) {
+            values.insert("first_name", name);
+        }
+    }
+    if let Some(minutes) = minutes {
+        values.insert("minutes", minutes.to_string());
+    }
+    if let Some(event) = &calendar.event {
+        values.insert("event", event.clone());
+    }
+    if let Some(free_at) = &calendar.free_at {
+        values.insert("free_at", free_at.clone());
+    }
+    values
+}
+
+/// Fill in a template; every variable it uses must have a value
+fn render(template: &str, values: &HashMap<&'static str, String>) -> Result<String, String> {
+    let mut text = String::new();
+    for part in parse(template)? {
+        match part {
+            Part::Text(part) => text.push_str(part),
+            Part::Variable(name) => text.push_str(
+                values
+                    .get(name)
+                    .ok_or_else(|| format!("Falta el valor de {{{}}} en \"{}\"", name, template))?,
+            ),
+        }
+    }
+    Ok(text)
+}
+
+/// Quick replies, held in Tauri managed state
+pub struct QuickReplies {
+    path: PathBuf,
+    settings: Mutex<QuickReplySettings>,
+    calendar: Mutex<CalendarState>,
+    /// The notification a quick reply answers unless another one is given
+    latest: Mutex<Option<NotificationEvent>>,
+    registered: Mutex<Vec<Shortcut>>,
+}
+
+impl QuickReplies {
+    pub fn new(config_dir: &Path) -> Self {
+        let path = config_dir.join("quick_replies.json");
+        Self {
+            settings: Mutex::new(settings::load(&path)),
+            path,
+            calendar: Mutex::new(CalendarState::default()),
+            latest: Mutex::new(None),
+            registered: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn settings(&self) -> QuickReplySettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn set_settings(
+        &self,
+        app: &AppHandle,
+        settings: QuickReplySettings,
+    ) -> Result<(), String> {
+        settings.validate()?;
+        settings::save(&self.path, &settings)?;
+        *self.settings.lock().unwrap() = settings;
+        self.apply(app)
+    }
+
+    /// Remember the notification quick replies answer by default
+    pub fn record(&self, notification: &NotificationEvent) {
+        *self.latest.lock().unwrap() = Some(notification.clone());
+    }
+
+    /// Register the global shortcuts
+    pub fn apply(&self, app: &AppHandle) -> Result<(), String> {
+        let global_shortcut = app.global_shortcut();
+        for previous in self.registered.lock().unwrap().drain(..) {
+            global_shortcut
+                .unregister(previous)
+                .map_err(|e| format!("Failed to unregister shortcut: {}", e))?;
+        }
+
+        let settings = self.settings();
+        let bindings = settings.shortcut.iter().map(|s| (s, None)).chain(
+            settings
+                .replies
+                .iter()
+                .filter_map(|r| Some((r.shortcut.as_ref()?, Some(r.id.clone())))),
+        );
+        for (keys, name) in bindings {
+            let shortcut = parse_shortcut(keys)?;
+            global_shortcut
+                .on_shortcut(shortcut, move |app, _shortcut, event| {
+                    if event.state == ShortcutState::Pressed {
+                        send_latest(app, name.clone(), None);
+                    }
+                })
+                .map_err(|e| format!("Failed to register shortcut {}: {}", keys, e))?;
+            self.registered.lock().unwrap().push(shortcut);
+        }
+        Ok(())
+    }
+
+    /// The reply to send: the text filled in and addressed to the sender
+    fn request(
+        &self,
+        name: Option<&str>,
+        notification: Option<NotificationEvent>,
+        minutes: Option<u32>,
+    ) -> Result<ReplyRequest, String> {
+        let notification = notification
+            .or_else(|| self.latest.lock().unwrap().clone())
+            .ok_or(NO_NOTIFICATION)?;
+        let app_name = answered_app(&notification);
+
+        let settings = self.settings();
+        let reply = settings.find(name, &app_name)?;
+        let calendar = self.calendar.lock().unwrap().clone();
+        let values = variables(Some(&notification), minutes, &calendar, Local::now());
+
+        Ok(ReplyRequest {
+            app_name,
+            recipient: Some(notification.sender.clone()),
+            text: render(&reply.text, &values)?,
+            notification_id: notification.inline_reply.as_ref().map(|r| r.id),
+            channel: None,
+            dry_run: false,
+            speak_confirmation: None,
+        })
+    }
+
+    /// The request for a dictated reply to the latest notification, or to
+    /// `recipient` in the same app; the confirmation is asked for aloud in
+    /// `language`
+    pub(super) fn dictated(
+        &self,
+        recipient: Option<String>,
+        text: String,
+        language: &str,
+    ) -> Result<ReplyRequest, String> {
+        let notification = self.latest.lock().unwrap().clone().ok_or(NO_NOTIFICATION)?;
+        // Only a reply to the sender can go through the notification itself
+        let notification_id = match recipient {
+            None => notification.inline_reply.as_ref().map(|r| r.id),
+            Some(_) => None,
+        };
+        Ok(ReplyRequest {
+            app_name: answered_app(&notification),
+            recipient: recipient.or(Some(notification.sender)),
+            text,
+            notification_id,
+            channel: None,
+            dry_run: false,
+            speak_confirmation: Some(language.to_string()),
+        })
+    }
+}
+
+const NO_NOTIFICATION: &str = "No hay ninguna notificación a la que responder";
+
+/// The app a notification came from, as the context mapper names it
+fn answered_app(notification: &NotificationEvent) -> String {
+    ContextMapper::new()
+        .find_context(&notification.app_name)
+        .map(|c| c.app_name)
+        .unwrap_or_else(|| notification.app_name.clone())
+}
+
+/// Send a quick reply to the latest notification, from a voice command or
+/// shortcut; it still waits for confirmation
+pub fn send_latest(app_handle: &AppHandle, name: Option<String>, minutes: Option<u32>) {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let request = app_handle
+            .state::<QuickReplies>()
+            .request(name.as_deref(), None, minutes);
+        let result = match request {
+            Ok(request) => super::prepare(&app_handle, request).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            eprintln!("✗ Failed to send quick reply: {}", e);
+            let _ = app_handle.emit("reply-error", e);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_quick_replies(quick: tauri::State<'_, QuickReplies>) -> QuickReplySettings {
+    quick.settings()
+}
+
+#[tauri::command]
+pub fn set_quick_replies(
+    app_handle: AppHandle,
+    quick: tauri::State<'_, QuickReplies>,
+    settings: QuickReplySettings,
+) -> Result<(), String> {
+    quick.set_settings(&app_handle, settings)
+}
+
+#[tauri::command]
+pub fn set_calendar_state(quick: tauri::State<'_, QuickReplies>, state: CalendarState) {
+    *quick.calendar.lock().unwrap() = state;
+}
+
+/// Prepare a quick reply like `send_reply`; the latest notification is
+/// answered when none is given
+#[tauri::command]
+pub async fn send_quick_reply(
+    app_handle: AppHandle,
+    name: Option<String>,
+    notification: Option<NotificationEvent>,
+    minutes: Option<u32>,
+    channel: Option<ReplyChannel>,
+    dry_run: Option<bool>,
+) -> Result<ReplyPreview, String> {
+    let mut request =
+        app_handle
+            .state::<QuickReplies>()
+            .request(name.as_deref(), notification, minutes)?;
+    request.channel = channel;
+    request.dry_run = dry_run.unwrap_or(false);
+    super::prepare(&app_handle, request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn notification(app_name: &str, sender: &str) -> NotificationEvent {
+        NotificationEvent {
+            app_name: app_name.to_string(),
+            sender: sender.to_string(),
+            message: "¿Vienes?".to_string(),
+            timestamp: "2024-01-01T10:00:00Z".to_string(),
+            app_icon: None,
+            inline_reply: None,
+        }
+    }
+
+    #[test]
+    fn test_templates_are_filled_in() {
+        let calendar = CalendarState {
+            event: Some("la revisión trimestral".to_string()),
+            free_at: Some("11:30".to_string()),
+        };
+        let now = Local.with_ymd_and_hms(2024, 1, 1, 10, 5, 0).unwrap();
+        let values = variables(
+            Some(&notification("Slack", "Ana Pérez")),
+            Some(10),
+            &calendar,
+            now,
+        );
+
+        assert_eq!(
+            render("¡Gracias, {first_name}!", &values).unwrap(),
+            "¡Gracias, Ana!"
+        );
+        assert_eq!(
+            render("Voy en {minutes} minutos ({time})", &values).unwrap(),
+            "Voy en 10 minutos (10:05)"
+        );
+        assert_eq!(
+            render("Estoy en {event} hasta las {free_at}", &values).unwrap(),
+            "Estoy en la revisión trimestral hasta las 11:30"
+        );
+
+        // Without a calendar there is nothing to fill {event} with
+        let values = variables(None, None, &CalendarState::default(), now);
+        assert!(render("Estoy en {event}", &values).is_err());
+        assert!(render("Voy en {minutes} minutos", &values).is_err());
+    }
+
+    #[test]
+    fn test_unknown_variables_are_rejected() {
+        assert_eq!(
+            parse("Hola { first_name }").unwrap(),
+            vec![
+                Part::Text("Hola "),
+                Part::Variable("first_name"),
+                Part::Text("")
+            ]
+        );
+        assert!(parse("Hola {nombre}").is_err());
+        assert!(parse("Hola {first_name").is_err());
+
+        let mut settings = QuickReplySettings::default();
+        assert!(settings.validate().is_ok());
+        settings.replies.push(QuickReply::new("Voy", "duplicado"));
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_quick_replies_are_found_by_name_or_app() {
+        let mut settings = QuickReplySettings::default();
+        assert_eq!(
+            settings.find(Some("Reunion"), "Slack").unwrap().id,
+            "reunión"
+        );
+        // The start of the text works as a name too
+        assert_eq!(
+            settings.find(Some("en una reunión"), "Slack").unwrap().id,
+            "reunión"
+        );
+        assert!(settings.find(Some("vacaciones"), "Slack").is_err());
+
+        assert_eq!(settings.find(None, "Slack").unwrap().id, "reunión");
+        settings.replies[1].default_for = vec!["slack".to_string()];
+        assert_eq!(settings.find(None, "Slack").unwrap().id, "voy");
+        settings.default = None;
+        assert!(settings.find(None, "Gmail").is_err());
+    }
+
+    #[test]
+    fn test_dictated_replies_answer_the_latest_notification() {
+        let dir = std::env::temp_dir().join(format!("birdie-dictated-{}", std::process::id()));
+        let quick = QuickReplies::new(&dir);
+        assert!(quick
+            .dictated(None, "voy en cinco minutos".to_string(), "es-ES")
+            .is_err());
+
+        quick.record(&notification("slack", "Ximena"));
+        let request = quick
+            .dictated(None, "voy en cinco minutos".to_string(), "es-ES")
+            .unwrap();
+        assert_eq!(request.app_name, "Slack");
+        assert_eq!(request.recipient.as_deref(), Some("Ximena"));
+        assert_eq!(request.text, "voy en cinco minutos");
+        assert_eq!(request.speak_confirmation.as_deref(), Some("es-ES"));
+
+        // A named recipient is looked up in the same app
+        let request = quick
+            .dictated(Some("Pablo".to_string()), "ya voy".to_string(), "es-ES")
+            .unwrap();
+        assert_eq!(request.app_name, "Slack");
+        assert_eq!(request.recipient.as_deref(), Some("Pablo"));
+    }
+
+    #[test]
+    fn test_first_name() {
+        assert_eq!(first_name("Ana Pérez").as_deref(), Some("Ana"));
+        assert_eq!(first_name(" (Marta) ").as_deref(), Some("Marta"));
+        assert_eq!(first_name(""), None);
+    }
+}
@@ -0,0 +1,34 @@
+//! JSON settings files stored in the app config directory
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// Load a settings file, falling back to the default when it is missing
+///
+/// A file that cannot be parsed is logged and replaced by the default rather
+/// than preventing the app from starting.
+pub fn load<T: DeserializeOwned + Default>(path: &Path) -> T {
+    match fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+            eprintln!("⚠ Ignoring invalid settings file {}: {}", path.display(), e);
+            T::default()
+        }),
+        Err(_) => T::default(),
+    }
+}
+
+/// Write a settings file atomically
+pub fn save<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+
+    let json = serde_json::to_vec_pretty(value)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, json).map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+    fs::rename(&tmp, path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
@@ -0,0 +1,394 @@
+//! Speech adaptation: phrase hints sent with every recognition request
+//!
+//! Recognizers mangle names and jargon they have never seen ("Ximena",
+//! "Kubernetes"). Hints are built from three sources and weighted:
+//! - senders of recent notifications, boosted more the more recent they are
+//! - the user's glossary
+//! - vocabulary of the apps in `ContextMapper`, favouring the app being replied to
+//!
+//! The same hints are rendered as Google `speechContexts` and as a Whisper
+//! initial prompt.
+
+use crate::context_mapper::ContextMapper;
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const GLOSSARY_FILE: &str = "glossary.json";
+const CONTACTS_FILE: &str = "contacts.json";
+
+/// Boost for contacts seen in the last day
+const RECENT_CONTACT_BOOST: f32 = 20.0;
+/// Boost for contacts seen in the last 30 days
+const KNOWN_CONTACT_BOOST: f32 = 10.0;
+/// Boost for older contacts
+const OLD_CONTACT_BOOST: f32 = 5.0;
+const GLOSSARY_BOOST: f32 = 15.0;
+/// Boost for the vocabulary of the app being replied to
+const ACTIVE_APP_BOOST: f32 = 10.0;
+const OTHER_APPS_BOOST: f32 = 4.0;
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// Contacts remembered, most recent first
+const MAX_CONTACTS: usize = 300;
+/// How often new counts of already known contacts are written to disk
+const CONTACTS_SAVE_INTERVAL_SECS: i64 = 10 * 60;
+/// Google rejects phrases longer than 100 characters
+const MAX_PHRASE_CHARS: usize = 100;
+/// Stay well under Google's 5000 phrases per request
+const MAX_PHRASES: usize = 1000;
+/// Whisper only reads the last ~224 tokens of the prompt
+const MAX_PROMPT_CHARS: usize = 600;
+
+/// A group of phrases sharing a boost, in Google's `SpeechContext` format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeechContext {
+    pub phrases: Vec<String>,
+    pub boost: f32,
+}
+
+/// Phrase hints for one recognition request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PhraseHints {
+    /// Highest boost first
+    pub contexts: Vec<SpeechContext>,
+}
+
+impl PhraseHints {
+    /// Build hints from `(phrase, boost)` pairs, keeping each phrase once at its highest boost
+    fn from_weighted(mut weighted: Vec<(String, f32)>) -> Self {
+        weighted.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut seen = HashSet::new();
+        let mut contexts: Vec<SpeechContext> = Vec::new();
+        for (phrase, boost) in weighted {
+            if seen.len() >= MAX_PHRASES {
+                break;
+            }
+            let phrase = phrase.trim();
+            if phrase.is_empty()
+                || phrase.chars().count() > MAX_PHRASE_CHARS
+                || !seen.insert(phrase.to_lowercase())
+            {
+                continue;
+            }
+
+            match contexts.last_mut() {
+                Some(context) if context.boost == boost => context.phrases.push(phrase.to_string()),
+                _ => contexts.push(SpeechContext {
+                    phrases: vec![phrase.to_string()],
+                    boost,
+                }),
+            }
+        }
+
+        Self { contexts }
+    }
+
+    /// Comma-separated phrase list for Whisper's `prompt`, most important first
+    pub fn whisper_prompt(&self) -> String {
+        let mut prompt = String::new();
+        for phrase in self.contexts.iter().flat_map(|c| &c.phrases) {
+            // Room for the separator and the final period
+            if prompt.len() + phrase.len() + 3 > MAX_PROMPT_CHARS {
+                break;
+            }
+            if !prompt.is_empty() {
+                prompt.push_str(", ");
+            }
+            prompt.push_str(phrase);
+        }
+        if !prompt.is_empty() {
+            prompt.push('.');
+        }
+        prompt
+    }
+}
+
+/// Hints in both provider formats, for display in settings
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PhraseHintsPreview {
+    pub contexts: Vec<SpeechContext>,
+    pub whisper_prompt: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ContactStats {
+    name: String,
+    count: u32,
+    /// Unix timestamp in seconds
+    last_seen: i64,
+}
+
+/// Turn a notification sender into a speakable name, if it is one
+///
+/// Drops emails, phone numbers and app decorations such as "Ana (Slack)".
+fn normalize_contact(sender: &str) -> Option<String> {
+    let name = match sender.find(['(', '[', '|']) {
+        Some(i) => &sender[..i],
+        None => sender,
+    };
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let letters = name.chars().filter(|c| c.is_alphabetic()).count();
+    if name.is_empty()
+        || name.contains('@')
+        || letters * 2 < name.chars().count()
+        || name.split(' ').count() > 5
+        || name.chars().count() > MAX_PHRASE_CHARS
+    {
+        return None;
+    }
+
+    Some(name)
+}
+
+/// Glossary and contact history held in Tauri managed state
+pub struct SpeechAdaptation {
+    config_dir: PathBuf,
+    glossary: Mutex<Vec<String>>,
+    contacts: Mutex<HashMap<String, ContactStats>>,
+    /// When the contact history was last written, as a unix timestamp
+    contacts_saved: Mutex<i64>,
+}
+
+impl SpeechAdaptation {
+    /// Load the glossary and contact history from `config_dir`
+    pub fn new(config_dir: PathBuf) -> Self {
+        let glossary: Vec<String> = settings::load(&config_dir.join(GLOSSARY_FILE));
+        let contacts: Vec<ContactStats> = settings::load(&config_dir.join(CONTACTS_FILE));
+
+        Self {
+            config_dir,
+            glossary: Mutex::new(glossary),
+            contacts: Mutex::new(
+                contacts
+                    .into_iter()
+                    .map(|c| (c.name.to_lowercase(), c))
+                    .collect(),
+            ),
+            contacts_saved: Mutex::new(0),
+        }
+    }
+
+    pub fn glossary(&self) -> Vec<String> {
+        self.glossary.lock().unwrap().clone()
+    }
+
+    pub fn set_glossary(&self, terms: Vec<String>) -> Result<(), String> {
+        let mut seen = HashSet::new();
+        let terms: Vec<String> = terms
+            .into_iter()
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty() && seen.insert(t.to_lowercase()))
+            .collect();
+
+        settings::save(&self.config_dir.join(GLOSSARY_FILE), &terms)?;
+        *self.glossary.lock().unwrap() = terms;
+        Ok(())
+    }
+
+    /// Remember the sender of a notification
+    ///
+    /// The history is written when a contact is added or dropped; updates
+    /// to known contacts are only written every few minutes.
+    pub fn record_contact(&self, sender: &str, now: i64) {
+        let Some(name) = normalize_contact(sender) else {
+            return;
+        };
+
+        let snapshot = {
+            let mut contacts = self.contacts.lock().unwrap();
+            let mut saved = self.contacts_saved.lock().unwrap();
+            let added = !contacts.contains_key(&name.to_lowercase());
+            let entry = contacts
+                .entry(name.to_lowercase())
+                .or_insert_with(|| ContactStats {
+                    name: name.clone(),
+                    count: 0,
+                    last_seen: now,
+                });
+            entry.name = name;
+            entry.count += 1;
+            entry.last_seen = now;
+
+            if contacts.len() > MAX_CONTACTS {
+                let mut by_age: Vec<(String, i64)> = contacts
+                    .iter()
+                    .map(|(key, c)| (key.clone(), c.last_seen))
+                    .collect();
+                by_age.sort_by_key(|(_, last_seen)| *last_seen);
+                for (key, _) in by_age.into_iter().take(contacts.len() - MAX_CONTACTS) {
+                    contacts.remove(&key);
+                }
+            }
+
+            if !added && now - *saved < CONTACTS_SAVE_INTERVAL_SECS {
+                return;
+            }
+            *saved = now;
+            contacts.values().cloned().collect::<Vec<_>>()
+        };
+
+        if let Err(e) = settings::save(&self.config_dir.join(CONTACTS_FILE), &snapshot) {
+            eprintln!("⚠ Failed to save contact history: {}", e);
+        }
+    }
+
+    /// Build the hints for a transcription, optionally replying within `app_name`
+    pub fn hints(&self, app_name: Option<&str>, mapper: &ContextMapper, now: i64) -> PhraseHints {
+        let mut weighted = Vec::new();
+
+        for contact in self.contacts.lock().unwrap().values() {
+            let age = now - contact.last_seen;
+            let boost = if age < DAY_SECS {
+                RECENT_CONTACT_BOOST
+            } else if age < 30 * DAY_SECS {
+                KNOWN_CONTACT_BOOST
+            } else {
+                OLD_CONTACT_BOOST
+            };
+            weighted.push((contact.name.clone(), boost));
+            // "¿Qué dijo Ana?" usually uses the first name only
+            if let Some((first, _)) = contact.name.split_once(' ') {
+                weighted.push((first.to_string(), boost));
+            }
+        }
+
+        for term in self.glossary.lock().unwrap().iter() {
+            weighted.push((term.clone(), GLOSSARY_BOOST));
+        }
+
+        let active = app_name.and_then(|name| mapper.find_context(name));
+        for task in mapper.get_enabled_contexts() {
+            let boost = match &active {
+                Some(active) if active.app_name == task.app_name => ACTIVE_APP_BOOST,
+                _ => OTHER_APPS_BOOST,
+            };
+            weighted.push((task.app_name.clone(), boost));
+            weighted.extend(task.vocabulary.iter().map(|term| (term.clone(), boost)));
+        }
+
+        PhraseHints::from_weighted(weighted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn adaptation(name: &str) -> (SpeechAdaptation, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("birdie-adaptation-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        (SpeechAdaptation::new(dir.clone()), dir)
+    }
+
+    fn boost_of(hints: &PhraseHints, phrase: &str) -> Option<f32> {
+        hints
+            .contexts
+            .iter()
+            .find(|c| c.phrases.iter().any(|p| p == phrase))
+            .map(|c| c.boost)
+    }
+
+    #[test]
+    fn test_normalize_contact() {
+        assert_eq!(normalize_contact("  Ximena   Ruiz "), Some("Ximena Ruiz".to_string()));
+        assert_eq!(normalize_contact("Ana (Slack)"), Some("Ana".to_string()));
+        assert_eq!(normalize_contact("ana@example.com"), None);
+        assert_eq!(normalize_contact("+34 600 123 456"), None);
+        assert_eq!(normalize_contact(""), None);
+    }
+
+    #[test]
+    fn test_recent_contacts_get_higher_boost() {
+        let (adaptation, dir) = adaptation("boost");
+        adaptation.record_contact("Ximena Ruiz", NOW - 60);
+        adaptation.record_contact("Pedro", NOW - 10 * DAY_SECS);
+        adaptation.record_contact("Olga", NOW - 90 * DAY_SECS);
+
+        let hints = adaptation.hints(None, &ContextMapper::new(), NOW);
+        assert_eq!(boost_of(&hints, "Ximena Ruiz"), Some(RECENT_CONTACT_BOOST));
+        assert_eq!(boost_of(&hints, "Ximena"), Some(RECENT_CONTACT_BOOST));
+        assert_eq!(boost_of(&hints, "Pedro"), Some(KNOWN_CONTACT_BOOST));
+        assert_eq!(boost_of(&hints, "Olga"), Some(OLD_CONTACT_BOOST));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_contacts_are_saved_when_the_set_changes() {
+        let (adaptation, dir) = adaptation("contacts");
+        let saved = || -> Vec<ContactStats> { settings::load(&dir.join(CONTACTS_FILE)) };
+        adaptation.record_contact("Ana", NOW);
+        assert_eq!(saved().len(), 1);
+
+        // Known contacts are counted in memory and written later
+        adaptation.record_contact("Ana", NOW + 1);
+        assert_eq!(saved()[0].count, 1);
+        adaptation.record_contact("Luis", NOW + 2);
+        assert_eq!(saved().len(), 2);
+        adaptation.record_contact("Ana", NOW + 2 + CONTACTS_SAVE_INTERVAL_SECS);
+        let ana = saved().into_iter().find(|c| c.name == "Ana").unwrap();
+        assert_eq!(ana.count, 3);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_active_app_vocabulary_is_favoured() {
+        let (adaptation, dir) = adaptation("apps");
+        let hints = adaptation.hints(Some("slack"), &ContextMapper::new(), NOW);
+
+        assert_eq!(boost_of(&hints, "huddle"), Some(ACTIVE_APP_BOOST));
+        assert_eq!(boost_of(&hints, "servidor"), Some(OTHER_APPS_BOOST));
+        // Shared terms keep the highest boost and appear once
+        let canal = hints
+            .contexts
+            .iter()
+            .flat_map(|c| &c.phrases)
+            .filter(|p| p.as_str() == "canal")
+            .count();
+        assert_eq!(canal, 1);
+        assert_eq!(boost_of(&hints, "canal"), Some(ACTIVE_APP_BOOST));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_glossary_is_persisted() {
+        let (adaptation, dir) = adaptation("glossary");
+        adaptation
+            .set_glossary(vec![
+                "Kubernetes".to_string(),
+                " deploy ".to_string(),
+                "kubernetes".to_string(),
+                String::new(),
+            ])
+            .unwrap();
+        assert_eq!(adaptation.glossary(), vec!["Kubernetes", "deploy"]);
+
+        let reloaded = SpeechAdaptation::new(dir.clone());
+        assert_eq!(reloaded.glossary(), vec!["Kubernetes", "deploy"]);
+        let hints = reloaded.hints(None, &ContextMapper::new(), NOW);
+        assert_eq!(boost_of(&hints, "Kubernetes"), Some(GLOSSARY_BOOST));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_whisper_prompt_lists_most_important_first() {
+        let hints = PhraseHints::from_weighted(vec![
+            ("deploy".to_string(), GLOSSARY_BOOST),
+            ("Ximena".to_string(), RECENT_CONTACT_BOOST),
+            ("canal".to_string(), OTHER_APPS_BOOST),
+        ]);
+        assert_eq!(hints.whisper_prompt(), "Ximena, deploy, canal.");
+
+        let many = PhraseHints::from_weighted(
+            (0..500).map(|i| (format!("término{}", i), 1.0)).collect(),
+        );
+        assert!(many.whisper_prompt().len() <= MAX_PROMPT_CHARS);
+    }
+}
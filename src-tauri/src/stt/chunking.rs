@@ -0,0 +1,403 @@
+//! Splitting of long recordings for Google's synchronous `speech:recognize`
+//! endpoint, which rejects audio longer than about one minute.
+//!
+//! Recordings are cut at the quietest point near the chunk limit. When no
+//! real pause is found the cut is forced and the next chunk starts a little
+//! earlier, so the words around the cut are recognized twice and removed
+//! again by [`stitch_transcripts`].
+
+/// Audio longer than this is split before being sent to Google
+pub const SYNC_LIMIT_SECS: f32 = 55.0;
+
+/// Upper bound for a single chunk
+const MAX_CHUNK_SECS: f32 = 50.0;
+
+/// Earliest point at which a chunk may be cut while looking for silence
+const MIN_CHUNK_SECS: f32 = 30.0;
+
+/// Audio repeated at the start of a chunk when the cut is forced
+const OVERLAP_SECS: f32 = 1.5;
+
+/// Energy analysis frame length
+const FRAME_MS: u32 = 20;
+
+/// Number of frames averaged when searching for a pause (~300 ms)
+const SMOOTHING_FRAMES: usize = 15;
+
+/// RMS level below which a frame always counts as silence
+const SILENCE_FLOOR_RMS: f32 = 300.0;
+
+/// Frames quieter than this fraction of the recording's mean level are silence
+const SILENCE_RELATIVE: f32 = 0.15;
+
+/// Maximum number of words compared when removing overlap duplicates
+const MAX_OVERLAP_WORDS: usize = 12;
+
+/// Mono 16-bit PCM audio
+#[derive(Debug, Clone, PartialEq)]
+pub struct PcmAudio {
+    pub sample_rate: u32,
+    pub samples: Vec<i16>,
+}
+
+impl PcmAudio {
+    pub fn duration_secs(&self) -> f32 {
+        if self.sample_rate == 0 {
+            return 0.0;
+        }
+        self.samples.len() as f32 / self.sample_rate as f32
+    }
+}
+
+/// A piece of a longer recording
+#[derive(Debug, Clone)]
+pub struct AudioChunk {
+    pub audio: PcmAudio,
+    /// Position of the chunk in the original recording
+    pub start_secs: f32,
+    /// Whether this chunk repeats the tail of the previous one
+    pub overlaps_previous: bool,
+}
+
+/// Transcript of one chunk, in recording order
+#[derive(Debug, Clone)]
+pub struct ChunkTranscript {
+    pub text: String,
+    pub overlaps_previous: bool,
+}
+
+/// Check for a RIFF/WAVE header
+pub fn is_wav(bytes: &[u8]) -> bool {
+    bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WAVE"
+}
+
+/// Parse a 16-bit PCM WAV file, mixing multiple channels down to mono
+pub fn parse_wav(bytes: &[u8]) -> Result<PcmAudio, String> {
+    if !is_wav(bytes) {
+        return Err("Audio is not a WAV file".to_string());
+    }
+
+    let mut channels = 0u16;
+    let mut sample_rate = 0u32;
+    let mut bits_per_sample = 0u16;
+    let mut data: Option<&[u8]> = None;
+
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let size = u32::from_le_bytes([bytes[pos + 4], bytes[pos + 5], bytes[pos + 6], bytes[pos + 7]])
+            as usize;
+        let body_start = pos + 8;
+        // Recorders that stream WAV often leave the data size at 0 or u32::MAX
+        let body_end = body_start.saturating_add(size).min(bytes.len());
+        let body = &bytes[body_start..body_end];
+
+        match id {
+            b"fmt " => {
+                if body.len() < 16 {
+                    return Err("Invalid WAV fmt chunk".to_string());
+                }
+                let format = u16::from_le_bytes([body[0], body[1]]);
+                // 1 = PCM, 0xFFFE = WAVE_FORMAT_EXTENSIBLE
+                if format != 1 && format != 0xFFFE {
+                    return Err(format!("Unsupported WAV format: {}", format));
+                }
+                channels = u16::from_le_bytes([body[2], body[3]]);
+                sample_rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+                bits_per_sample = u16::from_le_bytes([body[14], body[15]]);
+            }
+            b"data" => {
+                data = Some(if size == 0 { &bytes[body_start..] } else { body });
+                break;
+            }
+            _ => {}
+        }
+
+        // Chunks are padded to an even size
+        pos = body_end + (size & 1);
+    }
+
+    if channels == 0 || sample_rate == 0 {
+        return Err("WAV file has no fmt chunk".to_string());
+    }
+    if bits_per_sample != 16 {
+        return Err(format!(
+            "Unsupported WAV sample size: {} bits (expected 16)",
+            bits_per_sample
+        ));
+    }
+    let data = data.ok_or_else(|| "WAV file has no data chunk".to_string())?;
+
+    let channels = channels as usize;
+    let samples = data
+        .chunks_exact(2 * channels)
+        .map(|frame| {
+            let sum: i32 = frame
+                .chunks_exact(2)
+                .map(|s| i16::from_le_bytes([s[0], s[1]]) as i32)
+                .sum();
+            (sum / channels as i32) as i16
+        })
+        .collect();
+
+    Ok(PcmAudio {
+        sample_rate,
+        samples,
+    })
+}
+
+/// Encode mono PCM audio as a WAV file
+pub fn encode_wav(audio: &PcmAudio) -> Vec<u8> {
+    let data_len = (audio.samples.len() * 2) as u32;
+    let mut out = Vec::with_capacity(44 + data_len as usize);
+
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data_len).to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    out.extend_from_slice(&1u16.to_le_bytes()); // mono
+    out.extend_from_slice(&audio.sample_rate.to_le_bytes());
+    out.extend_from_slice(&(audio.sample_rate * 2).to_le_bytes()); // byte rate
+    out.extend_from_slice(&2u16.to_le_bytes()); // block align
+    out.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    for sample in &audio.samples {
+        out.extend_from_slice(&sample.to_le_bytes());
+    }
+
+    out
+}
+
+/// RMS level of each analysis frame
+fn frame_levels(audio: &PcmAudio, frame_len: usize) -> Vec<f32> {
+    audio
+        .samples
+        .chunks(frame_len)
+        .map(|frame| {
+            let sum: f64 = frame.iter().map(|&s| (s as f64) * (s as f64)).sum();
+            (sum / frame.len() as f64).sqrt() as f32
+        })
+        .collect()
+}
+
+/// Split audio into chunks short enough for synchronous recognition
+///
+/// Audio under [`SYNC_LIMIT_SECS`] is returned as a single chunk.
+pub fn split_at_silence(audio: &PcmAudio) -> Vec<AudioChunk> {
+    if audio.duration_secs() <= SYNC_LIMIT_SECS {
+        return vec![AudioChunk {
+            audio: audio.clone(),
+            start_secs: 0.0,
+            overlaps_previous: false,
+        }];
+    }
+
+    let frame_len = (audio.sample_rate * FRAME_MS / 1000).max(1) as usize;
+    let frames_per_sec = 1000 / FRAME_MS as usize;
+    let levels = frame_levels(audio, frame_len);
+
+    let mean = levels.iter().sum::<f32>() / levels.len().max(1) as f32;
+    let silence_threshold = SILENCE_FLOOR_RMS.max(mean * SILENCE_RELATIVE);
+
+    let max_frames = (MAX_CHUNK_SECS * frames_per_sec as f32) as usize;
+    let min_frames = (MIN_CHUNK_SECS * frames_per_sec as f32) as usize;
+    let overlap_frames = (OVERLAP_SECS * frames_per_sec as f32) as usize;
+
+    let mut chunks = Vec::new();
+    let mut start = 0usize;
+    let mut overlaps_previous = false;
+
+    while start < levels.len() {
+        let remaining = levels.len() - start;
+        if remaining <= max_frames {
+            chunks.push((start, levels.len(), overlaps_previous));
+            break;
+        }
+
+        // Quietest smoothed window between the minimum and maximum chunk length
+        let search_from = start + min_frames;
+        let search_to = start + max_frames;
+        let mut best_frame = search_to;
+        let mut best_level = f32::MAX;
+        for center in search_from..search_to {
+            let lo = center.saturating_sub(SMOOTHING_FRAMES / 2);
+            let hi = (center + SMOOTHING_FRAMES / 2 + 1).min(levels.len());
+            let level = levels[lo..hi].iter().sum::<f32>() / (hi - lo) as f32;
+            if level < best_level {
+                best_level = level;
+                best_frame = center;
+            }
+        }
+
+        if best_level <= silence_threshold {
+            chunks.push((start, best_frame, overlaps_previous));
+            start = best_frame;
+            overlaps_previous = false;
+        } else {
+            // No pause found: cut hard and repeat a little audio in the next chunk
+            chunks.push((start, search_to, overlaps_previous));
+            start = search_to - overlap_frames;
+            overlaps_previous = true;
+        }
+    }
+
+    chunks
+        .into_iter()
+        .map(|(from, to, overlaps_previous)| {
+            let from = (from * frame_len).min(audio.samples.len());
+            let to = (to * frame_len).min(audio.samples.len());
+            AudioChunk {
+                audio: PcmAudio {
+                    sample_rate: audio.sample_rate,
+                    samples: audio.samples[from..to].to_vec(),
+                },
+                start_secs: from as f32 / audio.sample_rate as f32,
+                overlaps_previous,
+            }
+        })
+        .collect()
+}
+
+/// Normalize a word for overlap comparison
+fn normalize_word(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Join chunk transcripts in order, dropping words repeated by overlapping chunks
+pub fn stitch_transcripts(parts: &[ChunkTranscript]) -> String {
+    let mut words: Vec<&str> = Vec::new();
+
+    for part in parts {
+        let next: Vec<&str> = part.text.split_whitespace().collect();
+        let mut skip = 0;
+
+        if part.overlaps_previous {
+            let limit = MAX_OVERLAP_WORDS.min(words.len()).min(next.len());
+            for k in (1..=limit).rev() {
+                let tail = &words[words.len() - k..];
+                let head = &next[..k];
+                if tail
+                    .iter()
+                    .zip(head)
+                    .all(|(a, b)| normalize_word(a) == normalize_word(b))
+                {
+                    skip = k;
+                    break;
+                }
+            }
+        }
+
+        words.extend_from_slice(&next[skip..]);
+    }
+
+    words.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(secs: f32, rate: u32, amplitude: i16) -> Vec<i16> {
+        let n = (secs * rate as f32) as usize;
+        (0..n)
+            .map(|i| if i % 20 < 10 { amplitude } else { -amplitude })
+            .collect()
+    }
+
+    #[test]
+    fn test_wav_roundtrip() {
+        let audio = PcmAudio {
+            sample_rate: 16000,
+            samples: vec![0, 1, -1, i16::MAX, i16::MIN],
+        };
+        let bytes = encode_wav(&audio);
+        assert!(is_wav(&bytes));
+        assert_eq!(parse_wav(&bytes).unwrap(), audio);
+    }
+
+    #[test]
+    fn test_short_audio_is_not_split() {
+        let audio = PcmAudio {
+            sample_rate: 16000,
+            samples: tone(10.0, 16000, 8000),
+        };
+        let chunks = split_at_silence(&audio);
+        assert_eq!(chunks.len(), 1);
+        assert!(!chunks[0].overlaps_previous);
+    }
+
+    #[test]
+    fn test_split_prefers_silence() {
+        let rate = 8000;
+        let mut samples = tone(40.0, rate, 8000);
+        samples.extend(vec![0i16; rate as usize]); // 1 s pause at 40 s
+        samples.extend(tone(40.0, rate, 8000));
+        let audio = PcmAudio {
+            sample_rate: rate,
+            samples,
+        };
+
+        let chunks = split_at_silence(&audio);
+        assert_eq!(chunks.len(), 2);
+        assert!(!chunks[1].overlaps_previous);
+        let first = chunks[0].audio.duration_secs();
+        assert!((40.0..=41.0).contains(&first), "cut at {}", first);
+        let total: usize = chunks.iter().map(|c| c.audio.samples.len()).sum();
+        assert_eq!(total, audio.samples.len());
+    }
+
+    #[test]
+    fn test_split_without_silence_overlaps() {
+        let rate = 8000;
+        let audio = PcmAudio {
+            sample_rate: rate,
+            samples: tone(120.0, rate, 8000),
+        };
+
+        let chunks = split_at_silence(&audio);
+        assert!(chunks.len() >= 3);
+        assert!(chunks.iter().skip(1).all(|c| c.overlaps_previous));
+        assert!(chunks
+            .iter()
+            .all(|c| c.audio.duration_secs() <= MAX_CHUNK_SECS + 0.01));
+    }
+
+    #[test]
+    fn test_stitch_removes_overlap() {
+        let parts = vec![
+            ChunkTranscript {
+                text: "te escribo para confirmar que el deploy".to_string(),
+                overlaps_previous: false,
+            },
+            ChunkTranscript {
+                text: "el Deploy, está listo para mañana".to_string(),
+                overlaps_previous: true,
+            },
+        ];
+        assert_eq!(
+            stitch_transcripts(&parts),
+            "te escribo para confirmar que el deploy está listo para mañana"
+        );
+    }
+
+    #[test]
+    fn test_stitch_keeps_repeats_at_silence_cuts() {
+        let parts = vec![
+            ChunkTranscript {
+                text: "no".to_string(),
+                overlaps_previous: false,
+            },
+            ChunkTranscript {
+                text: "no quiero".to_string(),
+                overlaps_previous: false,
+            },
+        ];
+        assert_eq!(stitch_transcripts(&parts), "no no quiero");
+    }
+}
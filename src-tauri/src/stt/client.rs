@@ -0,0 +1,518 @@
+//! HTTP client for Google Cloud Speech-to-Text
+//!
+//! A single `SttClient` is kept in Tauri managed state so connections are
+//! pooled across transcriptions. Failed requests are classified from the
+//! status code and Google's JSON error body; rate limits and server errors
+//! are retried with exponential backoff.
+
+use super::{GoogleSttRequest, GoogleSttResponse};
+use serde::Deserialize;
+use std::fmt;
+use std::time::Duration;
+
+const DEFAULT_ENDPOINT: &str = "https://speech.googleapis.com/v1/speech:recognize";
+
+/// Time allowed to establish a connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time allowed for a whole request, including uploading ~1 minute of audio
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Longest wait honored from a `Retry-After` header
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// Errors returned by the Speech-to-Text API or while reaching it
+#[derive(Debug, Clone, PartialEq)]
+pub enum SttError {
+    /// No API key has been configured
+    MissingApiKey,
+    /// 401: the API key is invalid
+    Unauthorized(String),
+    /// 403: the key is valid but the API is disabled or restricted
+    PermissionDenied(String),
+    /// 400: the request was rejected, e.g. unsupported or too long audio
+    InvalidRequest(String),
+    /// 429 after exhausting retries
+    RateLimited(String),
+    /// 5xx after exhausting retries
+    Server { status: u16, message: String },
+    /// Any other unexpected status
+    Api { status: u16, message: String },
+    /// The request did not complete within the timeout
+    Timeout,
+    /// Network failure before a response was received
+    Transport(String),
+    /// The response body could not be parsed
+    InvalidResponse(String),
+}
+
+impl fmt::Display for SttError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SttError::MissingApiKey => write!(
+                f,
+                "API key not configured. Please add your Google Cloud API key in settings."
+            ),
+            SttError::Unauthorized(_) => {
+                write!(f, "Invalid API key. Please check your Google Cloud API key.")
+            }
+            SttError::PermissionDenied(message) => write!(
+                f,
+                "Access denied. Please ensure Speech-to-Text API is enabled in Google Cloud. ({})",
+                message
+            ),
+            SttError::InvalidRequest(message) => write!(f, "Request rejected by Google: {}", message),
+            SttError::RateLimited(message) => {
+                write!(f, "Too many requests, please try again later: {}", message)
+            }
+            SttError::Server { status, message } => {
+                write!(f, "Google Speech service error ({}): {}", status, message)
+            }
+            SttError::Api { status, message } => {
+                write!(f, "API request failed ({}): {}", status, message)
+            }
+            SttError::Timeout => write!(f, "API request timed out"),
+            SttError::Transport(message) => write!(f, "API request failed: {}", message),
+            SttError::InvalidResponse(message) => {
+                write!(f, "Failed to parse API response: {}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SttError {}
+
+impl From<SttError> for String {
+    fn from(error: SttError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Error body returned by Google APIs
+#[derive(Deserialize)]
+struct GoogleErrorBody {
+    error: GoogleErrorDetail,
+}
+
+#[derive(Deserialize)]
+struct GoogleErrorDetail {
+    message: Option<String>,
+    status: Option<String>,
+}
+
+impl SttError {
+    /// Classify a non-success response
+    fn from_response(status: u16, body: &str) -> Self {
+        let message = match serde_json::from_str::<GoogleErrorBody>(body) {
+            Ok(parsed) => match (parsed.error.status, parsed.error.message) {
+                (Some(code), Some(message)) => format!("{}: {}", code, message),
+                (None, Some(message)) => message,
+                (Some(code), None) => code,
+                (None, None) => format!("HTTP {}", status),
+            },
+            Err(_) if body.trim().is_empty() => format!("HTTP {}", status),
+            Err(_) => body.trim().chars().take(200).collect(),
+        };
+
+        match status {
+            400 => SttError::InvalidRequest(message),
+            401 => SttError::Unauthorized(message),
+            403 => SttError::PermissionDenied(message),
+            429 => SttError::RateLimited(message),
+            500..=599 => SttError::Server { status, message },
+            _ => SttError::Api { status, message },
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        matches!(self, SttError::RateLimited(_) | SttError::Server { .. })
+    }
+}
+
+/// Retry behaviour for rate limits and server errors
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for every further attempt
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Shared Speech-to-Text client
+pub struct SttClient {
+    http: reqwest::Client,
+    endpoint: String,
+    retry: RetryPolicy,
+}
+
+impl SttClient {
+    pub fn new() -> Self {
+        Self::with_endpoint(DEFAULT_ENDPOINT, REQUEST_TIMEOUT, RetryPolicy::default())
+    }
+
+    /// Client for a custom endpoint, used to test against a local server
+    pub fn with_endpoint(endpoint: &str, timeout: Duration, retry: RetryPolicy) -> Self {
+        let http = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(timeout)
+            .build()
+            .expect("failed to build HTTP client");
+
+        Self {
+            http,
+            endpoint: endpoint.to_string(),
+            retry,
+        }
+    }
+
+    /// Send a synchronous recognition request, retrying transient failures
+    pub(super) async fn recognize(
+        &self,
+        api_key: &str,
+        request: &GoogleSttRequest,
+    ) -> Result<GoogleSttResponse, SttError> {
+        if api_key.is_empty() {
+            return Err(SttError::MissingApiKey);
+        }
+
+        let mut attempt = 1;
+        loop {
+            let (error, retry_after) = match self.send(api_key, request).await {
+                Ok(response) => return Ok(response),
+                Err(failure) => failure,
+            };
+
+            if !error.is_retryable() || attempt >= self.retry.max_attempts {
+                return Err(error);
+            }
+
+            let backoff = self.retry.base_delay * 2u32.pow(attempt - 1);
+            let delay = retry_after.map_or(backoff, |d| d.min(MAX_RETRY_AFTER).max(backoff));
+            eprintln!(
+                "⚠ STT request failed (attempt {}/{}): {}. Retrying in {:?}",
+                attempt, self.retry.max_attempts, error, delay
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Single request; failures carry the server's `Retry-After` hint
+    async fn send(
+        &self,
+        api_key: &str,
+        request: &GoogleSttRequest,
+    ) -> Result<GoogleSttResponse, (SttError, Option<Duration>)> {
+        // The key goes in a header so it never ends up in proxy or access logs
+        let response = self
+            .http
+            .post(&self.endpoint)
+            .header("x-goog-api-key", api_key)
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| (transport_error(e), None))?;
+
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+
+        let body = response
+            .text()
+            .await
+            .map_err(|e| (transport_error(e), None))?;
+
+        if !status.is_success() {
+            return Err((SttError::from_response(status.as_u16(), &body), retry_after));
+        }
+
+        serde_json::from_str(&body).map_err(|e| (SttError::InvalidResponse(e.to_string()), None))
+    }
+}
+
+impl Default for SttClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn transport_error(error: reqwest::Error) -> SttError {
+    if error.is_timeout() {
+        SttError::Timeout
+    } else {
+        SttError::Transport(error.to_string())
+    }
+}
+
+#[cfg(test)]
+pub(super) mod mock {
+    //! Minimal HTTP/1.1 server replaying scripted responses
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[derive(Clone)]
+    pub struct MockResponse {
+        pub status: u16,
+        pub body: String,
+        pub headers: Vec<(&'static str, String)>,
+        pub delay: Duration,
+    }
+
+    impl MockResponse {
+        pub fn json(status: u16, body: &str) -> Self {
+            Self {
+                status,
+                body: body.to_string(),
+                headers: Vec::new(),
+                delay: Duration::ZERO,
+            }
+        }
+    }
+
+    pub struct MockServer {
+        pub url: String,
+        pub hits: Arc<AtomicUsize>,
+        pub requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    /// Serve `responses` in order, repeating the last one
+    pub async fn start(responses: Vec<MockResponse>) -> MockServer {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1/speech:recognize", listener.local_addr().unwrap());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let server_hits = hits.clone();
+        let server_requests = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut socket, _)) = listener.accept().await else {
+                    return;
+                };
+                let index = server_hits.fetch_add(1, Ordering::SeqCst);
+                let response = responses[index.min(responses.len() - 1)].clone();
+                let requests = server_requests.clone();
+
+                tokio::spawn(async move {
+                    let request = read_request(&mut socket).await;
+                    requests.lock().unwrap().push(request);
+                    tokio::time::sleep(response.delay).await;
+
+                    let mut head = format!(
+                        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+                        response.status,
+                        response.body.len()
+                    );
+                    for (name, value) in &response.headers {
+                        head.push_str(&format!("{}: {}\r\n", name, value));
+                    }
+                    head.push_str("\r\n");
+                    let _ = socket.write_all(head.as_bytes()).await;
+                    let _ = socket.write_all(response.body.as_bytes()).await;
+                    let _ = socket.shutdown().await;
+                });
+            }
+        });
+
+        MockServer {
+            url,
+            hits,
+            requests,
+        }
+    }
+
+    /// Read headers and a `Content-Length` body
+    async fn read_request(socket: &mut tokio::net::TcpStream) -> String {
+        let mut data = Vec::new();
+        let mut buf = [0u8; 8192];
+        loop {
+            let n = socket.read(&mut buf).await.unwrap_or(0);
+            if n == 0 {
+                break;
+            }
+            data.extend_from_slice(&buf[..n]);
+
+            let text = String::from_utf8_lossy(&data);
+            if let Some(end) = text.find("\r\n\r\n") {
+                let length = text[..end]
+                    .lines()
+                    .find_map(|l| {
+                        let (name, value) = l.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse::<usize>().ok())?
+                    })
+                    .unwrap_or(0);
+                if data.len() >= end + 4 + length {
+                    break;
+                }
+            }
+        }
+        String::from_utf8_lossy(&data).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::{start, MockResponse};
+    use super::*;
+    use crate::stt::{AudioContent, RecognitionConfig};
+    use std::sync::atomic::Ordering;
+
+    fn request() -> GoogleSttRequest {
+        GoogleSttRequest {
+            audio: AudioContent {
+                content: "AAAA".to_string(),
+            },
+            config: RecognitionConfig {
+                encoding: "LINEAR16".to_string(),
+                sample_rate_hertz: 16000,
+                language_code: "es-ES".to_string(),
+                max_alternatives: 1,
+                enable_word_time_offsets: false,
+                enable_word_confidence: false,
+                speech_contexts: Vec::new(),
+            },
+        }
+    }
+
+    fn client(url: &str) -> SttClient {
+        SttClient::with_endpoint(
+            url,
+            Duration::from_millis(500),
+            RetryPolicy {
+                max_attempts: 3,
+                base_delay: Duration::from_millis(1),
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized_is_not_retried() {
+        let server = start(vec![MockResponse::json(
+            401,
+            r#"{"error": {"code": 401, "message": "API key not valid.", "status": "UNAUTHENTICATED"}}"#,
+        )])
+        .await;
+
+        let error = client(&server.url).recognize("bad", &request()).await.unwrap_err();
+        assert_eq!(
+            error,
+            SttError::Unauthorized("UNAUTHENTICATED: API key not valid.".to_string())
+        );
+        assert!(error.to_string().contains("Invalid API key"));
+        assert_eq!(server.hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_api_key_is_sent_in_header() {
+        let server = start(vec![MockResponse::json(200, "{}")]).await;
+
+        client(&server.url).recognize("AIza-secret", &request()).await.unwrap();
+        let requests = server.requests.lock().unwrap();
+        let request_line = requests[0].lines().next().unwrap();
+        assert_eq!(request_line, "POST /v1/speech:recognize HTTP/1.1");
+        assert!(requests[0]
+            .lines()
+            .any(|l| l.eq_ignore_ascii_case("x-goog-api-key: AIza-secret")));
+    }
+
+    #[tokio::test]
+    async fn test_permission_denied_keeps_google_message() {
+        let server = start(vec![MockResponse::json(
+            403,
+            r#"{"error": {"code": 403, "message": "Cloud Speech-to-Text API has not been used", "status": "PERMISSION_DENIED"}}"#,
+        )])
+        .await;
+
+        let error = client(&server.url).recognize("key", &request()).await.unwrap_err();
+        assert!(matches!(error, SttError::PermissionDenied(ref m) if m.contains("has not been used")));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_is_retried() {
+        let server = start(vec![
+            MockResponse::json(429, r#"{"error": {"message": "Quota exceeded"}}"#),
+            MockResponse::json(503, ""),
+            MockResponse::json(
+                200,
+                r#"{"results": [{"alternatives": [{"transcript": "hola"}]}]}"#,
+            ),
+        ])
+        .await;
+
+        let response = client(&server.url).recognize("key", &request()).await.unwrap();
+        assert_eq!(response.results.unwrap().len(), 1);
+        assert_eq!(server.hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_give_up_after_max_attempts() {
+        let server = start(vec![MockResponse::json(500, "Internal error")]).await;
+
+        let error = client(&server.url).recognize("key", &request()).await.unwrap_err();
+        assert_eq!(
+            error,
+            SttError::Server {
+                status: 500,
+                message: "Internal error".to_string()
+            }
+        );
+        assert_eq!(server.hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_argument_is_reported() {
+        let server = start(vec![MockResponse::json(
+            400,
+            r#"{"error": {"code": 400, "message": "Sync input too long.", "status": "INVALID_ARGUMENT"}}"#,
+        )])
+        .await;
+
+        let error = client(&server.url).recognize("key", &request()).await.unwrap_err();
+        assert!(matches!(error, SttError::InvalidRequest(ref m) if m.contains("Sync input too long")));
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let mut slow = MockResponse::json(200, "{}");
+        slow.delay = Duration::from_secs(2);
+        let server = start(vec![slow]).await;
+
+        let error = client(&server.url).recognize("key", &request()).await.unwrap_err();
+        assert_eq!(error, SttError::Timeout);
+    }
+
+    #[tokio::test]
+    async fn test_missing_key_skips_request() {
+        let server = start(vec![MockResponse::json(200, "{}")]).await;
+
+        let error = client(&server.url).recognize("", &request()).await.unwrap_err();
+        assert_eq!(error, SttError::MissingApiKey);
+        assert_eq!(server.hits.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_success_body() {
+        let server = start(vec![MockResponse::json(200, "<html>")]).await;
+
+        let error = client(&server.url).recognize("key", &request()).await.unwrap_err();
+        assert!(matches!(error, SttError::InvalidResponse(_)));
+    }
+}
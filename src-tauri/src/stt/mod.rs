@@ -0,0 +1,571 @@
+pub mod adaptation;
+mod chunking;
+mod client;
+pub mod postprocess;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+
+pub use chunking::{encode_wav, is_wav, parse_wav, PcmAudio};
+pub use client::{SttClient, SttError};
+
+/// Request structure for Google Cloud Speech-to-Text API
+#[derive(Serialize)]
+struct GoogleSttRequest {
+    audio: AudioContent,
+    config: RecognitionConfig,
+}
+
+#[derive(Serialize)]
+struct AudioContent {
+    content: String, // base64-encoded audio
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RecognitionConfig {
+    encoding: String,
+    sample_rate_hertz: u32,
+    language_code: String,
+    max_alternatives: u32,
+    enable_word_time_offsets: bool,
+    enable_word_confidence: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    speech_contexts: Vec<adaptation::SpeechContext>,
+}
+
+/// Response from Google Cloud Speech-to-Text API
+#[derive(Debug, Deserialize)]
+pub struct GoogleSttResponse {
+    results: Option<Vec<SpeechRecognitionResult>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpeechRecognitionResult {
+    alternatives: Option<Vec<Alternative>>,
+    result_end_time: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Alternative {
+    transcript: String,
+    confidence: Option<f32>,
+    words: Option<Vec<WordInfo>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WordInfo {
+    word: String,
+    start_time: Option<String>,
+    end_time: Option<String>,
+    confidence: Option<f32>,
+}
+
+/// Number of alternatives requested for each result segment
+const MAX_ALTERNATIVES: u32 = 5;
+
+/// One recognition hypothesis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptAlternative {
+    pub transcript: String,
+    pub confidence: Option<f32>,
+}
+
+/// A stretch of speech recognized as a unit, with its n-best alternatives
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptSegment {
+    pub alternatives: Vec<TranscriptAlternative>,
+    /// End of the segment from the start of the recording
+    pub end_secs: Option<f32>,
+}
+
+/// A recognized word and its position in the recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WordTiming {
+    pub word: String,
+    pub start_secs: f32,
+    pub end_secs: f32,
+    pub confidence: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TranscriptionResult {
+    /// Best transcript of every segment, in order
+    pub text: String,
+    pub success: bool,
+    pub error: Option<String>,
+    /// Mean confidence of the best transcript, when Google reports it
+    #[serde(default)]
+    pub confidence: Option<f32>,
+    /// Whole-utterance alternatives, best first
+    #[serde(default)]
+    pub alternatives: Vec<TranscriptAlternative>,
+    #[serde(default)]
+    pub segments: Vec<TranscriptSegment>,
+    /// Word timestamps of the best transcript
+    #[serde(default)]
+    pub words: Vec<WordTiming>,
+}
+
+impl TranscriptionResult {
+    fn failure(error: impl ToString) -> Self {
+        Self {
+            text: String::new(),
+            success: false,
+            error: Some(error.to_string()),
+            confidence: None,
+            alternatives: Vec::new(),
+            segments: Vec::new(),
+            words: Vec::new(),
+        }
+    }
+}
+
+/// Recognition output of a single request
+#[derive(Debug, Default)]
+struct Recognition {
+    segments: Vec<TranscriptSegment>,
+    words: Vec<WordTiming>,
+}
+
+impl Recognition {
+    fn from_response(response: GoogleSttResponse) -> Self {
+        let mut recognition = Recognition::default();
+
+        for result in response.results.unwrap_or_default() {
+            let alternatives = result.alternatives.unwrap_or_default();
+            let Some(best) = alternatives.first() else {
+                continue;
+            };
+            if best.transcript.trim().is_empty() {
+                continue;
+            }
+
+            recognition.words.extend(best.words.iter().flatten().map(|w| WordTiming {
+                word: w.word.clone(),
+                start_secs: w.start_time.as_deref().and_then(parse_duration).unwrap_or(0.0),
+                end_secs: w.end_time.as_deref().and_then(parse_duration).unwrap_or(0.0),
+                confidence: w.confidence,
+            }));
+
+            recognition.segments.push(TranscriptSegment {
+                alternatives: alternatives
+                    .iter()
+                    .map(|alt| TranscriptAlternative {
+                        transcript: alt.transcript.trim().to_string(),
+                        confidence: alt.confidence,
+                    })
+                    .collect(),
+                end_secs: result.result_end_time.as_deref().and_then(parse_duration),
+            });
+        }
+
+        recognition
+    }
+
+    fn text(&self) -> String {
+        join_segments(self.segments.iter().map(|s| s.alternatives[0].transcript.as_str()))
+    }
+
+    /// Move all timestamps by `offset` seconds
+    fn shift(mut self, offset: f32) -> Self {
+        for segment in &mut self.segments {
+            segment.end_secs = segment.end_secs.map(|t| t + offset);
+        }
+        for word in &mut self.words {
+            word.start_secs += offset;
+            word.end_secs += offset;
+        }
+        self
+    }
+}
+
+/// Parse a protobuf JSON duration such as `"1.300s"`
+fn parse_duration(value: &str) -> Option<f32> {
+    value.strip_suffix('s')?.parse().ok()
+}
+
+fn join_segments<'a>(parts: impl Iterator<Item = &'a str>) -> String {
+    parts
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Build the final result from per-chunk recognitions in recording order
+fn build_result(chunks: Vec<(Recognition, bool)>) -> TranscriptionResult {
+    let parts: Vec<chunking::ChunkTranscript> = chunks
+        .iter()
+        .map(|(recognition, overlaps_previous)| chunking::ChunkTranscript {
+            text: recognition.text(),
+            overlaps_previous: *overlaps_previous,
+        })
+        .collect();
+    let text = chunking::stitch_transcripts(&parts);
+
+    // How many segments each chunk has, to stitch the alternatives by chunk
+    let chunk_segments: Vec<(usize, bool)> = chunks
+        .iter()
+        .map(|(recognition, overlaps_previous)| (recognition.segments.len(), *overlaps_previous))
+        .collect();
+    let mut segments = Vec::new();
+    let mut words: Vec<WordTiming> = Vec::new();
+    for (recognition, overlaps_previous) in chunks {
+        // Words repeated by an overlapping chunk start before the last kept word ends
+        let covered_until = match words.last() {
+            Some(last) if overlaps_previous => last.end_secs,
+            _ => f32::MIN,
+        };
+        words.extend(
+            recognition
+                .words
+                .into_iter()
+                .filter(|w| w.start_secs >= covered_until),
+        );
+        segments.extend(recognition.segments);
+    }
+
+    if text.is_empty() {
+        return TranscriptionResult::failure(NO_RESULTS);
+    }
+
+    let best_confidences: Vec<f32> = segments
+        .iter()
+        .filter_map(|s| s.alternatives[0].confidence)
+        .collect();
+    let confidence = if best_confidences.is_empty() {
+        None
+    } else {
+        Some(best_confidences.iter().sum::<f32>() / best_confidences.len() as f32)
+    };
+
+    // The i-th alternative of the utterance takes the i-th hypothesis of every
+    // segment, falling back to the best one where a segment has fewer, and
+    // drops the words an overlapping chunk repeats like the best one does
+    let depth = segments.iter().map(|s| s.alternatives.len()).max().unwrap_or(0);
+    let mut alternatives = vec![TranscriptAlternative {
+        transcript: text.clone(),
+        confidence,
+    }];
+    for i in 1..depth {
+        let picks: Vec<&TranscriptAlternative> = segments
+            .iter()
+            .map(|s| s.alternatives.get(i).unwrap_or(&s.alternatives[0]))
+            .collect();
+        let mut rest = picks.as_slice();
+        let parts: Vec<chunking::ChunkTranscript> = chunk_segments
+            .iter()
+            .map(|&(count, overlaps_previous)| {
+                let (chunk, next) = rest.split_at(count);
+                rest = next;
+                chunking::ChunkTranscript {
+                    text: join_segments(chunk.iter().map(|a| a.transcript.as_str())),
+                    overlaps_previous,
+                }
+            })
+            .collect();
+        let transcript = chunking::stitch_transcripts(&parts);
+        if alternatives.iter().any(|a| a.transcript == transcript) {
+            continue;
+        }
+        let known: Vec<f32> = picks.iter().filter_map(|a| a.confidence).collect();
+        alternatives.push(TranscriptAlternative {
+            transcript,
+            confidence: if known.len() == picks.len() {
+                Some(known.iter().sum::<f32>() / known.len() as f32)
+            } else {
+                None
+            },
+        });
+    }
+
+    TranscriptionResult {
+        text,
+        success: true,
+        error: None,
+        confidence,
+        alternatives,
+        segments,
+        words,
+    }
+}
+
+/// Number of chunks recognized at the same time for long recordings
+const MAX_CONCURRENT_CHUNKS: usize = 4;
+
+/// Transcribe audio using Google Cloud Speech-to-Text API
+///
+/// WAV recordings longer than the synchronous limit are split at pauses,
+/// transcribed concurrently and stitched back together in order.
+pub async fn transcribe_audio(
+    client: &SttClient,
+    audio_base64: String,
+    api_key: String,
+    language_code: String,
+    hints: &adaptation::PhraseHints,
+) -> Result<TranscriptionResult, String> {
+    // Validate API key
+    if api_key.is_empty() {
+        return Ok(TranscriptionResult::failure(SttError::MissingApiKey));
+    }
+
+    let audio_bytes = BASE64.decode(&audio_base64).unwrap_or_default();
+    if !chunking::is_wav(&audio_bytes) {
+        return Ok(
+            match recognize(client, &api_key, audio_base64, "WEBM_OPUS", 48000, &language_code, hints).await {
+                Ok(recognition) => build_result(vec![(recognition, false)]),
+                Err(e) => TranscriptionResult::failure(e),
+            },
+        );
+    }
+
+    let audio = match chunking::parse_wav(&audio_bytes) {
+        Ok(audio) => audio,
+        Err(e) => {
+            return Ok(TranscriptionResult::failure(format!(
+                "Failed to read recording: {}",
+                e
+            )))
+        }
+    };
+
+    let chunks = chunking::split_at_silence(&audio);
+    // Collected first: a lazy `map` over borrowed chunks makes the future non-`Send`
+    let requests: Vec<_> = chunks
+        .iter()
+        .map(|chunk| {
+            let content = BASE64.encode(chunking::encode_wav(&chunk.audio));
+            recognize(client, &api_key, content, "LINEAR16", chunk.audio.sample_rate, &language_code, hints)
+        })
+        .collect();
+    let recognitions: Vec<Result<Recognition, SttError>> = stream::iter(requests)
+        .buffered(MAX_CONCURRENT_CHUNKS)
+        .collect()
+        .await;
+
+    let mut parts = Vec::with_capacity(chunks.len());
+    for (chunk, recognition) in chunks.iter().zip(recognitions) {
+        match recognition {
+            Ok(recognition) => {
+                parts.push((recognition.shift(chunk.start_secs), chunk.overlaps_previous))
+            }
+            Err(e) => return Ok(TranscriptionResult::failure(e)),
+        }
+    }
+
+    Ok(build_result(parts))
+}
+
+const NO_RESULTS: &str = "No transcription results found";
+
+/// Check an API key by recognizing a short stretch of silence
+pub async fn verify_api_key(client: &SttClient, api_key: &str) -> Result<(), SttError> {
+    let silence = chunking::PcmAudio {
+        sample_rate: 16000,
+        samples: vec![0; 1600],
+    };
+    let content = BASE64.encode(chunking::encode_wav(&silence));
+    let hints = adaptation::PhraseHints::default();
+    recognize(client, api_key, content, "LINEAR16", silence.sample_rate, "en-US", &hints)
+        .await
+        .map(|_| ())
+}
+
+/// Send a single synchronous recognition request
+async fn recognize(
+    client: &SttClient,
+    api_key: &str,
+    content: String,
+    encoding: &str,
+    sample_rate_hertz: u32,
+    language_code: &str,
+    hints: &adaptation::PhraseHints,
+) -> Result<Recognition, SttError> {
+    let request = GoogleSttRequest {
+        audio: AudioContent { content },
+        config: RecognitionConfig {
+            encoding: encoding.to_string(),
+            sample_rate_hertz,
+            language_code: language_code.to_string(),
+            max_alternatives: MAX_ALTERNATIVES,
+            enable_word_time_offsets: true,
+            enable_word_confidence: true,
+            speech_contexts: hints.contexts.clone(),
+        },
+    };
+
+    let response = client.recognize(api_key, &request).await?;
+    Ok(Recognition::from_response(response))
+}
+
+/// Copy text to clipboard
+pub fn copy_to_clipboard(text: String) -> Result<(), String> {
+    match arboard::Clipboard::new() {
+        Ok(mut clipboard) => {
+            clipboard
+                .set_text(text)
+                .map_err(|e| format!("Failed to copy to clipboard: {}", e))?;
+            Ok(())
+        }
+        Err(e) => Err(format!("Failed to access clipboard: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Recognition {
+        Recognition::from_response(serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("1.300s"), Some(1.3));
+        assert_eq!(parse_duration("2s"), Some(2.0));
+        assert_eq!(parse_duration("abc"), None);
+    }
+
+    #[test]
+    fn test_all_segments_and_alternatives_are_kept() {
+        let recognition = parse(
+            r#"{"results": [
+                {"alternatives": [
+                    {"transcript": "dile a Ximena", "confidence": 0.9,
+                     "words": [{"word": "dile", "startTime": "0s", "endTime": "0.400s"}]},
+                    {"transcript": "dile a Jimena", "confidence": 0.6}
+                 ], "resultEndTime": "1.200s"},
+                {"alternatives": [{"transcript": " que ya está listo", "confidence": 0.8}]}
+            ]}"#,
+        );
+
+        let result = build_result(vec![(recognition, false)]);
+        assert!(result.success);
+        assert_eq!(result.text, "dile a Ximena que ya está listo");
+        assert_eq!(result.segments.len(), 2);
+        assert_eq!(result.segments[0].end_secs, Some(1.2));
+        assert_eq!(result.alternatives.len(), 2);
+        assert_eq!(result.alternatives[1].transcript, "dile a Jimena que ya está listo");
+        assert!((result.confidence.unwrap() - 0.85).abs() < 1e-6);
+        assert_eq!(result.words.len(), 1);
+        assert_eq!(result.words[0].end_secs, 0.4);
+    }
+
+    #[test]
+    fn test_empty_response_is_failure() {
+        let result = build_result(vec![(parse("{}"), false)]);
+        assert!(!result.success);
+        assert_eq!(result.error.as_deref(), Some(NO_RESULTS));
+    }
+
+    #[test]
+    fn test_overlapping_chunk_words_are_dropped() {
+        let first = parse(
+            r#"{"results": [{"alternatives": [{"transcript": "hola equipo",
+                "words": [{"word": "hola", "startTime": "0s", "endTime": "0.500s"},
+                          {"word": "equipo", "startTime": "49s", "endTime": "49.800s"}]}]}]}"#,
+        );
+        let second = parse(
+            r#"{"results": [{"alternatives": [{"transcript": "equipo mañana",
+                "words": [{"word": "equipo", "startTime": "0.500s", "endTime": "1.300s"},
+                          {"word": "mañana", "startTime": "2s", "endTime": "2.500s"}]}]}]}"#,
+        )
+        .shift(48.5);
+
+        let result = build_result(vec![(first, false), (second, true)]);
+        assert_eq!(result.text, "hola equipo mañana");
+        let words: Vec<&str> = result.words.iter().map(|w| w.word.as_str()).collect();
+        assert_eq!(words, vec!["hola", "equipo", "mañana"]);
+        assert_eq!(result.words[2].start_secs, 50.5);
+    }
+
+    #[test]
+    fn test_overlapping_chunk_alternatives_are_stitched() {
+        let first = parse(
+            r#"{"results": [{"alternatives": [
+                {"transcript": "llama a Ximena"},
+                {"transcript": "llama a Jimena"}
+            ]}]}"#,
+        );
+        let second = parse(
+            r#"{"results": [{"alternatives": [
+                {"transcript": "a Ximena mañana"},
+                {"transcript": "a Jimena mañana"}
+            ]}]}"#,
+        );
+
+        let result = build_result(vec![(first, false), (second, true)]);
+        assert_eq!(result.text, "llama a Ximena mañana");
+        assert_eq!(result.alternatives[1].transcript, "llama a Jimena mañana");
+    }
+
+    #[tokio::test]
+    async fn test_bad_key_is_reported_instead_of_no_results() {
+        let server = client::mock::start(vec![client::mock::MockResponse::json(
+            401,
+            r#"{"error": {"code": 401, "message": "API key not valid.", "status": "UNAUTHENTICATED"}}"#,
+        )])
+        .await;
+        let client = SttClient::with_endpoint(
+            &server.url,
+            std::time::Duration::from_secs(5),
+            client::RetryPolicy::default(),
+        );
+
+        let result = transcribe_audio(
+            &client,
+            "AAAA".to_string(),
+            "bad".to_string(),
+            "es-ES".to_string(),
+            &adaptation::PhraseHints::default(),
+        )
+            .await
+            .unwrap();
+        assert!(!result.success);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("Invalid API key. Please check your Google Cloud API key.")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_long_wav_is_sent_in_chunks() {
+        let server = client::mock::start(vec![client::mock::MockResponse::json(
+            200,
+            r#"{"results": [{"alternatives": [{"transcript": "parte", "confidence": 0.9}]}]}"#,
+        )])
+        .await;
+        let client = SttClient::with_endpoint(
+            &server.url,
+            std::time::Duration::from_secs(5),
+            client::RetryPolicy::default(),
+        );
+        let audio = chunking::PcmAudio {
+            sample_rate: 8000,
+            samples: (0..8000 * 70).map(|i| if i % 20 < 10 { 8000 } else { -8000 }).collect(),
+        };
+        let audio_base64 = BASE64.encode(chunking::encode_wav(&audio));
+
+        let hints = adaptation::PhraseHints {
+            contexts: vec![adaptation::SpeechContext {
+                phrases: vec!["Ximena".to_string()],
+                boost: 20.0,
+            }],
+        };
+        let result = transcribe_audio(&client, audio_base64, "key".to_string(), "es-ES".to_string(), &hints)
+            .await
+            .unwrap();
+        assert!(result.success);
+        assert_eq!(server.hits.load(std::sync::atomic::Ordering::SeqCst), 2);
+        let requests = server.requests.lock().unwrap();
+        assert!(requests.iter().all(|r| r.contains("\"encoding\":\"LINEAR16\"")));
+        assert!(requests
+            .iter()
+            .all(|r| r.contains(r#""speechContexts":[{"phrases":["Ximena"],"boost":20.0}]"#)));
+    }
+}
@@ -0,0 +1,760 @@
+//! Dictation post-processing
+//!
+//! Google returns raw lower-case text without punctuation. This stage turns
+//! spoken punctuation ("coma", "question mark") and emoji names into symbols,
+//! writes larger numbers as digits, fixes capitalization and applies the
+//! user's per-language replacement table for words the recognizer keeps
+//! getting wrong.
+
+use super::TranscriptionResult;
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const REPLACEMENTS_FILE: &str = "replacements.json";
+
+/// Numbers from this value up are written as digits
+const MIN_DIGITS_VALUE: u64 = 10;
+
+/// A word or phrase the recognizer gets wrong and its correction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replacement {
+    pub from: String,
+    pub to: String,
+}
+
+/// Lower-case and strip accents, for comparing spoken words
+pub(crate) fn normalize(word: &str) -> String {
+    word.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'á' | 'à' | 'ä' | 'â' => 'a',
+            'é' | 'è' | 'ë' | 'ê' => 'e',
+            'í' | 'ì' | 'ï' | 'î' => 'i',
+            'ó' | 'ò' | 'ö' | 'ô' => 'o',
+            'ú' | 'ù' | 'ü' | 'û' => 'u',
+            'ñ' => 'n',
+            c => c,
+        })
+        .collect()
+}
+
+/// Primary language subtag: "es-ES" -> "es"
+fn language_of(language_code: &str) -> String {
+    language_code
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+fn default_replacements() -> HashMap<String, Vec<Replacement>> {
+    let table = |pairs: &[(&str, &str)]| {
+        pairs
+            .iter()
+            .map(|(from, to)| Replacement {
+                from: from.to_string(),
+                to: to.to_string(),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    HashMap::from([
+        (
+            "es".to_string(),
+            table(&[
+                ("guasap", "WhatsApp"),
+                ("whatsapp", "WhatsApp"),
+                ("git hub", "GitHub"),
+                ("cubernetes", "Kubernetes"),
+                ("e mail", "email"),
+            ]),
+        ),
+        (
+            "en".to_string(),
+            table(&[
+                ("what's app", "WhatsApp"),
+                ("whatsapp", "WhatsApp"),
+                ("git hub", "GitHub"),
+                ("java script", "JavaScript"),
+                ("type script", "TypeScript"),
+                ("e mail", "email"),
+            ]),
+        ),
+    ])
+}
+
+/// User-editable replacement tables, one per language
+pub struct Replacements {
+    path: PathBuf,
+    tables: Mutex<HashMap<String, Vec<Replacement>>>,
+}
+
+impl Replacements {
+    pub fn new(config_dir: PathBuf) -> Self {
+        let path = config_dir.join(REPLACEMENTS_FILE);
+        let tables = if path.exists() {
+            settings::load(&path)
+        } else {
+            default_replacements()
+        };
+        Self {
+            path,
+            tables: Mutex::new(tables),
+        }
+    }
+
+    pub fn get(&self, language_code: &str) -> Vec<Replacement> {
+        self.tables
+            .lock()
+            .unwrap()
+            .get(&language_of(language_code))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn set(&self, language_code: &str, replacements: Vec<Replacement>) -> Result<(), String> {
+        let replacements: Vec<Replacement> = replacements
+            .into_iter()
+            .filter(|r| !r.from.trim().is_empty())
+            .collect();
+
+        let mut tables = self.tables.lock().unwrap();
+        let mut updated = tables.clone();
+        updated.insert(language_of(language_code), replacements);
+        settings::save(&self.path, &updated)?;
+        *tables = updated;
+        Ok(())
+    }
+}
+
+/// How a punctuation mark joins the surrounding words
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Attach {
+    /// Sticks to the previous word: , . ? )
+    Left,
+    /// Sticks to the next word: ¿ ¡ (
+    Right,
+    /// Line breaks, with no spaces around them
+    Break,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Piece {
+    Word(String),
+    Mark(String, Attach),
+}
+
+impl Piece {
+    fn norm(&self) -> Option<String> {
+        match self {
+            Piece::Word(word) => Some(normalize(word)),
+            Piece::Mark(..) => None,
+        }
+    }
+}
+
+/// What a spoken phrase turns into
+#[derive(Clone, Copy)]
+enum Spoken {
+    Mark(&'static str, Attach),
+    Emoji(&'static str),
+}
+
+const SPANISH_PHRASES: &[(&str, Spoken)] = &[
+    ("punto y coma", Spoken::Mark(";", Attach::Left)),
+    ("punto y aparte", Spoken::Mark(".\n", Attach::Break)),
+    ("punto y seguido", Spoken::Mark(".", Attach::Left)),
+    ("punto final", Spoken::Mark(".", Attach::Left)),
+    ("puntos suspensivos", Spoken::Mark("…", Attach::Left)),
+    ("dos puntos", Spoken::Mark(":", Attach::Left)),
+    ("punto", Spoken::Mark(".", Attach::Left)),
+    ("coma", Spoken::Mark(",", Attach::Left)),
+    ("abre interrogacion", Spoken::Mark("¿", Attach::Right)),
+    ("cierra interrogacion", Spoken::Mark("?", Attach::Left)),
+    ("signo de interrogacion", Spoken::Mark("?", Attach::Left)),
+    ("interrogacion", Spoken::Mark("?", Attach::Left)),
+    ("abre exclamacion", Spoken::Mark("¡", Attach::Right)),
+    ("cierra exclamacion", Spoken::Mark("!", Attach::Left)),
+    ("signo de exclamacion", Spoken::Mark("!", Attach::Left)),
+    ("exclamacion", Spoken::Mark("!", Attach::Left)),
+    ("abre parentesis", Spoken::Mark("(", Attach::Right)),
+    ("cierra parentesis", Spoken::Mark(")", Attach::Left)),
+    ("nueva linea", Spoken::Mark("\n", Attach::Break)),
+    ("salto de linea", Spoken::Mark("\n", Attach::Break)),
+    ("nuevo parrafo", Spoken::Mark("\n\n", Attach::Break)),
+    ("carita feliz", Spoken::Emoji("🙂")),
+    ("carita triste", Spoken::Emoji("🙁")),
+    ("carita guinando", Spoken::Emoji("😉")),
+    ("emoji risa", Spoken::Emoji("😂")),
+    ("emoji corazon", Spoken::Emoji("❤️")),
+    ("emoji pulgar arriba", Spoken::Emoji("👍")),
+    ("emoji me gusta", Spoken::Emoji("👍")),
+    ("emoji fuego", Spoken::Emoji("🔥")),
+    ("emoji fiesta", Spoken::Emoji("🎉")),
+    ("emoji aplausos", Spoken::Emoji("👏")),
+    ("emoji manos rezando", Spoken::Emoji("🙏")),
+    ("emoji ojos", Spoken::Emoji("👀")),
+    ("emoji cohete", Spoken::Emoji("🚀")),
+    ("emoji check", Spoken::Emoji("✅")),
+    ("emoji pensando", Spoken::Emoji("🤔")),
+];
+
+const ENGLISH_PHRASES: &[(&str, Spoken)] = &[
+    ("full stop", Spoken::Mark(".", Attach::Left)),
+    ("period", Spoken::Mark(".", Attach::Left)),
+    ("comma", Spoken::Mark(",", Attach::Left)),
+    ("semicolon", Spoken::Mark(";", Attach::Left)),
+    ("colon", Spoken::Mark(":", Attach::Left)),
+    ("question mark", Spoken::Mark("?", Attach::Left)),
+    ("exclamation mark", Spoken::Mark("!", Attach::Left)),
+    ("exclamation point", Spoken::Mark("!", Attach::Left)),
+    ("ellipsis", Spoken::Mark("…", Attach::Left)),
+    ("open parenthesis", Spoken::Mark("(", Attach::Right)),
+    ("close parenthesis", Spoken::Mark(")", Attach::Left)),
+    ("new line", Spoken::Mark("\n", Attach::Break)),
+    ("new paragraph", Spoken::Mark("\n\n", Attach::Break)),
+    ("smiley face", Spoken::Emoji("🙂")),
+    ("sad face", Spoken::Emoji("🙁")),
+    ("winking face", Spoken::Emoji("😉")),
+    ("laughing emoji", Spoken::Emoji("😂")),
+    ("heart emoji", Spoken::Emoji("❤️")),
+    ("thumbs up emoji", Spoken::Emoji("👍")),
+    ("fire emoji", Spoken::Emoji("🔥")),
+    ("party emoji", Spoken::Emoji("🎉")),
+    ("clapping emoji", Spoken::Emoji("👏")),
+    ("praying hands emoji", Spoken::Emoji("🙏")),
+    ("eyes emoji", Spoken::Emoji("👀")),
+    ("rocket emoji", Spoken::Emoji("🚀")),
+    ("check mark emoji", Spoken::Emoji("✅")),
+    ("thinking emoji", Spoken::Emoji("🤔")),
+];
+
+/// Spoken phrases that only look like punctuation
+const SPANISH_KEEP: &[&str] = &[
+    "a punto de",
+    "en punto",
+    "punto de vista",
+    "punto de encuentro",
+];
+
+/// Split text into words and the punctuation already attached to them
+fn split_pieces(text: &str) -> Vec<Piece> {
+    const LEADING: &[char] = &['¿', '¡', '(', '"', '«'];
+    const TRAILING: &[char] = &[',', '.', ';', ':', '?', '!', ')', '"', '»', '…'];
+
+    let mut pieces = Vec::new();
+    for raw in text.split_whitespace() {
+        let mut word = raw;
+        while let Some(c) = word.chars().next().filter(|c| LEADING.contains(c)) {
+            pieces.push(Piece::Mark(c.to_string(), Attach::Right));
+            word = &word[c.len_utf8()..];
+        }
+        let mut trailing = Vec::new();
+        while let Some(c) = word.chars().last().filter(|c| TRAILING.contains(c)) {
+            trailing.push(Piece::Mark(c.to_string(), Attach::Left));
+            word = &word[..word.len() - c.len_utf8()];
+        }
+        if !word.is_empty() {
+            pieces.push(Piece::Word(word.to_string()));
+        }
+        pieces.extend(trailing.into_iter().rev());
+    }
+    pieces
+}
+
+/// Whether the words at `pos` spell `phrase` (already normalized)
+fn phrase_at(pieces: &[Piece], pos: usize, phrase: &str) -> Option<usize> {
+    let mut end = pos;
+    for word in phrase.split(' ') {
+        if pieces.get(end)?.norm()? != word {
+            return None;
+        }
+        end += 1;
+    }
+    Some(end)
+}
+
+fn apply_replacements(pieces: Vec<Piece>, replacements: &[Replacement]) -> Vec<Piece> {
+    let mut table: Vec<(String, &str)> = replacements
+        .iter()
+        .map(|r| {
+            let from: Vec<String> = r.from.split_whitespace().map(normalize).collect();
+            (from.join(" "), r.to.as_str())
+        })
+        .filter(|(from, _)| !from.is_empty())
+        .collect();
+    // Longest first so "git hub" wins over "git"
+    table.sort_by_key(|(from, _)| std::cmp::Reverse(from.split(' ').count()));
+
+    let mut out = Vec::with_capacity(pieces.len());
+    let mut pos = 0;
+    'outer: while pos < pieces.len() {
+        for (from, to) in &table {
+            if let Some(end) = phrase_at(&pieces, pos, from) {
+                out.extend(to.split_whitespace().map(|w| Piece::Word(w.to_string())));
+                pos = end;
+                continue 'outer;
+            }
+        }
+        out.push(pieces[pos].clone());
+        pos += 1;
+    }
+    out
+}
+
+fn apply_spoken_phrases(pieces: Vec<Piece>, language: &str) -> Vec<Piece> {
+    let (phrases, keep): (&[(&str, Spoken)], &[&str]) = match language {
+        "es" => (SPANISH_PHRASES, SPANISH_KEEP),
+        "en" => (ENGLISH_PHRASES, &[]),
+        _ => return pieces,
+    };
+
+    let mut out = Vec::with_capacity(pieces.len());
+    let mut pos = 0;
+    'outer: while pos < pieces.len() {
+        for phrase in keep {
+            if let Some(end) = phrase_at(&pieces, pos, phrase) {
+                out.extend_from_slice(&pieces[pos..end]);
+                pos = end;
+                continue 'outer;
+            }
+        }
+        for (phrase, spoken) in phrases {
+            if let Some(end) = phrase_at(&pieces, pos, phrase) {
+                out.push(match *spoken {
+                    Spoken::Mark(mark, attach) => Piece::Mark(mark.to_string(), attach),
+                    Spoken::Emoji(emoji) => Piece::Word(emoji.to_string()),
+                });
+                pos = end;
+                continue 'outer;
+            }
+        }
+        out.push(pieces[pos].clone());
+        pos += 1;
+    }
+    out
+}
+
+fn spanish_number(word: &str) -> Option<u64> {
+    Some(match word {
+        "cero" => 0,
+        "un" | "uno" | "una" => 1,
+        "dos" => 2,
+        "tres" => 3,
+        "cuatro" => 4,
+        "cinco" => 5,
+        "seis" => 6,
+        "siete" => 7,
+        "ocho" => 8,
+        "nueve" => 9,
+        "diez" => 10,
+        "once" => 11,
+        "doce" => 12,
+        "trece" => 13,
+        "catorce" => 14,
+        "quince" => 15,
+        "dieciseis" => 16,
+        "diecisiete" => 17,
+        "dieciocho" => 18,
+        "diecinueve" => 19,
+        "veinte" => 20,
+        "veintiun" | "veintiuno" | "veintiuna" => 21,
+        "veintidos" => 22,
+        "veintitres" => 23,
+        "veinticuatro" => 24,
+        "veinticinco" => 25,
+        "veintiseis" => 26,
+        "veintisiete" => 27,
+        "veintiocho" => 28,
+        "veintinueve" => 29,
+        "treinta" => 30,
+        "cuarenta" => 40,
+        "cincuenta" => 50,
+        "sesenta" => 60,
+        "setenta" => 70,
+        "ochenta" => 80,
+        "noventa" => 90,
+        "cien" | "ciento" => 100,
+        "doscientos" | "doscientas" => 200,
+        "trescientos" | "trescientas" => 300,
+        "cuatrocientos" | "cuatrocientas" => 400,
+        "quinientos" | "quinientas" => 500,
+        "seiscientos" | "seiscientas" => 600,
+        "setecientos" | "setecientas" => 700,
+        "ochocientos" | "ochocientas" => 800,
+        "novecientos" | "novecientas" => 900,
+        _ => return None,
+    })
+}
+
+fn english_number(word: &str) -> Option<u64> {
+    if let Some((tens, units)) = word.split_once('-') {
+        let (tens, units) = (english_number(tens)?, english_number(units)?);
+        return (tens >= 20 && tens % 10 == 0 && units < 10).then_some(tens + units);
+    }
+    Some(match word {
+        "zero" => 0,
+        "one" => 1,
+        "two" => 2,
+        "three" => 3,
+        "four" => 4,
+        "five" => 5,
+        "six" => 6,
+        "seven" => 7,
+        "eight" => 8,
+        "nine" => 9,
+        "ten" => 10,
+        "eleven" => 11,
+        "twelve" => 12,
+        "thirteen" => 13,
+        "fourteen" => 14,
+        "fifteen" => 15,
+        "sixteen" => 16,
+        "seventeen" => 17,
+        "eighteen" => 18,
+        "nineteen" => 19,
+        "twenty" => 20,
+        "thirty" => 30,
+        "forty" => 40,
+        "fifty" => 50,
+        "sixty" => 60,
+        "seventy" => 70,
+        "eighty" => 80,
+        "ninety" => 90,
+        _ => return None,
+    })
+}
+
+/// Whether `value` can follow `current` within the same number
+fn fits(current: u64, value: u64) -> bool {
+    let below_hundreds = current % 100;
+    match value {
+        0 => current == 0,
+        1..=9 => below_hundreds.is_multiple_of(10) && below_hundreds != 10,
+        10..=99 => below_hundreds == 0,
+        _ => current.is_multiple_of(1000),
+    }
+}
+
+/// Parse a spoken number at `pos`: returns its value and end position
+fn parse_number(pieces: &[Piece], pos: usize, language: &str) -> Option<(u64, usize)> {
+    let lookup = match language {
+        "es" => spanish_number,
+        "en" => english_number,
+        _ => return None,
+    };
+    let (joiner, hundred, thousand) = match language {
+        "es" => ("y", None, "mil"),
+        _ => ("and", Some("hundred"), "thousand"),
+    };
+
+    let (mut total, mut current) = (0u64, 0u64);
+    let mut end = pos;
+    let mut any = false;
+
+    while let Some(word) = pieces.get(end).and_then(Piece::norm) {
+        if let Some(value) = lookup(&word) {
+            if any && !fits(current, value) {
+                break;
+            }
+            current += value;
+        } else if Some(word.as_str()) == hundred && (1..10).contains(&current) {
+            current *= 100;
+        } else if word == thousand && current < 1000 && total == 0 {
+            total = current.max(1) * 1000;
+            current = 0;
+        } else if word == joiner && any {
+            // "treinta y cinco", "one hundred and five"
+            let next = pieces
+                .get(end + 1)
+                .and_then(Piece::norm)
+                .and_then(|w| lookup(&w))
+                .filter(|&v| fits(current, v) && v < 100);
+            if next.is_none() || (language == "es" && current < 30) {
+                break;
+            }
+        } else {
+            break;
+        }
+        any = true;
+        end += 1;
+    }
+
+    // A trailing joiner belongs to the following text
+    while end > pos && pieces[end - 1].norm().as_deref() == Some(joiner) {
+        end -= 1;
+    }
+    any.then_some((total + current, end))
+}
+
+fn apply_numbers(pieces: Vec<Piece>, language: &str) -> Vec<Piece> {
+    let percent: &[&str] = match language {
+        "es" => &["por ciento"],
+        "en" => &["percent", "per cent"],
+        _ => return pieces,
+    };
+
+    let mut out = Vec::with_capacity(pieces.len());
+    let mut pos = 0;
+    while pos < pieces.len() {
+        let Some((value, end)) = parse_number(&pieces, pos, language) else {
+            out.push(pieces[pos].clone());
+            pos += 1;
+            continue;
+        };
+
+        let percent_end = percent.iter().find_map(|p| phrase_at(&pieces, end, p));
+        if let Some(percent_end) = percent_end {
+            out.push(Piece::Word(format!("{}%", value)));
+            pos = percent_end;
+        } else if value >= MIN_DIGITS_VALUE {
+            out.push(Piece::Word(value.to_string()));
+            pos = end;
+        } else {
+            out.extend_from_slice(&pieces[pos..end]);
+            pos = end;
+        }
+    }
+    out
+}
+
+fn render(pieces: &[Piece]) -> String {
+    let mut out = String::new();
+    let mut space = false;
+    for piece in pieces {
+        match piece {
+            Piece::Word(word) => {
+                if space {
+                    out.push(' ');
+                }
+                out.push_str(word);
+                space = true;
+            }
+            Piece::Mark(mark, Attach::Left) => {
+                out.truncate(out.trim_end_matches(' ').len());
+                out.push_str(mark);
+                space = true;
+            }
+            Piece::Mark(mark, Attach::Right) => {
+                if space {
+                    out.push(' ');
+                }
+                out.push_str(mark);
+                space = false;
+            }
+            Piece::Mark(mark, Attach::Break) => {
+                out.truncate(out.trim_end_matches(' ').len());
+                out.push_str(mark);
+                space = false;
+            }
+        }
+    }
+    out
+}
+
+/// Add the opening ¿ and ¡ that Spanish questions and exclamations need
+fn add_inverted_marks(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 4);
+    let mut sentence_start = 0;
+
+    for c in text.chars() {
+        if let Some(opening) = match c {
+            '?' => Some('¿'),
+            '!' => Some('¡'),
+            _ => None,
+        } {
+            if !out[sentence_start..].contains(opening) {
+                let content = &out[sentence_start..];
+                let at = sentence_start + (content.len() - content.trim_start().len());
+                out.insert(at, opening);
+            }
+        }
+        out.push(c);
+        if matches!(c, '.' | '?' | '!' | '\n' | '…') {
+            sentence_start = out.len();
+        }
+    }
+    out
+}
+
+/// Capitalize the first letter of every sentence
+fn capitalize_sentences(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut capitalize = true;
+    for c in text.chars() {
+        if capitalize && c.is_alphabetic() {
+            out.extend(c.to_uppercase());
+            capitalize = false;
+            continue;
+        }
+        if c.is_alphanumeric() {
+            capitalize = false;
+        }
+        if matches!(c, '.' | '?' | '!' | '\n' | '…') {
+            capitalize = true;
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Clean up one dictated transcript
+pub fn process(text: &str, language_code: &str, replacements: &[Replacement]) -> String {
+    let language = language_of(language_code);
+
+    let mut pieces = split_pieces(text);
+    pieces = apply_replacements(pieces, replacements);
+    pieces = apply_spoken_phrases(pieces, &language);
+    pieces = apply_numbers(pieces, &language);
+
+    if language == "en" {
+        for piece in &mut pieces {
+            if let Piece::Word(word) = piece {
+                if word == "i" || word.starts_with("i'") {
+                    word.replace_range(..1, "I");
+                }
+            }
+        }
+    }
+
+    let mut text = render(&pieces);
+    if language == "es" {
+        text = add_inverted_marks(&text);
+    }
+    capitalize_sentences(&text)
+}
+
+/// Post-process a transcription result in place
+pub fn apply(result: &mut TranscriptionResult, language_code: &str, replacements: &[Replacement]) {
+    result.text = process(&result.text, language_code, replacements);
+    for alternative in &mut result.alternatives {
+        alternative.transcript = process(&alternative.transcript, language_code, replacements);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn es(text: &str) -> String {
+        process(text, "es-ES", &[])
+    }
+
+    fn en(text: &str) -> String {
+        process(text, "en-US", &[])
+    }
+
+    #[test]
+    fn test_spanish_spoken_punctuation() {
+        assert_eq!(
+            es("hola ana coma llego tarde punto nos vemos en la oficina"),
+            "Hola ana, llego tarde. Nos vemos en la oficina"
+        );
+        assert_eq!(
+            es("vienes a la reunión interrogación"),
+            "¿Vienes a la reunión?"
+        );
+        assert_eq!(
+            es("lista dos puntos nueva línea leche nueva línea pan"),
+            "Lista:\nLeche\nPan"
+        );
+    }
+
+    #[test]
+    fn test_spanish_phrases_that_are_not_punctuation() {
+        assert_eq!(es("estoy a punto de salir"), "Estoy a punto de salir");
+        assert_eq!(es("a las tres en punto"), "A las tres en punto");
+    }
+
+    #[test]
+    fn test_english_spoken_punctuation() {
+        assert_eq!(
+            en("are you coming question mark i think so period"),
+            "Are you coming? I think so."
+        );
+        assert_eq!(
+            en("great work exclamation point new paragraph thanks"),
+            "Great work!\n\nThanks"
+        );
+    }
+
+    #[test]
+    fn test_emoji() {
+        assert_eq!(es("gracias emoji corazón"), "Gracias ❤️");
+        assert_eq!(en("shipped it rocket emoji"), "Shipped it 🚀");
+        assert_eq!(es("el corazón del problema"), "El corazón del problema");
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(es("llego en cinco minutos"), "Llego en cinco minutos");
+        assert_eq!(es("llego en quince minutos"), "Llego en 15 minutos");
+        assert_eq!(es("son treinta y cinco euros"), "Son 35 euros");
+        assert_eq!(es("dos mil quinientos usuarios"), "2500 usuarios");
+        assert_eq!(es("el cincuenta por ciento"), "El 50%");
+        assert_eq!(es("tú y yo"), "Tú y yo");
+        assert_eq!(en("about twenty-five people"), "About 25 people");
+        assert_eq!(en("one hundred and five tickets"), "105 tickets");
+        assert_eq!(en("three thousand two hundred"), "3200");
+        assert_eq!(en("five percent"), "5%");
+    }
+
+    #[test]
+    fn test_replacements() {
+        let replacements = vec![
+            Replacement {
+                from: "cubernetes".to_string(),
+                to: "Kubernetes".to_string(),
+            },
+            Replacement {
+                from: "git hub".to_string(),
+                to: "GitHub".to_string(),
+            },
+        ];
+        assert_eq!(
+            process(
+                "el deploy de cubernetes está en git hub punto",
+                "es-MX",
+                &replacements
+            ),
+            "El deploy de Kubernetes está en GitHub."
+        );
+    }
+
+    #[test]
+    fn test_existing_punctuation_is_kept() {
+        assert_eq!(es("hola, ¿qué tal?"), "Hola, ¿qué tal?");
+        assert_eq!(en("i'm fine, thanks."), "I'm fine, thanks.");
+    }
+
+    #[test]
+    fn test_unknown_language_only_capitalizes() {
+        assert_eq!(
+            process("bonjour virgule ça va", "fr-FR", &[]),
+            "Bonjour virgule ça va"
+        );
+    }
+
+    #[test]
+    fn test_replacement_tables_are_per_language() {
+        let dir = std::env::temp_dir().join(format!("birdie-replacements-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let replacements = Replacements::new(dir.clone());
+        assert!(!replacements.get("es-ES").is_empty());
+
+        let custom = vec![Replacement {
+            from: "birdy".to_string(),
+            to: "birdie".to_string(),
+        }];
+        replacements.set("en-GB", custom.clone()).unwrap();
+
+        let reloaded = Replacements::new(dir.clone());
+        assert_eq!(reloaded.get("en-US"), custom);
+        assert!(!reloaded.get("es").is_empty());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
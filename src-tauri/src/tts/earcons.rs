@@ -0,0 +1,466 @@
+//! Short sounds played before announcements
+//!
+//! A cue is chosen by app and [`TaskPriority`] so the source of a message can
+//! be told apart before it is read. The bundled earcons are synthesized tones;
+//! users can also pick their own WAV or OGG files. OGG files are decoded with
+//! `ffmpeg` or `sox`.
+
+use super::{run_program, TtsError};
+use crate::context_mapper::TaskPriority;
+use crate::settings;
+use crate::stt::{self, PcmAudio};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+const SETTINGS_FILE: &str = "earcons.json";
+
+/// Rate bundled earcons and decoded OGG files are rendered at
+pub const SAMPLE_RATE: u32 = 22_050;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Earcon {
+    Tick,
+    Pop,
+    Knock,
+    Chime,
+    Bell,
+    Alert,
+}
+
+/// A note of a bundled earcon: frequency, start and length in milliseconds
+type Note = (f32, u32, u32);
+
+impl Earcon {
+    pub const ALL: [Earcon; 6] = [
+        Earcon::Tick,
+        Earcon::Pop,
+        Earcon::Knock,
+        Earcon::Chime,
+        Earcon::Bell,
+        Earcon::Alert,
+    ];
+
+    fn notes(self) -> &'static [Note] {
+        match self {
+            Earcon::Tick => &[(1800.0, 0, 40)],
+            Earcon::Pop => &[(660.0, 0, 60), (990.0, 50, 80)],
+            Earcon::Knock => &[(180.0, 0, 60), (180.0, 130, 60)],
+            Earcon::Chime => &[(880.0, 0, 300), (1320.0, 90, 300)],
+            Earcon::Bell => &[(1046.5, 0, 500), (2093.0, 0, 350)],
+            Earcon::Alert => &[(988.0, 0, 90), (988.0, 150, 90), (1318.5, 300, 200)],
+        }
+    }
+
+    /// Render the earcon: sine notes with a short attack and exponential decay
+    pub fn render(self, sample_rate: u32) -> PcmAudio {
+        let notes = self.notes();
+        let end_ms = notes
+            .iter()
+            .map(|&(_, start_ms, length_ms)| start_ms + length_ms)
+            .max()
+            .unwrap_or(0);
+        let mut mix = vec![0f32; (end_ms as u64 * sample_rate as u64 / 1000) as usize];
+
+        for &(freq, start_ms, length_ms) in notes {
+            let start = (start_ms as u64 * sample_rate as u64 / 1000) as usize;
+            let length = (length_ms as u64 * sample_rate as u64 / 1000) as usize;
+            let attack = (sample_rate as usize / 200).max(1);
+            for i in 0..length.min(mix.len().saturating_sub(start)) {
+                let t = i as f32 / sample_rate as f32;
+                let envelope =
+                    (i as f32 / attack as f32).min(1.0) * (-5.0 * i as f32 / length as f32).exp();
+                mix[start + i] += (2.0 * PI * freq * t).sin() * envelope;
+            }
+        }
+
+        let peak = mix.iter().fold(0f32, |peak, s| peak.max(s.abs())).max(1.0);
+        PcmAudio {
+            sample_rate,
+            samples: mix
+                .iter()
+                .map(|s| (s / peak * 0.8 * i16::MAX as f32) as i16)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum Sound {
+    Bundled(Earcon),
+    /// A WAV or OGG file chosen by the user
+    File(PathBuf),
+}
+
+/// Which sound to play for an app, a priority or both
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CueRule {
+    /// Any app if unset
+    pub app_name: Option<String>,
+    /// Any priority if unset
+    pub priority: Option<TaskPriority>,
+    /// No cue at all if unset
+    pub sound: Option<Sound>,
+}
+
+impl CueRule {
+    /// How specific a matching rule is; `None` if it does not apply
+    fn specificity(&self, app_name: &str, priority: TaskPriority) -> Option<u8> {
+        let app = match &self.app_name {
+            Some(name) if name.eq_ignore_ascii_case(app_name) => 2,
+            Some(_) => return None,
+            None => 0,
+        };
+        let level = match self.priority {
+            Some(p) if p == priority => 1,
+            Some(_) => return None,
+            None => 0,
+        };
+        Some(app + level)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EarconSettings {
+    pub enabled: bool,
+    /// Cue volume, 0.0 to 1.0
+    pub volume: f32,
+    pub rules: Vec<CueRule>,
+    /// Messages at or below this priority only play their cue
+    pub cue_only_up_to: Option<TaskPriority>,
+}
+
+impl Default for EarconSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            volume: 0.6,
+            rules: Vec::new(),
+            cue_only_up_to: None,
+        }
+    }
+}
+
+/// Cue used when no rule applies
+fn default_sound(priority: TaskPriority) -> Option<Sound> {
+    let earcon = match priority {
+        TaskPriority::Disabled => return None,
+        TaskPriority::Low => Earcon::Tick,
+        TaskPriority::Normal => Earcon::Pop,
+        TaskPriority::High => Earcon::Chime,
+        TaskPriority::Critical => Earcon::Alert,
+    };
+    Some(Sound::Bundled(earcon))
+}
+
+impl EarconSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.volume) {
+            return Err("Cue volume must be between 0 and 1".to_string());
+        }
+        for rule in &self.rules {
+            if let Some(Sound::File(path)) = &rule.sound {
+                if !path.is_file() {
+                    return Err(format!("Sound file not found: {}", path.display()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The most specific rule wins: app and priority, then app, then priority
+    pub fn sound_for(&self, app_name: &str, priority: TaskPriority) -> Option<Sound> {
+        if !self.enabled {
+            return None;
+        }
+        self.rules
+            .iter()
+            .filter_map(|rule| Some((rule.specificity(app_name, priority)?, rule)))
+            .max_by_key(|(specificity, _)| *specificity)
+            .map(|(_, rule)| rule.sound.clone())
+            .unwrap_or_else(|| default_sound(priority))
+    }
+
+    pub fn cue_only(&self, priority: TaskPriority) -> bool {
+        self.enabled && self.cue_only_up_to.is_some_and(|limit| priority <= limit)
+    }
+}
+
+/// Linear-interpolation resampling, good enough for short cues
+pub fn resample(audio: &PcmAudio, sample_rate: u32) -> PcmAudio {
+    if audio.sample_rate == sample_rate || audio.samples.is_empty() {
+        return PcmAudio {
+            sample_rate,
+            samples: audio.samples.clone(),
+        };
+    }
+    let ratio = audio.sample_rate as f64 / sample_rate as f64;
+    let length = (audio.samples.len() as f64 / ratio) as usize;
+    let last = audio.samples.len() - 1;
+    let samples = (0..length)
+        .map(|i| {
+            let position = i as f64 * ratio;
+            let index = (position as usize).min(last);
+            let next = (index + 1).min(last);
+            let fraction = position - index as f64;
+            (audio.samples[index] as f64 * (1.0 - fraction) + audio.samples[next] as f64 * fraction)
+                as i16
+        })
+        .collect();
+    PcmAudio {
+        sample_rate,
+        samples,
+    }
+}
+
+fn decode_ogg(path: &Path) -> Result<PcmAudio, TtsError> {
+    let rate = SAMPLE_RATE.to_string();
+    let mut ffmpeg = Command::new("ffmpeg");
+    ffmpeg
+        .args(["-v", "error", "-i"])
+        .arg(path)
+        .args(["-f", "s16le", "-ac", "1", "-ar", &rate, "-"]);
+    let raw = match run_program("ffmpeg", &mut ffmpeg, None) {
+        Err(TtsError::NotInstalled(_)) => {
+            let mut sox = Command::new("sox");
+            sox.arg(path).args([
+                "-t", "raw", "-e", "signed", "-b", "16", "-c", "1", "-r", &rate, "-",
+            ]);
+            run_program("sox", &mut sox, None)
+                .map_err(|_| TtsError::NotInstalled("ffmpeg or sox"))?
+        }
+        other => other?,
+    };
+    Ok(PcmAudio {
+        sample_rate: SAMPLE_RATE,
+        samples: raw
+            .chunks_exact(2)
+            .map(|s| i16::from_le_bytes([s[0], s[1]]))
+            .collect(),
+    })
+}
+
+fn load_file(path: &Path) -> Result<PcmAudio, TtsError> {
+    let bytes =
+        fs::read(path).map_err(|e| TtsError::Failed(format!("{}: {}", path.display(), e)))?;
+    if stt::is_wav(&bytes) {
+        return stt::parse_wav(&bytes).map_err(TtsError::Failed);
+    }
+    if bytes.starts_with(b"OggS") {
+        return decode_ogg(path);
+    }
+    Err(TtsError::Failed(format!(
+        "{} is not a WAV or OGG file",
+        path.display()
+    )))
+}
+
+/// Cue settings plus decoded sound files, held by the speech queue
+pub struct Earcons {
+    path: PathBuf,
+    settings: Mutex<EarconSettings>,
+    files: Mutex<HashMap<PathBuf, PcmAudio>>,
+}
+
+impl Earcons {
+    pub fn new(config_dir: &Path) -> Self {
+        let path = config_dir.join(SETTINGS_FILE);
+        Self {
+            settings: Mutex::new(settings::load(&path)),
+            path,
+            files: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn settings(&self) -> EarconSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn set_settings(&self, updated: EarconSettings) -> Result<(), String> {
+        updated.validate()?;
+        settings::save(&self.path, &updated)?;
+        *self.settings.lock().unwrap() = updated;
+        // Files may have been replaced on disk
+        self.files.lock().unwrap().clear();
+        Ok(())
+    }
+
+    /// Render a sound at the configured volume
+    pub fn render(&self, sound: &Sound) -> Result<PcmAudio, TtsError> {
+        let mut audio = match sound {
+            Sound::Bundled(earcon) => earcon.render(SAMPLE_RATE),
+            Sound::File(path) => {
+                let mut files = self.files.lock().unwrap();
+                match files.get(path) {
+                    Some(audio) => audio.clone(),
+                    None => {
+                        let audio = load_file(path)?;
+                        files.insert(path.clone(), audio.clone());
+                        audio
+                    }
+                }
+            }
+        };
+        let volume = self.settings.lock().unwrap().volume;
+        for sample in &mut audio.samples {
+            *sample = (*sample as f32 * volume) as i16;
+        }
+        Ok(audio)
+    }
+
+    /// The cue for a message, if any
+    pub fn cue(
+        &self,
+        app_name: &str,
+        priority: TaskPriority,
+    ) -> Result<Option<PcmAudio>, TtsError> {
+        let sound = self.settings.lock().unwrap().sound_for(app_name, priority);
+        sound.map(|sound| self.render(&sound)).transpose()
+    }
+
+    pub fn cue_only(&self, priority: TaskPriority) -> bool {
+        self.settings.lock().unwrap().cue_only(priority)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(app_name: Option<&str>, priority: Option<TaskPriority>, earcon: Earcon) -> CueRule {
+        CueRule {
+            app_name: app_name.map(str::to_string),
+            priority,
+            sound: Some(Sound::Bundled(earcon)),
+        }
+    }
+
+    #[test]
+    fn test_most_specific_rule_wins() {
+        let settings = EarconSettings {
+            rules: vec![
+                rule(None, Some(TaskPriority::High), Earcon::Bell),
+                rule(Some("Microsoft Teams"), None, Earcon::Knock),
+                rule(
+                    Some("microsoft teams"),
+                    Some(TaskPriority::Critical),
+                    Earcon::Alert,
+                ),
+            ],
+            ..Default::default()
+        };
+        let sound = |app, priority| settings.sound_for(app, priority);
+
+        assert_eq!(
+            sound("Microsoft Teams", TaskPriority::Normal),
+            Some(Sound::Bundled(Earcon::Knock))
+        );
+        assert_eq!(
+            sound("Microsoft Teams", TaskPriority::Critical),
+            Some(Sound::Bundled(Earcon::Alert))
+        );
+        assert_eq!(
+            sound("Slack", TaskPriority::High),
+            Some(Sound::Bundled(Earcon::Bell))
+        );
+        // No rule: the priority's default
+        assert_eq!(
+            sound("Slack", TaskPriority::Low),
+            Some(Sound::Bundled(Earcon::Tick))
+        );
+        assert_eq!(sound("Slack", TaskPriority::Disabled), None);
+    }
+
+    #[test]
+    fn test_cue_only_for_low_priorities() {
+        let settings = EarconSettings {
+            cue_only_up_to: Some(TaskPriority::Low),
+            ..Default::default()
+        };
+        assert!(settings.cue_only(TaskPriority::Low));
+        assert!(!settings.cue_only(TaskPriority::Normal));
+
+        let disabled = EarconSettings {
+            enabled: false,
+            ..settings
+        };
+        assert!(!disabled.cue_only(TaskPriority::Low));
+        assert_eq!(disabled.sound_for("Slack", TaskPriority::High), None);
+    }
+
+    #[test]
+    fn test_bundled_earcons_are_short_and_distinct() {
+        let rendered: Vec<PcmAudio> = Earcon::ALL.iter().map(|e| e.render(SAMPLE_RATE)).collect();
+        for audio in &rendered {
+            assert!(audio.duration_secs() > 0.0 && audio.duration_secs() < 1.0);
+            assert!(audio.samples.iter().any(|s| s.unsigned_abs() > 10_000));
+        }
+        for (i, a) in rendered.iter().enumerate() {
+            assert!(rendered[i + 1..].iter().all(|b| a != b));
+        }
+    }
+
+    #[test]
+    fn test_resample() {
+        let audio = PcmAudio {
+            sample_rate: 8_000,
+            samples: vec![0, 100, 200, 300],
+        };
+        let up = resample(&audio, 16_000);
+        assert_eq!(up.samples.len(), 8);
+        assert_eq!(up.samples[1], 50);
+        assert_eq!(resample(&up, 8_000).samples, audio.samples);
+    }
+
+    #[test]
+    fn test_user_wav_files_are_loaded_and_scaled() {
+        let dir = std::env::temp_dir().join(format!("birdie-earcons-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("teams.wav");
+        fs::write(
+            &file,
+            stt::encode_wav(&PcmAudio {
+                sample_rate: 16_000,
+                samples: vec![1000; 160],
+            }),
+        )
+        .unwrap();
+
+        let earcons = Earcons::new(&dir);
+        let settings = EarconSettings {
+            volume: 0.5,
+            rules: vec![CueRule {
+                app_name: Some("Microsoft Teams".to_string()),
+                priority: None,
+                sound: Some(Sound::File(file)),
+            }],
+            ..Default::default()
+        };
+        earcons.set_settings(settings.clone()).unwrap();
+        let cue = earcons
+            .cue("Microsoft Teams", TaskPriority::Normal)
+            .unwrap()
+            .unwrap();
+        assert_eq!(cue.sample_rate, 16_000);
+        assert_eq!(cue.samples[0], 500);
+        assert_eq!(Earcons::new(&dir).settings(), settings);
+
+        let missing = EarconSettings {
+            rules: vec![CueRule {
+                app_name: None,
+                priority: None,
+                sound: Some(Sound::File(dir.join("missing.ogg"))),
+            }],
+            ..Default::default()
+        };
+        assert!(earcons.set_settings(missing).is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
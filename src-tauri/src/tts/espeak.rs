@@ -0,0 +1,146 @@
+//! espeak-ng backend
+//!
+//! Fast formant synthesis available on practically every distribution.
+//! `--stdout` writes a WAV stream that is parsed into PCM.
+
+use super::{
+    program_exists, run_program, Capabilities, EngineId, SpeechRequest, TtsEngine, TtsError, Voice,
+};
+use crate::stt::{self, PcmAudio};
+use std::process::Command;
+
+const PROGRAM: &str = "espeak-ng";
+
+/// espeak-ng's default speed in words per minute
+const DEFAULT_WPM: f32 = 175.0;
+
+pub struct EspeakNg;
+
+/// Parse the table printed by `espeak-ng --voices`
+///
+/// ```text
+/// Pty Language       Age/Gender VoiceName          File                 Other Languages
+///  5  es              --/M      Spanish_(Spain)    roa/es
+/// ```
+fn parse_voices(output: &str) -> Vec<Voice> {
+    output
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            if columns.len() < 4 {
+                return None;
+            }
+            let gender = match columns[2].rsplit('/').next() {
+                Some("M") => Some("male".to_string()),
+                Some("F") => Some("female".to_string()),
+                _ => None,
+            };
+            Some(Voice {
+                engine: EngineId::EspeakNg,
+                id: columns[1].to_string(),
+                name: columns[3].replace('_', " "),
+                language: columns[1].to_string(),
+                gender,
+            })
+        })
+        .collect()
+}
+
+fn arguments(request: &SpeechRequest) -> Vec<String> {
+    let prosody = request.prosody;
+    let mut args = vec![
+        "--stdout".to_string(),
+        "-v".to_string(),
+        request
+            .voice
+            .clone()
+            .unwrap_or_else(|| super::primary_language(&request.language)),
+        "-s".to_string(),
+        ((DEFAULT_WPM * prosody.rate).clamp(80.0, 450.0) as u32).to_string(),
+        "-p".to_string(),
+        ((50.0 * prosody.pitch).clamp(0.0, 99.0) as u32).to_string(),
+        "-a".to_string(),
+        ((100.0 * prosody.volume).clamp(0.0, 200.0) as u32).to_string(),
+    ];
+    if request.ssml {
+        args.push("-m".to_string());
+    }
+    args
+}
+
+impl TtsEngine for EspeakNg {
+    fn id(&self) -> EngineId {
+        EngineId::EspeakNg
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            pcm: true,
+            ssml: true,
+            pitch: true,
+        }
+    }
+
+    fn is_available(&self) -> bool {
+        program_exists(PROGRAM)
+    }
+
+    fn voices(&self) -> Result<Vec<Voice>, TtsError> {
+        let output = run_program(PROGRAM, Command::new(PROGRAM).arg("--voices"), None)?;
+        Ok(parse_voices(&String::from_utf8_lossy(&output)))
+    }
+
+    fn synthesize(&self, request: &SpeechRequest) -> Result<PcmAudio, TtsError> {
+        let mut command = Command::new(PROGRAM);
+        command.args(arguments(request));
+        // Text on stdin avoids it being read as options
+        let wav = run_program(PROGRAM, &mut command, Some(request.text.as_bytes()))?;
+        stt::parse_wav(&wav).map_err(TtsError::Failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tts::Prosody;
+
+    #[test]
+    fn test_parse_voices() {
+        let output = "Pty Language       Age/Gender VoiceName          File                 Other Languages\n \
+                      5  es              --/M      Spanish_(Spain)    roa/es\n \
+                      5  es-419          --/F      Spanish_(Latin_America) roa/es-419\n \
+                      2  en-us           --/M      English_(America)  gmw/en-US            (en 10)\n";
+        let voices = parse_voices(output);
+        assert_eq!(voices.len(), 3);
+        assert_eq!(voices[0].id, "es");
+        assert_eq!(voices[0].name, "Spanish (Spain)");
+        assert_eq!(voices[1].gender.as_deref(), Some("female"));
+        assert_eq!(voices[2].language, "en-us");
+    }
+
+    #[test]
+    fn test_prosody_arguments() {
+        let request = SpeechRequest {
+            text: "hola".to_string(),
+            language: "es-ES".to_string(),
+            voice: None,
+            prosody: Prosody {
+                rate: 1.2,
+                pitch: 0.8,
+                volume: 1.5,
+            },
+            ssml: false,
+        };
+        assert_eq!(
+            arguments(&request),
+            vec!["--stdout", "-v", "es", "-s", "210", "-p", "40", "-a", "150"]
+        );
+
+        let ssml = SpeechRequest {
+            ssml: true,
+            ..request
+        };
+        assert_eq!(arguments(&ssml).last().map(String::as_str), Some("-m"));
+    }
+}
@@ -0,0 +1,646 @@
+//! Render notifications, threads and digests to audio files
+//!
+//! The text is built from the same kind of templates the announcements use,
+//! each part spoken with its app's voice profile and optionally preceded by
+//! the app's cue. WAV files carry a LIST/INFO chunk with the tags; OGG files
+//! are encoded with `ffmpeg` or `oggenc`, which write Vorbis comments.
+
+use super::earcons::{self, Earcons};
+use super::{primary_language, run_program, Tts, TtsError, VoiceProfile};
+use crate::context_mapper::{ContextMapper, TaskPriority};
+use crate::notifications::NotificationEvent;
+use crate::stt::{self, PcmAudio};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Silence between the parts of an export
+const PART_GAP_MS: u32 = 400;
+
+const ARTIST: &str = "Birdie";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExportContent {
+    Notification {
+        notification: NotificationEvent,
+    },
+    /// Messages of one conversation, oldest first
+    Thread {
+        #[serde(default)]
+        title: Option<String>,
+        messages: Vec<NotificationEvent>,
+    },
+    /// Notifications from any app, e.g. everything received overnight
+    Digest {
+        title: String,
+        notifications: Vec<NotificationEvent>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExportFormat {
+    Wav,
+    Ogg,
+}
+
+impl ExportFormat {
+    fn from_path(path: &Path) -> Result<Self, String> {
+        let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("wav") => Ok(ExportFormat::Wav),
+            Some("ogg") | Some("oga") => Ok(ExportFormat::Ogg),
+            _ => Err("Audio can be exported as .wav or .ogg".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioTags {
+    pub title: String,
+    pub artist: String,
+    pub date: String,
+    pub comment: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedAudio {
+    pub path: PathBuf,
+    pub duration_secs: f32,
+}
+
+/// One spoken part; parts without an app use the default voice
+#[derive(Debug, Clone, PartialEq)]
+struct Part {
+    app_name: Option<String>,
+    text: String,
+    /// Play the app's cue before this part
+    cue: bool,
+}
+
+impl Part {
+    fn narration(text: String) -> Self {
+        Self {
+            app_name: None,
+            text,
+            cue: false,
+        }
+    }
+
+    fn from_app(app_name: &str, text: String, cue: bool) -> Self {
+        Self {
+            app_name: Some(app_name.to_string()),
+            text,
+            cue,
+        }
+    }
+}
+
+/// Template strings for one language
+struct Templates {
+    notification: &'static str,
+    thread: &'static str,
+    message: &'static str,
+    notifications: (&'static str, &'static str),
+    messages: (&'static str, &'static str),
+    digest_intro: &'static str,
+    digest_empty: &'static str,
+    digest_end: &'static str,
+}
+
+const SPANISH: Templates = Templates {
+    notification: "Nueva notificación de {app}, de {sender}: {message}",
+    thread: "Conversación en {app}",
+    message: "{sender}: {message}",
+    notifications: ("notificación", "notificaciones"),
+    messages: ("mensaje", "mensajes"),
+    digest_intro: "{title}. {count}.",
+    digest_empty: "{title}. No hay notificaciones.",
+    digest_end: "Fin del resumen.",
+};
+
+const ENGLISH: Templates = Templates {
+    notification: "New notification from {app}, from {sender}: {message}",
+    thread: "Conversation in {app}",
+    message: "{sender}: {message}",
+    notifications: ("notification", "notifications"),
+    messages: ("message", "messages"),
+    digest_intro: "{title}. {count}.",
+    digest_empty: "{title}. No notifications.",
+    digest_end: "End of digest.",
+};
+
+fn templates(language: &str) -> &'static Templates {
+    match primary_language(language).as_str() {
+        "es" => &SPANISH,
+        _ => &ENGLISH,
+    }
+}
+
+fn fill(template: &str, values: &[(&str, &str)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (key, value)| {
+            text.replace(&format!("{{{}}}", key), value)
+        })
+}
+
+fn count(n: usize, (singular, plural): (&str, &str)) -> String {
+    format!("{} {}", n, if n == 1 { singular } else { plural })
+}
+
+fn message(templates: &Templates, event: &NotificationEvent) -> String {
+    fill(
+        templates.message,
+        &[("sender", &event.sender), ("message", &event.message)],
+    )
+}
+
+impl ExportContent {
+    fn parts(&self, language: &str) -> Result<Vec<Part>, String> {
+        let templates = templates(language);
+        match self {
+            ExportContent::Notification { notification: n } => Ok(vec![Part::from_app(
+                &n.app_name,
+                fill(
+                    templates.notification,
+                    &[
+                        ("app", &n.app_name),
+                        ("sender", &n.sender),
+                        ("message", &n.message),
+                    ],
+                ),
+                true,
+            )]),
+            ExportContent::Thread { title, messages } => {
+                let first = messages.first().ok_or("The thread has no messages")?;
+                let mut intro = fill(templates.thread, &[("app", &first.app_name)]);
+                if let Some(title) = title.as_deref().filter(|t| !t.trim().is_empty()) {
+                    intro = format!("{}: {}", intro, title.trim());
+                }
+                let mut parts = vec![Part::from_app(&first.app_name, intro, true)];
+                parts.extend(
+                    messages
+                        .iter()
+                        .map(|m| Part::from_app(&m.app_name, message(templates, m), false)),
+                );
+                Ok(parts)
+            }
+            ExportContent::Digest {
+                title,
+                notifications,
+            } => {
+                if notifications.is_empty() {
+                    return Ok(vec![Part::narration(fill(
+                        templates.digest_empty,
+                        &[("title", title)],
+                    ))]);
+                }
+
+                // Grouped by app, in order of first appearance
+                let mut groups: Vec<(&str, Vec<&NotificationEvent>)> = Vec::new();
+                for n in notifications {
+                    match groups.iter_mut().find(|(app, _)| *app == n.app_name) {
+                        Some((_, group)) => group.push(n),
+                        None => groups.push((&n.app_name, vec![n])),
+                    }
+                }
+
+                let total = count(notifications.len(), templates.notifications);
+                let mut parts = vec![Part::narration(fill(
+                    templates.digest_intro,
+                    &[("title", title), ("count", &total)],
+                ))];
+                for (app, group) in groups {
+                    let heading = format!("{}, {}.", app, count(group.len(), templates.messages));
+                    parts.push(Part::from_app(app, heading, true));
+                    parts.extend(
+                        group
+                            .iter()
+                            .map(|n| Part::from_app(app, message(templates, n), false)),
+                    );
+                }
+                parts.push(Part::narration(templates.digest_end.to_string()));
+                Ok(parts)
+            }
+        }
+    }
+
+    fn tags(&self, language: &str, date: String) -> AudioTags {
+        let templates = templates(language);
+        let (title, apps): (String, Vec<&str>) = match self {
+            ExportContent::Notification { notification: n } => (
+                format!("{}: {}", n.app_name, n.sender),
+                vec![n.app_name.as_str()],
+            ),
+            ExportContent::Thread { title, messages } => {
+                let app = messages.first().map(|m| m.app_name.as_str()).unwrap_or("");
+                let title = title
+                    .clone()
+                    .unwrap_or_else(|| fill(templates.thread, &[("app", app)]));
+                (title, vec![app])
+            }
+            ExportContent::Digest {
+                title,
+                notifications,
+            } => {
+                let mut apps: Vec<&str> = Vec::new();
+                for n in notifications {
+                    if !apps.contains(&n.app_name.as_str()) {
+                        apps.push(&n.app_name);
+                    }
+                }
+                (title.clone(), apps)
+            }
+        };
+        AudioTags {
+            title,
+            artist: ARTIST.to_string(),
+            date,
+            comment: apps.join(", "),
+        }
+    }
+}
+
+/// Speak every part with its app's profile and join them into one clip
+///
+/// Only engines that render PCM can be used. The clip has the sample rate of
+/// the first part; cues are taken from `earcons` when given.
+pub fn render(
+    tts: &Tts,
+    earcons: Option<&Earcons>,
+    content: &ExportContent,
+    language: &str,
+    mapper: &ContextMapper,
+) -> Result<PcmAudio, TtsError> {
+    let parts = content.parts(language).map_err(TtsError::Failed)?;
+    let mut output: Option<PcmAudio> = None;
+
+    for part in parts {
+        let profile = match &part.app_name {
+            Some(app) => tts.profile(app, None, mapper),
+            None => VoiceProfile::default(),
+        };
+        let (engine, voice) = tts.resolve(language, profile.voice.as_ref(), true)?;
+        let speech = engine.synthesize(&profile.request(
+            &part.text,
+            language,
+            voice,
+            engine.capabilities(),
+        ))?;
+
+        let cue = match (earcons, &part.app_name) {
+            (Some(earcons), Some(app)) if part.cue => {
+                let priority = mapper
+                    .find_context(app)
+                    .map(|c| c.priority)
+                    .unwrap_or(TaskPriority::Normal);
+                // A missing cue should not cost the whole export
+                earcons.cue(app, priority).unwrap_or_else(|e| {
+                    eprintln!("⚠ Failed to render the {} cue, skipping it: {}", app, e);
+                    None
+                })
+            }
+            _ => None,
+        };
+
+        let output = output.get_or_insert_with(|| PcmAudio {
+            sample_rate: speech.sample_rate,
+            samples: Vec::new(),
+        });
+        let rate = output.sample_rate;
+        if !output.samples.is_empty() {
+            let gap = (rate * PART_GAP_MS / 1000) as usize;
+            output.samples.resize(output.samples.len() + gap, 0);
+        }
+        for audio in cue.into_iter().chain([speech]) {
+            output
+                .samples
+                .extend(earcons::resample(&audio, rate).samples);
+        }
+    }
+
+    output.ok_or_else(|| TtsError::Failed("Nothing to export".to_string()))
+}
+
+/// A WAV file with a LIST/INFO chunk before the audio data
+pub fn encode_tagged_wav(audio: &PcmAudio, tags: &AudioTags) -> Vec<u8> {
+    let mut info = b"INFO".to_vec();
+    for (id, value) in [
+        (b"INAM", &tags.title),
+        (b"IART", &tags.artist),
+        (b"ICRD", &tags.date),
+        (b"ICMT", &tags.comment),
+        (b"ISFT", &ARTIST.to_string()),
+    ] {
+        if value.is_empty() {
+            continue;
+        }
+        // Zero-terminated and padded to an even size
+        let mut text = value.as_bytes().to_vec();
+        text.push(0);
+        info.extend_from_slice(id);
+        info.extend_from_slice(&(text.len() as u32).to_le_bytes());
+        info.extend_from_slice(&text);
+        if text.len() % 2 == 1 {
+            info.push(0);
+        }
+    }
+
+    let wav = stt::encode_wav(audio);
+    // The header and fmt chunk take the first 36 bytes
+    let mut out = Vec::with_capacity(wav.len() + info.len() + 8);
+    out.extend_from_slice(&wav[..36]);
+    out.extend_from_slice(b"LIST");
+    out.extend_from_slice(&(info.len() as u32).to_le_bytes());
+    out.extend_from_slice(&info);
+    out.extend_from_slice(&wav[36..]);
+    let riff_size = (out.len() - 8) as u32;
+    out[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    out
+}
+
+fn encode_ogg(audio: &PcmAudio, tags: &AudioTags, path: &Path) -> Result<(), TtsError> {
+    let raw: Vec<u8> = audio.samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+    let rate = audio.sample_rate.to_string();
+
+    let mut ffmpeg = Command::new("ffmpeg");
+    ffmpeg.args([
+        "-v", "error", "-f", "s16le", "-ar", &rate, "-ac", "1", "-i", "-",
+    ]);
+    ffmpeg.args(["-c:a", "libvorbis", "-q:a", "4"]);
+    for (key, value) in [
+        ("title", &tags.title),
+        ("artist", &tags.artist),
+        ("date", &tags.date),
+        ("comment", &tags.comment),
+    ] {
+        ffmpeg.arg("-metadata").arg(format!("{}={}", key, value));
+    }
+    ffmpeg.arg("-y").arg(path);
+
+    match run_program("ffmpeg", &mut ffmpeg, Some(&raw)) {
+        Err(TtsError::NotInstalled(_)) => {
+            let mut oggenc = Command::new("oggenc");
+            oggenc
+                .args(["-Q", "--raw", "--raw-bits=16", "--raw-chan=1"])
+                .arg(format!("--raw-rate={}", rate))
+                .args(["-t", &tags.title, "-a", &tags.artist, "-d", &tags.date])
+                .arg("-c")
+                .arg(format!("comment={}", tags.comment))
+                .arg("-o")
+                .arg(path)
+                .arg("-");
+            run_program("oggenc", &mut oggenc, Some(&raw))
+                .map_err(|_| TtsError::NotInstalled("ffmpeg or oggenc"))?;
+            Ok(())
+        }
+        other => other.map(|_| ()),
+    }
+}
+
+/// Render `content` and write it to `path`, as WAV or OGG by extension
+pub fn export(
+    tts: &Tts,
+    earcons: Option<&Earcons>,
+    content: &ExportContent,
+    language: &str,
+    path: &Path,
+) -> Result<ExportedAudio, String> {
+    let format = ExportFormat::from_path(path)?;
+    let audio = render(tts, earcons, content, language, &ContextMapper::new())?;
+    let tags = content.tags(
+        language,
+        chrono::Local::now().format("%Y-%m-%d").to_string(),
+    );
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    match format {
+        ExportFormat::Wav => fs::write(path, encode_tagged_wav(&audio, &tags))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?,
+        ExportFormat::Ogg => encode_ogg(&audio, &tags, path)?,
+    }
+
+    Ok(ExportedAudio {
+        path: path.to_path_buf(),
+        duration_secs: audio.duration_secs(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tts::{Capabilities, EngineId, SpeechRequest, TtsEngine, Voice};
+
+    /// Turns every character into a short, distinct run of samples so the
+    /// rendered audio only depends on the text and the prosody
+    struct ToneEngine;
+
+    impl TtsEngine for ToneEngine {
+        fn id(&self) -> EngineId {
+            EngineId::EspeakNg
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities {
+                pcm: true,
+                ssml: false,
+                pitch: true,
+            }
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+
+        fn voices(&self) -> Result<Vec<Voice>, TtsError> {
+            Ok(["es", "en"]
+                .iter()
+                .map(|language| Voice {
+                    engine: EngineId::EspeakNg,
+                    id: language.to_string(),
+                    name: language.to_string(),
+                    language: language.to_string(),
+                    gender: None,
+                })
+                .collect())
+        }
+
+        fn synthesize(&self, request: &SpeechRequest) -> Result<PcmAudio, TtsError> {
+            let length = (4.0 / request.prosody.rate).round() as usize;
+            let samples = request
+                .text
+                .chars()
+                .flat_map(|c| {
+                    let level = ((c as u32 % 64) as f32 * 256.0 * request.prosody.volume) as i16;
+                    std::iter::repeat_n(level, length)
+                })
+                .collect();
+            Ok(PcmAudio {
+                sample_rate: 1_000,
+                samples,
+            })
+        }
+    }
+
+    fn tone_tts(name: &str) -> Tts {
+        let dir =
+            std::env::temp_dir().join(format!("birdie-export-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Tts::with_engines(&dir, vec![Box::new(ToneEngine)])
+    }
+
+    fn notification(app_name: &str, sender: &str, message: &str) -> NotificationEvent {
+        NotificationEvent {
+            app_name: app_name.to_string(),
+            sender: sender.to_string(),
+            message: message.to_string(),
+            timestamp: "2026-01-15T07:30:00Z".to_string(),
+            app_icon: None,
+            inline_reply: None,
+        }
+    }
+
+    fn overnight_digest() -> ExportContent {
+        ExportContent::Digest {
+            title: "Resumen de la noche".to_string(),
+            notifications: vec![
+                notification("Slack", "Ana", "El despliegue terminó bien"),
+                notification("Telegram", "Luis", "¿Comemos mañana?"),
+                notification("Slack", "Marta", "Reunión movida a las diez"),
+            ],
+        }
+    }
+
+    /// Compare with a file in `golden/`; set BIRDIE_UPDATE_GOLDEN=1 to rewrite it
+    fn assert_golden(name: &str, expected: &[u8], actual: &[u8]) {
+        if std::env::var_os("BIRDIE_UPDATE_GOLDEN").is_some() {
+            let path = Path::new(file!()).with_file_name("golden").join(name);
+            fs::write(&path, actual).unwrap();
+            return;
+        }
+        assert!(
+            expected == actual,
+            "{} differs from the golden file; rerun with BIRDIE_UPDATE_GOLDEN=1 if the change is intended",
+            name
+        );
+    }
+
+    #[test]
+    fn test_digest_parts_are_grouped_by_app() {
+        let parts = overnight_digest().parts("es-ES").unwrap();
+        let texts: Vec<&str> = parts.iter().map(|p| p.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "Resumen de la noche. 3 notificaciones.",
+                "Slack, 2 mensajes.",
+                "Ana: El despliegue terminó bien",
+                "Marta: Reunión movida a las diez",
+                "Telegram, 1 mensaje.",
+                "Luis: ¿Comemos mañana?",
+                "Fin del resumen.",
+            ]
+        );
+        assert!(parts[1].cue && !parts[2].cue);
+        assert_eq!(parts[0].app_name, None);
+    }
+
+    #[test]
+    fn test_thread_and_notification_templates() {
+        let thread = ExportContent::Thread {
+            title: Some("despliegue".to_string()),
+            messages: vec![
+                notification("Slack", "Ana", "¿Listos?"),
+                notification("Slack", "Luis", "Sí"),
+            ],
+        };
+        let texts: Vec<String> = thread
+            .parts("en-US")
+            .unwrap()
+            .into_iter()
+            .map(|p| p.text)
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                "Conversation in Slack: despliegue",
+                "Ana: ¿Listos?",
+                "Luis: Sí"
+            ]
+        );
+
+        let single = ExportContent::Notification {
+            notification: notification("WhatsApp", "Eva", "Hola"),
+        };
+        assert_eq!(
+            single.parts("es").unwrap()[0].text,
+            "Nueva notificación de WhatsApp, de Eva: Hola"
+        );
+
+        let empty = ExportContent::Thread {
+            title: None,
+            messages: Vec::new(),
+        };
+        assert!(empty.parts("es").is_err());
+    }
+
+    #[test]
+    fn test_wav_tags_are_readable() {
+        let audio = PcmAudio {
+            sample_rate: 8_000,
+            samples: vec![1, -1, 2],
+        };
+        let tags = AudioTags {
+            title: "Resumen".to_string(),
+            artist: ARTIST.to_string(),
+            date: "2026-01-15".to_string(),
+            comment: "Slack".to_string(),
+        };
+        let wav = encode_tagged_wav(&audio, &tags);
+        assert_eq!(&wav[36..40], b"LIST");
+        assert_eq!(
+            u32::from_le_bytes(wav[4..8].try_into().unwrap()) as usize,
+            wav.len() - 8
+        );
+        assert!(wav.windows(8).any(|w| w == b"Resumen\0"));
+        assert_eq!(stt::parse_wav(&wav).unwrap(), audio);
+    }
+
+    #[test]
+    fn test_digest_matches_golden_audio() {
+        let tts = tone_tts("golden");
+        let content = overnight_digest();
+        let audio = render(&tts, None, &content, "es-ES", &ContextMapper::new()).unwrap();
+        let wav = encode_tagged_wav(&audio, &content.tags("es-ES", "2026-01-15".to_string()));
+        assert_golden(
+            "overnight_digest.wav",
+            include_bytes!("golden/overnight_digest.wav"),
+            &wav,
+        );
+    }
+
+    #[test]
+    fn test_export_writes_tagged_wav() {
+        let tts = tone_tts("file");
+        let dir = std::env::temp_dir().join(format!("birdie-export-out-{}", std::process::id()));
+        let path = dir.join("digest.wav");
+        let exported = export(&tts, None, &overnight_digest(), "es-ES", &path).unwrap();
+        assert!(exported.duration_secs > 0.0);
+        let wav = fs::read(&path).unwrap();
+        assert!(wav.windows(5).any(|w| w == b"Slack"));
+        assert!(export(
+            &tts,
+            None,
+            &overnight_digest(),
+            "es",
+            &dir.join("digest.mp3")
+        )
+        .is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
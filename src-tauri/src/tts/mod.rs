@@ -0,0 +1,849 @@
+//! Text-to-speech engines controlled from the backend
+//!
+//! Every engine implements [`TtsEngine`]: it lists its voices and renders
+//! text to PCM, which birdie then plays itself so it controls timing and can
+//! put earcons between announcements. Offline Linux engines are supported:
+//! Piper neural voices, espeak-ng and speech-dispatcher. speech-dispatcher
+//! plays audio on its own, so it can only be used for direct playback.
+
+mod earcons;
+mod espeak;
+mod export;
+mod piper;
+mod playback;
+mod profile;
+mod queue;
+mod speech_dispatcher;
+
+use crate::context_mapper::{ContextMapper, TaskPriority};
+use crate::settings;
+use crate::stt::PcmAudio;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{mpsc, Mutex};
+use tauri::{AppHandle, Manager};
+
+pub use earcons::{Earcon, EarconSettings, Sound};
+pub use export::{ExportContent, ExportedAudio};
+pub use playback::Player;
+pub use profile::VoiceProfile;
+pub use queue::{Announcement, SpeechQueue};
+
+const SETTINGS_FILE: &str = "tts.json";
+
+/// Engines in order of preference when no voice has been chosen
+const ENGINE_PREFERENCE: [EngineId; 3] = [
+    EngineId::Piper,
+    EngineId::EspeakNg,
+    EngineId::SpeechDispatcher,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EngineId {
+    SpeechDispatcher,
+    EspeakNg,
+    Piper,
+}
+
+/// What an engine can do
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Capabilities {
+    /// Can render to PCM instead of playing directly
+    pub pcm: bool,
+    /// Accepts SSML markup
+    pub ssml: bool,
+    /// Honours the pitch setting
+    pub pitch: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Voice {
+    pub engine: EngineId,
+    pub id: String,
+    pub name: String,
+    /// BCP 47 tag such as "es" or "es-419"
+    pub language: String,
+    pub gender: Option<String>,
+}
+
+/// A voice chosen by the user
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VoiceRef {
+    pub engine: EngineId,
+    pub voice: String,
+}
+
+/// Speaking style relative to the engine's defaults
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Prosody {
+    /// 1.0 is normal speed, 2.0 twice as fast
+    pub rate: f32,
+    /// 1.0 is the voice's normal pitch
+    pub pitch: f32,
+    /// 1.0 is full volume
+    pub volume: f32,
+}
+
+impl Default for Prosody {
+    fn default() -> Self {
+        Self {
+            rate: 1.0,
+            pitch: 1.0,
+            volume: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpeechRequest {
+    pub text: String,
+    pub language: String,
+    /// Engine-specific voice id; the engine picks one for `language` if unset
+    pub voice: Option<String>,
+    pub prosody: Prosody,
+    /// `text` is SSML markup; only sent to engines that support it
+    pub ssml: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TtsError {
+    /// The engine's program is not installed
+    NotInstalled(&'static str),
+    /// The engine cannot do this
+    Unsupported(&'static str),
+    NoVoice(String),
+    Failed(String),
+    Stopped,
+}
+
+impl fmt::Display for TtsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TtsError::NotInstalled(program) => write!(f, "{} is not installed", program),
+            TtsError::Unsupported(what) => write!(f, "Not supported by this engine: {}", what),
+            TtsError::NoVoice(language) => write!(f, "No voice available for {}", language),
+            TtsError::Failed(message) => write!(f, "Speech synthesis failed: {}", message),
+            TtsError::Stopped => write!(f, "Speech was stopped"),
+        }
+    }
+}
+
+impl std::error::Error for TtsError {}
+
+impl From<TtsError> for String {
+    fn from(error: TtsError) -> Self {
+        error.to_string()
+    }
+}
+
+pub trait TtsEngine: Send + Sync {
+    fn id(&self) -> EngineId;
+
+    fn capabilities(&self) -> Capabilities;
+
+    /// Whether the engine's program is installed
+    fn is_available(&self) -> bool;
+
+    fn voices(&self) -> Result<Vec<Voice>, TtsError>;
+
+    /// Render speech to mono PCM
+    fn synthesize(&self, request: &SpeechRequest) -> Result<PcmAudio, TtsError>;
+
+    /// Speak through `player`, blocking until done or stopped
+    fn speak(&self, request: &SpeechRequest, player: &Player) -> Result<(), TtsError> {
+        let audio = self.synthesize(request)?;
+        player.play(&audio)
+    }
+}
+
+/// Run a program to completion, feeding `input` on stdin
+fn run_program(
+    program: &'static str,
+    command: &mut Command,
+    input: Option<&[u8]>,
+) -> Result<Vec<u8>, TtsError> {
+    let mut child = command
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => TtsError::NotInstalled(program),
+            _ => TtsError::Failed(format!("{}: {}", program, e)),
+        })?;
+
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin
+            .write_all(input)
+            .map_err(|e| TtsError::Failed(format!("{}: {}", program, e)))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| TtsError::Failed(format!("{}: {}", program, e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(TtsError::Failed(format!("{}: {}", program, stderr.trim())));
+    }
+    Ok(output.stdout)
+}
+
+/// Whether a program can be started at all
+fn program_exists(program: &str) -> bool {
+    Command::new(program)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+/// Primary language subtag: "es-ES" -> "es"
+fn primary_language(tag: &str) -> String {
+    tag.split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+/// How well a voice language fits a requested one: 2 exact, 1 same language
+fn language_match(voice: &str, requested: &str) -> u8 {
+    let normalize = |tag: &str| tag.replace('_', "-").to_lowercase();
+    if normalize(voice) == normalize(requested) {
+        2
+    } else if primary_language(voice) == primary_language(requested) {
+        1
+    } else {
+        0
+    }
+}
+
+/// Best voice of `voices` for `language`
+fn best_voice<'a>(voices: &'a [Voice], language: &str) -> Option<&'a Voice> {
+    voices
+        .iter()
+        .filter(|v| language_match(&v.language, language) > 0)
+        .min_by_key(|v| std::cmp::Reverse(language_match(&v.language, language)))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TtsSettings {
+    /// Voice to use for each primary language ("es", "en")
+    pub default_voices: HashMap<String, VoiceRef>,
+    /// Profiles chosen by the user, overriding the apps' defaults
+    pub app_profiles: HashMap<String, VoiceProfile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EngineInfo {
+    pub id: EngineId,
+    pub available: bool,
+    pub capabilities: Capabilities,
+}
+
+/// Engines, voice defaults and the player, held in Tauri managed state
+pub struct Tts {
+    engines: Vec<Box<dyn TtsEngine>>,
+    settings_path: PathBuf,
+    settings: Mutex<TtsSettings>,
+    voices: Mutex<Option<Vec<Voice>>>,
+    player: Player,
+}
+
+impl Tts {
+    /// `data_dir` is searched for Piper voices in addition to the user's
+    /// shared `piper-voices` directory
+    pub fn new(config_dir: &Path, data_dir: &Path) -> Self {
+        Self::with_engines(
+            config_dir,
+            vec![
+                Box::new(piper::Piper::new(data_dir)),
+                Box::new(espeak::EspeakNg),
+                Box::new(speech_dispatcher::SpeechDispatcher),
+            ],
+        )
+    }
+
+    fn with_engines(config_dir: &Path, engines: Vec<Box<dyn TtsEngine>>) -> Self {
+        let settings_path = config_dir.join(SETTINGS_FILE);
+        Self {
+            engines,
+            settings: Mutex::new(settings::load(&settings_path)),
+            settings_path,
+            voices: Mutex::new(None),
+            player: Player::new(),
+        }
+    }
+
+    pub fn engines(&self) -> Vec<EngineInfo> {
+        self.engines
+            .iter()
+            .map(|engine| EngineInfo {
+                id: engine.id(),
+                available: engine.is_available(),
+                capabilities: engine.capabilities(),
+            })
+            .collect()
+    }
+
+    fn engine(&self, id: EngineId) -> Option<&dyn TtsEngine> {
+        self.engines
+            .iter()
+            .find(|engine| engine.id() == id)
+            .map(|engine| engine.as_ref())
+    }
+
+    /// Voices of every installed engine, cached until `refresh`
+    pub fn voices(&self, refresh: bool) -> Vec<Voice> {
+        let mut cache = self.voices.lock().unwrap();
+        if refresh || cache.is_none() {
+            let mut voices = Vec::new();
+            for engine in self.engines.iter().filter(|e| e.is_available()) {
+                match engine.voices() {
+                    Ok(found) => voices.extend(found),
+                    Err(e) => eprintln!("⚠ Failed to list {:?} voices: {}", engine.id(), e),
+                }
+            }
+            *cache = Some(voices);
+        }
+        cache.clone().unwrap_or_default()
+    }
+
+    pub fn settings(&self) -> TtsSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn set_default_voice(&self, language: &str, voice: Option<VoiceRef>) -> Result<(), String> {
+        let mut settings = self.settings.lock().unwrap();
+        let mut updated = settings.clone();
+        match voice {
+            Some(voice) => updated
+                .default_voices
+                .insert(primary_language(language), voice),
+            None => updated.default_voices.remove(&primary_language(language)),
+        };
+        settings::save(&self.settings_path, &updated)?;
+        *settings = updated;
+        Ok(())
+    }
+
+    /// Pick the engine and voice for a request
+    ///
+    /// An explicit voice wins, then the user's default for the language, then
+    /// the first engine in [`ENGINE_PREFERENCE`] with a matching voice.
+    pub fn resolve(
+        &self,
+        language: &str,
+        voice: Option<&VoiceRef>,
+        require_pcm: bool,
+    ) -> Result<(&dyn TtsEngine, Option<String>), TtsError> {
+        let usable = |engine: &dyn TtsEngine| {
+            engine.is_available() && (!require_pcm || engine.capabilities().pcm)
+        };
+
+        let default = self
+            .settings
+            .lock()
+            .unwrap()
+            .default_voices
+            .get(&primary_language(language))
+            .cloned();
+        for chosen in voice.cloned().into_iter().chain(default) {
+            if let Some(engine) = self.engine(chosen.engine).filter(|e| usable(*e)) {
+                return Ok((engine, Some(chosen.voice)));
+            }
+        }
+
+        let voices = self.voices(false);
+        for id in ENGINE_PREFERENCE {
+            let Some(engine) = self.engine(id).filter(|e| usable(*e)) else {
+                continue;
+            };
+            let engine_voices: Vec<Voice> =
+                voices.iter().filter(|v| v.engine == id).cloned().collect();
+            if let Some(best) = best_voice(&engine_voices, language) {
+                return Ok((engine, Some(best.id.clone())));
+            }
+        }
+
+        Err(TtsError::NoVoice(language.to_string()))
+    }
+
+    /// Render text to PCM with the voice chosen for its language
+    pub fn synthesize(
+        &self,
+        text: &str,
+        language: &str,
+        voice: Option<&VoiceRef>,
+        prosody: Prosody,
+    ) -> Result<PcmAudio, TtsError> {
+        let (engine, voice) = self.resolve(language, voice, true)?;
+        engine.synthesize(&SpeechRequest {
+            text: text.to_string(),
+            language: language.to_string(),
+            voice,
+            prosody,
+            ssml: false,
+        })
+    }
+
+    /// Speak text, blocking until it has been played or stopped
+    pub fn speak(
+        &self,
+        text: &str,
+        language: &str,
+        voice: Option<&VoiceRef>,
+        prosody: Prosody,
+    ) -> Result<(), TtsError> {
+        let (engine, voice) = self.resolve(language, voice, false)?;
+        engine.speak(
+            &SpeechRequest {
+                text: text.to_string(),
+                language: language.to_string(),
+                voice,
+                prosody,
+                ssml: false,
+            },
+            &self.player,
+        )
+    }
+
+    /// The profile an app's messages are read with: the user's choice, the
+    /// app's default or plain settings, adjusted for `priority` (the app's
+    /// own priority if unset)
+    pub fn profile(
+        &self,
+        app_name: &str,
+        priority: Option<TaskPriority>,
+        mapper: &ContextMapper,
+    ) -> VoiceProfile {
+        let context = mapper.find_context(app_name);
+        let custom = self
+            .settings
+            .lock()
+            .unwrap()
+            .app_profiles
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(app_name))
+            .map(|(_, profile)| profile.clone());
+        let profile = custom
+            .or_else(|| context.as_ref().and_then(|c| c.voice_profile.clone()))
+            .unwrap_or_default();
+        match priority.or(context.map(|c| c.priority)) {
+            Some(priority) => profile.for_priority(priority),
+            None => profile,
+        }
+    }
+
+    pub fn set_profile(&self, app_name: &str, profile: Option<VoiceProfile>) -> Result<(), String> {
+        if let Some(profile) = &profile {
+            profile.validate()?;
+        }
+        let mut settings = self.settings.lock().unwrap();
+        let mut updated = settings.clone();
+        updated
+            .app_profiles
+            .retain(|name, _| !name.eq_ignore_ascii_case(app_name));
+        if let Some(profile) = profile {
+            updated.app_profiles.insert(app_name.to_string(), profile);
+        }
+        settings::save(&self.settings_path, &updated)?;
+        *settings = updated;
+        Ok(())
+    }
+
+    /// Speak text with a voice profile, as SSML if the engine reads it
+    pub fn speak_with_profile(
+        &self,
+        text: &str,
+        language: &str,
+        profile: &VoiceProfile,
+    ) -> Result<(), TtsError> {
+        let (engine, voice) = self.resolve(language, profile.voice.as_ref(), false)?;
+        let request = profile.request(text, language, voice, engine.capabilities());
+        engine.speak(&request, &self.player)
+    }
+
+    pub fn player(&self) -> &Player {
+        &self.player
+    }
+}
+
+#[tauri::command]
+pub fn tts_get_engines(tts: tauri::State<'_, Tts>) -> Vec<EngineInfo> {
+    tts.engines()
+}
+
+#[tauri::command]
+pub async fn tts_list_voices(app_handle: AppHandle, refresh: bool) -> Result<Vec<Voice>, String> {
+    tauri::async_runtime::spawn_blocking(move || app_handle.state::<Tts>().voices(refresh))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn tts_get_settings(tts: tauri::State<'_, Tts>) -> TtsSettings {
+    tts.settings()
+}
+
+#[tauri::command]
+pub fn tts_set_default_voice(
+    tts: tauri::State<'_, Tts>,
+    language: String,
+    voice: Option<VoiceRef>,
+) -> Result<(), String> {
+    tts.set_default_voice(&language, voice)
+}
+
+#[tauri::command]
+pub async fn tts_speak(
+    app_handle: AppHandle,
+    text: String,
+    language: String,
+    voice: Option<VoiceRef>,
+    prosody: Option<Prosody>,
+) -> Result<(), String> {
+    let announcement = Announcement {
+        app_name: String::new(),
+        text,
+        language,
+        priority: None,
+    };
+    // Asked for by the user, so read before waiting notifications
+    let result = app_handle.state::<SpeechQueue>().push_direct(
+        announcement,
+        TaskPriority::High,
+        move |tts, announcement| {
+            tts.speak(
+                &announcement.text,
+                &announcement.language,
+                voice.as_ref(),
+                prosody.unwrap_or_default(),
+            )
+        },
+    );
+    wait_for(result).await
+}
+
+/// Wait for queued direct speech; clearing the queue stops it
+async fn wait_for(result: mpsc::Receiver<Result<(), TtsError>>) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || result.recv().unwrap_or(Err(TtsError::Stopped)))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(String::from)
+}
+
+/// Render speech to a base64 WAV, for playback in the webview
+#[tauri::command]
+pub async fn tts_synthesize(
+    app_handle: AppHandle,
+    text: String,
+    language: String,
+    voice: Option<VoiceRef>,
+    prosody: Option<Prosody>,
+) -> Result<String, String> {
+    let audio = tauri::async_runtime::spawn_blocking(move || {
+        app_handle.state::<Tts>().synthesize(
+            &text,
+            &language,
+            voice.as_ref(),
+            prosody.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    Ok(BASE64.encode(crate::stt::encode_wav(&audio)))
+}
+
+#[tauri::command]
+pub fn tts_get_voice_profile(tts: tauri::State<'_, Tts>, app_name: String) -> VoiceProfile {
+    tts.profile(&app_name, Some(TaskPriority::Normal), &ContextMapper::new())
+}
+
+#[tauri::command]
+pub fn tts_set_voice_profile(
+    tts: tauri::State<'_, Tts>,
+    app_name: String,
+    profile: Option<VoiceProfile>,
+) -> Result<(), String> {
+    tts.set_profile(&app_name, profile)
+}
+
+/// Read a message from an app with that app's voice profile
+#[tauri::command]
+pub async fn tts_speak_for_app(
+    app_handle: AppHandle,
+    app_name: String,
+    text: String,
+    language: String,
+    priority: Option<TaskPriority>,
+) -> Result<(), String> {
+    let mapper = ContextMapper::new();
+    let queued_at = priority
+        .or_else(|| mapper.find_context(&app_name).map(|c| c.priority))
+        .unwrap_or(TaskPriority::Normal);
+    let announcement = Announcement {
+        app_name,
+        text,
+        language,
+        priority,
+    };
+    let result = app_handle.state::<SpeechQueue>().push_direct(
+        announcement,
+        queued_at,
+        move |tts, announcement| {
+            let profile = tts.profile(&announcement.app_name, priority, &ContextMapper::new());
+            tts.speak_with_profile(&announcement.text, &announcement.language, &profile)
+        },
+    );
+    wait_for(result).await
+}
+
+/// Queue an announcement behind its cue; returns its id for the
+/// "announcement-state" events
+#[tauri::command]
+pub fn tts_enqueue(queue: tauri::State<'_, SpeechQueue>, announcement: Announcement) -> u64 {
+    queue.push(announcement, &ContextMapper::new())
+}
+
+#[tauri::command]
+pub fn tts_clear_queue(queue: tauri::State<'_, SpeechQueue>) -> usize {
+    queue.clear()
+}
+
+/// The bundled earcons, for choosing cues
+#[tauri::command]
+pub fn tts_list_earcons() -> Vec<Earcon> {
+    Earcon::ALL.to_vec()
+}
+
+#[tauri::command]
+pub fn tts_get_earcons(queue: tauri::State<'_, SpeechQueue>) -> EarconSettings {
+    queue.earcons().settings()
+}
+
+#[tauri::command]
+pub fn tts_set_earcons(
+    queue: tauri::State<'_, SpeechQueue>,
+    settings: EarconSettings,
+) -> Result<(), String> {
+    queue.earcons().set_settings(settings)
+}
+
+#[tauri::command]
+pub async fn tts_preview_earcon(app_handle: AppHandle, sound: Sound) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let cue = app_handle.state::<SpeechQueue>().earcons().render(&sound)?;
+        app_handle.state::<Tts>().player().play(&cue)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(String::from)
+}
+
+/// Render a notification, thread or digest to a .wav or .ogg file
+#[tauri::command]
+pub async fn tts_export_audio(
+    app_handle: AppHandle,
+    content: ExportContent,
+    language: String,
+    path: PathBuf,
+    include_cues: Option<bool>,
+) -> Result<ExportedAudio, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let tts = app_handle.state::<Tts>();
+        let queue = app_handle.state::<SpeechQueue>();
+        let earcons = include_cues.unwrap_or(true).then(|| queue.earcons());
+        export::export(&tts, earcons, &content, &language, &path)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn tts_stop(tts: tauri::State<'_, Tts>) {
+    tts.player().stop();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeEngine {
+        id: EngineId,
+        available: bool,
+        pcm: bool,
+        voices: Vec<(&'static str, &'static str)>,
+    }
+
+    impl TtsEngine for FakeEngine {
+        fn id(&self) -> EngineId {
+            self.id
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities {
+                pcm: self.pcm,
+                ssml: false,
+                pitch: true,
+            }
+        }
+
+        fn is_available(&self) -> bool {
+            self.available
+        }
+
+        fn voices(&self) -> Result<Vec<Voice>, TtsError> {
+            Ok(self
+                .voices
+                .iter()
+                .map(|(id, language)| Voice {
+                    engine: self.id,
+                    id: id.to_string(),
+                    name: id.to_string(),
+                    language: language.to_string(),
+                    gender: None,
+                })
+                .collect())
+        }
+
+        fn synthesize(&self, request: &SpeechRequest) -> Result<PcmAudio, TtsError> {
+            Ok(PcmAudio {
+                sample_rate: 16_000,
+                samples: vec![0; request.text.len()],
+            })
+        }
+    }
+
+    fn fake_tts(dir: &Path) -> Tts {
+        Tts::with_engines(
+            dir,
+            vec![
+                Box::new(FakeEngine {
+                    id: EngineId::Piper,
+                    available: true,
+                    pcm: true,
+                    voices: vec![("en_US-amy-medium", "en-US")],
+                }),
+                Box::new(FakeEngine {
+                    id: EngineId::EspeakNg,
+                    available: true,
+                    pcm: true,
+                    voices: vec![("es", "es"), ("es-419", "es-419"), ("en-us", "en-us")],
+                }),
+                Box::new(FakeEngine {
+                    id: EngineId::SpeechDispatcher,
+                    available: true,
+                    pcm: false,
+                    voices: vec![("Spanish (Spain)", "es")],
+                }),
+            ],
+        )
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("birdie-tts-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_language_matching_prefers_exact_region() {
+        let voices = fake_tts(&temp_dir("match")).voices(false);
+        let espeak: Vec<Voice> = voices
+            .into_iter()
+            .filter(|v| v.engine == EngineId::EspeakNg)
+            .collect();
+        assert_eq!(best_voice(&espeak, "es-419").unwrap().id, "es-419");
+        assert_eq!(best_voice(&espeak, "es-ES").unwrap().id, "es");
+        assert!(best_voice(&espeak, "fr-FR").is_none());
+    }
+
+    #[test]
+    fn test_resolve_follows_engine_preference() {
+        let tts = fake_tts(&temp_dir("preference"));
+
+        let (engine, voice) = tts.resolve("en-US", None, true).unwrap();
+        assert_eq!(engine.id(), EngineId::Piper);
+        assert_eq!(voice.as_deref(), Some("en_US-amy-medium"));
+
+        // Piper has no Spanish voice, so espeak-ng is next
+        let (engine, voice) = tts.resolve("es-ES", None, true).unwrap();
+        assert_eq!(engine.id(), EngineId::EspeakNg);
+        assert_eq!(voice.as_deref(), Some("es"));
+
+        assert_eq!(
+            tts.resolve("ja-JP", None, true).err(),
+            Some(TtsError::NoVoice("ja-JP".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_default_voice_is_persisted_and_used() {
+        let dir = temp_dir("defaults");
+        let tts = fake_tts(&dir);
+        let chosen = VoiceRef {
+            engine: EngineId::SpeechDispatcher,
+            voice: "Spanish (Spain)".to_string(),
+        };
+        tts.set_default_voice("es-MX", Some(chosen.clone()))
+            .unwrap();
+
+        let reloaded = fake_tts(&dir);
+        let (engine, voice) = reloaded.resolve("es-ES", None, false).unwrap();
+        assert_eq!(engine.id(), EngineId::SpeechDispatcher);
+        assert_eq!(voice.as_deref(), Some("Spanish (Spain)"));
+
+        // Rendering to PCM skips engines that only play directly
+        let (engine, _) = reloaded.resolve("es-ES", None, true).unwrap();
+        assert_eq!(engine.id(), EngineId::EspeakNg);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_profile_prefers_user_choice_and_follows_priority() {
+        let dir = temp_dir("profiles");
+        let tts = fake_tts(&dir);
+        let mapper = ContextMapper::new();
+        assert_eq!(
+            tts.profile("Slack", Some(TaskPriority::Normal), &mapper),
+            VoiceProfile::default()
+        );
+
+        let slack = VoiceProfile {
+            rate: 1.3,
+            ..Default::default()
+        };
+        tts.set_profile("Slack", Some(slack.clone())).unwrap();
+        let reloaded = fake_tts(&dir);
+        assert_eq!(
+            reloaded.profile("slack", Some(TaskPriority::Normal), &mapper),
+            slack
+        );
+        let critical = reloaded.profile("slack", Some(TaskPriority::Critical), &mapper);
+        assert!(critical.rate < slack.rate);
+        assert!(critical.volume > slack.volume);
+
+        let invalid = VoiceProfile {
+            ssml: Some("<emphasis/>".to_string()),
+            ..Default::default()
+        };
+        assert!(reloaded.set_profile("Slack", Some(invalid)).is_err());
+        reloaded.set_profile("SLACK", None).unwrap();
+        assert!(reloaded.settings().app_profiles.is_empty());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,230 @@
+//! Piper neural voices
+//!
+//! Piper runs VITS voices exported to ONNX on the CPU, fully offline. Each
+//! voice is a `<name>.onnx` model with a `<name>.onnx.json` config next to it,
+//! looked up in birdie's data directory and the user's shared `piper-voices`
+//! directory. The `piper` program writes raw 16-bit PCM with `--output_raw`.
+
+use super::{
+    program_exists, run_program, Capabilities, EngineId, SpeechRequest, TtsEngine, TtsError, Voice,
+};
+use crate::stt::PcmAudio;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const PROGRAM: &str = "piper";
+const VOICES_DIR: &str = "piper-voices";
+
+#[derive(Debug, Deserialize)]
+struct VoiceConfig {
+    audio: AudioConfig,
+    #[serde(default)]
+    language: Option<LanguageConfig>,
+    #[serde(default)]
+    dataset: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AudioConfig {
+    sample_rate: u32,
+    #[serde(default)]
+    quality: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LanguageConfig {
+    code: String,
+}
+
+/// An installed voice model
+#[derive(Debug, Clone)]
+struct Model {
+    path: PathBuf,
+    sample_rate: u32,
+    voice: Voice,
+}
+
+pub struct Piper {
+    search_dirs: Vec<PathBuf>,
+}
+
+impl Piper {
+    pub fn new(data_dir: &Path) -> Self {
+        let mut search_dirs = vec![data_dir.join(VOICES_DIR)];
+        let shared = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+            });
+        if let Some(shared) = shared {
+            search_dirs.push(shared.join(VOICES_DIR));
+        }
+        Self { search_dirs }
+    }
+
+    /// The bundled binary in a voices directory wins over one on `PATH`
+    fn program(&self) -> Option<PathBuf> {
+        self.search_dirs
+            .iter()
+            .map(|dir| dir.join(PROGRAM))
+            .find(|path| path.is_file())
+            .or_else(|| program_exists(PROGRAM).then(|| PathBuf::from(PROGRAM)))
+    }
+
+    fn models(&self) -> Vec<Model> {
+        self.search_dirs
+            .iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flatten()
+            .flatten()
+            .filter_map(|entry| load_model(&entry.path()))
+            .collect()
+    }
+
+    fn model(&self, request: &SpeechRequest) -> Result<Model, TtsError> {
+        let models = self.models();
+        let model = match &request.voice {
+            Some(id) => models.iter().find(|m| &m.voice.id == id),
+            None => super::best_voice(
+                &models.iter().map(|m| m.voice.clone()).collect::<Vec<_>>(),
+                &request.language,
+            )
+            .and_then(|best| models.iter().find(|m| m.voice.id == best.id)),
+        };
+        model
+            .cloned()
+            .ok_or_else(|| TtsError::NoVoice(request.language.clone()))
+    }
+}
+
+/// Read a voice model's config; `path` is the `.onnx` file
+fn load_model(path: &Path) -> Option<Model> {
+    if path.extension()? != "onnx" {
+        return None;
+    }
+    let id = path.file_stem()?.to_string_lossy().to_string();
+    let config = fs::read_to_string(path.with_extension("onnx.json")).ok()?;
+    let config: VoiceConfig = serde_json::from_str(&config).ok()?;
+
+    // Voice files are named "<lang>_<REGION>-<dataset>-<quality>"
+    let language = config
+        .language
+        .map(|l| l.code)
+        .or_else(|| id.split('-').next().map(str::to_string))?
+        .replace('_', "-");
+    let name = match (config.dataset, config.audio.quality) {
+        (Some(dataset), Some(quality)) => format!("{} ({})", dataset, quality),
+        (Some(dataset), None) => dataset,
+        _ => id.clone(),
+    };
+
+    Some(Model {
+        path: path.to_path_buf(),
+        sample_rate: config.audio.sample_rate,
+        voice: Voice {
+            engine: EngineId::Piper,
+            id,
+            name,
+            language,
+            gender: None,
+        },
+    })
+}
+
+impl TtsEngine for Piper {
+    fn id(&self) -> EngineId {
+        EngineId::Piper
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            pcm: true,
+            ssml: false,
+            pitch: false,
+        }
+    }
+
+    fn is_available(&self) -> bool {
+        self.program().is_some() && !self.models().is_empty()
+    }
+
+    fn voices(&self) -> Result<Vec<Voice>, TtsError> {
+        Ok(self.models().into_iter().map(|m| m.voice).collect())
+    }
+
+    fn synthesize(&self, request: &SpeechRequest) -> Result<PcmAudio, TtsError> {
+        let program = self.program().ok_or(TtsError::NotInstalled(PROGRAM))?;
+        let model = self.model(request)?;
+
+        // length_scale is the inverse of speed
+        let length_scale = 1.0 / request.prosody.rate.clamp(0.25, 4.0);
+        let mut command = Command::new(program);
+        command
+            .arg("--model")
+            .arg(&model.path)
+            .arg("--output_raw")
+            .arg("--length_scale")
+            .arg(format!("{:.3}", length_scale));
+        let raw = run_program(PROGRAM, &mut command, Some(request.text.as_bytes()))?;
+
+        let volume = request.prosody.volume.clamp(0.0, 2.0);
+        let samples = raw
+            .chunks_exact(2)
+            .map(|s| {
+                let sample = i16::from_le_bytes([s[0], s[1]]) as f32 * volume;
+                sample.clamp(i16::MIN as f32, i16::MAX as f32) as i16
+            })
+            .collect();
+
+        Ok(PcmAudio {
+            sample_rate: model.sample_rate,
+            samples,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_models_are_read_from_voice_configs() {
+        let dir = std::env::temp_dir().join(format!("birdie-piper-{}", std::process::id()));
+        let voices = dir.join(VOICES_DIR);
+        fs::create_dir_all(&voices).unwrap();
+        fs::write(voices.join("es_ES-davefx-medium.onnx"), b"").unwrap();
+        fs::write(
+            voices.join("es_ES-davefx-medium.onnx.json"),
+            r#"{"audio": {"sample_rate": 22050, "quality": "medium"},
+                "language": {"code": "es_ES"}, "dataset": "davefx"}"#,
+        )
+        .unwrap();
+        // A model without its config is ignored
+        fs::write(voices.join("broken.onnx"), b"").unwrap();
+
+        let piper = Piper {
+            search_dirs: vec![voices],
+        };
+        let models = piper.models();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].sample_rate, 22050);
+        assert_eq!(models[0].voice.id, "es_ES-davefx-medium");
+        assert_eq!(models[0].voice.language, "es-ES");
+        assert_eq!(models[0].voice.name, "davefx (medium)");
+
+        let request = SpeechRequest {
+            text: "hola".to_string(),
+            language: "es-MX".to_string(),
+            voice: None,
+            prosody: Default::default(),
+            ssml: false,
+        };
+        assert_eq!(
+            piper.model(&request).unwrap().voice.id,
+            "es_ES-davefx-medium"
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+}
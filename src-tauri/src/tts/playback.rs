@@ -0,0 +1,138 @@
+//! PCM playback through the system's audio tools
+//!
+//! Audio is piped to `paplay` (PulseAudio/PipeWire) or `aplay` (ALSA), the
+//! playback counterparts of the tools used for microphone capture.
+
+use super::TtsError;
+use crate::stt::PcmAudio;
+use std::io::{ErrorKind, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+fn playback_commands(sample_rate: u32) -> Vec<(&'static str, Command)> {
+    let rate = sample_rate.to_string();
+
+    let mut paplay = Command::new("paplay");
+    paplay.args(["--raw", "--format=s16le", "--channels=1"]);
+    paplay.arg(format!("--rate={}", rate));
+
+    let mut aplay = Command::new("aplay");
+    aplay.args(["-q", "-t", "raw", "-f", "S16_LE", "-c", "1", "-r", &rate]);
+
+    vec![("paplay", paplay), ("aplay", aplay)]
+}
+
+/// Plays one sound at a time; `stop` interrupts it from another thread
+pub struct Player {
+    current: Mutex<Option<Child>>,
+    stopped: AtomicBool,
+}
+
+impl Player {
+    pub fn new() -> Self {
+        Self {
+            current: Mutex::new(None),
+            stopped: AtomicBool::new(false),
+        }
+    }
+
+    /// Play mono PCM, blocking until it finishes or is stopped
+    pub fn play(&self, audio: &PcmAudio) -> Result<(), TtsError> {
+        if audio.samples.is_empty() {
+            return Ok(());
+        }
+        if !cfg!(target_os = "linux") {
+            return Err(TtsError::Unsupported("audio playback on this platform"));
+        }
+
+        let mut spawned = None;
+        for (program, mut command) in playback_commands(audio.sample_rate) {
+            match command
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+            {
+                Ok(child) => {
+                    spawned = Some(child);
+                    break;
+                }
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(TtsError::Failed(format!("{}: {}", program, e))),
+            }
+        }
+        let mut child = spawned.ok_or(TtsError::NotInstalled("paplay or aplay"))?;
+
+        let mut stdin = child.stdin.take();
+        self.run(child, move || {
+            let Some(stdin) = stdin.as_mut() else {
+                return;
+            };
+            let bytes: Vec<u8> = audio.samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+            // A broken pipe here means playback was stopped
+            let _ = stdin.write_all(&bytes);
+        })
+    }
+
+    /// Run a program that plays audio itself, such as `spd-say -w`
+    pub fn run_program(
+        &self,
+        program: &'static str,
+        command: &mut Command,
+    ) -> Result<(), TtsError> {
+        let child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                ErrorKind::NotFound => TtsError::NotInstalled(program),
+                _ => TtsError::Failed(format!("{}: {}", program, e)),
+            })?;
+        self.run(child, || {})
+    }
+
+    fn run(&self, child: Child, feed: impl FnOnce()) -> Result<(), TtsError> {
+        self.stopped.store(false, Ordering::SeqCst);
+        if let Some(mut previous) = self.current.lock().unwrap().replace(child) {
+            let _ = previous.kill();
+            let _ = previous.wait();
+        }
+
+        feed();
+
+        loop {
+            {
+                let mut current = self.current.lock().unwrap();
+                let Some(child) = current.as_mut() else {
+                    return Err(TtsError::Stopped);
+                };
+                match child.try_wait() {
+                    Ok(Some(_)) => {
+                        current.take();
+                        break;
+                    }
+                    Ok(None) => {}
+                    Err(e) => return Err(TtsError::Failed(e.to_string())),
+                }
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        if self.stopped.load(Ordering::SeqCst) {
+            return Err(TtsError::Stopped);
+        }
+        Ok(())
+    }
+
+    /// Stop whatever is playing
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(mut child) = self.current.lock().unwrap().take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! Per-app voice profiles
+//!
+//! A profile chooses the voice and speaking style an app's messages are read
+//! with, so Slack can sound different from the calendar. Engines that accept
+//! SSML get the text wrapped in `<speak><prosody>` markup together with the
+//! profile's own wrapper; the others receive the same values as [`Prosody`]
+//! parameters.
+
+use super::{Capabilities, Prosody, SpeechRequest, VoiceRef};
+use crate::context_mapper::TaskPriority;
+use serde::{Deserialize, Serialize};
+
+/// Placeholder for the message inside an SSML wrapper
+const TEXT_PLACEHOLDER: &str = "{text}";
+
+/// Critical messages are read this much slower and louder
+const CRITICAL_RATE: f32 = 0.85;
+const CRITICAL_VOLUME: f32 = 1.3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VoiceProfile {
+    /// Voice to use instead of the language default
+    pub voice: Option<VoiceRef>,
+    /// Same scale as [`Prosody`]: 1.0 is the engine's default
+    pub rate: f32,
+    pub pitch: f32,
+    pub volume: f32,
+    /// SSML around the message, such as `<emphasis>{text}</emphasis>`;
+    /// ignored by engines without SSML support
+    pub ssml: Option<String>,
+}
+
+impl Default for VoiceProfile {
+    fn default() -> Self {
+        Self {
+            voice: None,
+            rate: 1.0,
+            pitch: 1.0,
+            volume: 1.0,
+            ssml: None,
+        }
+    }
+}
+
+impl VoiceProfile {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, value, max) in [
+            ("rate", self.rate, 4.0),
+            ("pitch", self.pitch, 2.0),
+            ("volume", self.volume, 2.0),
+        ] {
+            if !(0.0..=max).contains(&value) {
+                return Err(format!("{} must be between 0 and {}", name, max));
+            }
+        }
+        if let Some(wrapper) = &self.ssml {
+            if !wrapper.contains(TEXT_PLACEHOLDER) {
+                return Err(format!("SSML wrapper must contain {}", TEXT_PLACEHOLDER));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn prosody(&self) -> Prosody {
+        Prosody {
+            rate: self.rate,
+            pitch: self.pitch,
+            volume: self.volume,
+        }
+    }
+
+    /// The profile adjusted for how urgent a message is
+    pub fn for_priority(&self, priority: TaskPriority) -> Self {
+        match priority {
+            TaskPriority::Critical => Self {
+                rate: self.rate * CRITICAL_RATE,
+                volume: (self.volume * CRITICAL_VOLUME).min(2.0),
+                ..self.clone()
+            },
+            _ => self.clone(),
+        }
+    }
+
+    /// Markup for SSML engines; the message itself is escaped
+    pub fn to_ssml(&self, text: &str, language: &str) -> String {
+        let escaped = escape(text);
+        let body = match &self.ssml {
+            Some(wrapper) if wrapper.contains(TEXT_PLACEHOLDER) => {
+                wrapper.replace(TEXT_PLACEHOLDER, &escaped)
+            }
+            _ => escaped,
+        };
+        let volume = if self.volume <= 0.0 {
+            "silent".to_string()
+        } else {
+            relative(self.volume)
+        };
+        format!(
+            r#"<speak xml:lang="{}"><prosody rate="{}" pitch="{}" volume="{}">{}</prosody></speak>"#,
+            escape(language),
+            relative(self.rate),
+            relative(self.pitch),
+            volume,
+            body
+        )
+    }
+
+    /// Build the request for an engine: markup if it reads SSML, parameters
+    /// otherwise
+    pub fn request(
+        &self,
+        text: &str,
+        language: &str,
+        voice: Option<String>,
+        capabilities: Capabilities,
+    ) -> SpeechRequest {
+        if capabilities.ssml {
+            SpeechRequest {
+                text: self.to_ssml(text, language),
+                language: language.to_string(),
+                voice,
+                // Already part of the markup
+                prosody: Prosody::default(),
+                ssml: true,
+            }
+        } else {
+            SpeechRequest {
+                text: text.to_string(),
+                language: language.to_string(),
+                voice,
+                prosody: self.prosody(),
+                ssml: false,
+            }
+        }
+    }
+}
+
+/// A 1.0-centred factor as an SSML relative change, e.g. "+20%"
+fn relative(factor: f32) -> String {
+    let percent = ((factor - 1.0) * 100.0).round() as i32;
+    format!("{:+}%", percent)
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SSML: Capabilities = Capabilities {
+        pcm: true,
+        ssml: true,
+        pitch: true,
+    };
+    const PLAIN: Capabilities = Capabilities {
+        pcm: true,
+        ssml: false,
+        pitch: false,
+    };
+
+    #[test]
+    fn test_ssml_wraps_escaped_text() {
+        let profile = VoiceProfile {
+            rate: 1.2,
+            pitch: 0.9,
+            ssml: Some("<emphasis level=\"strong\">{text}</emphasis>".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            profile.to_ssml("Ana <ana@x.com> & Luis", "es-ES"),
+            "<speak xml:lang=\"es-ES\"><prosody rate=\"+20%\" pitch=\"-10%\" volume=\"+0%\">\
+             <emphasis level=\"strong\">Ana &lt;ana@x.com&gt; &amp; Luis</emphasis>\
+             </prosody></speak>"
+        );
+    }
+
+    #[test]
+    fn test_request_depends_on_engine_capabilities() {
+        let profile = VoiceProfile {
+            rate: 0.8,
+            volume: 1.5,
+            ssml: Some("<emphasis>{text}</emphasis>".to_string()),
+            ..Default::default()
+        };
+
+        let request = profile.request("hola", "es-ES", None, SSML);
+        assert!(request.ssml);
+        assert!(request.text.starts_with("<speak"));
+        assert_eq!(request.prosody, Prosody::default());
+
+        let request = profile.request("hola", "es-ES", Some("es".to_string()), PLAIN);
+        assert!(!request.ssml);
+        assert_eq!(request.text, "hola");
+        assert_eq!(request.prosody.rate, 0.8);
+        assert_eq!(request.prosody.volume, 1.5);
+        assert_eq!(request.voice.as_deref(), Some("es"));
+    }
+
+    #[test]
+    fn test_critical_messages_are_slower_and_louder() {
+        let profile = VoiceProfile {
+            volume: 1.8,
+            ..Default::default()
+        };
+        let critical = profile.for_priority(TaskPriority::Critical);
+        assert!(critical.rate < profile.rate);
+        assert_eq!(critical.volume, 2.0);
+        assert_eq!(profile.for_priority(TaskPriority::Normal), profile);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(VoiceProfile::default().validate().is_ok());
+        let wrapper = VoiceProfile {
+            ssml: Some("<emphasis/>".to_string()),
+            ..Default::default()
+        };
+        assert!(wrapper.validate().is_err());
+        let loud = VoiceProfile {
+            volume: 3.0,
+            ..Default::default()
+        };
+        assert!(loud.validate().is_err());
+    }
+}
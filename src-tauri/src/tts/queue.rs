@@ -0,0 +1,339 @@
+//! Backend speech queue
+//!
+//! Announcements are read one at a time by a worker thread, highest priority
+//! first. Each one starts with its cue: for engines that render PCM the cue
+//! and the speech are joined into a single clip, otherwise the cue is played
+//! before the engine speaks, so the two never overlap.
+//!
+//! Speech asked for directly, such as `tts_speak`, goes through the same
+//! queue, as every item shares one player.
+
+use super::earcons::{self, Earcons};
+use super::{Tts, TtsError, VoiceProfile};
+use crate::context_mapper::{ContextMapper, TaskPriority};
+use crate::stt::PcmAudio;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Condvar, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Silence between a cue and the speech after it
+const CUE_GAP_MS: u32 = 150;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Announcement {
+    pub app_name: String,
+    pub text: String,
+    pub language: String,
+    /// The app's priority if unset
+    #[serde(default)]
+    pub priority: Option<TaskPriority>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnouncementState {
+    Playing,
+    Finished,
+    Stopped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AnnouncementEvent {
+    pub id: u64,
+    pub app_name: String,
+    pub state: AnnouncementState,
+    pub error: Option<String>,
+}
+
+/// Speech read as asked for, without a cue
+type Direct = Box<dyn FnOnce(&Tts, &Announcement) -> Result<(), TtsError> + Send>;
+
+struct Queued {
+    id: u64,
+    priority: TaskPriority,
+    announcement: Announcement,
+    /// Set for direct speech, with where its result goes
+    direct: Option<(Direct, mpsc::Sender<Result<(), TtsError>>)>,
+}
+
+pub struct SpeechQueue {
+    pending: Mutex<VecDeque<Queued>>,
+    ready: Condvar,
+    next_id: AtomicU64,
+    earcons: Earcons,
+}
+
+impl SpeechQueue {
+    pub fn new(config_dir: &Path) -> Self {
+        Self {
+            pending: Mutex::new(VecDeque::new()),
+            ready: Condvar::new(),
+            next_id: AtomicU64::new(1),
+            earcons: Earcons::new(config_dir),
+        }
+    }
+
+    pub fn earcons(&self) -> &Earcons {
+        &self.earcons
+    }
+
+    /// Start the worker thread; the queue must already be managed state
+    pub fn start(app: &AppHandle) {
+        let app = app.clone();
+        std::thread::spawn(move || loop {
+            let queue = app.state::<SpeechQueue>();
+            let mut item = queue.next();
+            let emit = |state, error| {
+                let event = AnnouncementEvent {
+                    id: item.id,
+                    app_name: item.announcement.app_name.clone(),
+                    state,
+                    error,
+                };
+                if let Err(e) = app.emit("announcement-state", event) {
+                    eprintln!("✗ Error emitting announcement state: {}", e);
+                }
+            };
+
+            emit(AnnouncementState::Playing, None);
+            let tts = app.state::<Tts>();
+            let result = match item.direct.take() {
+                Some((speak, done)) => {
+                    let result = speak(&tts, &item.announcement);
+                    let _ = done.send(result.clone());
+                    result
+                }
+                None => queue.play(&tts, &item),
+            };
+            match result {
+                Ok(()) => emit(AnnouncementState::Finished, None),
+                Err(TtsError::Stopped) => emit(AnnouncementState::Stopped, None),
+                Err(e) => {
+                    eprintln!("✗ Failed to read announcement: {}", e);
+                    emit(AnnouncementState::Failed, Some(e.to_string()));
+                }
+            }
+        });
+    }
+
+    /// Queue an announcement after everything of the same or higher priority
+    pub fn push(&self, announcement: Announcement, mapper: &ContextMapper) -> u64 {
+        let priority = announcement
+            .priority
+            .or_else(|| {
+                mapper
+                    .find_context(&announcement.app_name)
+                    .map(|c| c.priority)
+            })
+            .unwrap_or(TaskPriority::Normal);
+        self.insert(priority, announcement, None)
+    }
+
+    /// Queue direct speech at `priority`; the receiver gets its result, or
+    /// is dropped if the queue is cleared first
+    pub fn push_direct(
+        &self,
+        announcement: Announcement,
+        priority: TaskPriority,
+        speak: impl FnOnce(&Tts, &Announcement) -> Result<(), TtsError> + Send + 'static,
+    ) -> mpsc::Receiver<Result<(), TtsError>> {
+        let (done, result) = mpsc::channel();
+        self.insert(priority, announcement, Some((Box::new(speak), done)));
+        result
+    }
+
+    fn insert(
+        &self,
+        priority: TaskPriority,
+        announcement: Announcement,
+        direct: Option<(Direct, mpsc::Sender<Result<(), TtsError>>)>,
+    ) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+
+        let mut pending = self.pending.lock().unwrap();
+        let position = pending
+            .iter()
+            .position(|queued| queued.priority < priority)
+            .unwrap_or(pending.len());
+        pending.insert(
+            position,
+            Queued {
+                id,
+                priority,
+                announcement,
+                direct,
+            },
+        );
+        self.ready.notify_one();
+        id
+    }
+
+    /// Drop everything not yet started; returns how many were dropped
+    pub fn clear(&self) -> usize {
+        let mut pending = self.pending.lock().unwrap();
+        let dropped = pending.len();
+        pending.clear();
+        dropped
+    }
+
+    fn next(&self) -> Queued {
+        let mut pending = self.pending.lock().unwrap();
+        loop {
+            if let Some(item) = pending.pop_front() {
+                return item;
+            }
+            pending = self.ready.wait(pending).unwrap();
+        }
+    }
+
+    fn play(&self, tts: &Tts, item: &Queued) -> Result<(), TtsError> {
+        // Disabled apps are only shown, never read
+        if item.priority == TaskPriority::Disabled {
+            return Ok(());
+        }
+        let announcement = &item.announcement;
+        // A cue that cannot be rendered is left out rather than losing the
+        // announcement
+        let cue = self
+            .earcons
+            .cue(&announcement.app_name, item.priority)
+            .map_err(|e| eprintln!("⚠ Failed to render cue, reading without it: {}", e));
+        if self.earcons.cue_only(item.priority) {
+            match cue {
+                Ok(Some(cue)) => return tts.player().play(&cue),
+                Ok(None) => return Ok(()),
+                Err(()) => {}
+            }
+        }
+        let cue = cue.unwrap_or(None);
+
+        let profile = tts.profile(
+            &announcement.app_name,
+            Some(item.priority),
+            &ContextMapper::new(),
+        );
+        announce(tts, announcement, &profile, cue)
+    }
+}
+
+/// Speak with the cue in front, as one clip when the engine renders PCM
+fn announce(
+    tts: &Tts,
+    announcement: &Announcement,
+    profile: &VoiceProfile,
+    cue: Option<PcmAudio>,
+) -> Result<(), TtsError> {
+    let (engine, voice) = tts.resolve(&announcement.language, profile.voice.as_ref(), false)?;
+    let request = profile.request(
+        &announcement.text,
+        &announcement.language,
+        voice,
+        engine.capabilities(),
+    );
+
+    if engine.capabilities().pcm {
+        let speech = engine.synthesize(&request)?;
+        return tts.player().play(&with_cue(cue, speech));
+    }
+    if let Some(cue) = cue {
+        tts.player().play(&cue)?;
+    }
+    engine.speak(&request, tts.player())
+}
+
+/// Join a cue, a short gap and the speech at the speech's sample rate
+fn with_cue(cue: Option<PcmAudio>, speech: PcmAudio) -> PcmAudio {
+    let Some(cue) = cue else {
+        return speech;
+    };
+    let cue = earcons::resample(&cue, speech.sample_rate);
+    let gap = (speech.sample_rate * CUE_GAP_MS / 1000) as usize;
+    let mut samples = Vec::with_capacity(cue.samples.len() + gap + speech.samples.len());
+    samples.extend_from_slice(&cue.samples);
+    samples.resize(samples.len() + gap, 0);
+    samples.extend_from_slice(&speech.samples);
+    PcmAudio {
+        sample_rate: speech.sample_rate,
+        samples,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn announcement(app_name: &str, priority: Option<TaskPriority>) -> Announcement {
+        Announcement {
+            app_name: app_name.to_string(),
+            text: "hola".to_string(),
+            language: "es-ES".to_string(),
+            priority,
+        }
+    }
+
+    #[test]
+    fn test_higher_priorities_go_first() {
+        let dir = std::env::temp_dir().join(format!("birdie-queue-{}", std::process::id()));
+        let queue = SpeechQueue::new(&dir);
+        let mapper = ContextMapper::new();
+
+        let first = queue.push(announcement("Telegram", None), &mapper);
+        let second = queue.push(announcement("Telegram", None), &mapper);
+        // Slack is high priority by default
+        let slack = queue.push(announcement("Slack", None), &mapper);
+        let critical = queue.push(
+            announcement("Telegram", Some(TaskPriority::Critical)),
+            &mapper,
+        );
+
+        let order: Vec<u64> = (0..4).map(|_| queue.next().id).collect();
+        assert_eq!(order, vec![critical, slack, first, second]);
+
+        queue.push(announcement("Telegram", None), &mapper);
+        assert_eq!(queue.clear(), 1);
+        assert_eq!(queue.clear(), 0);
+    }
+
+    #[test]
+    fn test_direct_speech_is_queued() {
+        let dir = std::env::temp_dir().join(format!("birdie-queue-direct-{}", std::process::id()));
+        let queue = SpeechQueue::new(&dir);
+        let mapper = ContextMapper::new();
+
+        let telegram = queue.push(announcement("Telegram", None), &mapper);
+        let result = queue.push_direct(announcement("", None), TaskPriority::High, |_, _| Ok(()));
+        let direct = queue.next();
+        assert!(direct.direct.is_some());
+        assert_eq!(queue.next().id, telegram);
+
+        drop(direct);
+        assert!(result.recv().is_err());
+        // Clearing the queue drops direct speech, and its caller hears of it
+        let result = queue.push_direct(announcement("", None), TaskPriority::High, |_, _| Ok(()));
+        assert_eq!(queue.clear(), 1);
+        assert!(result.recv().is_err());
+    }
+
+    #[test]
+    fn test_cue_is_joined_before_speech() {
+        let speech = PcmAudio {
+            sample_rate: 16_000,
+            samples: vec![7; 100],
+        };
+        let cue = PcmAudio {
+            sample_rate: 8_000,
+            samples: vec![3; 50],
+        };
+        let joined = with_cue(Some(cue), speech.clone());
+        assert_eq!(joined.sample_rate, 16_000);
+        assert_eq!(joined.samples.len(), 100 + 2_400 + 100);
+        assert_eq!(joined.samples[0], 3);
+        assert_eq!(joined.samples[150], 0);
+        assert_eq!(joined.samples[2_500..], speech.samples[..]);
+        assert_eq!(with_cue(None, speech.clone()), speech);
+    }
+}
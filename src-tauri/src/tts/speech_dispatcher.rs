@@ -0,0 +1,158 @@
+//! speech-dispatcher backend
+//!
+//! Uses whatever output module the desktop has configured (often the same
+//! voices screen readers use). speech-dispatcher plays audio itself, so this
+//! engine cannot render to PCM; `spd-say -w` is run under the player so it
+//! can still be stopped.
+
+use super::{
+    program_exists, run_program, Capabilities, EngineId, Player, SpeechRequest, TtsEngine,
+    TtsError, Voice,
+};
+use crate::stt::PcmAudio;
+use std::process::Command;
+
+const PROGRAM: &str = "spd-say";
+
+pub struct SpeechDispatcher;
+
+/// Parse the table printed by `spd-say -L`
+///
+/// ```text
+///      NAME                 LANGUAGE  VARIANT
+///      Spanish (Spain)            es      none
+/// ```
+fn parse_voices(output: &str) -> Vec<Voice> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            if columns.len() < 3 || columns[0] == "NAME" {
+                return None;
+            }
+            let language = columns[columns.len() - 2];
+            let name = columns[..columns.len() - 2].join(" ");
+            Some(Voice {
+                engine: EngineId::SpeechDispatcher,
+                id: name.clone(),
+                name,
+                language: language.to_string(),
+                gender: None,
+            })
+        })
+        .collect()
+}
+
+/// Map a 1.0-centred factor onto speech-dispatcher's -100..100 scale
+fn scale(factor: f32) -> i32 {
+    ((factor - 1.0) * 100.0).clamp(-100.0, 100.0).round() as i32
+}
+
+fn arguments(request: &SpeechRequest) -> Vec<String> {
+    let prosody = request.prosody;
+    let mut args = vec![
+        "-w".to_string(),
+        "-l".to_string(),
+        super::primary_language(&request.language),
+        "-r".to_string(),
+        scale(prosody.rate).to_string(),
+        "-p".to_string(),
+        scale(prosody.pitch).to_string(),
+        "-i".to_string(),
+        scale(prosody.volume).to_string(),
+    ];
+    if let Some(voice) = &request.voice {
+        args.push("-y".to_string());
+        args.push(voice.clone());
+    }
+    if request.ssml {
+        args.push("-x".to_string());
+    }
+    args.push("--".to_string());
+    args.push(request.text.clone());
+    args
+}
+
+impl TtsEngine for SpeechDispatcher {
+    fn id(&self) -> EngineId {
+        EngineId::SpeechDispatcher
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            pcm: false,
+            ssml: true,
+            pitch: true,
+        }
+    }
+
+    fn is_available(&self) -> bool {
+        program_exists(PROGRAM)
+    }
+
+    fn voices(&self) -> Result<Vec<Voice>, TtsError> {
+        let output = run_program(PROGRAM, Command::new(PROGRAM).arg("-L"), None)?;
+        Ok(parse_voices(&String::from_utf8_lossy(&output)))
+    }
+
+    fn synthesize(&self, _request: &SpeechRequest) -> Result<PcmAudio, TtsError> {
+        Err(TtsError::Unsupported(
+            "rendering speech-dispatcher voices to audio",
+        ))
+    }
+
+    fn speak(&self, request: &SpeechRequest, player: &Player) -> Result<(), TtsError> {
+        player.run_program(PROGRAM, Command::new(PROGRAM).args(arguments(request)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tts::Prosody;
+
+    #[test]
+    fn test_parse_voices() {
+        let output = "     NAME                 LANGUAGE  VARIANT\n\
+                      \x20    Spanish (Spain)            es      none\n\
+                      \x20    English (America)       en-US      none\n";
+        let voices = parse_voices(output);
+        assert_eq!(voices.len(), 2);
+        assert_eq!(voices[0].id, "Spanish (Spain)");
+        assert_eq!(voices[0].language, "es");
+        assert_eq!(voices[1].language, "en-US");
+    }
+
+    #[test]
+    fn test_arguments() {
+        let request = SpeechRequest {
+            text: "-hola".to_string(),
+            language: "es-ES".to_string(),
+            voice: Some("Spanish (Spain)".to_string()),
+            prosody: Prosody {
+                rate: 0.5,
+                pitch: 1.0,
+                volume: 2.0,
+            },
+            ssml: false,
+        };
+        assert_eq!(
+            arguments(&request),
+            vec![
+                "-w",
+                "-l",
+                "es",
+                "-r",
+                "-50",
+                "-p",
+                "0",
+                "-i",
+                "100",
+                "-y",
+                "Spanish (Spain)",
+                "--",
+                "-hola"
+            ]
+        );
+    }
+}
@@ -1,5 +1,6 @@
-use chromiumoxide::browser::Browser;
+use crate::cdp::CdpManager;
 use serde::{Deserialize, Serialize};
+use tauri::State;
 
 /// Result of context extraction from a tab
 #[derive(Debug, Serialize, Deserialize)]
@@ -26,37 +27,22 @@ pub struct ContextResult {
 /// println!("Content: {}", result.content);
 /// ```
 #[tauri::command]
-pub async fn get_active_tab_context(target_name: String) -> Result<ContextResult, String> {
-    // Connect to Chrome DevTools Protocol
-    let mut browser = match Browser::connect("http://localhost:9222").await {
-        Ok((browser, _handler)) => browser,
-        Err(e) => {
-            return Err(format!(
-                "No se pudo conectar a Chrome en puerto 9222: {}. \
-                Asegúrate de que Chrome está abierto con --remote-debugging-port=9222",
-                e
-            ))
-        }
-    };
-
-    // Get all targets (tabs) from the browser
-    let targets = match browser.fetch_targets().await {
-        Ok(targets) => targets,
-        Err(e) => {
-            return Err(format!("No se pudo obtener las pestañas: {}", e));
-        }
-    };
+pub async fn get_active_tab_context(
+    manager: State<'_, CdpManager>,
+    target_name: String,
+) -> Result<ContextResult, String> {
+    // Get all tabs through the shared connection
+    let targets = manager.pages().await?;
 
     // Find the tab that matches the target_name (case-insensitive)
     let target = match targets
         .iter()
-        .find(|t| t.r#type == "page" && t.title.to_lowercase().contains(&target_name.to_lowercase()))
+        .find(|t| t.title.to_lowercase().contains(&target_name.to_lowercase()))
     {
         Some(target) => target,
         None => {
             let available = targets
                 .iter()
-                .filter(|t| t.r#type == "page")
                 .map(|t| format!("'{}' ({})", t.title, t.url))
                 .collect::<Vec<_>>()
                 .join(", ");
@@ -71,12 +57,7 @@ pub async fn get_active_tab_context(target_name: String) -> Result<ContextResult
     let tab_url = target.url.clone();
 
     // Get the page for this tab
-    let page = match browser.get_page(target.target_id.clone()).await {
-        Ok(page) => page,
-        Err(e) => {
-            return Err(format!("No se pudo acceder a la pestaña: {}", e));
-        }
-    };
+    let page = manager.page(target.target_id.as_ref()).await?;
 
     // Extract JavaScript based on the domain
     let js_script = get_extraction_script(&tab_url);
@@ -92,15 +73,15 @@ pub async fn get_active_tab_context(target_name: String) -> Result<ContextResult
         .await
     {
         Ok(eval_result) => {
-            // Extract the content from the evaluation result
-            // The structure of eval_result may vary; we'll use the debug format
-            let content = format!("{:?}", eval_result)
+            // The scripts return a string
+            let content = eval_result
+                .into_value::<String>()
+                .unwrap_or_default()
                 .chars()
                 .take(500) // Limit to first 500 characters
                 .collect::<String>();
 
-            // Clean up excessive debug formatting
-            let content = if content.is_empty() || content.contains("Undefined") {
+            let content = if content.trim().is_empty() {
                 "No se encontró contenido en la pestaña".to_string()
             } else {
                 content
@@ -131,7 +112,7 @@ pub async fn get_active_tab_context(target_name: String) -> Result<ContextResult
 ///
 /// This function returns different selectors for different platforms
 /// to extract the most relevant content (chat messages, visible text, etc.)
-fn get_extraction_script(url: &str) -> String {
+pub(crate) fn get_extraction_script(url: &str) -> String {
     let url_lower = url.to_lowercase();
 
     if url_lower.contains("meet.google.com") {
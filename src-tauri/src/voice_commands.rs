@@ -0,0 +1,1279 @@
+//! Voice command interpreter
+//!
+//! Turns a transcript such as "siguiente y silencia Slack una hora" or
+//! "dile a Ximena que el deploy está listo" into typed actions for the speech
+//! queue, do-not-disturb, rules and reply subsystems, and carries them out.
+//!
+//! Parsing is grammar based: every command is a sequence of patterns
+//! (keywords, app names, durations, people, free text) matched against
+//! accent-insensitive tokens. Several commands can be chained with "y",
+//! "and", "luego"... and anything that matches no rule is returned as an
+//! unrecognized fragment instead of being guessed at.
+
+use crate::context_mapper::ContextMapper;
+use crate::hands_free::HandsFree;
+use crate::notifications::NotificationRules;
+use crate::stt::postprocess::{self, normalize, Replacement};
+use crate::tts::{SpeechQueue, Tts};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Transcripts recognized with less confidence than this are not acted on
+const MIN_CONFIDENCE: f32 = 0.6;
+
+/// Maximum number of words in a person's name
+const MAX_PERSON_WORDS: usize = 4;
+
+/// Commands for the notification speech queue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum QueueCommand {
+    Next,
+    Repeat,
+    Stop,
+    Pause,
+    Resume,
+    /// Read the latest message from someone
+    ReadLastFrom {
+        sender: String,
+        app: Option<String>,
+    },
+    /// Mark everything (or everything from one app) as read
+    MarkAllRead {
+        app: Option<String>,
+    },
+}
+
+/// Do-not-disturb commands; `minutes: None` means until turned off
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DndCommand {
+    Enable { minutes: Option<u32> },
+    Disable,
+    MuteApp { app: String, minutes: Option<u32> },
+    UnmuteApp { app: String },
+}
+
+/// What a rule applies to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "name", rename_all = "snake_case")]
+pub enum RuleTarget {
+    Sender(String),
+    App(String),
+}
+
+/// Commands that add notification rules
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleCommand {
+    Ignore { target: RuleTarget },
+    Prioritize { target: RuleTarget },
+}
+
+/// Commands for the reply subsystem
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReplyCommand {
+    /// Reply to the current notification, or to `recipient` when named
+    Send {
+        recipient: Option<String>,
+        text: String,
+    },
+    /// Reply to the latest notification with a quick reply, the app's
+    /// default one when unnamed
+    Quick {
+        name: Option<String>,
+        minutes: Option<u32>,
+    },
+    /// Send the reply waiting for confirmation
+    Confirm,
+    /// Discard the reply waiting for confirmation
+    Cancel,
+}
+
+/// An action for one of birdie's subsystems
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "subsystem", content = "command", rename_all = "snake_case")]
+pub enum Action {
+    Queue(QueueCommand),
+    Dnd(DndCommand),
+    Rules(RuleCommand),
+    Reply(ReplyCommand),
+}
+
+/// Result of interpreting a transcript
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Interpretation {
+    pub actions: Vec<Action>,
+    /// Parts of the transcript that matched no command, in order
+    pub unrecognized: Vec<String>,
+    /// The transcript was too uncertain to act on
+    pub low_confidence: bool,
+}
+
+/// A word of the transcript
+#[derive(Debug, Clone)]
+struct Token {
+    /// Lower-case, accent-free form used for matching
+    norm: String,
+    /// Byte range in the original transcript
+    start: usize,
+    end: usize,
+}
+
+/// Split into words; `:` and `,` are kept as their own tokens
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word_start: Option<usize> = None;
+
+    let flush = |tokens: &mut Vec<Token>, start: usize, end: usize| {
+        let norm = normalize(&text[start..end]);
+        if !norm.is_empty() {
+            tokens.push(Token { norm, start, end });
+        }
+    };
+
+    for (i, c) in text.char_indices() {
+        let is_word_char = c.is_alphanumeric() || c == '\'';
+        if is_word_char {
+            word_start.get_or_insert(i);
+            continue;
+        }
+        if let Some(start) = word_start.take() {
+            flush(&mut tokens, start, i);
+        }
+        if c == ':' || c == ',' || c == ';' || c == '.' {
+            let norm = if c == ':' { ":" } else { "," };
+            tokens.push(Token {
+                norm: norm.to_string(),
+                start: i,
+                end: i + c.len_utf8(),
+            });
+        }
+    }
+    if let Some(start) = word_start {
+        flush(&mut tokens, start, text.len());
+    }
+
+    tokens
+}
+
+/// Words that separate chained commands
+const CONJUNCTIONS: &[&str] = &[
+    ",", "y", "e", "and", "luego", "despues", "then", "tambien", "also",
+];
+
+/// Politeness and wake words skipped around commands
+const FILLERS: &[&str] = &[
+    "por favor",
+    "porfa",
+    "please",
+    "oye birdie",
+    "hey birdie",
+    "ok birdie",
+    "birdie",
+    "oye",
+    "hey",
+];
+
+/// Number words in Spanish and English
+fn number_word(word: &str) -> Option<u32> {
+    Some(match word {
+        "un" | "una" | "uno" | "a" | "an" | "one" => 1,
+        "dos" | "two" => 2,
+        "tres" | "three" => 3,
+        "cuatro" | "four" => 4,
+        "cinco" | "five" => 5,
+        "seis" | "six" => 6,
+        "siete" | "seven" => 7,
+        "ocho" | "eight" => 8,
+        "nueve" | "nine" => 9,
+        "diez" | "ten" => 10,
+        "once" | "eleven" => 11,
+        "doce" | "twelve" => 12,
+        "quince" | "fifteen" => 15,
+        "veinte" | "twenty" => 20,
+        "veinticinco" => 25,
+        "treinta" | "thirty" => 30,
+        "cuarenta" | "forty" => 40,
+        "cincuenta" | "fifty" => 50,
+        "sesenta" | "sixty" => 60,
+        "noventa" | "ninety" => 90,
+        _ => word.parse().ok()?,
+    })
+}
+
+/// A pattern element of a command rule
+#[derive(Debug, Clone)]
+enum Pat {
+    /// One of several keyword phrases
+    Words(&'static [&'static str]),
+    /// A sequence that may be absent
+    Opt(Vec<Pat>),
+    /// A known app name
+    App,
+    /// A duration, captured in minutes
+    Duration,
+    /// A person's name of up to `MAX_PERSON_WORDS` words
+    Person,
+    /// A person or app, for rules
+    Target,
+    /// The rest of the transcript, verbatim
+    Rest,
+}
+
+/// Values captured while matching a rule
+#[derive(Debug, Clone, Default)]
+struct Captures {
+    app: Option<String>,
+    minutes: Option<u32>,
+    person: Option<String>,
+    target: Option<RuleTarget>,
+    rest: Option<String>,
+}
+
+struct Rule {
+    pattern: Vec<Pat>,
+    build: fn(Captures) -> Option<Action>,
+}
+
+const REPLY_VERBS: &[&str] = &[
+    "responde",
+    "responder",
+    "respondele",
+    "contesta",
+    "contestale",
+    "reply",
+    "answer",
+    "respond",
+];
+
+fn grammar() -> Vec<Rule> {
+    use Pat::*;
+
+    vec![
+        // Replies come first: they swallow the rest of the transcript
+        Rule {
+            // The name is matched like a person's, up to a few words
+            pattern: vec![
+                Words(&[
+                    "respuesta rapida",
+                    "usa la respuesta rapida",
+                    "manda la respuesta rapida",
+                    "quick reply",
+                    "send quick reply",
+                    "send the quick reply",
+                ]),
+                Opt(vec![Person]),
+                Opt(vec![Words(&["en", "in"]), Duration]),
+            ],
+            build: |c| {
+                Some(Action::Reply(ReplyCommand::Quick {
+                    name: c.person,
+                    minutes: c.minutes,
+                }))
+            },
+        },
+        Rule {
+            pattern: vec![
+                Words(REPLY_VERBS),
+                Opt(vec![Words(&["a", "to"]), Person]),
+                Words(&[":", "que", "that", "saying", "diciendo", "with"]),
+                Rest,
+            ],
+            build: |c| reply(c.person, c.rest?),
+        },
+        Rule {
+            pattern: vec![
+                Words(&["dile a", "dile", "tell"]),
+                Person,
+                Words(&["que", "that", ":"]),
+                Rest,
+            ],
+            build: |c| reply(c.person, c.rest?),
+        },
+        Rule {
+            pattern: vec![Words(REPLY_VERBS), Rest],
+            build: |c| reply(None, c.rest?),
+        },
+        Rule {
+            pattern: vec![
+                Opt(vec![Words(&["si", "yes"]), Opt(vec![Words(&[","])])]),
+                Words(&[
+                    "envialo",
+                    "confirma el envio",
+                    "confirma",
+                    "confirmo",
+                    "send it",
+                    "confirm",
+                ]),
+            ],
+            build: |_| Some(Action::Reply(ReplyCommand::Confirm)),
+        },
+        Rule {
+            pattern: vec![
+                Opt(vec![Words(&["no"]), Opt(vec![Words(&[","])])]),
+                Words(&[
+                    "no lo envies",
+                    "cancela el envio",
+                    "cancela",
+                    "cancelar",
+                    "descartalo",
+                    "do not send it",
+                    "cancel",
+                    "discard it",
+                ]),
+            ],
+            build: |_| Some(Action::Reply(ReplyCommand::Cancel)),
+        },
+        // Speech queue
+        Rule {
+            pattern: vec![Words(&[
+                "siguiente",
+                "la siguiente",
+                "el siguiente",
+                "pasa",
+                "salta",
+                "next",
+                "next one",
+                "skip",
+            ])],
+            build: |_| Some(Action::Queue(QueueCommand::Next)),
+        },
+        Rule {
+            pattern: vec![Words(&[
+                "repite",
+                "repitelo",
+                "repetir",
+                "otra vez",
+                "repeat",
+                "repeat that",
+                "say that again",
+                "again",
+            ])],
+            build: |_| Some(Action::Queue(QueueCommand::Repeat)),
+        },
+        Rule {
+            pattern: vec![Words(&[
+                "para",
+                "parar",
+                "detente",
+                "callate",
+                "stop",
+                "stop reading",
+                "shut up",
+            ])],
+            build: |_| Some(Action::Queue(QueueCommand::Stop)),
+        },
+        Rule {
+            pattern: vec![Words(&["pausa", "pausar", "pause"])],
+            build: |_| Some(Action::Queue(QueueCommand::Pause)),
+        },
+        Rule {
+            pattern: vec![Words(&[
+                "continua", "sigue", "reanuda", "resume", "continue", "go on",
+            ])],
+            build: |_| Some(Action::Queue(QueueCommand::Resume)),
+        },
+        Rule {
+            pattern: vec![
+                Words(&[
+                    "que dijo",
+                    "que ha dicho",
+                    "que escribio",
+                    "que me dijo",
+                    "what did",
+                    "what has",
+                ]),
+                Person,
+                Opt(vec![Words(&["say", "said", "write", "written", "wrote"])]),
+                Opt(vec![Words(&["en", "in", "on"]), App]),
+            ],
+            build: |c| {
+                Some(Action::Queue(QueueCommand::ReadLastFrom {
+                    sender: c.person?,
+                    app: c.app,
+                }))
+            },
+        },
+        Rule {
+            pattern: vec![
+                Words(&["lee", "leeme", "read", "read me"]),
+                Words(&[
+                    "lo ultimo de",
+                    "el ultimo mensaje de",
+                    "los mensajes de",
+                    "el mensaje de",
+                    "the last message from",
+                    "the latest from",
+                    "messages from",
+                ]),
+                Person,
+                Opt(vec![Words(&["en", "in", "on"]), App]),
+            ],
+            build: |c| {
+                Some(Action::Queue(QueueCommand::ReadLastFrom {
+                    sender: c.person?,
+                    app: c.app,
+                }))
+            },
+        },
+        Rule {
+            pattern: vec![Words(&[
+                "marca todo como leido",
+                "marcar todo como leido",
+                "marca todos como leidos",
+                "mark all as read",
+                "mark everything as read",
+                "mark all read",
+            ])],
+            build: |_| Some(Action::Queue(QueueCommand::MarkAllRead { app: None })),
+        },
+        Rule {
+            pattern: vec![
+                Words(&["marca", "marcar", "mark"]),
+                Opt(vec![Words(&[
+                    "todo de",
+                    "todo lo de",
+                    "everything from",
+                    "all from",
+                ])]),
+                App,
+                Words(&["como leido", "como leidos", "as read", "read"]),
+            ],
+            build: |c| Some(Action::Queue(QueueCommand::MarkAllRead { app: c.app })),
+        },
+        // Do not disturb
+        Rule {
+            pattern: vec![Words(&[
+                "desactiva no molestar",
+                "desactiva el modo no molestar",
+                "quita el no molestar",
+                "quita no molestar",
+                "turn off do not disturb",
+                "disable do not disturb",
+            ])],
+            build: |_| Some(Action::Dnd(DndCommand::Disable)),
+        },
+        Rule {
+            pattern: vec![
+                Words(&[
+                    "activa el modo no molestar",
+                    "activa no molestar",
+                    "modo no molestar",
+                    "no molestar",
+                    "silencia todo",
+                    "silencio",
+                    "turn on do not disturb",
+                    "enable do not disturb",
+                    "do not disturb",
+                    "mute everything",
+                    "mute all",
+                ]),
+                Opt(vec![Duration]),
+            ],
+            build: |c| Some(Action::Dnd(DndCommand::Enable { minutes: c.minutes })),
+        },
+        Rule {
+            pattern: vec![
+                Words(&[
+                    "activa el sonido de",
+                    "quita el silencio de",
+                    "desilencia",
+                    "reactiva",
+                    "unmute",
+                ]),
+                App,
+            ],
+            build: |c| Some(Action::Dnd(DndCommand::UnmuteApp { app: c.app? })),
+        },
+        Rule {
+            pattern: vec![
+                Words(&["silencia", "silenciar", "mutea", "calla", "mute"]),
+                Opt(vec![Words(&["a"])]),
+                App,
+                Opt(vec![Duration]),
+            ],
+            build: |c| {
+                Some(Action::Dnd(DndCommand::MuteApp {
+                    app: c.app?,
+                    minutes: c.minutes,
+                }))
+            },
+        },
+        // Rules
+        Rule {
+            pattern: vec![
+                Words(&["ignora a", "ignora", "ignorar a", "ignore"]),
+                Target,
+            ],
+            build: |c| Some(Action::Rules(RuleCommand::Ignore { target: c.target? })),
+        },
+        Rule {
+            pattern: vec![
+                Words(&[
+                    "prioriza a",
+                    "prioriza",
+                    "avisame siempre de",
+                    "prioritize",
+                    "always notify me about",
+                ]),
+                Target,
+            ],
+            build: |c| Some(Action::Rules(RuleCommand::Prioritize { target: c.target? })),
+        },
+    ]
+}
+
+fn reply(recipient: Option<String>, text: String) -> Option<Action> {
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return None;
+    }
+    Some(Action::Reply(ReplyCommand::Send { recipient, text }))
+}
+
+/// Parser with the grammar and the app names it knows about
+pub struct VoiceCommandParser {
+    rules: Vec<Rule>,
+    /// Normalized alias words -> app name, longest aliases first
+    apps: Vec<(Vec<String>, String)>,
+}
+
+fn split_words(phrase: &str) -> Vec<String> {
+    phrase.split_whitespace().map(normalize).collect()
+}
+
+impl VoiceCommandParser {
+    /// Build a parser that recognizes the apps in `mapper`
+    pub fn new(mapper: &ContextMapper) -> Self {
+        let mut apps = Vec::new();
+        for task in mapper.get_enabled_contexts() {
+            apps.push((split_words(&task.app_name), task.app_name.clone()));
+            for alias in &task.search_targets {
+                apps.push((split_words(alias), task.app_name.clone()));
+            }
+        }
+        apps.sort_by_key(|(words, _)| std::cmp::Reverse(words.len()));
+
+        Self {
+            rules: grammar(),
+            apps,
+        }
+    }
+
+    /// Interpret a transcript, rejecting it when the recognizer was unsure
+    pub fn interpret(&self, text: &str, confidence: Option<f32>) -> Interpretation {
+        if confidence.is_some_and(|c| c < MIN_CONFIDENCE) {
+            return Interpretation {
+                actions: Vec::new(),
+                unrecognized: vec![text.trim().to_string()],
+                low_confidence: true,
+            };
+        }
+
+        let tokens = tokenize(text);
+        let mut interpretation = Interpretation::default();
+        let mut pos = 0;
+
+        while pos < tokens.len() {
+            loop {
+                let next = self.skip(&tokens, self.skip(&tokens, pos, CONJUNCTIONS), FILLERS);
+                if next == pos {
+                    break;
+                }
+                pos = next;
+            }
+            if pos >= tokens.len() {
+                break;
+            }
+
+            match self.match_command(text, &tokens, pos) {
+                Some((action, end)) => {
+                    interpretation.actions.push(action);
+                    pos = end;
+                }
+                None => {
+                    // Skip to the next conjunction and report what was skipped
+                    let end = (pos + 1..tokens.len())
+                        .find(|&i| CONJUNCTIONS.contains(&tokens[i].norm.as_str()))
+                        .unwrap_or(tokens.len());
+                    let fragment = text[tokens[pos].start..tokens[end - 1].end].trim();
+                    if !fragment.is_empty() {
+                        interpretation.unrecognized.push(fragment.to_string());
+                    }
+                    pos = end;
+                }
+            }
+        }
+
+        interpretation
+    }
+
+    /// Skip any of `phrases` at `pos`
+    fn skip(&self, tokens: &[Token], mut pos: usize, phrases: &[&str]) -> usize {
+        loop {
+            match phrases.iter().find_map(|p| match_phrase(tokens, pos, p)) {
+                Some(end) => pos = end,
+                None => return pos,
+            }
+        }
+    }
+
+    /// Match the first rule that covers a whole command starting at `pos`
+    fn match_command(&self, text: &str, tokens: &[Token], pos: usize) -> Option<(Action, usize)> {
+        for rule in &self.rules {
+            let mut found = None;
+            self.match_seq(
+                text,
+                tokens,
+                &rule.pattern,
+                pos,
+                Captures::default(),
+                &mut |end, caps| {
+                    // A command must end at a boundary, optionally followed by "please"
+                    let end = self.skip(tokens, end, FILLERS);
+                    let at_boundary =
+                        end == tokens.len() || CONJUNCTIONS.contains(&tokens[end].norm.as_str());
+                    if !at_boundary {
+                        return false;
+                    }
+                    match (rule.build)(caps) {
+                        Some(action) => {
+                            found = Some((action, end));
+                            true
+                        }
+                        None => false,
+                    }
+                },
+            );
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+
+    /// Backtracking matcher; `accept` returns true to stop searching
+    fn match_seq(
+        &self,
+        text: &str,
+        tokens: &[Token],
+        pattern: &[Pat],
+        pos: usize,
+        caps: Captures,
+        accept: &mut dyn FnMut(usize, Captures) -> bool,
+    ) -> bool {
+        let Some((first, rest)) = pattern.split_first() else {
+            return accept(pos, caps);
+        };
+
+        match first {
+            Pat::Words(phrases) => {
+                // Longer phrases first so "la siguiente" wins over "la"
+                let mut ends: Vec<usize> = phrases
+                    .iter()
+                    .filter_map(|p| match_phrase(tokens, pos, p))
+                    .collect();
+                ends.sort_unstable_by(|a, b| b.cmp(a));
+                ends.dedup();
+                ends.into_iter()
+                    .any(|end| self.match_seq(text, tokens, rest, end, caps.clone(), accept))
+            }
+            Pat::Opt(inner) => {
+                let mut combined = inner.clone();
+                combined.extend_from_slice(rest);
+                self.match_seq(text, tokens, &combined, pos, caps.clone(), accept)
+                    || self.match_seq(text, tokens, rest, pos, caps, accept)
+            }
+            Pat::App => self.match_apps(tokens, pos).into_iter().any(|(app, end)| {
+                let mut caps = caps.clone();
+                caps.app = Some(app);
+                self.match_seq(text, tokens, rest, end, caps, accept)
+            }),
+            Pat::Duration => match parse_duration(tokens, pos) {
+                Some((minutes, end)) => {
+                    let mut caps = caps;
+                    caps.minutes = Some(minutes);
+                    self.match_seq(text, tokens, rest, end, caps, accept)
+                }
+                None => false,
+            },
+            Pat::Person => (1..=MAX_PERSON_WORDS).any(|len| {
+                let end = pos + len;
+                if end > tokens.len() || !tokens[pos..end].iter().all(is_name_word) {
+                    return false;
+                }
+                let mut caps = caps.clone();
+                caps.person = Some(text[tokens[pos].start..tokens[end - 1].end].to_string());
+                self.match_seq(text, tokens, rest, end, caps, accept)
+            }),
+            Pat::Target => {
+                let apps = self.match_apps(tokens, pos).into_iter().any(|(app, end)| {
+                    let mut caps = caps.clone();
+                    caps.target = Some(RuleTarget::App(app));
+                    self.match_seq(text, tokens, rest, end, caps, accept)
+                });
+                apps || (1..=MAX_PERSON_WORDS).any(|len| {
+                    let end = pos + len;
+                    if end > tokens.len() || !tokens[pos..end].iter().all(is_name_word) {
+                        return false;
+                    }
+                    let mut caps = caps.clone();
+                    caps.target = Some(RuleTarget::Sender(
+                        text[tokens[pos].start..tokens[end - 1].end].to_string(),
+                    ));
+                    self.match_seq(text, tokens, rest, end, caps, accept)
+                })
+            }
+            Pat::Rest => {
+                if pos >= tokens.len() {
+                    return false;
+                }
+                let mut caps = caps;
+                caps.rest = Some(text[tokens[pos].start..].trim().to_string());
+                self.match_seq(text, tokens, rest, tokens.len(), caps, accept)
+            }
+        }
+    }
+
+    /// App names starting at `pos`, longest first
+    fn match_apps(&self, tokens: &[Token], pos: usize) -> Vec<(String, usize)> {
+        self.apps
+            .iter()
+            .filter(|(words, _)| {
+                pos + words.len() <= tokens.len()
+                    && words.iter().zip(&tokens[pos..]).all(|(w, t)| *w == t.norm)
+            })
+            .map(|(words, app)| (app.clone(), pos + words.len()))
+            .collect()
+    }
+}
+
+fn is_name_word(token: &Token) -> bool {
+    token.norm != ":" && token.norm != "," && !CONJUNCTIONS.contains(&token.norm.as_str())
+}
+
+/// Match a space-separated keyword phrase at `pos`, returning the end position
+fn match_phrase(tokens: &[Token], pos: usize, phrase: &str) -> Option<usize> {
+    let mut end = pos;
+    for word in phrase.split(' ') {
+        if tokens.get(end)?.norm != word {
+            return None;
+        }
+        end += 1;
+    }
+    Some(end)
+}
+
+/// Parse "una hora", "30 minutos", "por media hora", "for two and a half hours"...
+fn parse_duration(tokens: &[Token], pos: usize) -> Option<(u32, usize)> {
+    let word = |i: usize| tokens.get(i).map(|t| t.norm.as_str());
+    let mut i = pos;
+    if matches!(word(i), Some("por" | "durante" | "for" | "during")) {
+        i += 1;
+    }
+
+    // "media hora", "half an hour", "half hour"
+    if word(i) == Some("media") && matches!(word(i + 1), Some("hora")) {
+        return Some((30, i + 2));
+    }
+    if word(i) == Some("half") {
+        let j = if matches!(word(i + 1), Some("an" | "a")) {
+            i + 2
+        } else {
+            i + 1
+        };
+        if word(j) == Some("hour") {
+            return Some((30, j + 1));
+        }
+    }
+
+    // Number, possibly compound: "veinte y cinco", "twenty five"
+    let mut amount = number_word(word(i)?)?;
+    i += 1;
+    if amount >= 20 && amount % 10 == 0 {
+        let j = if word(i) == Some("y") { i + 1 } else { i };
+        if let Some(units) = word(j)
+            .and_then(number_word)
+            .filter(|n| (1..10).contains(n))
+        {
+            amount += units;
+            i = j + 1;
+        }
+    }
+
+    let mut half = false;
+    if matches!((word(i), word(i + 1)), (Some("and"), Some("a"))) && word(i + 2) == Some("half") {
+        half = true;
+        i += 3;
+    }
+
+    let minutes = match word(i)? {
+        "minuto" | "minutos" | "min" | "mins" | "minute" | "minutes" => amount,
+        "hora" | "horas" | "h" | "hour" | "hours" => amount * 60 + if half { 30 } else { 0 },
+        "dia" | "dias" | "day" | "days" => amount * 24 * 60,
+        _ => return None,
+    };
+    i += 1;
+
+    // "una hora y media"
+    if minutes % 60 == 0 && matches!((word(i), word(i + 1)), (Some("y"), Some("media"))) {
+        return Some((minutes + 30, i + 2));
+    }
+
+    Some((minutes, i))
+}
+
+/// Post-process the dictated text of replies, as dictation is; the rest of
+/// the transcript is left for command matching
+pub fn postprocess_dictation(
+    interpretation: &mut Interpretation,
+    language_code: &str,
+    replacements: &[Replacement],
+) {
+    for action in &mut interpretation.actions {
+        if let Action::Reply(ReplyCommand::Send { text, .. }) = action {
+            *text = postprocess::process(text, language_code, replacements);
+        }
+    }
+}
+
+/// Carry out each action, and emit it as a `voice-action` event
+///
+/// Do-not-disturb, rules and replies are handled here, as the backend holds
+/// them; so is stopping or skipping what the speech queue is reading. The
+/// notification list is the frontend's, which acts on the queue commands in
+/// the event. `language_code` is the transcript's, in which a reply's
+/// confirmation is asked for.
+pub fn execute(app_handle: &AppHandle, interpretation: &Interpretation, language_code: &str) {
+    for action in &interpretation.actions {
+        let result = match action {
+            Action::Queue(command) => {
+                run_queue(app_handle, command);
+                Ok(())
+            }
+            Action::Dnd(command) => app_handle
+                .state::<NotificationRules>()
+                .do_not_disturb(command, chrono::Utc::now().timestamp()),
+            Action::Rules(command) => app_handle.state::<NotificationRules>().add(command),
+            Action::Reply(command) => {
+                run_reply(app_handle, command, language_code);
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            eprintln!("✗ Failed to carry out voice command: {}", e);
+        }
+        if let Err(e) = app_handle.emit("voice-action", action) {
+            eprintln!("✗ Error emitting voice action: {}", e);
+        }
+    }
+}
+
+/// The backend's part of a queue command
+fn run_queue(app_handle: &AppHandle, command: &QueueCommand) {
+    let tts = app_handle.state::<Tts>();
+    match command {
+        QueueCommand::Stop | QueueCommand::MarkAllRead { app: None } => {
+            app_handle.state::<SpeechQueue>().clear();
+            tts.player().stop();
+        }
+        // The speech queue moves on to its next announcement
+        QueueCommand::Next | QueueCommand::Pause => tts.player().stop(),
+        QueueCommand::Repeat
+        | QueueCommand::Resume
+        | QueueCommand::ReadLastFrom { .. }
+        | QueueCommand::MarkAllRead { app: Some(_) } => {}
+    }
+}
+
+fn run_reply(app_handle: &AppHandle, command: &ReplyCommand, language_code: &str) {
+    match command {
+        ReplyCommand::Send { recipient, text } => crate::reply::send_dictated_latest(
+            app_handle,
+            recipient.clone(),
+            text.clone(),
+            language_code.to_string(),
+        ),
+        ReplyCommand::Quick { name, minutes } => {
+            crate::reply::send_quick_latest(app_handle, name.clone(), *minutes)
+        }
+        ReplyCommand::Confirm => crate::reply::resolve_latest(app_handle, true),
+        ReplyCommand::Cancel => crate::reply::resolve_latest(app_handle, false),
+    }
+}
+
+/// Interpret a transcript and dispatch the resulting actions
+///
+/// `language_code` defaults to the hands-free language.
+#[tauri::command]
+pub fn run_voice_command(
+    app_handle: AppHandle,
+    text: String,
+    confidence: Option<f32>,
+    language_code: Option<String>,
+) -> Interpretation {
+    let language_code =
+        language_code.unwrap_or_else(|| app_handle.state::<HandsFree>().config().language_code);
+    let parser = VoiceCommandParser::new(&ContextMapper::new());
+    let interpretation = parser.interpret(&text, confidence);
+    execute(&app_handle, &interpretation, &language_code);
+    interpretation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Interpretation {
+        VoiceCommandParser::new(&ContextMapper::new()).interpret(text, None)
+    }
+
+    fn actions(text: &str) -> Vec<Action> {
+        let result = parse(text);
+        assert!(
+            result.unrecognized.is_empty(),
+            "unrecognized in {:?}: {:?}",
+            text,
+            result.unrecognized
+        );
+        result.actions
+    }
+
+    #[test]
+    fn test_queue_commands() {
+        assert_eq!(
+            actions("Siguiente"),
+            vec![Action::Queue(QueueCommand::Next)]
+        );
+        assert_eq!(actions("next"), vec![Action::Queue(QueueCommand::Next)]);
+        assert_eq!(
+            actions("repite, por favor"),
+            vec![Action::Queue(QueueCommand::Repeat)]
+        );
+        assert_eq!(
+            actions("say that again"),
+            vec![Action::Queue(QueueCommand::Repeat)]
+        );
+        assert_eq!(actions("Stop"), vec![Action::Queue(QueueCommand::Stop)]);
+    }
+
+    #[test]
+    fn test_mute_app_with_duration() {
+        assert_eq!(
+            actions("silencia Slack una hora"),
+            vec![Action::Dnd(DndCommand::MuteApp {
+                app: "Slack".to_string(),
+                minutes: Some(60)
+            })]
+        );
+        assert_eq!(
+            actions("mute Microsoft Teams for 30 minutes"),
+            vec![Action::Dnd(DndCommand::MuteApp {
+                app: "Microsoft Teams".to_string(),
+                minutes: Some(30)
+            })]
+        );
+        assert_eq!(
+            actions("silencia teams"),
+            vec![Action::Dnd(DndCommand::MuteApp {
+                app: "Microsoft Teams".to_string(),
+                minutes: None
+            })]
+        );
+    }
+
+    #[test]
+    fn test_durations() {
+        let minutes = |text: &str| parse_duration(&tokenize(text), 0).map(|(m, _)| m);
+        assert_eq!(minutes("media hora"), Some(30));
+        assert_eq!(minutes("por dos horas"), Some(120));
+        assert_eq!(minutes("una hora y media"), Some(90));
+        assert_eq!(minutes("veinte y cinco minutos"), Some(25));
+        assert_eq!(minutes("twenty five minutes"), Some(25));
+        assert_eq!(minutes("half an hour"), Some(30));
+        assert_eq!(minutes("two and a half hours"), Some(150));
+        assert_eq!(minutes("45 min"), Some(45));
+        assert_eq!(minutes("cinco"), None);
+    }
+
+    #[test]
+    fn test_do_not_disturb() {
+        assert_eq!(
+            actions("activa no molestar durante dos horas"),
+            vec![Action::Dnd(DndCommand::Enable { minutes: Some(120) })]
+        );
+        assert_eq!(
+            actions("turn off do not disturb"),
+            vec![Action::Dnd(DndCommand::Disable)]
+        );
+    }
+
+    #[test]
+    fn test_reply_with_colon_keeps_original_text() {
+        assert_eq!(
+            actions("Responde: voy en cinco minutos"),
+            vec![Action::Reply(ReplyCommand::Send {
+                recipient: None,
+                text: "voy en cinco minutos".to_string()
+            })]
+        );
+    }
+
+    #[test]
+    fn test_reply_to_person() {
+        assert_eq!(
+            actions("Dile a Ximena que el deploy de Kubernetes está listo"),
+            vec![Action::Reply(ReplyCommand::Send {
+                recipient: Some("Ximena".to_string()),
+                text: "el deploy de Kubernetes está listo".to_string()
+            })]
+        );
+        assert_eq!(
+            actions("reply to Ana Pérez: on my way, see you"),
+            vec![Action::Reply(ReplyCommand::Send {
+                recipient: Some("Ana Pérez".to_string()),
+                text: "on my way, see you".to_string()
+            })]
+        );
+        assert_eq!(
+            actions("contesta que sí y que voy"),
+            vec![Action::Reply(ReplyCommand::Send {
+                recipient: None,
+                text: "sí y que voy".to_string()
+            })]
+        );
+    }
+
+    #[test]
+    fn test_only_dictated_replies_are_postprocessed() {
+        let mut interpretation = parse("siguiente y dile a Ana que llego tarde coma lo siento");
+        postprocess_dictation(&mut interpretation, "es-ES", &[]);
+        assert_eq!(
+            interpretation.actions,
+            vec![
+                Action::Queue(QueueCommand::Next),
+                Action::Reply(ReplyCommand::Send {
+                    recipient: Some("Ana".to_string()),
+                    text: "Llego tarde, lo siento".to_string()
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_quick_reply() {
+        assert_eq!(
+            actions("respuesta rápida reunión"),
+            vec![Action::Reply(ReplyCommand::Quick {
+                name: Some("reunión".to_string()),
+                minutes: None
+            })]
+        );
+        assert_eq!(
+            actions("Respuesta rápida voy en diez minutos"),
+            vec![Action::Reply(ReplyCommand::Quick {
+                name: Some("voy".to_string()),
+                minutes: Some(10)
+            })]
+        );
+        assert_eq!(
+            actions("quick reply"),
+            vec![Action::Reply(ReplyCommand::Quick {
+                name: None,
+                minutes: None
+            })]
+        );
+    }
+
+    #[test]
+    fn test_reply_confirmation() {
+        assert_eq!(
+            actions("Sí, envíalo"),
+            vec![Action::Reply(ReplyCommand::Confirm)]
+        );
+        assert_eq!(
+            actions("no lo envíes"),
+            vec![Action::Reply(ReplyCommand::Cancel)]
+        );
+        assert_eq!(
+            actions("No, cancela"),
+            vec![Action::Reply(ReplyCommand::Cancel)]
+        );
+        assert_eq!(actions("send it please"), vec![Action::Reply(ReplyCommand::Confirm)]);
+    }
+
+    #[test]
+    fn test_what_did_someone_say() {
+        assert_eq!(
+            actions("¿Qué dijo Ana?"),
+            vec![Action::Queue(QueueCommand::ReadLastFrom {
+                sender: "Ana".to_string(),
+                app: None
+            })]
+        );
+        assert_eq!(
+            actions("what did Ana say on Slack"),
+            vec![Action::Queue(QueueCommand::ReadLastFrom {
+                sender: "Ana".to_string(),
+                app: Some("Slack".to_string())
+            })]
+        );
+    }
+
+    #[test]
+    fn test_mark_read() {
+        assert_eq!(
+            actions("marca todo como leído"),
+            vec![Action::Queue(QueueCommand::MarkAllRead { app: None })]
+        );
+        assert_eq!(
+            actions("mark Discord as read"),
+            vec![Action::Queue(QueueCommand::MarkAllRead {
+                app: Some("Discord".to_string())
+            })]
+        );
+    }
+
+    #[test]
+    fn test_rules() {
+        assert_eq!(
+            actions("ignora a Pedro"),
+            vec![Action::Rules(RuleCommand::Ignore {
+                target: RuleTarget::Sender("Pedro".to_string())
+            })]
+        );
+        assert_eq!(
+            actions("prioritize WhatsApp"),
+            vec![Action::Rules(RuleCommand::Prioritize {
+                target: RuleTarget::App("WhatsApp".to_string())
+            })]
+        );
+    }
+
+    #[test]
+    fn test_chained_commands() {
+        assert_eq!(
+            actions("oye birdie, siguiente y luego silencia Slack media hora"),
+            vec![
+                Action::Queue(QueueCommand::Next),
+                Action::Dnd(DndCommand::MuteApp {
+                    app: "Slack".to_string(),
+                    minutes: Some(30)
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unrecognized_fragments_are_reported() {
+        let result = parse("siguiente y haz un café y repite");
+        assert_eq!(
+            result.actions,
+            vec![
+                Action::Queue(QueueCommand::Next),
+                Action::Queue(QueueCommand::Repeat)
+            ]
+        );
+        assert_eq!(result.unrecognized, vec!["haz un café"]);
+    }
+
+    #[test]
+    fn test_low_confidence_is_rejected() {
+        let parser = VoiceCommandParser::new(&ContextMapper::new());
+        let result = parser.interpret("marca todo como leído", Some(0.3));
+        assert!(result.low_confidence);
+        assert!(result.actions.is_empty());
+
+        let result = parser.interpret("marca todo como leído", Some(0.9));
+        assert_eq!(result.actions.len(), 1);
+    }
+
+    #[test]
+    fn test_actions_serialize_by_subsystem() {
+        let json = serde_json::to_value(Action::Dnd(DndCommand::MuteApp {
+            app: "Slack".to_string(),
+            minutes: Some(60),
+        }))
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "subsystem": "dnd",
+                "command": {"type": "mute_app", "app": "Slack", "minutes": 60}
+            })
+        );
+    }
+
+    /// Where each action is carried out; adding a variant fails to compile
+    /// here until it is given a handler
+    fn handled_by_frontend(action: &Action) -> bool {
+        match action {
+            Action::Queue(command) => match command {
+                QueueCommand::Next
+                | QueueCommand::Repeat
+                | QueueCommand::Stop
+                | QueueCommand::Pause
+                | QueueCommand::Resume
+                | QueueCommand::ReadLastFrom { .. }
+                | QueueCommand::MarkAllRead { .. } => true,
+            },
+            Action::Dnd(_) | Action::Rules(_) | Action::Reply(_) => false,
+        }
+    }
+
+    #[test]
+    fn test_every_action_has_a_handler() {
+        let app = Some("Slack".to_string());
+        let target = RuleTarget::Sender("Pablo".to_string());
+        let actions = vec![
+            Action::Queue(QueueCommand::Next),
+            Action::Queue(QueueCommand::Repeat),
+            Action::Queue(QueueCommand::Stop),
+            Action::Queue(QueueCommand::Pause),
+            Action::Queue(QueueCommand::Resume),
+            Action::Queue(QueueCommand::ReadLastFrom {
+                sender: "Pablo".to_string(),
+                app: app.clone(),
+            }),
+            Action::Queue(QueueCommand::MarkAllRead { app: app.clone() }),
+            Action::Dnd(DndCommand::Enable { minutes: Some(30) }),
+            Action::Dnd(DndCommand::Disable),
+            Action::Dnd(DndCommand::MuteApp {
+                app: "Slack".to_string(),
+                minutes: None,
+            }),
+            Action::Dnd(DndCommand::UnmuteApp {
+                app: "Slack".to_string(),
+            }),
+            Action::Rules(RuleCommand::Ignore {
+                target: target.clone(),
+            }),
+            Action::Rules(RuleCommand::Prioritize { target }),
+        ];
+
+        // The frontend owns the notification list
+        let hook = include_str!("../../src/hooks/useNotifications.ts");
+        assert!(hook.contains("listen<VoiceAction>('voice-action'"));
+        let dir = std::env::temp_dir().join(format!("birdie-voice-{}", std::process::id()));
+        let rules = NotificationRules::new(&dir);
+        for action in &actions {
+            if handled_by_frontend(action) {
+                let command = serde_json::to_value(action).unwrap()["command"]["type"].clone();
+                let case = format!("case '{}':", command.as_str().unwrap());
+                assert!(hook.contains(&case), "no {} in useNotifications", case);
+                continue;
+            }
+            // The backend keeps do-not-disturb and rules
+            let before = rules.settings();
+            match action {
+                Action::Dnd(command) => rules.do_not_disturb(command, 0).unwrap(),
+                Action::Rules(command) => rules.add(command).unwrap(),
+                _ => unreachable!(),
+            }
+            assert_ne!(rules.settings(), before, "{:?} changed nothing", action);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
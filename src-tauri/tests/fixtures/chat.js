@@ -0,0 +1,53 @@
+// Behaviour shared by the fixture pages. Each page names its parts in data
+// attributes on <body> and gives the markup of an incoming message in
+// <template id="message">. Tests drive it through `window.fixture`:
+// `receive` adds a message the way the app renders one, and whatever the
+// composer holds when it is sent ends up in `sent`.
+(function () {
+  const parts = document.body.dataset;
+  const composer = () => document.querySelector(parts.composer);
+  const contents = el => (typeof el.value === 'string' ? el.value : el.innerText).trim();
+
+  const fixture = {
+    conversation: null,
+    sent: [],
+    receive(sender, message) {
+      const rendered = document.getElementById('message').content.cloneNode(true);
+      rendered.querySelector('[data-fixture="sender"]').textContent = sender;
+      rendered.querySelector('[data-fixture="text"]').textContent = message;
+      document.querySelector(parts.messages).appendChild(rendered);
+    },
+  };
+
+  const send = () => {
+    const el = composer();
+    const message = contents(el);
+    if (!message) return;
+    fixture.sent.push({ conversation: fixture.conversation, message });
+    if (typeof el.value === 'string') {
+      el.value = '';
+    } else {
+      el.textContent = '';
+    }
+  };
+
+  document.addEventListener('keydown', event => {
+    if (event.key === 'Enter' && !event.shiftKey && composer().contains(event.target)) {
+      event.preventDefault();
+      send();
+    }
+  });
+  if (parts.send) {
+    document.querySelector(parts.send).addEventListener('click', send);
+  }
+  if (parts.conversations) {
+    document.querySelectorAll(parts.conversations).forEach(entry => {
+      entry.addEventListener('click', event => {
+        event.preventDefault();
+        fixture.conversation = entry.textContent.trim();
+      });
+    });
+  }
+
+  window.fixture = fixture;
+})();
@@ -1,23 +1,82 @@
-import { useState } from 'react';
+import { useState, useEffect } from 'react';
+import { invoke } from '@tauri-apps/api/core';
 import { useNotifications } from './hooks/useNotifications';
 import CurrentNotification from './components/CurrentNotification';
 import NotificationList from './components/NotificationList';
 import PlaybackControls from './components/PlaybackControls';
 import VoiceRecorder from './components/VoiceRecorder';
 import CDPPanel from './components/CDPPanel';
+import type { CredentialStatus, KeyTestResult } from './types/credentials';
 import './App.css';
 
+const LEGACY_API_KEY_STORAGE = 'google-cloud-api-key';
+
 function App() {
   const { queue, currentNotification, isPlaying, playNext, stop, skip } = useNotifications();
-  const [apiKey, setApiKey] = useState<string>(localStorage.getItem('google-cloud-api-key') || '');
+  const [credentialStatus, setCredentialStatus] = useState<CredentialStatus | null>(null);
   const [showSettings, setShowSettings] = useState(false);
   const [showCDP, setShowCDP] = useState(false);
-  const [tempApiKey, setTempApiKey] = useState(apiKey);
+  const [tempApiKey, setTempApiKey] = useState('');
+  const [keyMessage, setKeyMessage] = useState<string | null>(null);
+
+  // Load the key status, moving keys saved by older versions out of localStorage
+  useEffect(() => {
+    const loadStatus = async () => {
+      try {
+        const legacyKey = localStorage.getItem(LEGACY_API_KEY_STORAGE);
+        if (legacyKey) {
+          const status = await invoke<CredentialStatus>('set_api_key', {
+            provider: 'google',
+            apiKey: legacyKey,
+          });
+          localStorage.removeItem(LEGACY_API_KEY_STORAGE);
+          setCredentialStatus(status);
+          return;
+        }
+        setCredentialStatus(
+          await invoke<CredentialStatus>('get_credential_status', { provider: 'google' })
+        );
+      } catch (error) {
+        console.error('Failed to load API key status:', error);
+      }
+    };
+
+    loadStatus();
+  }, []);
+
+  const handleSaveSettings = async () => {
+    try {
+      if (tempApiKey.trim()) {
+        setCredentialStatus(
+          await invoke<CredentialStatus>('set_api_key', { provider: 'google', apiKey: tempApiKey })
+        );
+      }
+      setTempApiKey('');
+      setKeyMessage(null);
+      setShowSettings(false);
+    } catch (error) {
+      setKeyMessage(`❌ ${error}`);
+    }
+  };
+
+  const handleTestKey = async () => {
+    try {
+      const result = await invoke<KeyTestResult>('test_api_key', { provider: 'google' });
+      setKeyMessage(`${result.valid ? '✓' : '❌'} ${result.message}`);
+    } catch (error) {
+      setKeyMessage(`❌ ${error}`);
+    }
+  };
 
-  const handleSaveSettings = () => {
-    localStorage.setItem('google-cloud-api-key', tempApiKey);
-    setApiKey(tempApiKey);
-    setShowSettings(false);
+  const handleClearKey = async () => {
+    try {
+      setCredentialStatus(
+        await invoke<CredentialStatus>('clear_api_key', { provider: 'google' })
+      );
+      setKeyMessage(null);
+    } catch (error) {
+      setKeyMessage(`❌ ${error}`);
+    }
   };
 
   return (
@@ -42,7 +101,8 @@ function App() {
               className="settings-btn"
               onClick={() => {
                 setShowSettings(!showSettings);
-                setTempApiKey(apiKey);
+                setTempApiKey('');
+                setKeyMessage(null);
               }}
               title="Configuración"
             >
@@ -65,8 +125,21 @@ function App() {
                 type="password"
                 value={tempApiKey}
                 onChange={(e) => setTempApiKey(e.target.value)}
-                placeholder="Ingrese su API key..."
+                placeholder={
+                  credentialStatus?.configured
+                    ? 'API key guardada (ingrese una nueva para reemplazarla)'
+                    : 'Ingrese su API key...'
+                }
               />
+              {credentialStatus?.configured && (
+                <p className="settings-hint">
+                  🔒 Guardada en{' '}
+                  {credentialStatus.backend === 'secret_service'
+                    ? 'el llavero del sistema'
+                    : 'un archivo cifrado'}
+                </p>
+              )}
+              {keyMessage && <p className="settings-hint">{keyMessage}</p>}
               <p className="settings-hint">
                 🔗 Obtenga su API key en{' '}
                 <a
@@ -82,11 +155,21 @@ function App() {
               <button className="btn-primary" onClick={handleSaveSettings}>
                 Guardar
               </button>
+              {credentialStatus?.configured && (
+                <>
+                  <button className="btn-secondary" onClick={handleTestKey}>
+                    Probar
+                  </button>
+                  <button className="btn-secondary" onClick={handleClearKey}>
+                    Borrar
+                  </button>
+                </>
+              )}
               <button
                 className="btn-secondary"
                 onClick={() => {
                   setShowSettings(false);
-                  setTempApiKey(apiKey);
+                  setTempApiKey('');
                 }}
               >
                 Cancelar
@@ -111,7 +194,10 @@ function App() {
       </section>
 
       <section className="voice-recorder-section">
-        <VoiceRecorder apiKey={apiKey} languageCode="es-ES" />
+        <VoiceRecorder
+          apiKeyConfigured={credentialStatus?.configured ?? false}
+          languageCode="es-ES"
+        />
       </section>
 
       <section className="queue-section">
@@ -6,14 +6,17 @@ export default function CDPPanel() {
     connected,
     tabs,
     error,
-    errorHelpUrl,
+    diagnostics,
     monitoring,
     monitoringStatus,
     messages,
+    selectorWarnings,
     connect,
+    launchBrowser,
     refreshTabs,
     startMonitoring,
     stopMonitoring,
+    checkSelectors,
   } = useCDP();
 
   const [port, setPort] = useState<number>(9222);
@@ -69,10 +72,17 @@ export default function CDPPanel() {
               <p>
                 <strong>❌ Error:</strong> {error}
               </p>
-              {errorHelpUrl && (
-                <a href={errorHelpUrl} target="_blank" rel="noopener noreferrer">
-                  📖 Ver guía de configuración
-                </a>
+              {diagnostics && (
+                <ul className="cdp-hints">
+                  {diagnostics.hints.map((hint) => (
+                    <li key={hint}>{hint}</li>
+                  ))}
+                </ul>
+              )}
+              {diagnostics && diagnostics.browsers.length > 0 && (
+                <button className="btn-secondary" onClick={() => launchBrowser()}>
+                  🚀 Abrir {diagnostics.browsers[0].name}
+                </button>
               )}
             </div>
           )}
@@ -81,11 +91,11 @@ export default function CDPPanel() {
           {!connected && !error && (
             <div className="cdp-help">
               <p>
-                <strong>💡 Cómo habilitar depuración en Chrome:</strong>
+                <strong>💡 Birdie puede abrir un navegador con depuración activada:</strong>
               </p>
-              <code className="code-block">
-                chrome.exe --remote-debugging-port=9222
-              </code>
+              <button className="btn-secondary" onClick={() => launchBrowser()}>
+                🚀 Abrir navegador
+              </button>
             </div>
           )}
         </div>
@@ -121,8 +131,27 @@ export default function CDPPanel() {
               <button className="btn-secondary" onClick={refreshTabs}>
                 🔄 Actualizar
               </button>
+
+              <button className="btn-secondary" onClick={checkSelectors}>
+                🩺 Comprobar selectores
+              </button>
             </div>
 
+            {selectorWarnings.length > 0 && (
+              <div className="cdp-error">
+                <p>⚠️ Los selectores no encuentran mensajes en:</p>
+                <ul className="cdp-hints">
+                  {selectorWarnings.map((tab) => (
+                    <li key={tab.tab_id}>
+                      {tab.app_name}: {tab.title}
+                      {tab.last_extraction &&
+                        ` (última lectura: ${new Date(tab.last_extraction).toLocaleString()})`}
+                    </li>
+                  ))}
+                </ul>
+              </div>
+            )}
+
             {monitoring && monitoringStatus && (
               <div className="cdp-monitoring-status">
                 <p>
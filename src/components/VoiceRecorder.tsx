@@ -4,11 +4,11 @@ import { useSpeechToText } from '../hooks/useSpeechToText';
 import { useGlobalShortcut } from '../hooks/useGlobalShortcut';
 
 interface VoiceRecorderProps {
-  apiKey: string;
+  apiKeyConfigured: boolean;
   languageCode?: string;
 }
 
-const VoiceRecorder: React.FC<VoiceRecorderProps> = ({ apiKey, languageCode = 'es-ES' }) => {
+const VoiceRecorder: React.FC<VoiceRecorderProps> = ({ apiKeyConfigured, languageCode = 'es-ES' }) => {
   const [isCopied, setIsCopied] = useState(false);
   const voiceRecorder = useVoiceRecorder();
   const { transcribe, copyToClipboard } = useSpeechToText();
@@ -22,7 +22,7 @@ const VoiceRecorder: React.FC<VoiceRecorderProps> = ({ apiKey, languageCode = 'e
         voiceRecorder.toggleRecording();
       }
     },
-    !!apiKey // Only enable if API key is set
+    apiKeyConfigured // Only enable if API key is set
   );
 
   // Auto-transcribe when recording stops
@@ -39,7 +39,7 @@ const VoiceRecorder: React.FC<VoiceRecorderProps> = ({ apiKey, languageCode = 'e
           transcriptionArea.textContent = 'Transcribiendo...';
         }
 
-        const result = await transcribe(audioBlob, apiKey, languageCode);
+        const result = await transcribe(audioBlob, languageCode);
 
         if (result.success) {
           // Update the transcription area
@@ -55,7 +55,7 @@ const VoiceRecorder: React.FC<VoiceRecorderProps> = ({ apiKey, languageCode = 'e
 
       performTranscription();
     }
-  }, [voiceRecorder.state, voiceRecorder.isRecording, transcribe, apiKey, languageCode]);
+  }, [voiceRecorder.state, voiceRecorder.isRecording, transcribe, languageCode]);
 
   const handleCopy = async () => {
     const transcriptionArea = document.getElementById('transcription-area') as HTMLDivElement;
@@ -80,7 +80,7 @@ const VoiceRecorder: React.FC<VoiceRecorderProps> = ({ apiKey, languageCode = 'e
     return `${minutes}:${secs.toString().padStart(2, '0')}`;
   };
 
-  if (!apiKey) {
+  if (!apiKeyConfigured) {
     return (
       <div className="voice-recorder voice-recorder--disabled">
         <div className="voice-recorder__message">
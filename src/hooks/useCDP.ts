@@ -2,43 +2,92 @@ import { useState, useEffect, useCallback } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
 import type {
+  BrowserKind,
+  ConnectionEvent,
   ConnectionResult,
+  ConnectionState,
+  DesktopAppInfo,
+  Diagnostics,
   TabInfo,
   MonitoringStatus,
+  MonitorMode,
   CDPMessage,
   ScriptResult,
+  TabEvent,
+  TabHealth,
 } from '../types/cdp';
 
 export function useCDP() {
   const [connected, setConnected] = useState<boolean>(false);
   const [tabs, setTabs] = useState<TabInfo[]>([]);
   const [error, setError] = useState<string | null>(null);
-  const [errorHelpUrl, setErrorHelpUrl] = useState<string | null>(null);
+  const [diagnostics, setDiagnostics] = useState<Diagnostics | null>(null);
+  const [connectionState, setConnectionState] = useState<ConnectionState>({ state: 'disconnected' });
   const [monitoring, setMonitoring] = useState<boolean>(false);
   const [monitoringStatus, setMonitoringStatus] = useState<MonitoringStatus | null>(null);
   const [messages, setMessages] = useState<CDPMessage[]>([]);
   const [tabsMonitored, setTabsMonitored] = useState<number>(0);
+  const [selectorWarnings, setSelectorWarnings] = useState<TabHealth[]>([]);
+
+  const applyConnection = (result: ConnectionResult) => {
+    setConnected(result.success);
+    setError(result.success ? null : result.message);
+    setDiagnostics(result.diagnostics || null);
+    if (result.success) {
+      // Refresh tabs list
+      refreshTabs();
+    }
+  };
 
   // Connect to Chrome
   const connect = useCallback(async (port: number = 9222) => {
     setError(null);
-    setErrorHelpUrl(null);
+    setDiagnostics(null);
     try {
       const result: ConnectionResult = await invoke('cdp_connect', { port });
-      if (result.success) {
-        setConnected(true);
-        setError(null);
-        setErrorHelpUrl(null);
-        // Refresh tabs list
-        refreshTabs();
-      } else {
-        setConnected(false);
-        setError(result.message);
-        setErrorHelpUrl(result.error_help_url || null);
-      }
+      applyConnection(result);
+    } catch (err) {
+      setConnected(false);
+      setError(typeof err === 'string' ? err : 'Error desconocido');
+    }
+  }, []);
+
+  // Open a browser with its own debugging profile and connect to it
+  const launchBrowser = useCallback(async (kind?: BrowserKind) => {
+    setError(null);
+    setDiagnostics(null);
+    try {
+      const result: ConnectionResult = await invoke('cdp_launch_browser', { kind });
+      applyConnection(result);
     } catch (err) {
       setConnected(false);
-      setError(err instanceof Error ? err.message : 'Error desconocido');
+      setError(typeof err === 'string' ? err : 'Error al abrir el navegador');
+    }
+  }, []);
+
+  // Desktop apps that can be attached to
+  const listDesktopApps = useCallback(async (): Promise<DesktopAppInfo[]> => {
+    try {
+      return await invoke('cdp_list_desktop_apps');
+    } catch (err) {
+      setError(typeof err === 'string' ? err : 'Error al listar las apps de escritorio');
+      return [];
+    }
+  }, []);
+
+  // Attach to a desktop app, launching it with a debugging port unless a port is given
+  const attachDesktopApp = useCallback(async (id: DesktopAppInfo['id'], port?: number) => {
+    setError(null);
+    try {
+      const windows: TabInfo[] = port
+        ? await invoke('cdp_attach_desktop_app', { id, port })
+        : await invoke('cdp_launch_desktop_app', { id });
+      setConnected(true);
+      refreshTabs();
+      return windows;
+    } catch (err) {
+      setError(typeof err === 'string' ? err : 'Error al conectar con la app');
+      return [];
     }
   }, []);
 
@@ -48,34 +97,33 @@ export function useCDP() {
       const tabsList: TabInfo[] = await invoke('cdp_get_tabs');
       setTabs(tabsList);
     } catch (err) {
-      setError(err instanceof Error ? err.message : 'Error al obtener pestañas');
+      setError(typeof err === 'string' ? err : 'Error al obtener pestañas');
     }
   }, []);
 
   // Find tab by title
   const findTab = useCallback(async (titleContains: string): Promise<TabInfo | null> => {
     try {
-      const tab: TabInfo | null = await invoke('cdp_find_tab', {
-        title_contains: titleContains,
-      });
+      const tab: TabInfo | null = await invoke('cdp_find_tab', { titleContains });
       return tab;
     } catch (err) {
-      setError(err instanceof Error ? err.message : 'Error al buscar pestaña');
+      setError(typeof err === 'string' ? err : 'Error al buscar pestaña');
       return null;
     }
   }, []);
 
-  // Execute JavaScript in a tab
+  // Execute JavaScript in a tab; the backend stops it after timeoutMs (10 s by default)
   const executeScript = useCallback(
-    async (tabId: string, script: string): Promise<ScriptResult | null> => {
+    async (tabId: string, script: string, timeoutMs?: number): Promise<ScriptResult | null> => {
       try {
         const result: ScriptResult = await invoke('cdp_execute_script', {
-          tab_id: tabId,
+          tabId,
           script,
+          timeoutMs,
         });
         return result;
       } catch (err) {
-        setError(err instanceof Error ? err.message : 'Error al ejecutar script');
+        setError(typeof err === 'string' ? err : 'Error al ejecutar script');
         return null;
       }
     },
@@ -83,18 +131,18 @@ export function useCDP() {
   );
 
   // Start monitoring
-  const startMonitoring = useCallback(async (intervalMs: number = 2000) => {
+  const startMonitoring = useCallback(async (intervalMs: number = 2000, mode: MonitorMode = 'poll') => {
     try {
       const status: MonitoringStatus = await invoke('cdp_start_monitoring', {
-        app_handle: undefined, // Tauri handles this internally
-        interval_ms: intervalMs,
+        intervalMs,
+        mode,
       });
       setMonitoring(true);
       setMonitoringStatus(status);
       setTabsMonitored(status.tabs_monitored);
       setError(null);
     } catch (err) {
-      setError(err instanceof Error ? err.message : 'Error al iniciar monitoreo');
+      setError(typeof err === 'string' ? err : 'Error al iniciar monitoreo');
       setMonitoring(false);
     }
   }, []);
@@ -108,7 +156,19 @@ export function useCDP() {
       setTabsMonitored(0);
       setError(null);
     } catch (err) {
-      setError(err instanceof Error ? err.message : 'Error al detener monitoreo');
+      setError(typeof err === 'string' ? err : 'Error al detener monitoreo');
+    }
+  }, []);
+
+  // Check what each app's selectors match in its open tabs
+  const checkSelectors = useCallback(async (): Promise<TabHealth[]> => {
+    try {
+      const report: TabHealth[] = await invoke('cdp_check_selectors');
+      setSelectorWarnings(report.filter((tab) => tab.broken));
+      return report;
+    } catch (err) {
+      setError(typeof err === 'string' ? err : 'Error al comprobar los selectores');
+      return [];
     }
   }, []);
 
@@ -118,7 +178,7 @@ export function useCDP() {
 
     const setupListener = async () => {
       try {
-        unlistener = await listen<CDPMessage>('cdp-message-detected', (event) => {
+        unlistener = await listen<CDPMessage>('cdp-message', (event) => {
           setMessages((prev) => {
             // Keep only last 10 messages
             const updated = [event.payload, ...prev];
@@ -139,20 +199,60 @@ export function useCDP() {
     };
   }, []);
 
+  // Follow the browser connection and keep the tab list current
+  useEffect(() => {
+    const unlisteners = [
+      listen<ConnectionEvent>('cdp-connection-state', (event) => {
+        if (event.payload.source !== 'browser') return;
+        setConnectionState(event.payload);
+        setConnected(event.payload.state === 'connected');
+      }),
+      listen<TabHealth>('cdp-selector-warning', (event) => {
+        setSelectorWarnings((prev) => [
+          event.payload,
+          ...prev.filter((t) => t.tab_id !== event.payload.tab_id),
+        ]);
+      }),
+      listen<TabEvent>('cdp-tab', (event) => {
+        const { change, tab_id, tab } = event.payload;
+        if (change === 'destroyed') {
+          setSelectorWarnings((prev) => prev.filter((t) => t.tab_id !== tab_id));
+        }
+        setTabs((prev) => {
+          if (change === 'destroyed') return prev.filter((t) => t.id !== tab_id);
+          if (!tab) return prev;
+          return prev.some((t) => t.id === tab_id)
+            ? prev.map((t) => (t.id === tab_id ? tab : t))
+            : [tab, ...prev];
+        });
+      }),
+    ];
+
+    return () => {
+      unlisteners.forEach((unlisten) => unlisten.then((fn) => fn()));
+    };
+  }, []);
+
   return {
     connected,
+    connectionState,
     tabs,
     error,
-    errorHelpUrl,
+    diagnostics,
     monitoring,
     monitoringStatus,
     messages,
     tabsMonitored,
+    selectorWarnings,
     connect,
+    launchBrowser,
+    listDesktopApps,
+    attachDesktopApp,
     refreshTabs,
     findTab,
     executeScript,
     startMonitoring,
     stopMonitoring,
+    checkSelectors,
   };
 }
@@ -2,6 +2,7 @@ import { useState, useEffect, useCallback, useRef } from 'react';
 import { listen } from '@tauri-apps/api/event';
 import { invoke } from '@tauri-apps/api/core';
 import type { NotificationEvent, NotificationWithId } from '../types/notification';
+import type { QueueCommand, VoiceAction } from '../types/voiceCommands';
 
 export function useNotifications() {
   const [queue, setQueue] = useState<NotificationWithId[]>([]);
@@ -170,6 +171,77 @@ export function useNotifications() {
     }
   }, [stop]);
 
+  // Pause keeps the current notification, to be read again on resume
+  const pause = useCallback(async () => {
+    try {
+      await invoke('plugin:tts|stop', {});
+    } catch (error) {
+      console.error('%c❌ [CONTROLES] Error al pausar:', 'color: #DC143C; font-weight: bold; font-size: 11px', error);
+    }
+    setIsPlaying(false);
+    isPlayingRef.current = false;
+  }, []);
+
+  // Queue commands given by voice; the backend handles the rest
+  const runQueueCommand = useCallback(async (command: QueueCommand) => {
+    console.log('%c🎙️ [VOZ] Comando de cola:', 'color: #20B2AA; font-weight: bold; font-size: 12px', command.type);
+    const matches = (value: string, wanted: string) =>
+      value.toLowerCase().includes(wanted.toLowerCase());
+
+    switch (command.type) {
+      case 'next':
+        await skip();
+        break;
+      case 'stop':
+        await stop();
+        break;
+      case 'pause':
+        await pause();
+        break;
+      case 'repeat':
+      case 'resume':
+        await pause();
+        playNext();
+        break;
+      case 'read_last_from': {
+        const found = [...queueRef.current]
+          .reverse()
+          .find((n) => matches(n.sender, command.sender) && (!command.app || matches(n.app_name, command.app)));
+        if (!found) {
+          console.log('%c⚠️ [VOZ] No hay mensajes de', 'color: #FFD700; font-weight: bold; font-size: 11px', command.sender);
+          break;
+        }
+        await pause();
+        const reordered = [found, ...queueRef.current.filter((n) => n.id !== found.id)];
+        queueRef.current = reordered;
+        setQueue(reordered);
+        playNextInternal(reordered);
+        break;
+      }
+      case 'mark_all_read': {
+        const app = command.app;
+        const remaining = app ? queueRef.current.filter((n) => !matches(n.app_name, app)) : [];
+        if (remaining[0]?.id !== queueRef.current[0]?.id) {
+          await stop();
+        }
+        queueRef.current = remaining;
+        setQueue(remaining);
+        break;
+      }
+    }
+  }, [skip, stop, pause, playNext]);
+
+  useEffect(() => {
+    const unlistenPromise = listen<VoiceAction>('voice-action', (event) => {
+      if (event.payload.subsystem === 'queue') {
+        runQueueCommand(event.payload.command);
+      }
+    });
+    return () => {
+      unlistenPromise.then((fn) => fn());
+    };
+  }, [runQueueCommand]);
+
   return {
     queue,
     currentNotification,
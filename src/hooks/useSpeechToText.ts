@@ -18,19 +18,68 @@ export const useSpeechToText = () => {
     });
   }, []);
 
+  // Decode the recording and re-encode it as 16 kHz mono WAV, which the
+  // backend can split into chunks for long dictations
+  const blobToWav = useCallback(async (blob: Blob): Promise<Blob> => {
+    const sampleRate = 16000;
+    const audioContext = new AudioContext();
+    try {
+      const decoded = await audioContext.decodeAudioData(await blob.arrayBuffer());
+      const offline = new OfflineAudioContext(
+        1,
+        Math.ceil(decoded.duration * sampleRate),
+        sampleRate
+      );
+      const source = offline.createBufferSource();
+      source.buffer = decoded;
+      source.connect(offline.destination);
+      source.start();
+      const samples = (await offline.startRendering()).getChannelData(0);
+
+      const buffer = new ArrayBuffer(44 + samples.length * 2);
+      const view = new DataView(buffer);
+      const writeString = (offset: number, value: string) => {
+        for (let i = 0; i < value.length; i++) {
+          view.setUint8(offset + i, value.charCodeAt(i));
+        }
+      };
+      writeString(0, 'RIFF');
+      view.setUint32(4, 36 + samples.length * 2, true);
+      writeString(8, 'WAVE');
+      writeString(12, 'fmt ');
+      view.setUint32(16, 16, true);
+      view.setUint16(20, 1, true); // PCM
+      view.setUint16(22, 1, true); // mono
+      view.setUint32(24, sampleRate, true);
+      view.setUint32(28, sampleRate * 2, true);
+      view.setUint16(32, 2, true);
+      view.setUint16(34, 16, true);
+      writeString(36, 'data');
+      view.setUint32(40, samples.length * 2, true);
+      samples.forEach((sample, i) => {
+        const clamped = Math.max(-1, Math.min(1, sample));
+        view.setInt16(44 + i * 2, clamped < 0 ? clamped * 0x8000 : clamped * 0x7fff, true);
+      });
+
+      return new Blob([buffer], { type: 'audio/wav' });
+    } finally {
+      await audioContext.close();
+    }
+  }, []);
+
   // Transcribe audio using Tauri command
   const transcribe = useCallback(
     async (
       audioBlob: Blob,
-      apiKey: string,
       languageCode: string = 'es-ES'
     ): Promise<TranscriptionResult> => {
       try {
-        const base64Audio = await blobToBase64(audioBlob);
+        // Fall back to the original WebM recording if decoding fails
+        const wavBlob = await blobToWav(audioBlob).catch(() => audioBlob);
+        const base64Audio = await blobToBase64(wavBlob);
 
         const result = await invoke<TranscriptionResult>('transcribe_audio', {
           audioBase64: base64Audio,
-          apiKey,
           languageCode,
         });
 
@@ -44,7 +93,7 @@ export const useSpeechToText = () => {
         };
       }
     },
-    [blobToBase64]
+    [blobToBase64, blobToWav]
   );
 
   // Copy text to clipboard
@@ -62,5 +111,6 @@ export const useSpeechToText = () => {
     transcribe,
     copyToClipboard,
     blobToBase64,
+    blobToWav,
   };
 };
@@ -2,11 +2,56 @@
  * Chrome DevTools Protocol (CDP) types and interfaces
  */
 
+export type BrowserKind = 'chrome' | 'chromium' | 'edge' | 'brave';
+
+export interface InstalledBrowser {
+  kind: BrowserKind;
+  name: string;
+  path: string;
+}
+
+/** A browser birdie launched with its own profile */
+export interface ManagedBrowser {
+  kind: BrowserKind;
+  name: string;
+  port: number;
+  pid?: number;
+}
+
+/** What answers on the DevTools endpoint */
+export type EndpointStatus =
+  | { status: 'debuggable'; browser: string }
+  | { status: 'busy' }
+  | { status: 'closed' };
+
+/** Returned by cdp_diagnose, and by a failed connection */
+export interface Diagnostics {
+  endpoint: string;
+  status: EndpointStatus;
+  browsers: InstalledBrowser[];
+  managed?: ManagedBrowser;
+  /** Steps to follow, in the order to try them */
+  hints: string[];
+}
+
 export interface ConnectionResult {
   success: boolean;
   message: string;
   tabs_count: number;
-  error_help_url?: string;
+  diagnostics?: Diagnostics;
+}
+
+/** Returned by cdp_list_desktop_apps */
+export interface DesktopAppInfo {
+  id: 'slack' | 'discord' | 'teams';
+  name: string;
+  /** Domains its windows load, those of the web version */
+  domains: string[];
+  /** Port cdp_attach_desktop_app uses when none is given */
+  port: number;
+  installed: boolean;
+  /** Set while attached */
+  connection?: ConnectionState;
 }
 
 export interface TabInfo {
@@ -19,16 +64,27 @@ export interface TabInfo {
 
 export interface ScriptResult {
   success: boolean;
-  result?: string;
+  /** The script's return value as JSON, awaited if it was a promise */
+  result?: unknown;
   error?: string;
 }
 
+/**
+ * "poll" reads tabs every interval, "observe" has tabs report new messages,
+ * "frames" reads Slack and Discord messages from their WebSocket traffic
+ */
+export type MonitorMode = 'poll' | 'observe' | 'frames';
+
 export interface MonitoringStatus {
   is_monitoring: boolean;
   tabs_monitored: number;
   interval_ms: number;
+  mode: MonitorMode;
+  /** New messages found since monitoring started */
+  messages_detected: number;
 }
 
+/** Carried by the "cdp-message" event */
 export interface CDPMessage {
   tab_id: string;
   tab_title: string;
@@ -37,4 +93,64 @@ export interface CDPMessage {
   message: string;
   timestamp: string;
   source: string;
+  /** Account id of the sender, set in "frames" mode */
+  sender_id?: string;
+  /** Channel or conversation id, set in "frames" mode */
+  channel?: string;
+}
+
+/** Returned by cdp_get_connection_state and cdp_disconnect */
+export type ConnectionState =
+  | { state: 'disconnected' }
+  | { state: 'connecting' }
+  | { state: 'connected'; browser: string; endpoint: string }
+  | { state: 'reconnecting'; attempt: number; retry_in_ms: number; error: string }
+  | { state: 'failed'; error: string };
+
+/** Carried by the "cdp-connection-state" event */
+export type ConnectionEvent = ConnectionState & {
+  /** "browser", or the id of a desktop app */
+  source: string;
+};
+
+/** Carried by the "cdp-tab" event */
+export interface TabEvent {
+  source: string;
+  change: 'created' | 'changed' | 'destroyed';
+  tab_id: string;
+  /** null for closed tabs */
+  tab: TabInfo | null;
+}
+
+/** Matches of the monitor's selectors in a tab */
+export interface SelectorCounts {
+  container: number;
+  /** null for apps without a sender selector */
+  sender: number | null;
+  text: number;
+}
+
+/**
+ * Returned by cdp_check_selectors, one per open tab of a known app, and
+ * carried by the "cdp-selector-warning" event when a tab breaks
+ */
+export interface TabHealth {
+  tab_id: string;
+  title: string;
+  domain: string;
+  app_name: string;
+  /** null for apps the monitor does not read */
+  selectors: SelectorCounts | null;
+  /**
+   * Whether the context mapper's script found messages; null for apps it has
+   * no task for
+   */
+  context_found: boolean | null;
+  /** The app's composer is there, and its chat list if it has one */
+  is_chat: boolean;
+  /** A chat in which the app's selectors find no messages */
+  broken: boolean;
+  /** When the monitor last read messages from this app; null if never */
+  last_extraction: string | null;
+  error?: string;
 }
@@ -0,0 +1,14 @@
+export type Provider = 'google';
+
+export type StorageBackend = 'secret_service' | 'encrypted_file';
+
+export interface CredentialStatus {
+  provider: Provider;
+  configured: boolean;
+  backend?: StorageBackend;
+}
+
+export interface KeyTestResult {
+  valid: boolean;
+  message: string;
+}
@@ -0,0 +1,18 @@
+export type PushToTalkMode = 'hold' | 'toggle';
+
+export type HandsFreeState = 'idle' | 'listening' | 'recording' | 'transcribing';
+
+export interface HandsFreeConfig {
+  push_to_talk_enabled: boolean;
+  push_to_talk_shortcut: string;
+  push_to_talk_mode: PushToTalkMode;
+  wake_phrase_enabled: boolean;
+  wake_phrase: string;
+  wake_sensitivity: number;
+  language_code: string;
+}
+
+export interface HandsFreeStatus {
+  config: HandsFreeConfig;
+  wake_samples: number;
+}
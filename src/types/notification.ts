@@ -1,11 +1,36 @@
+import type { RuleTarget } from './voiceCommands';
+
 export interface NotificationEvent {
   app_name: string;
   sender: string;
   message: string;
   timestamp: string;
   app_icon?: string;
+  /** Set when the notification can be answered in place */
+  inline_reply?: InlineReply;
+}
+
+/** A notification that takes a reply text through its inline-reply action */
+export interface InlineReply {
+  id: number;
+  placeholder?: string;
 }
 
 export interface NotificationWithId extends NotificationEvent {
   id: string; // Unique ID for React keys
 }
+
+/** A silence that ends at `until` (Unix seconds), or when turned off if null */
+export interface Silence {
+  until: number | null;
+}
+
+/** Returned by get_notification_rules and taken by set_notification_rules */
+export interface NotificationRuleSettings {
+  /** null when do-not-disturb is off */
+  do_not_disturb: Silence | null;
+  /** By app name */
+  muted_apps: Record<string, Silence>;
+  ignored: RuleTarget[];
+  prioritized: RuleTarget[];
+}
@@ -0,0 +1,64 @@
+export type ReplyChannel = 'cdp' | 'notification' | 'clipboard';
+
+export interface ReplyRequest {
+  app_name: string;
+  /** Conversation to open; the one already open if omitted */
+  recipient?: string | null;
+  text: string;
+  /** Answer this notification in place when it still accepts a reply (NotificationEvent.inline_reply.id) */
+  notification_id?: number | null;
+  /** Use this channel instead of the best one available */
+  channel?: ReplyChannel | null;
+  dry_run?: boolean;
+  /** Ask for confirmation aloud, in this language */
+  speak_confirmation?: string | null;
+}
+
+/** Returned by send_reply and carried by the "reply-pending", "reply-sent" and "reply-cancelled" events */
+export interface ReplyPreview {
+  /** Id to confirm or cancel with; null for dry runs */
+  id: number | null;
+  channel: ReplyChannel;
+  app_name: string;
+  recipient: string | null;
+  text: string;
+  destination: string;
+  submit_with: string;
+  dry_run: boolean;
+}
+
+/** Variables a quick reply template can use, as {name} */
+export type QuickReplyVariable =
+  | 'sender'
+  | 'first_name'
+  | 'app'
+  | 'minutes'
+  | 'time'
+  | 'event'
+  | 'free_at';
+
+export interface QuickReply {
+  /** Short name to pick it by voice */
+  id: string;
+  text: string;
+  /** Global shortcut that sends it to the latest notification */
+  shortcut?: string | null;
+  /** Apps it is the default quick reply for */
+  default_for?: string[];
+}
+
+/** Returned by get_quick_replies and passed to set_quick_replies */
+export interface QuickReplySettings {
+  replies: QuickReply[];
+  /** Used for apps without a default of their own */
+  default: string | null;
+  /** Sends the default quick reply of the latest notification's app */
+  shortcut: string | null;
+}
+
+/** Passed to set_calendar_state; fills {event} and {free_at} */
+export interface CalendarState {
+  event?: string | null;
+  /** When the user is free again, as it should be read ("11:30") */
+  free_at?: string | null;
+}
@@ -1,7 +1,28 @@
+export interface TranscriptAlternative {
+  transcript: string;
+  confidence?: number;
+}
+
+export interface TranscriptSegment {
+  alternatives: TranscriptAlternative[];
+  end_secs?: number;
+}
+
+export interface WordTiming {
+  word: string;
+  start_secs: number;
+  end_secs: number;
+  confidence?: number;
+}
+
 export interface TranscriptionResult {
   text: string;
   success: boolean;
   error?: string;
+  confidence?: number;
+  alternatives?: TranscriptAlternative[];
+  segments?: TranscriptSegment[];
+  words?: WordTiming[];
 }
 
 export type RecordingState = 'idle' | 'recording' | 'transcribing' | 'completed' | 'error';
@@ -14,3 +35,8 @@ export interface VoiceRecorderState {
   duration: number;
   isProcessing: boolean;
 }
+
+export interface Replacement {
+  from: string;
+  to: string;
+}
@@ -0,0 +1,98 @@
+import type { NotificationEvent } from './notification';
+
+export type EngineId = 'speech_dispatcher' | 'espeak_ng' | 'piper';
+
+export interface Capabilities {
+  pcm: boolean;
+  ssml: boolean;
+  pitch: boolean;
+}
+
+export interface EngineInfo {
+  id: EngineId;
+  available: boolean;
+  capabilities: Capabilities;
+}
+
+export interface Voice {
+  engine: EngineId;
+  id: string;
+  name: string;
+  language: string;
+  gender?: string | null;
+}
+
+export interface VoiceRef {
+  engine: EngineId;
+  voice: string;
+}
+
+export interface Prosody {
+  rate: number;
+  pitch: number;
+  volume: number;
+}
+
+export interface VoiceProfile {
+  voice?: VoiceRef | null;
+  rate: number;
+  pitch: number;
+  volume: number;
+  /** SSML around the message, with `{text}` where it goes */
+  ssml?: string | null;
+}
+
+export interface TtsSettings {
+  default_voices: Record<string, VoiceRef>;
+  app_profiles: Record<string, VoiceProfile>;
+}
+
+export type TaskPriority = 'Disabled' | 'Low' | 'Normal' | 'High' | 'Critical';
+
+export type Earcon = 'tick' | 'pop' | 'knock' | 'chime' | 'bell' | 'alert';
+
+export type Sound =
+  | { kind: 'bundled'; value: Earcon }
+  | { kind: 'file'; value: string };
+
+export interface CueRule {
+  app_name?: string | null;
+  priority?: TaskPriority | null;
+  /** No cue when null */
+  sound?: Sound | null;
+}
+
+export interface EarconSettings {
+  enabled: boolean;
+  volume: number;
+  rules: CueRule[];
+  /** Messages at or below this priority only play their cue */
+  cue_only_up_to?: TaskPriority | null;
+}
+
+export interface Announcement {
+  app_name: string;
+  text: string;
+  language: string;
+  priority?: TaskPriority | null;
+}
+
+export type AnnouncementState = 'playing' | 'finished' | 'stopped' | 'failed';
+
+/** Payload of the "announcement-state" event */
+export interface AnnouncementEvent {
+  id: number;
+  app_name: string;
+  state: AnnouncementState;
+  error?: string | null;
+}
+
+export type ExportContent =
+  | { kind: 'notification'; notification: NotificationEvent }
+  | { kind: 'thread'; title?: string | null; messages: NotificationEvent[] }
+  | { kind: 'digest'; title: string; notifications: NotificationEvent[] };
+
+export interface ExportedAudio {
+  path: string;
+  duration_secs: number;
+}
@@ -0,0 +1,38 @@
+export type QueueCommand =
+  | { type: 'next' }
+  | { type: 'repeat' }
+  | { type: 'stop' }
+  | { type: 'pause' }
+  | { type: 'resume' }
+  | { type: 'read_last_from'; sender: string; app?: string | null }
+  | { type: 'mark_all_read'; app?: string | null };
+
+export type DndCommand =
+  | { type: 'enable'; minutes?: number | null }
+  | { type: 'disable' }
+  | { type: 'mute_app'; app: string; minutes?: number | null }
+  | { type: 'unmute_app'; app: string };
+
+export type RuleTarget = { type: 'sender'; name: string } | { type: 'app'; name: string };
+
+export type RuleCommand =
+  | { type: 'ignore'; target: RuleTarget }
+  | { type: 'prioritize'; target: RuleTarget };
+
+export type ReplyCommand =
+  | { type: 'send'; recipient?: string | null; text: string }
+  | { type: 'quick'; name: string | null; minutes: number | null }
+  | { type: 'confirm' }
+  | { type: 'cancel' };
+
+export type VoiceAction =
+  | { subsystem: 'queue'; command: QueueCommand }
+  | { subsystem: 'dnd'; command: DndCommand }
+  | { subsystem: 'rules'; command: RuleCommand }
+  | { subsystem: 'reply'; command: ReplyCommand };
+
+export interface Interpretation {
+  actions: VoiceAction[];
+  unrecognized: string[];
+  low_confidence: boolean;
+}